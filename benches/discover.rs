@@ -0,0 +1,130 @@
+//! Compares the discovery with one thread and with one per CPU, with and without searching the
+//! working trees of the repositories found.
+//!
+//! Without arguments a fixture tree is generated in a temporary directory, or the tree under
+//! the given directory is used:
+//!
+//! ```text
+//! cargo bench --bench discover [-- DIR [DEPTH]]
+//! ```
+//!
+//! The page cache is dropped before each run when running as root on Linux, otherwise the
+//! numbers are for a warm cache.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use gitjuggling::{discover_superprojects_with_progress, DiscoverOptions};
+
+const RUNS: usize = 5;
+
+/// Creates `width` directories of `width` directories each, a tenth of them repositories with a
+/// git directory laid out like a real one, and a few files and directories to walk through.
+fn generate(root: &Path, width: usize) {
+    for i in 0..width {
+        for j in 0..width {
+            let dir = root
+                .join(format!("group-{}", i))
+                .join(format!("project-{}", j));
+            if j % 10 == 0 {
+                let git_dir = dir.join(".git");
+                for sub in [
+                    "hooks",
+                    "info",
+                    "refs/heads",
+                    "refs/tags",
+                    "logs/refs/heads",
+                ] {
+                    std::fs::create_dir_all(git_dir.join(sub)).unwrap();
+                }
+                for object in 0..=255 {
+                    std::fs::create_dir_all(git_dir.join(format!("objects/{:02x}", object)))
+                        .unwrap();
+                }
+                std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+            }
+            std::fs::create_dir_all(dir.join("src")).unwrap();
+            for k in 0..5 {
+                std::fs::write(dir.join("src").join(format!("file-{}.rs", k)), "").unwrap();
+            }
+        }
+    }
+}
+
+fn drop_caches() -> bool {
+    std::fs::write("/proc/sys/vm/drop_caches", "3").is_ok()
+}
+
+/// Returns the best time of the runs, the directories visited and the repositories found.
+fn measure(threads: usize, options: &DiscoverOptions) -> (Duration, usize, usize) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+
+    let mut best = Duration::MAX;
+    let directories = AtomicUsize::new(0);
+    let mut found = 0;
+    for _ in 0..RUNS {
+        drop_caches();
+        let start = Instant::now();
+        found = pool.install(|| {
+            discover_superprojects_with_progress(options, &|progress| {
+                directories.store(progress.directories, Ordering::Relaxed)
+            })
+            .unwrap()
+            .len()
+        });
+        best = best.min(start.elapsed());
+    }
+
+    (best, directories.load(Ordering::Relaxed), found)
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).filter(|arg| arg != "--bench");
+    let fixture;
+    let (root, depth) = match args.next() {
+        Some(root) => {
+            let depth = args.next().map(|depth| depth.parse().unwrap()).unwrap_or(3);
+            (PathBuf::from(root), depth)
+        }
+        None => {
+            fixture = tempfile::tempdir().unwrap();
+            generate(fixture.path(), 60);
+            (fixture.path().to_path_buf(), 5)
+        }
+    };
+
+    let cpus = std::thread::available_parallelism()
+        .map(|cpus| cpus.get())
+        .unwrap_or(1);
+
+    println!(
+        "{} with depth {}, {} cache, best of {} runs",
+        root.display(),
+        depth,
+        if drop_caches() { "cold" } else { "warm" },
+        RUNS
+    );
+    for nested in [true, false] {
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            depth,
+            nested,
+            ..DiscoverOptions::default()
+        };
+        for threads in [1, cpus, cpus * 4] {
+            let (elapsed, directories, found) = measure(threads, &options);
+            println!(
+                "{:>3} threads{}: {:>8.1?}, {} directories for {} repositories",
+                threads,
+                if nested { "" } else { ", no nested" },
+                elapsed,
+                directories,
+                found
+            );
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Bakes the git commit and the build date in the binary, see src/version.rs.
+///
+/// When building from a tarball without git metadata the commit is left empty.
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default();
+
+    // Honor SOURCE_DATE_EPOCH for reproducible builds
+    let build_time = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .map(|epoch| UNIX_EPOCH + Duration::from_secs(epoch))
+        .unwrap_or_else(SystemTime::now);
+    let build_date = humantime::format_rfc3339_seconds(build_time).to_string();
+
+    println!("cargo:rustc-env=GITJUGGLING_COMMIT={}", commit);
+    println!(
+        "cargo:rustc-env=GITJUGGLING_BUILD_DATE={}",
+        &build_date[..10]
+    );
+
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
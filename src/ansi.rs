@@ -0,0 +1,122 @@
+//! Handle the ANSI escape sequences in the output of the commands.
+
+use std::borrow::Cow;
+
+/// Removes the ANSI escape sequences (CSI and OSC) from `text`.
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    match strip_ansi_bytes(text.as_bytes()) {
+        Cow::Borrowed(_) => Cow::Borrowed(text),
+        Cow::Owned(bytes) => Cow::Owned(
+            String::from_utf8(bytes).expect("only whole characters are removed from valid UTF-8"),
+        ),
+    }
+}
+
+/// Like [`strip_ansi`] for output that may not be UTF-8, the other bytes are left untouched.
+pub fn strip_ansi_bytes(text: &[u8]) -> Cow<'_, [u8]> {
+    if !text.contains(&0x1b) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = Vec::with_capacity(text.len());
+    let mut bytes = text.iter().copied().peekable();
+
+    while let Some(byte) = bytes.next() {
+        if byte != 0x1b {
+            result.push(byte);
+            continue;
+        }
+
+        match bytes.next() {
+            // CSI: parameters and intermediate bytes followed by a final byte
+            Some(b'[') => {
+                for byte in bytes.by_ref() {
+                    if (0x40..=0x7e).contains(&byte) {
+                        break;
+                    }
+                }
+            }
+            // OSC: terminated by BEL or ST
+            Some(b']') => {
+                while let Some(byte) = bytes.next() {
+                    if byte == 0x07 {
+                        break;
+                    }
+                    if byte == 0x1b && bytes.peek() == Some(&b'\\') {
+                        bytes.next();
+                        break;
+                    }
+                }
+            }
+            // Any other escape drops the character after it, all of its bytes
+            Some(_) => while bytes.next_if(|byte| (0x80..0xc0).contains(byte)).is_some() {},
+            None => {}
+        }
+    }
+
+    Cow::Owned(result)
+}
+
+/// Removes the CSI sequences other than the colors and the styles from `text`, the output of a
+/// command moving the cursor or erasing the screen would garble everything around it.
+pub fn sanitize(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        result.push_str(&rest[..start]);
+        let sequence = &rest[start..];
+        // Parameters and intermediate bytes followed by a final byte
+        let end = sequence[2..]
+            .find(|c: char| ('\x40'..='\x7e').contains(&c))
+            .map_or(sequence.len(), |end| end + 3);
+        if sequence[..end].ends_with('m') {
+            result.push_str(&sequence[..end]);
+        }
+        rest = &sequence[end..];
+    }
+    result.push_str(rest);
+
+    Cow::Owned(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!("foo", strip_ansi("foo"));
+        assert_eq!(
+            "foo bar",
+            strip_ansi("\x1b[32mfoo\x1b[0m \x1b[38;2;1;2;3mbar\x1b[0m")
+        );
+        assert_eq!(
+            "link",
+            strip_ansi("\x1b]8;;file:///tmp\x1b\\link\x1b]8;;\x1b\\")
+        );
+        assert_eq!("abb", strip_ansi("a\x1bébb"));
+
+        assert_eq!(
+            &b"caf\xe9"[..],
+            &*strip_ansi_bytes(b"\x1b[1mcaf\xe9\x1b[0m")
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!("foo", sanitize("foo"));
+        assert_eq!(
+            "\x1b[32mfoo\x1b[0m \x1b[1;31mbar\x1b[m",
+            sanitize("\x1b[2J\x1b[H\x1b[32mfoo\x1b[0m \x1b[1;31mbar\x1b[m\x1b[K")
+        );
+        assert_eq!(
+            "a\x1b]8;;file:///tmp\x1b\\b",
+            sanitize("a\x1b[3A\x1b]8;;file:///tmp\x1b\\b")
+        );
+        assert_eq!("é", sanitize("é\x1b[1"));
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use globset::{GlobBuilder, GlobMatcher};
+
+/// A line of an [`ArgsFile`].
+pub struct Mapping {
+    /// The line number, from 1
+    pub line: usize,
+    /// The path or the glob of the repositories
+    pub pattern: String,
+    /// The arguments appended to the git arguments of the repositories
+    pub args: Vec<String>,
+    matcher: GlobMatcher,
+}
+
+impl Mapping {
+    /// Returns true if the repository at `path`, `relative` to its root, is mapped by this line.
+    /// An absolute pattern is matched against the absolute path.
+    fn is_match(&self, path: &Path, relative: &Path) -> bool {
+        if Path::new(&self.pattern).is_absolute() {
+            self.matcher.is_match(path)
+        } else {
+            self.matcher.is_match(relative)
+        }
+    }
+}
+
+/// The extra git arguments of some repositories, read from the file of --args-file.
+pub struct ArgsFile {
+    pub mappings: Vec<Mapping>,
+}
+
+impl ArgsFile {
+    /// Reads the file at `path`, the errors name the file and the line.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("unable to read {}: {}", path.display(), err))?;
+
+        Self::parse(&input).map_err(|err| anyhow!("{}:{}", path.display(), err))
+    }
+
+    /// Parses one mapping per line: the path or the glob of the repositories, relative to the
+    /// root, then their arguments split like a shell does. The empty lines and the ones starting
+    /// with `#` are ignored.
+    ///
+    /// A `*` of a glob stays in a directory, like `work/*` for the repositories right under work,
+    /// and `**` goes into the subdirectories.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut mappings: Vec<Mapping> = Vec::new();
+
+        for (index, line) in input.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some(mut words) = shlex::split(line) else {
+                return Err(anyhow!("{}: unbalanced quotes", line_number));
+            };
+            let pattern = words.remove(0);
+            if let Some(previous) = mappings.iter().find(|mapping| mapping.pattern == pattern) {
+                return Err(anyhow!(
+                    "{}: {} is already mapped on line {}",
+                    line_number,
+                    pattern,
+                    previous.line
+                ));
+            }
+            let matcher = GlobBuilder::new(&pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| anyhow!("{}: invalid glob {}: {}", line_number, pattern, err))?
+                .compile_matcher();
+
+            mappings.push(Mapping {
+                line: line_number,
+                pattern,
+                args: words,
+                matcher,
+            });
+        }
+
+        Ok(Self { mappings })
+    }
+
+    /// Returns the line mapping each repository of `paths` one of them maps, the relative
+    /// patterns are matched against the path relative to one of the `roots`. A repository
+    /// mapped by several lines is an error naming them.
+    pub fn resolve(
+        &self,
+        paths: &[PathBuf],
+        roots: &[PathBuf],
+    ) -> anyhow::Result<HashMap<PathBuf, &Mapping>> {
+        let mut mapped = HashMap::new();
+
+        for path in paths {
+            let relative = roots
+                .iter()
+                .find_map(|root| path.strip_prefix(root).ok())
+                .unwrap_or(path);
+            let matching: Vec<&Mapping> = self
+                .mappings
+                .iter()
+                .filter(|mapping| mapping.is_match(path, relative))
+                .collect();
+            match matching.as_slice() {
+                [] => {}
+                [mapping] => {
+                    mapped.insert(path.clone(), *mapping);
+                }
+                [first, second, ..] => {
+                    return Err(anyhow!(
+                        "{} is mapped by line {} ({}) and line {} ({})",
+                        relative.display(),
+                        first.line,
+                        first.pattern,
+                        second.line,
+                        second.pattern
+                    ))
+                }
+            }
+        }
+
+        Ok(mapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let args_file = ArgsFile::parse(
+            "# the new branches\n\
+             foo origin main:trunk\n\
+             \n\
+             work/* origin 'HEAD:refs/heads/new name'\n\
+             /src/bar\n",
+        )
+        .unwrap();
+
+        let mappings: Vec<(usize, &str, Vec<&str>)> = args_file
+            .mappings
+            .iter()
+            .map(|mapping| {
+                let args = mapping.args.iter().map(String::as_str).collect();
+                (mapping.line, mapping.pattern.as_str(), args)
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (2, "foo", vec!["origin", "main:trunk"]),
+                (4, "work/*", vec!["origin", "HEAD:refs/heads/new name"]),
+                (5, "/src/bar", vec![]),
+            ],
+            mappings
+        );
+
+        let err = ArgsFile::parse("foo a\nbar b\nfoo c\n").err().unwrap();
+        assert_eq!("3: foo is already mapped on line 1", err.to_string());
+        assert!(ArgsFile::parse("foo 'a\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let roots = vec![PathBuf::from("/src")];
+        let paths = vec![
+            PathBuf::from("/src/foo"),
+            PathBuf::from("/src/bar"),
+            PathBuf::from("/src/work/baz"),
+            PathBuf::from("/src/work/baz/nested"),
+        ];
+
+        let args_file = ArgsFile::parse("foo a\nwork/* b\n/src/bar c\n").unwrap();
+        let mapped = args_file.resolve(&paths, &roots).unwrap();
+        assert_eq!(vec!["a"], mapped[Path::new("/src/foo")].args);
+        assert_eq!(vec!["c"], mapped[Path::new("/src/bar")].args);
+        assert_eq!(vec!["b"], mapped[Path::new("/src/work/baz")].args);
+        // * doesn't go into the subdirectories
+        assert!(!mapped.contains_key(Path::new("/src/work/baz/nested")));
+
+        let args_file = ArgsFile::parse("work/** b\n").unwrap();
+        let mapped = args_file.resolve(&paths, &roots).unwrap();
+        assert_eq!(vec!["b"], mapped[Path::new("/src/work/baz")].args);
+        assert_eq!(vec!["b"], mapped[Path::new("/src/work/baz/nested")].args);
+
+        let args_file = ArgsFile::parse("foo a\n*o b\n").unwrap();
+        let err = args_file.resolve(&paths, &roots).err().unwrap();
+        assert_eq!(
+            "foo is mapped by line 1 (foo) and line 2 (*o)",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Find the work that only exists in a local repository.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::Serialize;
+
+use crate::branches::parse_track;
+use crate::git::Git;
+use crate::status::RepoStatus;
+
+/// A local branch with commits its upstream doesn't have.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AheadBranch {
+    /// The name of the branch
+    pub branch: String,
+    /// The number of commits not pushed
+    pub commits: usize,
+}
+
+/// The work of a repository that isn't committed or isn't pushed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Audit {
+    /// The number of staged, modified or conflicted files
+    pub modified: usize,
+    /// The summary of `git diff --shortstat HEAD`, if there are changes and a HEAD to compare to
+    pub diffstat: Option<String>,
+    /// The number of untracked files
+    pub untracked: usize,
+    /// The number of stash entries
+    pub stashes: usize,
+    /// The local branches without an upstream, or whose upstream is gone
+    pub no_upstream: Vec<String>,
+    /// The local branches ahead of their upstream
+    pub ahead: Vec<AheadBranch>,
+}
+
+impl Audit {
+    /// Audits the repository at `path`.
+    pub fn probe(git: &Git, path: &Path) -> anyhow::Result<Self> {
+        let status = RepoStatus::probe(git, path)?;
+
+        let diffstat = if status.modified > 0 {
+            git.stdout(path, &["diff", "--shortstat", "HEAD", "--"])
+                .filter(|diffstat| !diffstat.is_empty())
+        } else {
+            None
+        };
+
+        let stashes = git
+            .stdout(path, &["stash", "list"])
+            .ok_or_else(|| anyhow!("git stash list failed"))?
+            .lines()
+            .count();
+
+        let branches = git
+            .stdout(
+                path,
+                &[
+                    "for-each-ref",
+                    "--format=%(refname:short)%00%(upstream:short)%00%(upstream:track,nobracket)",
+                    "refs/heads",
+                ],
+            )
+            .ok_or_else(|| anyhow!("git for-each-ref failed"))?;
+        let (no_upstream, ahead) = parse_branches(&branches);
+
+        Ok(Self {
+            modified: status.modified,
+            diffstat,
+            untracked: status.untracked,
+            stashes,
+            no_upstream,
+            ahead,
+        })
+    }
+
+    /// Returns true if everything in the repository is committed and pushed.
+    pub fn is_clean(&self) -> bool {
+        self.modified == 0
+            && self.untracked == 0
+            && self.stashes == 0
+            && self.no_upstream.is_empty()
+            && self.ahead.is_empty()
+    }
+}
+
+/// Parses the branches listed by `git for-each-ref` with their upstream and its tracking
+/// information, separated by NUL bytes.
+///
+/// Returns the branches without an upstream and the ones ahead of it.
+fn parse_branches(output: &str) -> (Vec<String>, Vec<AheadBranch>) {
+    let mut no_upstream = Vec::new();
+    let mut ahead = Vec::new();
+
+    for line in output.lines() {
+        let mut fields = line.split('\0');
+        let (Some(branch), upstream, track) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let track = track.unwrap_or("");
+
+        if upstream.unwrap_or("").is_empty() {
+            no_upstream.push(branch.to_string());
+            continue;
+        }
+
+        match parse_track(track) {
+            None => no_upstream.push(branch.to_string()),
+            Some((commits, _)) if commits > 0 => ahead.push(AheadBranch {
+                branch: branch.to_string(),
+                commits,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    (no_upstream, ahead)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_branches() {
+        let output = "main\0origin/main\0\n\
+                      dev\0origin/dev\0ahead 2, behind 1\n\
+                      fix\0origin/fix\0behind 3\n\
+                      wip\0\0\n\
+                      old\0origin/old\0gone\n";
+
+        let (no_upstream, ahead) = parse_branches(output);
+        assert_eq!(vec!["wip", "old"], no_upstream);
+        assert_eq!(
+            vec![AheadBranch {
+                branch: "dev".to_string(),
+                commits: 2
+            }],
+            ahead
+        );
+    }
+}
@@ -0,0 +1,425 @@
+//! List the local branches of a repository.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::Serialize;
+
+use crate::git::Git;
+
+/// A local branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Branch {
+    /// The name of the branch
+    pub name: String,
+    /// The upstream of the branch, if it has one and it still exists
+    pub upstream: Option<String>,
+    /// How many commits the branch is ahead of its upstream
+    pub ahead: Option<usize>,
+    /// How many commits the branch is behind its upstream
+    pub behind: Option<usize>,
+    /// The committer date of the last commit, in seconds since the Unix epoch
+    pub timestamp: i64,
+    /// Whether this is the current branch
+    pub current: bool,
+    /// Whether all the commits of the branch are in the default branch
+    pub merged: bool,
+    /// Whether the branch had an upstream that doesn't exist anymore
+    pub gone: bool,
+}
+
+/// Why a branch can be pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PruneReason {
+    /// All its commits are in the default branch
+    Merged,
+    /// All its commits are in its upstream
+    MergedUpstream,
+    /// Its upstream doesn't exist anymore
+    Gone,
+}
+
+impl fmt::Display for PruneReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PruneReason::Merged => "merged",
+            PruneReason::MergedUpstream => "merged into its upstream",
+            PruneReason::Gone => "[gone]",
+        })
+    }
+}
+
+/// A branch pruned, or that would be.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Pruned {
+    /// The name of the branch
+    pub name: String,
+    /// Why it was pruned
+    pub reason: PruneReason,
+    /// Why it couldn't be deleted, if it couldn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The branches of a repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Branches {
+    /// The branch the others are merged into: the HEAD of origin, or the local main or master
+    pub default: Option<String>,
+    /// The local branches, sorted by name
+    pub branches: Vec<Branch>,
+}
+
+impl Branches {
+    /// Lists the local branches of the repository at `path`.
+    pub fn list(git: &Git, path: &Path) -> anyhow::Result<Self> {
+        let output = git
+            .stdout(
+                path,
+                &[
+                    "for-each-ref",
+                    "--format=%(refname:short)%00%(upstream:short)%00%(upstream:track,nobracket)%00%(committerdate:unix)%00%(HEAD)",
+                    "refs/heads",
+                ],
+            )
+            .ok_or_else(|| anyhow!("git for-each-ref failed"))?;
+        let mut branches = parse(&output);
+
+        let default = default_branch(git, path);
+        if let Some(default) = &default {
+            let merged = git
+                .stdout(
+                    path,
+                    &[
+                        "for-each-ref",
+                        "--format=%(refname:short)",
+                        "--merged",
+                        default,
+                        "refs/heads",
+                    ],
+                )
+                .ok_or_else(|| anyhow!("git for-each-ref --merged {} failed", default))?;
+            let merged: Vec<&str> = merged.lines().collect();
+
+            for branch in &mut branches {
+                branch.merged =
+                    merged.contains(&branch.name.as_str()) && !is_default(&branch.name, default);
+            }
+        }
+
+        Ok(Self { default, branches })
+    }
+
+    /// Returns the merged branches that can be deleted: neither the current one nor the
+    /// default one.
+    pub fn deletable(&self) -> impl Iterator<Item = &Branch> {
+        self.branches
+            .iter()
+            .filter(|branch| branch.merged && !branch.current)
+    }
+
+    /// Returns the branches that can be pruned with why, never the current one nor the default
+    /// one nor the `protected` ones. The branches whose upstream is gone are only included if
+    /// `gone` is true: their commits may be nowhere else.
+    pub fn prunable<'a>(
+        &'a self,
+        gone: bool,
+        protected: impl Fn(&str) -> bool + 'a,
+    ) -> impl Iterator<Item = (&'a Branch, PruneReason)> + 'a {
+        self.branches
+            .iter()
+            .filter(move |branch| {
+                !branch.current
+                    && !self
+                        .default
+                        .as_deref()
+                        .is_some_and(|default| is_default(&branch.name, default))
+                    && !protected(&branch.name)
+            })
+            .filter_map(move |branch| {
+                if branch.merged {
+                    Some((branch, PruneReason::Merged))
+                } else if branch.ahead == Some(0) {
+                    Some((branch, PruneReason::MergedUpstream))
+                } else if gone && branch.gone {
+                    Some((branch, PruneReason::Gone))
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+/// Where HEAD is when it's not on the default branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OffDefault {
+    /// The current branch, `None` if HEAD is detached
+    pub branch: Option<String>,
+    /// The abbreviated commit of HEAD
+    pub commit: String,
+    /// The default branch, see [`default_branch`]
+    pub default: String,
+    /// How many commits HEAD has that the default branch doesn't
+    pub ahead: usize,
+    /// How many commits the default branch has that HEAD doesn't
+    pub behind: usize,
+}
+
+impl OffDefault {
+    /// Returns the name of the local default branch, without the remote of the HEAD of origin.
+    pub fn local_default(&self) -> &str {
+        self.default
+            .strip_prefix("origin/")
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Compares the HEAD of the repository at `path` to its default branch.
+///
+/// Returns `None` if HEAD is on the default branch, or if there are no commits yet.
+pub fn off_default(git: &Git, path: &Path) -> anyhow::Result<Option<OffDefault>> {
+    let Some(commit) = git.stdout(
+        path,
+        &["rev-parse", "--quiet", "--verify", "--short", "HEAD"],
+    ) else {
+        return Ok(None);
+    };
+    let default =
+        default_branch(git, path).ok_or_else(|| anyhow!("unable to find the default branch"))?;
+
+    let branch = git.stdout(path, &["symbolic-ref", "--quiet", "--short", "HEAD"]);
+    if branch
+        .as_deref()
+        .is_some_and(|branch| is_default(branch, &default))
+    {
+        return Ok(None);
+    }
+
+    let counts = git
+        .stdout(
+            path,
+            &[
+                "rev-list",
+                "--left-right",
+                "--count",
+                &format!("HEAD...{}", default),
+            ],
+        )
+        .ok_or_else(|| anyhow!("git rev-list HEAD...{} failed", default))?;
+    let Some((ahead, behind)) = counts
+        .split_once('\t')
+        .and_then(|(ahead, behind)| Some((ahead.parse().ok()?, behind.parse().ok()?)))
+    else {
+        return Err(anyhow!(
+            "unable to parse the output of git rev-list: {}",
+            counts
+        ));
+    };
+
+    Ok(Some(OffDefault {
+        branch,
+        commit,
+        default,
+        ahead,
+        behind,
+    }))
+}
+
+/// Deletes the branch `name` of the repository at `path`. Unless `force` is true git refuses if
+/// it's not merged into HEAD or its upstream.
+///
+/// git always refuses to delete a branch checked out in a worktree.
+pub fn delete(git: &Git, path: &Path, name: &str, force: bool) -> anyhow::Result<()> {
+    let mut command = git.command(path);
+    command.args(["branch", "--delete", "--quiet"]);
+    if force {
+        command.arg("--force");
+    }
+    let output = command
+        .arg(name)
+        .output()
+        .map_err(|err| anyhow!("unable to run {}: {}", git.program().display(), err))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git branch --delete {} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns true if the local branch `name` is the default branch `default`, which can be a
+/// remote-tracking branch.
+fn is_default(name: &str, default: &str) -> bool {
+    name == default
+        || default
+            .split_once('/')
+            .is_some_and(|(_, branch)| branch == name)
+}
+
+/// Returns the default branch of the repository at `path`: the HEAD of origin, or else the local
+/// branch named by `init.defaultBranch`, main or master.
+pub fn default_branch(git: &Git, path: &Path) -> Option<String> {
+    if let Some(head) = git.stdout(
+        path,
+        &[
+            "symbolic-ref",
+            "--quiet",
+            "--short",
+            "refs/remotes/origin/HEAD",
+        ],
+    ) {
+        return Some(head);
+    }
+
+    git.config(path, "init.defaultBranch")
+        .into_iter()
+        .chain(["main".to_string(), "master".to_string()])
+        .find(|name| {
+            git.stdout(
+                path,
+                &[
+                    "rev-parse",
+                    "--quiet",
+                    "--verify",
+                    &format!("refs/heads/{}", name),
+                ],
+            )
+            .is_some()
+        })
+}
+
+/// Parses the tracking information of `%(upstream:track,nobracket)` into the ahead and behind
+/// counts, `None` if the upstream is gone.
+pub(crate) fn parse_track(track: &str) -> Option<(usize, usize)> {
+    if track == "gone" {
+        return None;
+    }
+
+    let mut counts = (0, 0);
+    for part in track.split(", ") {
+        if let Some(ahead) = part.strip_prefix("ahead ") {
+            counts.0 = ahead.parse().ok()?;
+        } else if let Some(behind) = part.strip_prefix("behind ") {
+            counts.1 = behind.parse().ok()?;
+        }
+    }
+
+    Some(counts)
+}
+
+fn parse(output: &str) -> Vec<Branch> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next()?;
+            let upstream = fields.next()?;
+            let track = fields.next()?;
+            let timestamp = fields.next()?.parse().ok()?;
+            let current = fields.next()? == "*";
+
+            let counts = if upstream.is_empty() {
+                None
+            } else {
+                parse_track(track)
+            };
+            let gone = !upstream.is_empty() && counts.is_none();
+
+            Some(Branch {
+                name: name.to_string(),
+                upstream: counts.map(|_| upstream.to_string()),
+                ahead: counts.map(|counts| counts.0),
+                behind: counts.map(|counts| counts.1),
+                timestamp,
+                current,
+                merged: false,
+                gone,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "dev\0origin/dev\0ahead 2, behind 1\x001717077720\0 \n\
+                      main\0origin/main\0\x001717077000\0*\n\
+                      old\0origin/old\0gone\x001600000000\0 \n\
+                      wip\0\0\x001717000000\0 \n";
+
+        let branches = parse(output);
+        assert_eq!(4, branches.len());
+        assert_eq!(Some(2), branches[0].ahead);
+        assert_eq!(Some(1), branches[0].behind);
+        assert_eq!(Some(0), branches[1].ahead);
+        assert!(branches[1].current);
+        assert_eq!(None, branches[2].upstream);
+        assert!(branches[2].gone);
+        assert!(!branches[3].gone);
+        assert_eq!(None, branches[3].ahead);
+        assert_eq!(1717000000, branches[3].timestamp);
+    }
+
+    #[test]
+    fn test_prunable() {
+        let branch = |name: &str, ahead: Option<usize>, merged, gone| Branch {
+            name: name.to_string(),
+            upstream: ahead.map(|_| format!("origin/{}", name)),
+            ahead,
+            behind: ahead.map(|_| 0),
+            timestamp: 0,
+            current: name == "wip",
+            merged,
+            gone,
+        };
+        let branches = Branches {
+            default: Some("origin/main".to_string()),
+            branches: vec![
+                branch("done", None, true, false),
+                branch("main", Some(0), false, false),
+                branch("old", None, false, true),
+                branch("release/1", None, true, false),
+                branch("review", Some(0), false, false),
+                branch("spike", Some(3), false, false),
+                branch("wip", None, true, false),
+            ],
+        };
+
+        let prunable = |gone| {
+            branches
+                .prunable(gone, |name| name.starts_with("release/"))
+                .map(|(branch, reason)| (branch.name.as_str(), reason))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                ("done", PruneReason::Merged),
+                ("review", PruneReason::MergedUpstream)
+            ],
+            prunable(false)
+        );
+        assert_eq!(
+            vec![
+                ("done", PruneReason::Merged),
+                ("old", PruneReason::Gone),
+                ("review", PruneReason::MergedUpstream)
+            ],
+            prunable(true)
+        );
+    }
+
+    #[test]
+    fn test_is_default() {
+        assert!(is_default("main", "origin/main"));
+        assert!(is_default("main", "main"));
+        assert!(!is_default("dev", "origin/main"));
+    }
+}
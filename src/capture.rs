@@ -0,0 +1,368 @@
+//! Capture the output of a command, spilling it to a temporary file when it's too big.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::maintenance::format_size;
+
+/// Past this many bytes the output of a command is spilled to a temporary file.
+pub const DEFAULT_SPILL_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// How many bytes of the beginning and of the end of a spilled output are kept in memory.
+const EXCERPT_LEN: usize = 64 * 1024;
+
+/// What a command wrote to its standard output or error, trimmed.
+///
+/// The bytes are kept as they are: git prints file names and diffs in whatever encoding they're
+/// in, they're only converted to UTF-8 to be displayed.
+///
+/// An output bigger than the spill threshold only keeps an excerpt of its beginning and of its
+/// end in memory, all of it is in an anonymous temporary file which the OS removes when it's
+/// closed, whatever way gitjuggling exits.
+///
+/// It's serialized as a string if it's valid UTF-8, as `{"base64": "..."}` otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawOutput(Repr);
+
+#[derive(Debug, Clone)]
+enum Repr {
+    Memory(Vec<u8>),
+    Spilled(Arc<Spilled>),
+}
+
+impl Default for Repr {
+    fn default() -> Self {
+        Repr::Memory(Vec::new())
+    }
+}
+
+impl PartialEq for Repr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Repr::Memory(a), Repr::Memory(b)) => a == b,
+            (Repr::Spilled(a), Repr::Spilled(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Repr {}
+
+#[derive(Debug)]
+struct Spilled {
+    head: Vec<u8>,
+    tail: Vec<u8>,
+    /// The number of bytes between the head and the tail
+    omitted: u64,
+    file: Mutex<File>,
+}
+
+impl RawOutput {
+    /// Creates an output from the bytes of a command, trimming the whitespace around them.
+    pub fn new(bytes: &[u8]) -> Self {
+        Self(Repr::Memory(bytes.trim_ascii().to_vec()))
+    }
+
+    /// Reads the output of a command until its end, spilling it to a temporary file once it's
+    /// bigger than `spill_threshold` bytes.
+    ///
+    /// The reader is drained even if spilling fails, so that the command doesn't block on a
+    /// full pipe.
+    pub fn capture(mut reader: impl Read, spill_threshold: u64) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        (&mut reader)
+            .take(spill_threshold.saturating_add(1))
+            .read_to_end(&mut bytes)?;
+        if bytes.len() as u64 <= spill_threshold {
+            return Ok(Self::new(&bytes));
+        }
+
+        let excerpt_len = EXCERPT_LEN.min(spill_threshold as usize / 2);
+        let spilled = spill(&mut reader, bytes, excerpt_len);
+        if spilled.is_err() {
+            let _ = io::copy(&mut reader, &mut io::sink());
+        }
+
+        spilled.map(|spilled| Self(Repr::Spilled(Arc::new(spilled))))
+    }
+
+    /// Returns true if the output was spilled to a temporary file.
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.0, Repr::Spilled(_))
+    }
+
+    /// Returns true if the command wrote nothing but whitespace.
+    pub fn is_empty(&self) -> bool {
+        match &self.0 {
+            Repr::Memory(bytes) => bytes.is_empty(),
+            Repr::Spilled(_) => false,
+        }
+    }
+
+    /// Returns the whole output as the command wrote it, read back from the temporary file if
+    /// it was spilled.
+    pub fn read(&self) -> io::Result<Cow<'_, [u8]>> {
+        match &self.0 {
+            Repr::Memory(bytes) => Ok(Cow::Borrowed(bytes)),
+            Repr::Spilled(spilled) => {
+                let mut file = spilled.file.lock().unwrap();
+                file.seek(SeekFrom::Start(0))?;
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes)?;
+
+                Ok(Cow::Owned(bytes.trim_ascii().to_vec()))
+            }
+        }
+    }
+
+    /// Like [`RawOutput::read`] but converted to UTF-8, the invalid sequences replaced with
+    /// U+FFFD. The excerpt is returned if the temporary file can't be read back.
+    pub fn read_lossy(&self) -> Cow<'_, str> {
+        match self.read() {
+            Ok(Cow::Borrowed(bytes)) => String::from_utf8_lossy(bytes),
+            Ok(Cow::Owned(bytes)) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+            Err(_) => self.to_str_lossy(),
+        }
+    }
+
+    /// Returns true if the bytes kept in memory are valid UTF-8.
+    pub fn is_utf8(&self) -> bool {
+        match &self.0 {
+            Repr::Memory(bytes) => std::str::from_utf8(bytes).is_ok(),
+            Repr::Spilled(spilled) => {
+                std::str::from_utf8(&spilled.head).is_ok()
+                    && std::str::from_utf8(&spilled.tail).is_ok()
+            }
+        }
+    }
+
+    /// Returns the output converted to UTF-8, the invalid sequences replaced with U+FFFD.
+    ///
+    /// Only the excerpt of a spilled output is returned, with how much was left out between
+    /// its beginning and its end.
+    pub fn to_str_lossy(&self) -> Cow<'_, str> {
+        match &self.0 {
+            Repr::Memory(bytes) => String::from_utf8_lossy(bytes),
+            Repr::Spilled(spilled) => Cow::Owned(format!(
+                "{}\n… {} omitted …\n{}",
+                String::from_utf8_lossy(&spilled.head),
+                format_size(spilled.omitted),
+                String::from_utf8_lossy(&spilled.tail)
+            )),
+        }
+    }
+}
+
+/// Writes `bytes`, then the rest of `reader`, to a temporary file and keeps `excerpt_len` bytes
+/// of the beginning and of the end.
+fn spill(reader: &mut impl Read, bytes: Vec<u8>, excerpt_len: usize) -> io::Result<Spilled> {
+    let mut file = tempfile::tempfile()?;
+    file.write_all(&bytes)?;
+
+    let head_len = excerpt_len.min(bytes.len());
+    let mut tail = bytes[head_len..].to_vec();
+    let mut len = bytes.len() as u64;
+    let mut buffer = vec![0; EXCERPT_LEN];
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        file.write_all(&buffer[..n])?;
+        len += n as u64;
+
+        tail.extend_from_slice(&buffer[..n]);
+        if tail.len() > 2 * excerpt_len {
+            tail.drain(..tail.len() - excerpt_len);
+        }
+    }
+    if tail.len() > excerpt_len {
+        tail.drain(..tail.len() - excerpt_len);
+    }
+
+    // Cut at line boundaries when there are some
+    let mut head = &bytes[..head_len];
+    if let Some(end) = head.iter().rposition(|byte| *byte == b'\n') {
+        head = &head[..end];
+    }
+    let mut tail = &tail[..];
+    if let Some(start) = tail.iter().position(|byte| *byte == b'\n') {
+        tail = &tail[start + 1..];
+    }
+
+    Ok(Spilled {
+        head: head.trim_ascii_start().to_vec(),
+        omitted: len - head.len() as u64 - tail.len() as u64,
+        tail: tail.trim_ascii_end().to_vec(),
+        file: Mutex::new(file),
+    })
+}
+
+impl From<&str> for RawOutput {
+    fn from(s: &str) -> Self {
+        Self::new(s.as_bytes())
+    }
+}
+
+impl fmt::Display for RawOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_str_lossy())
+    }
+}
+
+/// How [`RawOutput`] is serialized.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawOutputRepr<'a> {
+    Text(Cow<'a, str>),
+    Base64 { base64: String },
+}
+
+impl Serialize for RawOutput {
+    /// A spilled output is read back, all of it is serialized.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.read().map_err(serde::ser::Error::custom)?;
+
+        match std::str::from_utf8(&bytes) {
+            Ok(text) => RawOutputRepr::Text(Cow::Borrowed(text)),
+            Err(_) => RawOutputRepr::Base64 {
+                base64: base64_encode(&bytes),
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawOutput {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = match RawOutputRepr::deserialize(deserializer)? {
+            RawOutputRepr::Text(text) => text.into_owned().into_bytes(),
+            RawOutputRepr::Base64 { base64 } => {
+                base64_decode(&base64).ok_or_else(|| serde::de::Error::custom("invalid base64"))?
+            }
+        };
+
+        Ok(Self(Repr::Memory(bytes)))
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` with the standard base64 alphabet, padded.
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes padded base64, returns `None` if `encoded` isn't.
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    if !encoded.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() / 4 * 3);
+    for chunk in encoded.as_bytes().chunks(4) {
+        let padding = chunk.iter().rev().take_while(|byte| **byte == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, byte) in chunk[..4 - padding].iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|c| c == byte)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        bytes.extend(n.to_be_bytes()[1..4 - padding].iter());
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_output() {
+        let output = RawOutput::new(b"  caf\xe9.txt\n");
+        assert_eq!(&b"caf\xe9.txt"[..], &*output.read().unwrap());
+        assert!(!output.is_utf8());
+        assert_eq!("caf\u{fffd}.txt", output.to_str_lossy());
+
+        let json = serde_json::to_string(&output).unwrap();
+        assert_eq!(r#"{"base64":"Y2Fm6S50eHQ="}"#, json);
+        assert_eq!(output, serde_json::from_str(&json).unwrap());
+
+        let output = RawOutput::from("café");
+        assert_eq!(r#""café""#, serde_json::to_string(&output).unwrap());
+        assert_eq!(
+            output,
+            serde_json::from_str::<RawOutput>(r#""café""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_capture() {
+        let output = RawOutput::capture(&b"short\n"[..], 1024).unwrap();
+        assert!(!output.is_spilled());
+        assert_eq!("short", output.to_str_lossy());
+
+        let lines: String = (0..1000).map(|i| format!("line {:03}\n", i)).collect();
+        let output = RawOutput::capture(lines.as_bytes(), 1024).unwrap();
+        assert!(output.is_spilled());
+        assert_eq!(lines.trim_end().as_bytes(), &*output.read().unwrap());
+        assert_eq!(lines.trim_end(), output.read_lossy());
+
+        // The whole lines of the 512 bytes of each end are kept in memory
+        let excerpt = output.to_str_lossy();
+        assert!(excerpt.starts_with("line 000\nline 001\n"), "{}", excerpt);
+        assert!(
+            excerpt.contains("line 055\n… 7.8 KiB omitted …\nline 944\n"),
+            "{}",
+            excerpt
+        );
+        assert!(excerpt.ends_with("line 998\nline 999"), "{}", excerpt);
+
+        assert!(serde_json::to_string(&output)
+            .unwrap()
+            .ends_with("line 999\""));
+    }
+
+    #[test]
+    fn test_base64() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\xff\xfe\x00", "//4A"),
+        ] {
+            assert_eq!(encoded, base64_encode(bytes));
+            assert_eq!(Some(bytes.to_vec()), base64_decode(encoded));
+        }
+        assert_eq!(None, base64_decode("Zm9"));
+        assert_eq!(None, base64_decode("Zm9!"));
+    }
+}
@@ -0,0 +1,137 @@
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A continuous integration system, its logs need plain output without a spinner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ci {
+    GitHubActions,
+    GitLab,
+    AzurePipelines,
+    Buildkite,
+    /// A system setting CI, or any system with --ci
+    Other,
+}
+
+impl Ci {
+    /// Returns the system gitjuggling runs under: none with --no-ci, the one detected with its
+    /// variables, or another one with --ci.
+    pub fn from_matches(matches: &clap::ArgMatches) -> Option<Self> {
+        let flag = |id: &str| {
+            matches
+                .try_get_one::<bool>(id)
+                .ok()
+                .flatten()
+                .copied()
+                .unwrap_or(false)
+        };
+        if flag("no_ci") {
+            return None;
+        }
+
+        let detected = Self::detect(|name| env::var(name).ok());
+        if flag("ci") {
+            return Some(detected.unwrap_or(Ci::Other));
+        }
+        detected
+    }
+
+    /// Detects the system with the variables it sets, read with `var`.
+    fn detect(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let set = |name: &str| {
+            var(name).is_some_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+        };
+
+        if set("GITHUB_ACTIONS") {
+            Some(Ci::GitHubActions)
+        } else if set("GITLAB_CI") {
+            Some(Ci::GitLab)
+        } else if set("TF_BUILD") {
+            Some(Ci::AzurePipelines)
+        } else if set("BUILDKITE") {
+            Some(Ci::Buildkite)
+        } else if set("CI") {
+            Some(Ci::Other)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ci::GitHubActions => "GitHub Actions",
+            Ci::GitLab => "GitLab CI",
+            Ci::AzurePipelines => "Azure Pipelines",
+            Ci::Buildkite => "Buildkite",
+            Ci::Other => "CI",
+        }
+    }
+
+    /// Wraps `output` in the markers folding it under `title` in the logs, if the system
+    /// supports it. `index` makes the section unique for the systems that need it.
+    pub fn group(&self, title: &str, index: usize, output: &str) -> String {
+        let output = output.trim_end_matches('\n');
+        match self {
+            Ci::GitHubActions => format!("::group::{}\n{}\n::endgroup::\n", title, output),
+            Ci::AzurePipelines => format!("##[group]{}\n{}\n##[endgroup]\n", title, output),
+            Ci::GitLab => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                format!(
+                    "\x1b[0Ksection_start:{now}:gitjuggling_{index}[collapsed=true]\r\x1b[0K{}\n{}\n\
+                     \x1b[0Ksection_end:{now}:gitjuggling_{index}\r\x1b[0K\n",
+                    title,
+                    output,
+                    now = now,
+                    index = index
+                )
+            }
+            // Buildkite folds everything until the next header
+            Ci::Buildkite => format!("--- {}\n{}\n", title, output),
+            Ci::Other => format!("{}\n", output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let detect = |vars: &[(&str, &str)]| {
+            Ci::detect(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+
+        assert_eq!(None, detect(&[]));
+        assert_eq!(None, detect(&[("CI", "false")]));
+        assert_eq!(Some(Ci::Other), detect(&[("CI", "true")]));
+        assert_eq!(
+            Some(Ci::GitHubActions),
+            detect(&[("CI", "true"), ("GITHUB_ACTIONS", "true")])
+        );
+        assert_eq!(Some(Ci::GitLab), detect(&[("GITLAB_CI", "true")]));
+        assert_eq!(Some(Ci::AzurePipelines), detect(&[("TF_BUILD", "True")]));
+    }
+
+    #[test]
+    fn test_group() {
+        assert_eq!(
+            "::group::foo\nfoo executing status\n::endgroup::\n",
+            Ci::GitHubActions.group("foo", 0, "foo executing status\n")
+        );
+        assert_eq!(
+            "foo executing status\n",
+            Ci::Other.group("foo", 0, "foo executing status\n")
+        );
+
+        let gitlab = Ci::GitLab.group("foo", 3, "output\n");
+        assert!(gitlab.contains(":gitjuggling_3[collapsed=true]\r\x1b[0Kfoo\noutput\n"));
+        assert!(gitlab.ends_with(":gitjuggling_3\r\x1b[0K\n"));
+    }
+}
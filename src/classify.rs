@@ -0,0 +1,179 @@
+//! Decide whether a command succeeded.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::ansi::strip_ansi;
+
+/// Git progress lines that are never considered a sign of failure by --fail-on-stderr.
+const PROGRESS_PREFIXES: &[&str] = &[
+    "Enumerating objects",
+    "Counting objects",
+    "Compressing objects",
+    "Delta compression",
+    "Total ",
+    "Receiving objects",
+    "Resolving deltas",
+    "Unpacking objects",
+    "Writing objects",
+    "Updating files",
+    "Checking out files",
+];
+
+/// Decides whether a command succeeded or failed.
+///
+/// By default a command succeeded if it exited with code 0, this can be changed by accepting
+/// other exit codes or by failing if the output matches a regex or if anything was written to stderr.
+pub struct Classifier {
+    fail_regex: Option<Regex>,
+    ok_exit_codes: Vec<i32>,
+    fail_on_stderr: bool,
+}
+
+/// The policy that made a command fail even though its exit code says it succeeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Policy {
+    /// The output matched the fail regex
+    FailRegex,
+    /// The command wrote to stderr and that's not allowed
+    Stderr,
+}
+
+/// The outcome of the classification.
+pub struct Verdict {
+    /// Whether the command succeeded
+    pub success: bool,
+    /// Explains why the outcome differs from what the exit code says
+    pub reason: Option<String>,
+    /// The policy that made the command fail, if any
+    pub policy: Option<Policy>,
+}
+
+impl Default for Classifier {
+    /// Only considers the exit code.
+    fn default() -> Self {
+        Self::new(None, Vec::new(), false)
+    }
+}
+
+impl Classifier {
+    /// Creates a classifier.
+    ///
+    /// A command fails if its output matches `fail_regex`, if it exited with a non-zero code
+    /// not in `ok_exit_codes`, or if `fail_on_stderr` is set and it wrote to stderr.
+    pub fn new(fail_regex: Option<Regex>, ok_exit_codes: Vec<i32>, fail_on_stderr: bool) -> Self {
+        Self {
+            fail_regex,
+            ok_exit_codes,
+            fail_on_stderr,
+        }
+    }
+
+    /// Classifies the outcome of a command.
+    pub fn classify(&self, exit_code: Option<i32>, stdout: &str, stderr: &str) -> Verdict {
+        let verdict = match exit_code {
+            Some(0) => Verdict {
+                success: true,
+                reason: None,
+                policy: None,
+            },
+            Some(code) if self.ok_exit_codes.contains(&code) => Verdict {
+                success: true,
+                reason: Some(format!("exit code {} accepted by --ok-exit-codes", code)),
+                policy: None,
+            },
+            _ => Verdict {
+                success: false,
+                reason: None,
+                policy: None,
+            },
+        };
+
+        // The colors of the output would get in the way of the patterns
+        let stdout = strip_ansi(stdout);
+        let stderr = strip_ansi(stderr);
+        if let Some(re) = &self.fail_regex {
+            if re.is_match(&stdout) || re.is_match(&stderr) {
+                return Verdict {
+                    success: false,
+                    reason: Some(format!("output matched --fail-regex {}", re)),
+                    policy: Some(Policy::FailRegex),
+                };
+            }
+        }
+
+        if verdict.success && self.fail_on_stderr && has_meaningful_stderr(&stderr) {
+            return Verdict {
+                success: false,
+                reason: Some("wrote to stderr with --fail-on-stderr".to_string()),
+                policy: Some(Policy::Stderr),
+            };
+        }
+
+        verdict
+    }
+}
+
+/// Returns true if `stderr` contains anything other than git progress lines.
+fn has_meaningful_stderr(stderr: &str) -> bool {
+    stderr
+        .lines()
+        .flat_map(|line| line.split('\r'))
+        .map(|line| line.trim_start_matches("remote: ").trim())
+        .any(|line| {
+            !line.is_empty()
+                && !PROGRESS_PREFIXES
+                    .iter()
+                    .any(|prefix| line.starts_with(prefix))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let classifier = Classifier::new(
+            Some(Regex::new("^warning: redirecting").unwrap()),
+            vec![1],
+            false,
+        );
+
+        let verdict = classifier.classify(Some(0), "", "");
+        assert!(verdict.success);
+        assert!(verdict.reason.is_none());
+
+        let verdict = classifier.classify(Some(1), "", "");
+        assert!(verdict.success);
+        assert!(verdict.reason.is_some());
+
+        let verdict = classifier.classify(Some(128), "", "");
+        assert!(!verdict.success);
+        assert!(verdict.reason.is_none());
+
+        let verdict = classifier.classify(Some(0), "", "warning: redirecting to foo");
+        assert!(!verdict.success);
+        assert_eq!(Some(Policy::FailRegex), verdict.policy);
+
+        let verdict = classifier.classify(Some(0), "", "\x1b[33mwarning:\x1b[m redirecting to foo");
+        assert_eq!(Some(Policy::FailRegex), verdict.policy);
+    }
+
+    #[test]
+    fn test_classify_fail_on_stderr() {
+        let classifier = Classifier::new(None, vec![], true);
+
+        let stderr = "remote: Enumerating objects: 5, done.\nReceiving objects: 100% (5/5)\rResolving deltas: 100%";
+        let verdict = classifier.classify(Some(0), "", stderr);
+        assert!(verdict.success);
+
+        let verdict = classifier.classify(Some(0), "", "warning: redirecting to foo");
+        assert!(!verdict.success);
+        assert_eq!(Some(Policy::Stderr), verdict.policy);
+
+        let verdict = classifier.classify(Some(1), "", "error: foo");
+        assert!(!verdict.success);
+        assert_eq!(None, verdict.policy);
+    }
+}
@@ -0,0 +1,234 @@
+//! Clone the repositories of a list of URLs, the missing ones only.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::anyhow;
+
+use crate::git::Git;
+use crate::remotes::error_lines;
+
+/// A repository to clone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneTarget {
+    /// The URL as written in the list
+    pub url: String,
+    /// Where to clone it, relative to the directory the repositories are cloned into
+    pub path: PathBuf,
+}
+
+/// What happened to a URL of the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloneUrlOutcome {
+    /// The repository was cloned
+    Cloned,
+    /// A git repository already exists at the target, it was left untouched
+    Present,
+    /// The clone failed, or something other than a repository is in the way
+    Failed {
+        /// The URL that couldn't be cloned
+        url: String,
+        /// What failed
+        error: String,
+    },
+}
+
+impl CloneUrlOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CloneUrlOutcome::Cloned => "cloned",
+            CloneUrlOutcome::Present => "already present",
+            CloneUrlOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for CloneUrlOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloneUrlOutcome::Failed { url, error } => write!(f, "failed, {}: {}", url, error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+/// Returns the directory to clone `url` into: the path of the URL without its `.git` suffix,
+/// like `vrischmann/gitjuggling` for `git@github.com:vrischmann/gitjuggling.git`.
+///
+/// Returns `None` if the URL has no path, or if it would escape the directory.
+pub fn target_dir(url: &str) -> Option<PathBuf> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        // The scp-like syntax, user@host:path
+        None => match url.split_once(':') {
+            Some((authority, path)) if !authority.contains('/') => path,
+            _ => url,
+        },
+    };
+    let path = path.trim_matches('/');
+    let path = path
+        .strip_suffix(".git")
+        .unwrap_or(path)
+        .trim_end_matches('/');
+    // The home directory of a user on the server, ssh://host/~user/repo
+    let path = match path.strip_prefix('~') {
+        Some(rest) => rest.split_once('/').map(|(_, path)| path).unwrap_or(rest),
+        None => path,
+    };
+
+    let path = PathBuf::from(path);
+    let valid = path.components().count() > 0
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+    valid.then_some(path)
+}
+
+/// Parses a list of URLs, one per line. Blank lines and lines starting with `#` are ignored.
+///
+/// Fails if a directory can't be derived from a URL or if two URLs go to the same directory.
+pub fn parse_list(input: &str) -> anyhow::Result<Vec<CloneTarget>> {
+    let mut targets = Vec::new();
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let url = line.trim();
+        if url.is_empty() || url.starts_with('#') {
+            continue;
+        }
+
+        let path = target_dir(url).ok_or_else(|| {
+            anyhow!(
+                "line {}: unable to derive a directory from {}",
+                index + 1,
+                url
+            )
+        })?;
+        if let Some(previous) = seen.insert(path.clone(), index + 1) {
+            return Err(anyhow!(
+                "line {}: {} goes to {} like line {}",
+                index + 1,
+                url,
+                path.display(),
+                previous
+            ));
+        }
+
+        targets.push(CloneTarget {
+            url: url.to_string(),
+            path,
+        });
+    }
+
+    Ok(targets)
+}
+
+/// Clones `target` under `root` unless a repository is already there.
+///
+/// Credentials are never prompted for: with many clones at the same time the prompts would be
+/// unusable.
+pub fn clone(git: &Git, root: &Path, target: &CloneTarget) -> CloneUrlOutcome {
+    let failed = |error: String| CloneUrlOutcome::Failed {
+        url: target.url.clone(),
+        error,
+    };
+
+    let path = root.join(&target.path);
+    if path.join(".git").symlink_metadata().is_ok() {
+        return CloneUrlOutcome::Present;
+    }
+    // git clone accepts an empty directory, anything else is in the way
+    let empty = match path.read_dir() {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => !path.exists(),
+    };
+    if !empty {
+        return failed(format!(
+            "{} exists and isn't a git repository",
+            path.display()
+        ));
+    }
+
+    let output = git
+        .command(root)
+        .args(["clone", "--quiet", "--", &target.url])
+        .arg(&target.path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output();
+    match output {
+        Ok(output) if output.status.success() => CloneUrlOutcome::Cloned,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let error = error_lines(&stderr).next().unwrap_or("git clone failed");
+            failed(error.to_string())
+        }
+        Err(err) => failed(format!(
+            "unable to run {}: {}",
+            git.program().display(),
+            err
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_dir() {
+        let cases = [
+            (
+                "git@github.com:vrischmann/gitjuggling.git",
+                Some("vrischmann/gitjuggling"),
+            ),
+            (
+                "https://github.com/vrischmann/gitjuggling",
+                Some("vrischmann/gitjuggling"),
+            ),
+            ("https://git.example.com/foo.git/", Some("foo")),
+            (
+                "ssh://git@example.com:2222/~jane/dotfiles.git",
+                Some("dotfiles"),
+            ),
+            ("host:/srv/git/foo.git", Some("srv/git/foo")),
+            ("https://example.com/", None),
+            ("https://example.com/../foo", None),
+        ];
+
+        for (url, expected) in cases {
+            assert_eq!(expected.map(PathBuf::from), target_dir(url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let input = "# work\n\
+                     git@github.com:foo/bar.git\n\
+                     \n\
+                     https://gitlab.com/baz/qux\n";
+        assert_eq!(
+            vec![
+                CloneTarget {
+                    url: "git@github.com:foo/bar.git".to_string(),
+                    path: PathBuf::from("foo/bar"),
+                },
+                CloneTarget {
+                    url: "https://gitlab.com/baz/qux".to_string(),
+                    path: PathBuf::from("baz/qux"),
+                },
+            ],
+            parse_list(input).unwrap()
+        );
+
+        let err = parse_list("git@github.com:foo/bar.git\nhttps://gitlab.com/foo/bar\n")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            "line 2: https://gitlab.com/foo/bar goes to foo/bar like line 1",
+            err
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! The apply-all subcommand.
+
+use std::path::PathBuf;
+use std::process;
+
+use gitjuggling::patch::{self, PatchOptions, PatchOutcome};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE, EXIT_USAGE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    // git runs in every repository, the patch must be found from all of them
+    let patch = matches.get_one::<PathBuf>("patch").unwrap();
+    let patch = match patch.canonicalize() {
+        Ok(patch) => patch,
+        Err(err) => {
+            eprintln!("invalid patch {}: {}", patch.display(), err);
+            process::exit(EXIT_USAGE);
+        }
+    };
+    let options = PatchOptions {
+        am: matches.get_flag("am"),
+        branch: matches.get_one::<String>("branch").cloned(),
+        message: matches.get_one::<String>("message").cloned(),
+    };
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut entries: Vec<(String, PatchOutcome)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let outcome = patch::apply(&git, path, &patch, &options);
+            (path_display.display(path), outcome)
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, PatchOutcome::Failed { .. }))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,46 @@
+//! The audit subcommand.
+
+use std::process;
+
+use gitjuggling::audit::Audit;
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut entries: Vec<(String, Result<Audit, String>)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let audit = Audit::probe(&git, path).map_err(|err| err.to_string());
+            (path_display.display(path), audit)
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let show_clean = matches.get_flag("all");
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => print!("{}", overview::render_audit(&entries, show_clean)),
+        Format::Json => print!("{}", overview::render_audit_json(&entries, show_clean)),
+    }
+
+    if entries
+        .iter()
+        .any(|(_, audit)| !audit.as_ref().is_ok_and(Audit::is_clean))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,94 @@
+//! The branches subcommand.
+
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, SystemTime};
+
+use colored::Colorize;
+use gitjuggling::branches::{self, Branches};
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let stale = matches
+        .get_one::<Duration>("stale")
+        .map(|stale| now - stale.as_secs() as i64);
+
+    let mut repositories: Vec<(&PathBuf, String, Result<Branches, String>)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let branches = Branches::list(&git, path)
+                .map(|mut branches| {
+                    if let Some(stale) = stale {
+                        branches.branches.retain(|branch| branch.timestamp <= stale);
+                    }
+                    branches
+                })
+                .map_err(|err| err.to_string());
+            (path, path_display.display(path), branches)
+        })
+        .collect();
+    repositories.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut failed = repositories
+        .iter()
+        .any(|(_, _, branches)| branches.is_err());
+
+    if matches.get_flag("delete_merged") {
+        let dry_run = matches.get_flag("dry_run");
+
+        for (path, name, branches) in &repositories {
+            match branches {
+                Ok(branches) => {
+                    for branch in branches.deletable() {
+                        if dry_run {
+                            println!("{}: would delete {}", name, branch.name);
+                            continue;
+                        }
+                        match branches::delete(&git, path, &branch.name, false) {
+                            Ok(()) => println!("{}: deleted {}", name, branch.name),
+                            Err(err) => {
+                                println!("{}: {}", name, err.to_string().bright_red());
+                                failed = true;
+                            }
+                        }
+                    }
+                }
+                Err(err) => println!("{}: {}", name, err.bright_red()),
+            }
+        }
+    } else {
+        let repositories: Vec<(String, Result<Branches, String>)> = repositories
+            .into_iter()
+            .map(|(_, name, branches)| (name, branches))
+            .collect();
+
+        let format = matches
+            .get_one::<String>("format")
+            .map(|s| s.parse::<Format>().unwrap())
+            .unwrap_or(Format::Text);
+        match format {
+            Format::Text => print!("{}", overview::render_branches(&repositories, now)),
+            Format::Json => print!("{}", overview::render_branches_json(&repositories)),
+        }
+    }
+
+    if failed {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,81 @@
+//! The clone-all subcommand.
+
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::Ordering;
+
+use gitjuggling::clone::{self, CloneTarget, CloneUrlOutcome};
+use gitjuggling::remotes::{self, HostLimiter};
+use rayon::prelude::*;
+
+use crate::ci::Ci;
+use crate::{
+    build_thread_pool, check_git, config_or_exit, default_root, git_from_matches, overview,
+    setting, EXIT_FAILURE, EXIT_USAGE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let path = matches.get_one::<PathBuf>("file").unwrap();
+    let targets = match std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|contents| clone::parse_list(&contents))
+    {
+        Ok(targets) => targets,
+        Err(err) => {
+            eprintln!("invalid list of URLs {}: {}", path.display(), err);
+            process::exit(EXIT_USAGE);
+        }
+    };
+    let into = setting(matches, "root", config.root.clone()).unwrap_or_else(default_root);
+    if let Err(err) = std::fs::create_dir_all(&into) {
+        eprintln!("unable to create {}: {}", into.display(), err);
+        process::exit(EXIT_FAILURE);
+    }
+
+    // Can't use to many threads due to SSH multiplexing
+    build_thread_pool(setting(matches, "jobs", config.jobs).unwrap_or(0));
+    let limiter = HostLimiter::new(*matches.get_one::<usize>("per_host").unwrap());
+
+    // Clones can take a while, tell which ones are done as they complete
+    let progress = io::stderr().is_terminal() && Ci::from_matches(matches).is_none();
+    let done = std::sync::atomic::AtomicUsize::new(0);
+
+    let mut entries: Vec<(String, CloneUrlOutcome)> = targets
+        .par_iter()
+        .map(|target: &CloneTarget| {
+            let outcome = limiter.run(remotes::host(&target.url), || {
+                clone::clone(&git, &into, target)
+            });
+            if progress {
+                let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+                eprintln!(
+                    "[{}/{}] {} {}",
+                    done,
+                    targets.len(),
+                    target.path.display(),
+                    outcome.label()
+                );
+            }
+            (target.path.to_string_lossy().to_string(), outcome)
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    let failed: Vec<&str> = entries
+        .iter()
+        .filter_map(|(_, outcome)| match outcome {
+            CloneUrlOutcome::Failed { url, .. } => Some(url.as_str()),
+            _ => None,
+        })
+        .collect();
+    if !failed.is_empty() {
+        println!("\nFailed URLs:\n{}", failed.join("\n"));
+        process::exit(EXIT_FAILURE);
+    }
+}
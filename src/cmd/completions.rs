@@ -0,0 +1,13 @@
+//! The completions subcommand.
+
+use std::io;
+
+use crate::cli;
+
+pub fn run(matches: &clap::ArgMatches) {
+    let shell = *matches.get_one::<clap_complete::Shell>("shell").unwrap();
+
+    let mut command = cli();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+}
@@ -0,0 +1,171 @@
+//! The config subcommand.
+
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::config::{Config, Layer};
+use crate::{config, load_config, EXIT_FAILURE, EXIT_USAGE};
+
+/// Returns all the layers of settings, from the defaults to the environment variables.
+fn all_config_layers(matches: &clap::ArgMatches) -> Vec<Layer> {
+    let layers = load_config(matches).and_then(|mut layers| {
+        layers.insert(0, Layer::defaults());
+        layers.push(Layer::env()?);
+        Ok(layers)
+    });
+
+    match layers {
+        Ok(layers) => {
+            for warning in layers.iter().flat_map(|layer| &layer.warnings) {
+                eprintln!("warning: {}", warning);
+            }
+            layers
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+/// Returns the config file written by config set and config edit.
+fn writable_config_path(matches: &clap::ArgMatches) -> PathBuf {
+    match matches
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(config::user_config_path)
+    {
+        Some(path) => path,
+        None => {
+            eprintln!(
+                "unable to find the config directory, neither XDG_CONFIG_HOME nor HOME is set"
+            );
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+/// Runs `editor` on `path` with the shell, so that it can have arguments.
+fn run_editor(editor: &str, path: &Path) -> io::Result<process::ExitStatus> {
+    #[cfg(unix)]
+    let mut command = {
+        let mut command = process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{} \"$@\"", editor))
+            .arg(editor)
+            .arg(path);
+        command
+    };
+    #[cfg(not(unix))]
+    let mut command = {
+        let mut command = process::Command::new(editor);
+        command.arg(path);
+        command
+    };
+
+    command.status()
+}
+
+pub fn run(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("show", matches)) => {
+            let layers = all_config_layers(matches);
+            print!("{}", config::render(&config::resolve(&layers)));
+        }
+        Some(("get", matches)) => {
+            let key = matches.get_one::<String>("key").unwrap();
+            let known = config::KEYS.iter().any(|k| {
+                key == k
+                    || key
+                        .strip_prefix(k)
+                        .is_some_and(|rest| rest.starts_with('.'))
+            });
+            if !known {
+                eprintln!(
+                    "unknown key {}, the keys are: {}",
+                    key,
+                    config::KEYS.join(", ")
+                );
+                process::exit(EXIT_USAGE);
+            }
+
+            let layers = all_config_layers(matches);
+            let settings: Vec<_> = config::resolve(&layers)
+                .into_iter()
+                .filter(|setting| setting.key == key || setting.full_key() == *key)
+                .collect();
+            if settings.is_empty() {
+                process::exit(EXIT_FAILURE);
+            }
+
+            for setting in settings {
+                // All the entries of a table are printed with their key
+                if setting.full_key() == *key {
+                    println!("{} # {}", setting.value, setting.source);
+                } else {
+                    println!(
+                        "{} = {} # {}",
+                        setting.full_key(),
+                        setting.value,
+                        setting.source
+                    );
+                }
+            }
+        }
+        Some(("set", matches)) => {
+            let path = writable_config_path(matches);
+            let key = matches.get_one::<String>("key").unwrap();
+            let value = matches.get_one::<String>("value").unwrap();
+
+            if let Err(err) = config::set(&path, key, value) {
+                eprintln!("unable to set {} in {}: {}", key, path.display(), err);
+                process::exit(EXIT_USAGE);
+            }
+        }
+        Some(("edit", matches)) => {
+            let path = writable_config_path(matches);
+            if !path.exists() {
+                let created = path
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|_| std::fs::write(&path, config::DEFAULT_CONFIG));
+                if let Err(err) = created {
+                    eprintln!("unable to create {}: {}", path.display(), err);
+                    process::exit(EXIT_FAILURE);
+                }
+            }
+
+            let editor = env::var("VISUAL")
+                .or_else(|_| env::var("EDITOR"))
+                .unwrap_or_else(|_| "vi".to_string());
+            match run_editor(&editor, &path) {
+                Ok(status) if status.success() => {}
+                Ok(status) => {
+                    eprintln!("{} failed: {}", editor, status);
+                    process::exit(EXIT_FAILURE);
+                }
+                Err(err) => {
+                    eprintln!("unable to run {}: {}", editor, err);
+                    process::exit(EXIT_FAILURE);
+                }
+            }
+
+            // Check the file like it's checked when it's read
+            match Config::load(&path) {
+                Ok(config) => {
+                    for key in config.iter().flat_map(Config::unknown_keys) {
+                        eprintln!("warning: unknown key {} in {}", key, path.display());
+                    }
+                }
+                Err(err) => {
+                    eprintln!("invalid config file {}: {}", path.display(), err);
+                    process::exit(EXIT_USAGE);
+                }
+            }
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,53 @@
+//! The diverged subcommand.
+
+use std::process;
+
+use gitjuggling::RepoStatus;
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut entries: Vec<overview::Entry> = discovery
+        .paths
+        .par_iter()
+        .filter_map(|path| {
+            let status = match RepoStatus::probe(&git, path) {
+                Ok(status) if !status.is_diverged() => return None,
+                Ok(status) => Ok(status),
+                Err(err) => Err(err.to_string()),
+            };
+            Some(overview::Entry {
+                name: path_display.display(path),
+                status,
+                remotes: discovery
+                    .remotes
+                    .as_ref()
+                    .map(|remotes| remotes[path].clone()),
+            })
+        })
+        .collect();
+    overview::sort(&mut entries);
+
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => print!("{}", overview::render_diverged(&entries)),
+        Format::Json => print!("{}", overview::render_json(&entries)),
+    }
+
+    if !entries.is_empty() {
+        process::exit(EXIT_FAILURE);
+    }
+}
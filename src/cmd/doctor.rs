@@ -0,0 +1,26 @@
+//! The doctor subcommand.
+
+use std::process;
+
+use crate::config::{self, Config};
+use crate::{default_root, doctor, git_from_matches, load_config, setting, EXIT_FAILURE};
+
+/// Runs the checks, an invalid config file is reported instead of failing.
+pub fn run(matches: &clap::ArgMatches) {
+    let layers = load_config(matches);
+    let config = match &layers {
+        Ok(layers) => config::merge(layers),
+        Err(_) => Config::default(),
+    };
+    let root = setting(matches, "root", config.root.clone()).unwrap_or_else(default_root);
+
+    let checks = doctor::run(&git_from_matches(matches, &config), &root, &layers);
+    print!("{}", doctor::render(&checks));
+
+    if checks
+        .iter()
+        .any(|check| check.status == doctor::Status::Fail)
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
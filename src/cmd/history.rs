@@ -0,0 +1,173 @@
+//! The history subcommand.
+
+use std::fmt::Write as FmtWrite;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use gitjuggling::{RawOutput, RunResult};
+
+use crate::ci::Ci;
+use crate::paths::PathDisplay;
+use crate::render::{
+    format_failure_details, format_not_attempted_details, format_skipped_details, format_summary,
+};
+use crate::theme::Theme;
+use crate::{
+    config_or_exit, history, output, setup_colors, Item, NotAttempted, Skipped, Status,
+    EXIT_FAILURE, EXIT_USAGE,
+};
+
+/// Runs the history subcommand with its `matches`, the colors follow the `main_matches` of the
+/// command line like for a run.
+pub fn run(main_matches: &clap::ArgMatches, matches: &clap::ArgMatches) {
+    let Some(path) = history::history_path() else {
+        eprintln!("unable to find the data directory, neither XDG_DATA_HOME nor HOME is set");
+        process::exit(EXIT_USAGE);
+    };
+    let runs = match history::load(&path) {
+        Ok(runs) => runs,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_FAILURE);
+        }
+    };
+
+    if let Some(("show", matches)) = matches.subcommand() {
+        let id = *matches.get_one::<u64>("id").unwrap();
+        let Some(run) = runs.iter().find(|run| run.id == id) else {
+            eprintln!("no run {} in {}", id, path.display());
+            process::exit(EXIT_FAILURE);
+        };
+        let config = config_or_exit(main_matches);
+        let theme = setup_colors(main_matches, &config, Ci::from_matches(main_matches));
+        print!("{}", format_run(run, &theme));
+        return;
+    }
+
+    let limit = *matches.get_one::<usize>("limit").unwrap();
+    for run in &runs[runs.len().saturating_sub(limit)..] {
+        let mut counts = format!(
+            "{} ok, {} failed",
+            run.count(Status::Succeeded.name()),
+            run.count(Status::Failed.name())
+        );
+        let skipped = run.count(Status::Skipped(String::new()).name());
+        if skipped > 0 {
+            write!(&mut counts, ", {} skipped", skipped).unwrap();
+        }
+        let not_attempted = run.count(Status::NotAttempted(String::new()).name());
+        if not_attempted > 0 {
+            write!(&mut counts, ", {} not run", not_attempted).unwrap();
+        }
+        if run.interrupted {
+            counts.push_str(", interrupted");
+        }
+
+        println!(
+            "{:>4}  {}  {:<30} {:>8}  git {}",
+            run.id,
+            run.time,
+            counts,
+            output::format_duration(Duration::from_millis(run.duration_ms)),
+            run.args.join(" ")
+        );
+    }
+}
+
+/// Formats a recorded run like it was printed when it ended: the failure details and the
+/// summary.
+fn format_run(run: &history::Run, theme: &Theme) -> String {
+    let path_display = PathDisplay::new(&run.roots, run.roots.len() == 1, false);
+    let mut failed = Vec::new();
+    let mut succeeded = Vec::new();
+    let mut skipped = Vec::new();
+    let mut not_attempted = Vec::new();
+    for entry in &run.repositories {
+        let display = path_display.display(&entry.path);
+        if entry.status == Status::Skipped(String::new()).name() {
+            skipped.push(Skipped {
+                path: entry.path.clone(),
+                display,
+                reason: entry.reason.clone().unwrap_or_default(),
+                in_progress: entry.in_progress,
+                broken: false,
+            });
+            continue;
+        }
+        if entry.status == Status::NotAttempted(String::new()).name() {
+            not_attempted.push(NotAttempted {
+                path: entry.path.clone(),
+                display,
+                reason: entry.reason.clone().unwrap_or_default(),
+            });
+            continue;
+        }
+
+        let success = entry.status == Status::Succeeded.name();
+        let item = Item {
+            result: RunResult {
+                path: entry.path.clone(),
+                branch: None,
+                state: None,
+                broken: None,
+                ahead: None,
+                behind: None,
+                program: PathBuf::from("git"),
+                args: run.args.clone(),
+                config: Vec::new(),
+                env: Vec::new(),
+                success,
+                exit_code: entry.exit_code,
+                signal: None,
+                stdout: RawOutput::from(entry.stdout.as_str()),
+                stderr: RawOutput::from(entry.stderr.as_str()),
+                duration: Duration::from_millis(entry.duration_ms),
+                reason: entry.reason.clone(),
+                policy: None,
+                error: None,
+                priority: Vec::new(),
+                warnings: Vec::new(),
+                transfer: None,
+                step: None,
+                steps: Vec::new(),
+            },
+            display,
+            link: None,
+            prefix: None,
+        };
+        if success {
+            succeeded.push(item);
+        } else {
+            failed.push(item);
+        }
+    }
+
+    let mut output = format!(
+        "run {} of git {} at {}, {}{}\n",
+        run.id,
+        run.args.join(" "),
+        run.time,
+        output::format_duration(Duration::from_millis(run.duration_ms)),
+        if run.interrupted { ", interrupted" } else { "" }
+    );
+    if !failed.is_empty() {
+        output.push_str(&format_failure_details(&failed, theme, None));
+    }
+    if !skipped.is_empty() {
+        output.push_str(&format_skipped_details(&skipped, theme));
+    }
+    if !not_attempted.is_empty() {
+        output.push_str(&format_not_attempted_details(&not_attempted, theme));
+    }
+    output.push_str(&format_summary(
+        &succeeded,
+        None,
+        &failed,
+        &skipped,
+        &not_attempted,
+        theme,
+    ));
+
+    output
+}
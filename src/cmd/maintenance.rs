@@ -0,0 +1,92 @@
+//! The maintenance subcommand.
+
+use std::env;
+use std::process;
+
+use gitjuggling::maintenance::{self, Task, TaskOutcome};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    build_thread_pool, check_git, config_or_exit, discover, git_from_matches, overview, settings,
+    EXIT_FAILURE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let tasks: Vec<Task> = match settings(matches, "task", config.maintenance_tasks.clone()) {
+        // The names were validated with the command line or the config file
+        Some(names) => names.iter().map(|name| name.parse().unwrap()).collect(),
+        None => maintenance::DEFAULT_TASKS.to_vec(),
+    };
+    let min_size = matches.get_one::<u64>("min_size").copied();
+
+    // Before the discovery, it runs on the global pool too
+    build_thread_pool(*matches.get_one::<usize>("jobs").unwrap());
+
+    let discovery = discover(matches, &config);
+
+    if matches.get_flag("schedule") {
+        let program = env::current_exe()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "gitjuggling".to_string());
+        let mut args = vec![program, "maintenance".to_string()];
+        for root in &discovery.roots {
+            args.extend(["--root".to_string(), root.to_string_lossy().to_string()]);
+        }
+        for task in &tasks {
+            args.extend(["--task".to_string(), task.name().to_string()]);
+        }
+        if let Some(min_size) = min_size {
+            args.extend(["--min-size".to_string(), min_size.to_string()]);
+        }
+
+        let command: Vec<String> = args
+            .iter()
+            .map(|arg| shlex::try_quote(arg).map_or_else(|_| arg.clone(), |arg| arg.to_string()))
+            .collect();
+        println!("0 3 * * * {}", command.join(" "));
+        return;
+    }
+
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut entries: Vec<(String, TaskOutcome)> = discovery
+        .paths
+        .par_iter()
+        .flat_map_iter(|path| {
+            let name = path_display.display(path);
+
+            if let Some(min_size) = min_size {
+                match maintenance::git_dir_size(&git, path) {
+                    Ok(size) if size < min_size => {
+                        let reason = format!(".git is {}", maintenance::format_size(size));
+                        return vec![(name, TaskOutcome::Skipped { reason })];
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        let error = err.to_string();
+                        return vec![(name, TaskOutcome::Failed { error })];
+                    }
+                }
+            }
+
+            maintenance::run(&git, path, &tasks)
+                .into_iter()
+                .map(|(task, outcome)| (format!("{} {}", name, task.name()), outcome))
+                .collect()
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, TaskOutcome::Failed { .. }))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,96 @@
+//! The manifest subcommand.
+
+use std::path::PathBuf;
+use std::process;
+
+use colored::Colorize;
+use gitjuggling::manifest::{CloneOutcome, Manifest};
+use gitjuggling::{discover_repositories, DiscoverOptions};
+
+use crate::{
+    check_git, config_or_exit, default_root, exit, git_from_matches, setting, EXIT_DISCOVERY,
+    EXIT_FAILURE, EXIT_USAGE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let Some((_, sub_matches)) = matches.subcommand() else {
+        unreachable!();
+    };
+    let config = config_or_exit(sub_matches);
+    let git = git_from_matches(sub_matches, &config);
+    check_git(&git);
+
+    let root = |matches: &clap::ArgMatches| {
+        setting(matches, "root", config.root.clone()).unwrap_or_else(default_root)
+    };
+
+    match matches.subcommand() {
+        Some(("export", matches)) => {
+            let root = root(matches);
+            let root = match root.canonicalize() {
+                Ok(root) => root,
+                Err(err) => {
+                    let error = format!("invalid root {}: {}", root.display(), err);
+                    eprintln!("{}", error.bright_red());
+                    exit(EXIT_DISCOVERY);
+                }
+            };
+            let options = DiscoverOptions {
+                roots: vec![root.clone()],
+                depth: setting(matches, "depth", config.depth).unwrap_or(3),
+                excludes: config.excludes.clone().unwrap_or_default(),
+                ..DiscoverOptions::default()
+            };
+            let paths = match discover_repositories(&options) {
+                Ok(paths) => paths,
+                Err(err) => {
+                    eprintln!("{}", err.to_string().bright_red());
+                    exit(EXIT_DISCOVERY);
+                }
+            };
+
+            let manifest = Manifest::export(&git, &root, &paths).to_toml();
+            match matches.get_one::<PathBuf>("output") {
+                Some(path) => {
+                    if let Err(err) = std::fs::write(path, manifest) {
+                        eprintln!("unable to write manifest {}: {}", path.display(), err);
+                        process::exit(EXIT_FAILURE);
+                    }
+                }
+                None => print!("{}", manifest),
+            }
+        }
+        Some(("clone", matches)) => {
+            let path = matches.get_one::<PathBuf>("file").unwrap();
+            let manifest = match std::fs::read_to_string(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Manifest::parse(&contents))
+            {
+                Ok(manifest) => manifest,
+                Err(err) => {
+                    eprintln!("invalid manifest {}: {}", path.display(), err);
+                    process::exit(EXIT_USAGE);
+                }
+            };
+
+            let mut failed = 0;
+            for (entry, outcome) in manifest.clone_missing(&git, &root(matches)) {
+                let path = entry.path.to_string_lossy();
+                match outcome {
+                    CloneOutcome::Cloned => println!("{} {}", path, "cloned".bright_green()),
+                    CloneOutcome::Exists => println!("{} already exists, left untouched", path),
+                    CloneOutcome::NoUrl => println!("{} has no URL, skipped", path.bright_yellow()),
+                    CloneOutcome::Failed(err) => {
+                        failed += 1;
+                        println!("{} {}", path, format!("failed: {}", err).bright_red());
+                    }
+                }
+            }
+
+            if failed > 0 {
+                process::exit(EXIT_FAILURE);
+            }
+        }
+        _ => unreachable!(),
+    }
+}
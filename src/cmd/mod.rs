@@ -0,0 +1,27 @@
+//! The subcommands, each in a module of its name with a `run` function taking its matches.
+
+pub mod apply_all;
+pub mod audit;
+pub mod branches;
+pub mod clone_all;
+pub mod completions;
+pub mod config;
+pub mod diverged;
+pub mod doctor;
+pub mod history;
+pub mod maintenance;
+pub mod manifest;
+pub mod off_default;
+pub mod prune_branches;
+pub mod push_all;
+pub mod remotes;
+pub mod sizes;
+pub mod stash_all;
+pub mod status;
+pub mod submodules;
+pub mod switch_all;
+pub mod sync;
+pub mod tag_all;
+pub mod timeline;
+pub mod unstash_all;
+pub mod verify;
@@ -0,0 +1,80 @@
+//! The off-default subcommand.
+
+use std::path::PathBuf;
+use std::process;
+
+use gitjuggling::branches::{self, OffDefault};
+use gitjuggling::switch::{self, SwitchOptions, SwitchOutcome};
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut repositories: Vec<(&PathBuf, String, Result<OffDefault, String>)> = discovery
+        .paths
+        .par_iter()
+        .filter_map(|path| {
+            let off = match branches::off_default(&git, path) {
+                Ok(None) => return None,
+                Ok(Some(off)) => Ok(off),
+                Err(err) => Err(err.to_string()),
+            };
+            Some((path, path_display.display(path), off))
+        })
+        .collect();
+    repositories.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let failed = repositories.iter().any(|(_, _, off)| off.is_err());
+
+    if matches.get_flag("switch_back") {
+        let mut entries: Vec<(String, SwitchOutcome)> = repositories
+            .par_iter()
+            .filter_map(|(path, name, off)| {
+                let off = off.as_ref().ok().filter(|off| off.branch.is_some())?;
+                let options = SwitchOptions {
+                    create_tracking: true,
+                    force: false,
+                };
+                let outcome = switch::switch(&git, path, off.local_default(), options);
+                Some((name.clone(), outcome))
+            })
+            .collect();
+        overview::sort_outcomes(&mut entries);
+        print!("{}", overview::render_outcomes(&entries));
+
+        if failed
+            || entries
+                .iter()
+                .any(|(_, outcome)| matches!(outcome, SwitchOutcome::Failed { .. }))
+        {
+            process::exit(EXIT_FAILURE);
+        }
+        return;
+    }
+
+    let repositories: Vec<(String, Result<OffDefault, String>)> = repositories
+        .into_iter()
+        .map(|(_, name, off)| (name, off))
+        .collect();
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => print!("{}", overview::render_off_default(&repositories)),
+        Format::Json => print!("{}", overview::render_off_default_json(&repositories)),
+    }
+
+    if failed {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,76 @@
+//! The prune-branches subcommand.
+
+use std::process;
+
+use gitjuggling::branches::{self, Branches, Pruned};
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let mut builder = globset::GlobSetBuilder::new();
+    for glob in matches.get_many::<String>("protect").into_iter().flatten() {
+        builder.add(globset::Glob::new(glob).expect("the globs are checked when parsed"));
+    }
+    let protect = builder.build().expect("the globs are valid");
+    let gone = matches.get_flag("gone");
+    let dry_run = matches.get_flag("dry_run");
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut repositories: Vec<(String, Result<Vec<Pruned>, String>)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let pruned = Branches::list(&git, path)
+                .map(|branches| {
+                    branches
+                        .prunable(gone, |name| protect.is_match(name))
+                        .map(|(branch, reason)| {
+                            // The merges were checked against the default branch or the
+                            // upstream, git would only check against HEAD
+                            let error = if dry_run {
+                                None
+                            } else {
+                                branches::delete(&git, path, &branch.name, true)
+                                    .err()
+                                    .map(|err| err.to_string())
+                            };
+                            Pruned {
+                                name: branch.name.clone(),
+                                reason,
+                                error,
+                            }
+                        })
+                        .collect()
+                })
+                .map_err(|err| err.to_string());
+            (path_display.display(path), pruned)
+        })
+        .collect();
+    repositories.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => print!("{}", overview::render_pruned(&repositories, dry_run)),
+        Format::Json => print!("{}", overview::render_pruned_json(&repositories)),
+    }
+
+    let failed = repositories.iter().any(|(_, pruned)| match pruned {
+        Ok(pruned) => pruned.iter().any(|branch| branch.error.is_some()),
+        Err(_) => true,
+    });
+    if failed {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,73 @@
+//! The push-all subcommand.
+
+use std::process;
+
+use gitjuggling::push::{self, PushOptions, PushOutcome};
+use gitjuggling::remotes::{self, HostLimiter};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    build_thread_pool, check_git, config_or_exit, discover, git_from_matches, overview, setting,
+    EXIT_FAILURE, EXIT_USAGE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let options = PushOptions {
+        remote: matches.get_one::<String>("remote").cloned(),
+        refspecs: matches
+            .get_many::<String>("refspecs")
+            .map(|refspecs| refspecs.cloned().collect())
+            .unwrap_or_default(),
+        allow_force: matches.get_flag("allow_force"),
+        set_upstream: matches.get_flag("set_upstream"),
+        dry_run: matches.get_flag("dry_run"),
+    };
+    if let Some(refspec) = options
+        .refspecs
+        .iter()
+        .find(|refspec| push::is_forced(refspec))
+    {
+        if !options.allow_force {
+            eprintln!(
+                "{} forces the push in every repository, pass --allow-force to push anyway",
+                refspec
+            );
+            process::exit(EXIT_USAGE);
+        }
+    }
+
+    // Can't use to many threads due to SSH multiplexing
+    build_thread_pool(setting(matches, "jobs", config.jobs).unwrap_or(0));
+    let limiter = HostLimiter::new(*matches.get_one::<usize>("per_host").unwrap());
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut entries: Vec<(String, PushOutcome)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let outcome = match push::plan(&git, path, &options) {
+                Ok(plan) => limiter.run(remotes::host(&plan.url), || push::run(&git, path, &plan)),
+                Err(outcome) => outcome,
+            };
+            (path_display.display(path), outcome)
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries.iter().any(|(_, outcome)| {
+        matches!(
+            outcome,
+            PushOutcome::Rejected { .. } | PushOutcome::Failed { .. }
+        )
+    }) {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,162 @@
+//! The remotes subcommand.
+
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+use colored::Colorize;
+use gitjuggling::remotes::{self, HostLimiter, RemoteHealth, Rewrite, RewritePlan};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE, EXIT_USAGE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("rewrite", matches)) => run_rewrite(matches),
+        Some(("check", matches)) => run_check(matches),
+        _ => unreachable!(),
+    }
+}
+
+fn run_check(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let timeout = *matches.get_one::<Duration>("timeout").unwrap();
+    let limiter = HostLimiter::new(*matches.get_one::<usize>("per_host").unwrap());
+
+    let mut failed = false;
+    let mut probes = Vec::new();
+    for path in &discovery.paths {
+        let list = match &discovery.remotes {
+            Some(remotes) => Ok(remotes[path].clone()),
+            None => remotes::list(&git, path),
+        };
+        match list {
+            Ok(list) => probes.extend(list.into_iter().map(|(remote, url)| (path, remote, url))),
+            Err(err) => {
+                eprintln!("{}: {}", path_display.display(path), err);
+                failed = true;
+            }
+        }
+    }
+
+    let mut entries: Vec<(String, RemoteHealth)> = probes
+        .par_iter()
+        .map(|(path, remote, url)| {
+            let health = limiter.run(remotes::host(url), || {
+                remotes::check(&git, path, remote, timeout)
+            });
+            (format!("{} {}", path_display.display(path), remote), health)
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if failed || entries.iter().any(|(_, health)| health.is_unreachable()) {
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+fn run_rewrite(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let from = matches.get_one::<String>("from").unwrap().clone();
+    let to = matches.get_one::<String>("to").unwrap().clone();
+    let rewrite = if matches.get_flag("regex") {
+        match regex::Regex::new(&from) {
+            Ok(from) => Rewrite::Regex { from, to },
+            Err(err) => {
+                eprintln!("invalid --from pattern: {}", err);
+                process::exit(EXIT_USAGE);
+            }
+        }
+    } else {
+        Rewrite::Prefix { from, to }
+    };
+    let remote = if matches.get_flag("all_remotes") {
+        None
+    } else {
+        matches.get_one::<String>("remote").map(String::as_str)
+    };
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let mut plans: Vec<(&PathBuf, String, Result<RewritePlan, String>)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let plan = remotes::plan(&git, path, &rewrite, remote).map_err(|err| err.to_string());
+            (path, path_display.display(path), plan)
+        })
+        .collect();
+    plans.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let dry_run = matches.get_flag("dry_run");
+    let mut failed = false;
+    let mut rewritten = 0;
+
+    for (path, name, plan) in &plans {
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(err) => {
+                println!("{}: {}", name, err.bright_red());
+                failed = true;
+                continue;
+            }
+        };
+        for warning in &plan.warnings {
+            eprintln!("warning: {}: {}", name, warning);
+        }
+
+        for change in &plan.changes {
+            let line = format!(
+                "{}: {}{} {} → {}",
+                name,
+                change.remote,
+                if change.push { " (push)" } else { "" },
+                change.old,
+                change.new
+            );
+            if dry_run {
+                println!("{}", line);
+                rewritten += 1;
+                continue;
+            }
+            match remotes::apply(&git, path, change) {
+                Ok(()) => {
+                    println!("{}", line);
+                    rewritten += 1;
+                }
+                Err(err) => {
+                    println!("{}: {}", name, err.to_string().bright_red());
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    println!(
+        "\n{} URLs {}",
+        rewritten,
+        if dry_run {
+            "would be rewritten"
+        } else {
+            "rewritten"
+        }
+    );
+
+    if failed {
+        process::exit(EXIT_FAILURE);
+    }
+}
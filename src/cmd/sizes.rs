@@ -0,0 +1,49 @@
+//! The sizes subcommand.
+
+use std::process;
+
+use gitjuggling::sizes::{self, Sizes};
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+    let largest = matches.get_one::<usize>("largest").copied().unwrap_or(0);
+
+    let mut repositories: Vec<(String, Result<Sizes, String>)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let sizes = sizes::measure(&git, path, largest).map_err(|err| err.to_string());
+            (path_display.display(path), sizes)
+        })
+        .collect();
+    // The largest first, the ones that couldn't be measured last
+    repositories.sort_by_key(|(name, sizes)| {
+        (
+            std::cmp::Reverse(sizes.as_ref().map(Sizes::total).ok()),
+            name.clone(),
+        )
+    });
+
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => print!("{}", overview::render_sizes(&repositories)),
+        Format::Json => print!("{}", overview::render_sizes_json(&repositories)),
+    }
+
+    if repositories.iter().any(|(_, sizes)| sizes.is_err()) {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,96 @@
+//! The stash-all subcommand, and the state file it shares with unstash-all.
+
+use std::path::{Path, PathBuf};
+use std::process;
+
+use gitjuggling::stash::{self, Stash, StashOutcome, StashState};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE, EXIT_USAGE,
+};
+
+/// Returns the state file of stash-all and unstash-all.
+pub fn state_path(matches: &clap::ArgMatches) -> PathBuf {
+    match matches
+        .get_one::<PathBuf>("state_file")
+        .cloned()
+        .or_else(stash::state_path)
+    {
+        Some(path) => path,
+        None => {
+            eprintln!("unable to find the state directory, neither XDG_STATE_HOME nor HOME is set");
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+pub fn load_state(path: &Path) -> StashState {
+    match StashState::load(path) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_FAILURE);
+        }
+    }
+}
+
+pub fn save_state(state: &StashState, path: &Path) {
+    if let Err(err) = state.save(path) {
+        eprintln!("{}", err);
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let state_path = state_path(matches);
+    let mut state = load_state(&state_path);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let outcomes: Vec<(&PathBuf, StashOutcome)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let recorded = state.stashes.iter().find(|stash| &stash.path == path);
+            let outcome = match recorded {
+                Some(stash) => StashOutcome::AlreadyStashed {
+                    id: stash.id.clone(),
+                },
+                None => stash::stash(&git, path),
+            };
+            (path, outcome)
+        })
+        .collect();
+
+    for (path, outcome) in &outcomes {
+        if let StashOutcome::Stashed { id } = outcome {
+            state.stashes.push(Stash {
+                path: path.to_path_buf(),
+                id: id.clone(),
+            });
+        }
+    }
+    state.stashes.sort_by(|a, b| a.path.cmp(&b.path));
+    save_state(&state, &state_path);
+
+    let mut entries: Vec<(String, StashOutcome)> = outcomes
+        .into_iter()
+        .map(|(path, outcome)| (path_display.display(path), outcome))
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, StashOutcome::Failed { .. }))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,138 @@
+//! The status subcommand.
+
+use std::path::PathBuf;
+use std::process;
+use std::time::SystemTime;
+
+use gitjuggling::RepoStatus;
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::snapshot::Snapshot;
+use crate::{
+    check_git, config_or_exit, discover, git_from_matches, overview, snapshot, EXIT_FAILURE,
+    EXIT_USAGE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+
+    let statuses: Vec<(PathBuf, Result<RepoStatus, String>)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let status = RepoStatus::probe(&git, path).map_err(|err| err.to_string());
+            (path.clone(), status)
+        })
+        .collect();
+    let snapshots_dir = || {
+        snapshot::snapshots_dir().unwrap_or_else(|| {
+            eprintln!("unable to find the data directory, neither XDG_DATA_HOME nor HOME is set");
+            process::exit(EXIT_USAGE);
+        })
+    };
+
+    // The diff replaces the usual output
+    if let Some(name) = matches.get_one::<String>("diff") {
+        let before = match snapshot::load(&snapshots_dir(), name) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+        };
+
+        let mut changes: Vec<overview::StatusChange> = statuses
+            .iter()
+            .filter_map(|(path, after)| {
+                let before = before
+                    .repositories
+                    .iter()
+                    .find(|entry| &entry.path == path)
+                    .map(snapshot::Entry::status);
+                (before.as_ref() != Some(after)).then(|| overview::StatusChange {
+                    name: path_display.display(path),
+                    before,
+                    after: Some(after.clone()),
+                })
+            })
+            .collect();
+        changes.extend(
+            before
+                .repositories
+                .iter()
+                .filter(|entry| !statuses.iter().any(|(path, _)| *path == entry.path))
+                .map(|entry| overview::StatusChange {
+                    name: path_display.display(&entry.path),
+                    before: Some(entry.status()),
+                    after: None,
+                }),
+        );
+        changes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        match format {
+            Format::Text => {
+                print!("{}", overview::render_status_diff(&changes));
+                println!(
+                    "{} changed since {} at {}",
+                    changes.len(),
+                    name,
+                    before.time
+                );
+            }
+            Format::Json => print!("{}", overview::render_status_diff_json(&changes)),
+        }
+    }
+
+    if let Some(name) = matches.get_one::<String>("save") {
+        let snapshot = Snapshot {
+            time: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            repositories: statuses
+                .iter()
+                .map(|(path, status)| snapshot::Entry::new(path.clone(), status.clone()))
+                .collect(),
+        };
+        if let Err(err) = snapshot::save(&snapshots_dir(), name, &snapshot) {
+            eprintln!("{}", err);
+            process::exit(EXIT_FAILURE);
+        }
+    }
+
+    let mut entries: Vec<overview::Entry> = statuses
+        .into_iter()
+        .map(|(path, status)| overview::Entry {
+            name: path_display.display(&path),
+            status,
+            remotes: discovery
+                .remotes
+                .as_ref()
+                .map(|remotes| remotes[&path].clone()),
+        })
+        .collect();
+    overview::sort(&mut entries);
+
+    if !matches.contains_id("diff") {
+        match format {
+            Format::Text => print!("{}", overview::render(&entries)),
+            Format::Json => print!("{}", overview::render_json(&entries)),
+        }
+    }
+
+    let failed = entries.iter().any(|entry| match &entry.status {
+        Err(_) => true,
+        Ok(status) => matches.get_flag("check") && (status.is_dirty() || status.is_diverged()),
+    });
+    if failed {
+        process::exit(EXIT_FAILURE);
+    }
+}
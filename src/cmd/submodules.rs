@@ -0,0 +1,62 @@
+//! The submodules subcommand.
+
+use std::path::PathBuf;
+use std::process;
+
+use gitjuggling::submodules::{self, SubmoduleOutcome};
+use gitjuggling::GitModules;
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    build_thread_pool, check_git, config_or_exit, discover, git_from_matches, overview, setting,
+    EXIT_FAILURE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    match matches.subcommand() {
+        Some(("update", matches)) => run_update(matches),
+        _ => unreachable!(),
+    }
+}
+
+fn run_update(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    // Can't use to many threads due to SSH multiplexing
+    build_thread_pool(setting(matches, "jobs", config.jobs).unwrap_or(0));
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    // Only the repositories with submodules, the others have nothing to report
+    let superprojects: Vec<(&PathBuf, &GitModules)> = discovery
+        .paths
+        .iter()
+        .filter_map(|path| {
+            let gitmodules = discovery.gitmodules.get(path)?;
+            Some((path, gitmodules)).filter(|_| !gitmodules.submodules().is_empty())
+        })
+        .collect();
+
+    let mut entries: Vec<(String, SubmoduleOutcome)> = superprojects
+        .par_iter()
+        .flat_map_iter(|(path, gitmodules)| {
+            let name = path_display.display(path);
+            submodules::update(&git, path, gitmodules)
+                .into_iter()
+                .map(move |(submodule, outcome)| (format!("{} {}", name, submodule), outcome))
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, SubmoduleOutcome::Failed { .. }))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
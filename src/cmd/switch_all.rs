@@ -0,0 +1,42 @@
+//! The switch-all subcommand.
+
+use std::process;
+
+use gitjuggling::switch::{self, SwitchOptions, SwitchOutcome};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let branch = matches.get_one::<String>("branch").unwrap();
+    let options = SwitchOptions {
+        create_tracking: matches.get_flag("create_tracking"),
+        force: matches.get_flag("force"),
+    };
+
+    let mut entries: Vec<(String, SwitchOutcome)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let outcome = switch::switch(&git, path, branch, options);
+            (path_display.display(path), outcome)
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, SwitchOutcome::Failed { .. }))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,59 @@
+//! The sync subcommand.
+
+use std::process;
+
+use gitjuggling::sync::{self, SyncOutcome};
+use gitjuggling::Runner;
+
+use crate::paths::PathDisplay;
+use crate::{
+    build_thread_pool, check_git, config_or_exit, discover, git_from_matches, overview, setting,
+    EXIT_FAILURE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    // Can't use to many threads due to SSH multiplexing
+    build_thread_pool(setting(matches, "jobs", config.jobs).unwrap_or(0));
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let runner = Runner::new(sync::FETCH_ARGS)
+        .git(git.clone())
+        .show_branch(false);
+    let mut entries: Vec<(String, SyncOutcome)> = runner.run_with(&discovery.paths, |_, result| {
+        let result = result?;
+        let outcome = if result.success {
+            sync::fast_forward(&git, &result.path)
+        } else {
+            let error = result
+                .stderr
+                .to_str_lossy()
+                .lines()
+                .last()
+                .map(str::to_string);
+            SyncOutcome::Failed {
+                error: format!(
+                    "git fetch {}{}",
+                    result.failure_reason(),
+                    error.map(|line| format!(": {}", line)).unwrap_or_default()
+                ),
+            }
+        };
+
+        Some((path_display.display(&result.path), outcome))
+    });
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| matches!(outcome, SyncOutcome::Failed { .. }))
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
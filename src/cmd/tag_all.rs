@@ -0,0 +1,113 @@
+//! The tag-all subcommand.
+
+use std::path::PathBuf;
+use std::process;
+
+use gitjuggling::remotes::{self, HostLimiter};
+use gitjuggling::tag::{self, TagOptions, TagOutcome};
+use gitjuggling::{RunResult, Runner};
+use rayon::prelude::*;
+
+use crate::paths::PathDisplay;
+use crate::{
+    build_thread_pool, check_git, config_or_exit, discover, git_from_matches, overview, setting,
+    EXIT_FAILURE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    // Can't use to many threads due to SSH multiplexing
+    build_thread_pool(setting(matches, "jobs", config.jobs).unwrap_or(0));
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let limiter = HostLimiter::new(*matches.get_one::<usize>("per_host").unwrap());
+
+    let name = matches.get_one::<String>("tag").unwrap();
+    let options = TagOptions {
+        message: matches.get_one::<String>("message").cloned(),
+        sign: matches.get_flag("sign"),
+        force: matches.get_flag("force"),
+    };
+
+    let mut outcomes: Vec<(PathBuf, TagOutcome)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| (path.clone(), tag::tag(&git, path, name, &options)))
+        .collect();
+
+    if let Some(remote) = matches.get_one::<String>("push") {
+        let tagged: Vec<PathBuf> = outcomes
+            .iter()
+            .filter(|(_, outcome)| {
+                matches!(
+                    outcome,
+                    TagOutcome::Created | TagOutcome::AlreadyPresent | TagOutcome::Moved { .. }
+                )
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let refspec = format!("refs/tags/{}", name);
+        let mut push_args = vec!["push", "--quiet"];
+        if options.force {
+            push_args.push("--force");
+        }
+        push_args.extend([remote.as_str(), refspec.as_str()]);
+
+        let runner = Runner::new(&push_args).git(git.clone()).show_branch(false);
+        let results: Vec<RunResult> = tagged
+            .par_iter()
+            .flat_map_iter(|path| {
+                // The remote is a name or a URL
+                let url = git
+                    .config(path, &format!("remote.{}.pushurl", remote))
+                    .or_else(|| git.config(path, &format!("remote.{}.url", remote)))
+                    .unwrap_or_else(|| remote.clone());
+                limiter.run(remotes::host(&url), || {
+                    runner.run(std::slice::from_ref(path))
+                })
+            })
+            .collect();
+        for result in results {
+            if result.success {
+                continue;
+            }
+            let error = result
+                .stderr
+                .to_str_lossy()
+                .lines()
+                .last()
+                .map(str::to_string);
+            if let Some((_, outcome)) = outcomes.iter_mut().find(|(path, _)| *path == result.path) {
+                *outcome = TagOutcome::Failed {
+                    error: format!(
+                        "tagged, but git push {}{}",
+                        result.failure_reason(),
+                        error.map(|line| format!(": {}", line)).unwrap_or_default()
+                    ),
+                };
+            }
+        }
+    }
+
+    let mut entries: Vec<(String, TagOutcome)> = outcomes
+        .into_iter()
+        .map(|(path, outcome)| (path_display.display(&path), outcome))
+        .collect();
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries.iter().any(|(_, outcome)| {
+        matches!(
+            outcome,
+            TagOutcome::Conflict { .. } | TagOutcome::Failed { .. }
+        )
+    }) {
+        process::exit(EXIT_FAILURE);
+    }
+}
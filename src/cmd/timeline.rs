@@ -0,0 +1,68 @@
+//! The timeline subcommand.
+
+use std::process;
+
+use gitjuggling::timeline::{self, Commit, LogOptions};
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let options = LogOptions {
+        since: matches.get_one::<String>("since").cloned(),
+        until: matches.get_one::<String>("until").cloned(),
+        author: matches.get_one::<String>("author").cloned(),
+        all: matches.get_flag("all"),
+    };
+    let logs: Vec<_> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            (
+                path_display.display(path),
+                timeline::log(&git, path, &options),
+            )
+        })
+        .collect();
+
+    let mut failed = false;
+    let mut commits: Vec<(String, Commit)> = Vec::new();
+    for (name, log) in logs {
+        match log {
+            Ok(log) => commits.extend(log.into_iter().map(|commit| (name.clone(), commit))),
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                failed = true;
+            }
+        }
+    }
+    commits.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp).then(a.0.cmp(&b.0)));
+
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => {
+            let group = matches
+                .get_one::<String>("group_by")
+                .map(|s| s.parse::<overview::TimelineGroup>().unwrap())
+                .unwrap_or(overview::TimelineGroup::None);
+            print!("{}", overview::render_timeline(&commits, group));
+        }
+        Format::Json => print!("{}", overview::render_timeline_json(&commits)),
+    }
+
+    if failed {
+        process::exit(EXIT_FAILURE);
+    }
+}
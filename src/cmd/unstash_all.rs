@@ -0,0 +1,63 @@
+//! The unstash-all subcommand.
+
+use std::process;
+
+use gitjuggling::stash::{self, Stash, UnstashOutcome};
+use rayon::prelude::*;
+
+use super::stash_all;
+use crate::paths::PathDisplay;
+use crate::{check_git, config_or_exit, discover, git_from_matches, overview, EXIT_FAILURE};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let state_path = stash_all::state_path(matches);
+    let mut state = stash_all::load_state(&state_path);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+
+    let (found, others): (Vec<Stash>, Vec<Stash>) = state
+        .stashes
+        .drain(..)
+        .partition(|stash| discovery.paths.contains(&stash.path));
+
+    let outcomes: Vec<(Stash, UnstashOutcome)> = found
+        .into_par_iter()
+        .map(|stash| {
+            let outcome = stash::unstash(&git, &stash.path, &stash.id);
+            (stash, outcome)
+        })
+        .collect();
+
+    state.stashes = others;
+    if !state.stashes.is_empty() {
+        eprintln!(
+            "warning: {} recorded stashes are in repositories not found under the roots, they stay recorded",
+            state.stashes.len()
+        );
+    }
+    let mut entries = Vec::new();
+    for (stash, outcome) in outcomes {
+        let name = path_display.display(&stash.path);
+        if outcome != UnstashOutcome::Restored {
+            state.stashes.push(stash);
+        }
+        entries.push((name, outcome));
+    }
+    state.stashes.sort_by(|a, b| a.path.cmp(&b.path));
+    stash_all::save_state(&state, &state_path);
+
+    overview::sort_outcomes(&mut entries);
+    print!("{}", overview::render_outcomes(&entries));
+
+    if entries
+        .iter()
+        .any(|(_, outcome)| *outcome != UnstashOutcome::Restored)
+    {
+        process::exit(EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,56 @@
+//! The verify subcommand.
+
+use std::process;
+
+use gitjuggling::verify::{self, Signature};
+use rayon::prelude::*;
+
+use crate::output::Format;
+use crate::paths::PathDisplay;
+use crate::{
+    check_git, config_or_exit, config_skips, discover, git_from_matches, overview,
+    relative_to_root, EXIT_FAILURE,
+};
+
+pub fn run(matches: &clap::ArgMatches) {
+    let config = config_or_exit(matches);
+    let git = git_from_matches(matches, &config);
+    check_git(&git);
+
+    let discovery = discover(matches, &config);
+    let path_display = PathDisplay::new(&discovery.roots, true, false);
+    let exempted = config_skips(&config.repos, "verify");
+
+    let mut entries: Vec<(String, Signature)> = discovery
+        .paths
+        .par_iter()
+        .map(|path| {
+            let signature = if exempted.is_match(relative_to_root(&discovery.roots, path)) {
+                Signature::Exempted
+            } else {
+                verify::verify(&git, path)
+            };
+            (path_display.display(path), signature)
+        })
+        .collect();
+    overview::sort_outcomes(&mut entries);
+
+    let format = matches
+        .get_one::<String>("format")
+        .map(|s| s.parse::<Format>().unwrap())
+        .unwrap_or(Format::Text);
+    match format {
+        Format::Text => print!("{}", overview::render_outcomes(&entries)),
+        Format::Json => print!("{}", overview::render_signatures_json(&entries)),
+    }
+
+    let allowed = |signature: &Signature| match signature {
+        Signature::Trusted { .. } | Signature::Exempted => true,
+        Signature::UnknownKey { .. } => matches.get_flag("allow_unknown_key"),
+        Signature::Unsigned { .. } => matches.get_flag("allow_unsigned"),
+        Signature::Error { .. } => false,
+    };
+    if !entries.iter().all(|(_, signature)| allowed(signature)) {
+        process::exit(EXIT_FAILURE);
+    }
+}
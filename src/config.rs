@@ -0,0 +1,844 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use gitjuggling::maintenance::Task;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::theme::{self, ThemeName};
+
+/// The name of the project config file, looked up at the root.
+pub const PROJECT_CONFIG_FILE: &str = ".gitjuggling.toml";
+
+/// The keys of the settings, in the order they're shown.
+pub const KEYS: &[&str] = &[
+    "root",
+    "depth",
+    "jobs",
+    "path_jobs",
+    "excludes",
+    "theme",
+    "colors",
+    "git",
+    "ssh_command",
+    "maintenance_tasks",
+    "history",
+    "groups",
+    "aliases",
+    "repos",
+];
+
+/// Written by config edit when there's no config file yet.
+pub const DEFAULT_CONFIG: &str = r#"# The settings of gitjuggling, the command line flags override them.
+
+# Searched when no --root is given
+# root = "~/src"
+
+# How many directory levels below the root are searched
+# depth = 3
+
+# How many commands run at the same time, 0 is one per CPU
+# jobs = 0
+
+# How many commands run at the same time under some directories, like the mount of a slow disk
+# [path_jobs]
+# "/mnt/nas" = 2
+
+# Glob patterns of the repositories to ignore, relative to the root
+# excludes = ["archive/*"]
+
+# light, dark or plain
+# theme = "dark"
+
+# The colors replacing the ones of the theme for path, command, stdout, stderr and summary: a
+# name like bright blue, or #rrggbb, the closest name without a truecolor terminal
+# [colors]
+# path = "cyan"
+
+# The git program and the SSH command it uses
+# git = "git"
+# ssh_command = "ssh -o ControlMaster=auto"
+
+# The tasks of gitjuggling maintenance: gc, maintenance, prune, repack and commit-graph
+# maintenance_tasks = ["gc", "prune"]
+
+# Record the runs for gitjuggling history, in $XDG_DATA_HOME/gitjuggling/history.jsonl
+# history = true
+
+# Groups of repositories selected with --group NAME
+# [groups]
+# work = ["work/*"]
+
+# Aliases run with @NAME
+# [aliases]
+# sync = ["fetch", "--all", "--prune"]
+
+# How the arguments change in the repositories matching a glob, and the subcommands skipping them
+# [repos."vendor/*"]
+# append = ["--no-verify"]
+# skip = ["verify"]
+
+# Profiles selected with --profile NAME, they can set root, depth, excludes, jobs, path_jobs and
+# groups
+# [profile.work]
+# root = "~/work"
+# depth = 2
+"#;
+
+/// The settings a project config file can define.
+///
+/// It's usually committed in a shared checkout: it can't run other programs or change settings
+/// that are a matter of taste.
+const PROJECT_KEYS: &[&str] = &["depth", "excludes", "groups", "aliases", "repos"];
+
+/// The settings a profile of the user config file can define.
+const PROFILE_KEYS: &[&str] = &["root", "depth", "excludes", "jobs", "path_jobs", "groups"];
+
+/// The defaults read from a config file, every setting is optional.
+///
+/// Command line flags override them, and so do the environment variables of the flags.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Config {
+    /// Searched when no --root is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    /// Directories and how many commands run at the same time in the repositories under them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub path_jobs: BTreeMap<PathBuf, usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excludes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Roles and the colors replacing the ones of the theme
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub colors: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_command: Option<String>,
+    /// Run by gitjuggling maintenance when no --task is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance_tasks: Option<Vec<String>>,
+    /// Whether the runs are recorded for gitjuggling history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<bool>,
+    /// Names of groups of repositories and the globs of their paths relative to the root
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Names of aliases and the arguments they expand to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, Vec<String>>,
+    /// Globs of repository paths relative to the root and how their git arguments change,
+    /// they apply in the order they're declared
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub repos: IndexMap<String, RepoOverride>,
+    /// Names of profiles and their settings, overriding the others when selected with --profile
+    #[serde(default, rename = "profile", skip_serializing)]
+    pub profiles: BTreeMap<String, Config>,
+    /// Keys that aren't settings, they're reported as warnings
+    #[serde(flatten, skip_serializing)]
+    unknown: toml::Table,
+}
+
+/// Changes the git arguments of the repositories matching a glob.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RepoOverride {
+    /// Replaces the arguments given on the command line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace: Option<Vec<String>>,
+    /// Appended to the arguments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub append: Vec<String>,
+    /// The subcommands leaving the repositories out, like verify
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skip: Vec<String>,
+    #[serde(flatten, skip_serializing)]
+    unknown: toml::Table,
+}
+
+impl Config {
+    /// Parses and validates the contents of a config file.
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut config: Config = toml::from_str(contents)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn validate(&mut self) -> anyhow::Result<()> {
+        if let Some(theme) = &self.theme {
+            theme.parse::<ThemeName>()?;
+        }
+        for (role, color) in &self.colors {
+            if !theme::ROLES.contains(&role.as_str()) {
+                return Err(anyhow!(
+                    "unknown color role {}, the roles are {}",
+                    role,
+                    theme::ROLES.join(", ")
+                ));
+            }
+            theme::parse_color(color).map_err(|err| anyhow!("colors.{}: {}", role, err))?;
+        }
+        for task in self.maintenance_tasks.iter().flatten() {
+            task.parse::<Task>()?;
+        }
+        let groups = self.groups.values().flatten();
+        let repos = self.repos.keys();
+        for pattern in self.excludes.iter().flatten().chain(groups).chain(repos) {
+            globset::Glob::new(pattern)
+                .map_err(|err| anyhow!("invalid pattern {}: {}", pattern, err))?;
+        }
+        self.root = self.root.take().map(|root| expand_tilde(&root));
+        self.path_jobs = mem::take(&mut self.path_jobs)
+            .into_iter()
+            .map(|(path, jobs)| (expand_tilde(&path), jobs))
+            .collect();
+
+        for (name, profile) in &mut self.profiles {
+            profile
+                .validate()
+                .map_err(|err| anyhow!("profile {}: {}", name, err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads the config file at `path`, returns None if it doesn't exist.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Returns the keys of the file that aren't settings.
+    pub fn unknown_keys(&self) -> impl Iterator<Item = &str> {
+        self.unknown.keys().map(String::as_str)
+    }
+
+    /// Removes the settings whose key isn't in `keys`, returns the keys removed.
+    fn retain(&mut self, keys: &[&str]) -> Vec<&'static str> {
+        let drop = |key: &str| !keys.contains(&key);
+        let mut removed = Vec::new();
+
+        if drop("root") && self.root.take().is_some() {
+            removed.push("root");
+        }
+        if drop("depth") && self.depth.take().is_some() {
+            removed.push("depth");
+        }
+        if drop("jobs") && self.jobs.take().is_some() {
+            removed.push("jobs");
+        }
+        if drop("path_jobs") && !mem::take(&mut self.path_jobs).is_empty() {
+            removed.push("path_jobs");
+        }
+        if drop("excludes") && self.excludes.take().is_some() {
+            removed.push("excludes");
+        }
+        if drop("theme") && self.theme.take().is_some() {
+            removed.push("theme");
+        }
+        if drop("colors") && !mem::take(&mut self.colors).is_empty() {
+            removed.push("colors");
+        }
+        if drop("git") && self.git.take().is_some() {
+            removed.push("git");
+        }
+        if drop("ssh_command") && self.ssh_command.take().is_some() {
+            removed.push("ssh_command");
+        }
+        if drop("maintenance_tasks") && self.maintenance_tasks.take().is_some() {
+            removed.push("maintenance_tasks");
+        }
+        if drop("history") && self.history.take().is_some() {
+            removed.push("history");
+        }
+        if drop("groups") && !mem::take(&mut self.groups).is_empty() {
+            removed.push("groups");
+        }
+        if drop("aliases") && !mem::take(&mut self.aliases).is_empty() {
+            removed.push("aliases");
+        }
+        if drop("repos") && !mem::take(&mut self.repos).is_empty() {
+            removed.push("repos");
+        }
+        if drop("profile") && !mem::take(&mut self.profiles).is_empty() {
+            removed.push("profile");
+        }
+
+        removed
+    }
+}
+
+/// Where the value of a setting comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    User(PathBuf),
+    /// A profile of the user config file
+    Profile(String, PathBuf),
+    Project(PathBuf),
+    Env,
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::User(path) => write!(f, "user config {}", path.display()),
+            Source::Profile(name, path) => {
+                write!(f, "profile {} of user config {}", name, path.display())
+            }
+            Source::Project(path) => write!(f, "project config {}", path.display()),
+            Source::Env => write!(f, "environment"),
+            Source::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// The settings from one source.
+#[derive(Debug)]
+pub struct Layer {
+    pub source: Source,
+    pub config: Config,
+    /// Problems found while loading the settings that didn't prevent it
+    pub warnings: Vec<String>,
+}
+
+impl Layer {
+    fn new(source: Source, config: Config) -> Self {
+        let mut warnings: Vec<String> = config
+            .unknown_keys()
+            .map(|key| format!("unknown key {} in {}", key, source))
+            .collect();
+        for (glob, repo) in &config.repos {
+            warnings.extend(repo.unknown.keys().map(|key| {
+                format!(
+                    "unknown key {} in repos.{} of {}",
+                    key,
+                    toml::Value::from(glob.as_str()),
+                    source
+                )
+            }));
+        }
+
+        Self {
+            source,
+            config,
+            warnings,
+        }
+    }
+
+    /// Creates a layer keeping only the settings in `keys`, the others are warned about.
+    fn restricted(source: Source, mut config: Config, keys: &[&str]) -> Self {
+        let removed = config.retain(keys);
+        let mut layer = Self::new(source, config);
+        for key in removed {
+            layer.warnings.push(format!(
+                "{} can't be set in {}, only {} can",
+                key,
+                layer.source,
+                keys.join(", ")
+            ));
+        }
+
+        layer
+    }
+
+    /// Loads the user config file, in $XDG_CONFIG_HOME or ~/.config.
+    pub fn user() -> anyhow::Result<Option<Self>> {
+        let Some(path) = user_config_path() else {
+            return Ok(None);
+        };
+
+        load(&path)?
+            .map(|config| Ok(Self::new(Source::User(path), config)))
+            .transpose()
+    }
+
+    /// Loads the project config file of `root`.
+    pub fn project(root: &Path) -> anyhow::Result<Option<Self>> {
+        let path = root.join(PROJECT_CONFIG_FILE);
+        let Some(config) = load(&path)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self::restricted(
+            Source::Project(path),
+            config,
+            PROJECT_KEYS,
+        )))
+    }
+
+    /// Takes the profile `name` out of `user`, the layer of the user config file if there's one.
+    ///
+    /// The profile is a layer of its own that overrides the user config file.
+    pub fn profile(user: Option<&mut Layer>, name: &str) -> anyhow::Result<Self> {
+        let (path, profiles) = match user {
+            Some(Layer {
+                source: Source::User(path),
+                config,
+                ..
+            }) => (path.clone(), &mut config.profiles),
+            _ => {
+                return Err(anyhow!(
+                    "unknown profile {}, there's no user config file",
+                    name
+                ))
+            }
+        };
+
+        match profiles.remove(name) {
+            Some(profile) => Ok(Self::restricted(
+                Source::Profile(name.to_string(), path),
+                profile,
+                PROFILE_KEYS,
+            )),
+            None if profiles.is_empty() => Err(anyhow!(
+                "unknown profile {}, {} doesn't define any",
+                name,
+                path.display()
+            )),
+            None => {
+                let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                Err(anyhow!(
+                    "unknown profile {}, the profiles are: {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Loads the config file given with --config, it replaces the project config file.
+    pub fn file(path: &Path) -> anyhow::Result<Self> {
+        match load(path)? {
+            Some(config) => Ok(Self::new(Source::Project(path.to_path_buf()), config)),
+            None => Err(anyhow!("config file {} doesn't exist", path.display())),
+        }
+    }
+
+    /// Returns the values used when no source sets them.
+    pub fn defaults() -> Self {
+        let config = Config {
+            root: Some(PathBuf::from(".")),
+            depth: Some(3),
+            jobs: Some(0),
+            excludes: Some(Vec::new()),
+            theme: Some("dark".to_string()),
+            git: Some(PathBuf::from("git")),
+            history: Some(true),
+            ..Config::default()
+        };
+
+        Self::new(Source::Default, config)
+    }
+
+    /// Returns the settings set with the environment variables of the flags.
+    pub fn env() -> anyhow::Result<Self> {
+        fn var<T: std::str::FromStr>(name: &str) -> anyhow::Result<Option<T>>
+        where
+            T::Err: fmt::Display,
+        {
+            match env::var(name) {
+                Ok(value) => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|err| anyhow!("invalid {}: {}", name, err)),
+                Err(_) => Ok(None),
+            }
+        }
+
+        let theme: Option<String> = var("GITJUGGLING_THEME")?;
+        if let Some(theme) = &theme {
+            theme
+                .parse::<ThemeName>()
+                .map_err(|err| anyhow!("invalid GITJUGGLING_THEME: {}", err))?;
+        }
+
+        let config = Config {
+            root: var("GITJUGGLING_ROOT")?,
+            depth: var("GITJUGGLING_DEPTH")?,
+            jobs: var("GITJUGGLING_JOBS")?,
+            theme,
+            git: var("GITJUGGLING_GIT")?,
+            ..Config::default()
+        };
+
+        Ok(Self::new(Source::Env, config))
+    }
+
+    /// Returns the settings given with flags on the command line.
+    pub fn command_line(config: Config) -> Self {
+        Self::new(Source::CommandLine, config)
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<Option<Config>> {
+    Config::load(path).map_err(|err| anyhow!("invalid config file {}: {}", path.display(), err))
+}
+
+/// The effective value of a setting.
+#[derive(Debug)]
+pub struct Setting<'a> {
+    pub key: &'static str,
+    /// The name of the group or alias for the settings that are tables
+    pub name: Option<String>,
+    pub value: toml::Value,
+    pub source: &'a Source,
+}
+
+impl Setting<'_> {
+    /// Returns the key as written in a config file, like groups.work.
+    pub fn full_key(&self) -> String {
+        match &self.name {
+            Some(name) if is_bare_key(name) => format!("{}.{}", self.key, name),
+            Some(name) => format!("{}.{}", self.key, toml::Value::from(name.as_str())),
+            None => self.key.to_string(),
+        }
+    }
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns the value of every setting and its layer, later layers override earlier ones.
+///
+/// Groups and aliases are overridden one by one.
+pub fn resolve(layers: &[Layer]) -> Vec<Setting<'_>> {
+    let mut settings: Vec<Setting> = Vec::new();
+
+    for layer in layers {
+        let toml::Value::Table(table) = toml::Value::try_from(&layer.config).unwrap() else {
+            unreachable!("a config always serializes to a table");
+        };
+
+        for (key, value) in table {
+            let Some(key) = KEYS.iter().copied().find(|k| *k == key) else {
+                continue;
+            };
+
+            let entries = match value {
+                toml::Value::Table(entries) => entries
+                    .into_iter()
+                    .map(|(name, value)| (Some(name), value))
+                    .collect(),
+                value => vec![(None, value)],
+            };
+            for (name, value) in entries {
+                let setting = Setting {
+                    key,
+                    name,
+                    value,
+                    source: &layer.source,
+                };
+
+                match settings
+                    .iter_mut()
+                    .find(|s| s.key == setting.key && s.name == setting.name)
+                {
+                    Some(existing) => *existing = setting,
+                    None => settings.push(setting),
+                }
+            }
+        }
+    }
+
+    // The names keep the order of their declaration, it matters for the repos
+    settings.sort_by_key(|setting| KEYS.iter().position(|key| *key == setting.key));
+    settings
+}
+
+/// Merges the layers into a single config, later layers override earlier ones.
+pub fn merge(layers: &[Layer]) -> Config {
+    let mut table = toml::Table::new();
+    for setting in resolve(layers) {
+        match setting.name {
+            Some(name) => {
+                let entries = table
+                    .entry(setting.key)
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                if let toml::Value::Table(entries) = entries {
+                    entries.insert(name, setting.value);
+                }
+            }
+            None => {
+                table.insert(setting.key.to_string(), setting.value);
+            }
+        }
+    }
+
+    toml::Value::Table(table)
+        .try_into()
+        .expect("the settings of config files always form a config")
+}
+
+/// Renders the settings as a TOML document, with the source of each as a comment.
+pub fn render(settings: &[Setting<'_>]) -> String {
+    let mut output = String::new();
+    for setting in settings {
+        writeln!(
+            &mut output,
+            "{} = {} # {}",
+            setting.full_key(),
+            setting.value,
+            setting.source
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Sets `key`, a dotted path like groups.work, to `value` in the config file at `path`.
+///
+/// `value` is parsed as a TOML value, or taken as a string if it isn't one. The file keeps its
+/// comments and formatting, and it's only written if it's still a valid config.
+pub fn set(path: &Path, key: &str, value: &str) -> anyhow::Result<()> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let mut document: toml_edit::DocumentMut = contents
+        .parse()
+        .map_err(|err| anyhow!("invalid config file {}: {}", path.display(), err))?;
+
+    let segments: Vec<&str> = key.split('.').collect();
+    let (last, parents) = segments.split_last().unwrap();
+    let mut table: &mut dyn toml_edit::TableLike = document.as_table_mut();
+    for segment in parents {
+        table = table
+            .entry(segment)
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow!("{} isn't a table", segment))?;
+    }
+    let mut value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| value.into());
+    match table.get_mut(last).and_then(toml_edit::Item::as_value_mut) {
+        // Replacing the value only keeps the comments around the key
+        Some(existing) => {
+            *value.decor_mut() = existing.decor().clone();
+            *existing = value;
+        }
+        None => {
+            table.insert(last, toml_edit::value(value));
+        }
+    }
+
+    let contents = document.to_string();
+    let config = Config::parse(&contents).map_err(|err| anyhow!("invalid value: {}", err))?;
+    let unknown = match segments.as_slice() {
+        ["profile", name, key, ..] => config.profiles[*name].unknown.contains_key(*key),
+        [key, ..] => config.unknown.contains_key(*key),
+        [] => unreachable!(),
+    };
+    if unknown {
+        return Err(anyhow!("unknown key {}", key));
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
+
+/// Returns the path of the user config file, in $XDG_CONFIG_HOME or ~/.config.
+pub fn user_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_home.join("gitjuggling").join("config.toml"))
+}
+
+/// Replaces a leading ~ with the home directory.
+fn expand_tilde(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            r#"
+depth = 2
+jobs = 4
+excludes = ["archive/*"]
+theme = "plain"
+colour = "never"
+
+[path_jobs]
+"/mnt/nas" = 2
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(2), config.depth);
+        assert_eq!(Some(4), config.jobs);
+        assert_eq!(Some(vec!["archive/*".to_string()]), config.excludes);
+        assert_eq!(Some("plain"), config.theme.as_deref());
+        assert_eq!(Some(&2), config.path_jobs.get(Path::new("/mnt/nas")));
+        assert_eq!(vec!["colour"], config.unknown_keys().collect::<Vec<_>>());
+
+        assert!(Config::parse("theme = \"blue\"").is_err());
+
+        let config = Config::parse("[colors]\npath = \"cyan\"\nstderr = \"#ff8000\"\n").unwrap();
+        assert_eq!(Some("cyan"), config.colors.get("path").map(String::as_str));
+        let err = Config::parse("[colors]\nbanner = \"red\"\n").err().unwrap();
+        assert!(
+            err.to_string().starts_with("unknown color role banner"),
+            "{}",
+            err
+        );
+        let err = Config::parse("[colors]\npath = \"orange\"\n")
+            .err()
+            .unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("colors.path: unknown color orange"),
+            "{}",
+            err
+        );
+        assert!(Config::parse("depth = \"deep\"").is_err());
+        assert!(Config::parse("[path_jobs]\n\"/mnt/nas\" = -1").is_err());
+        assert!(Config::parse("excludes = [\"a/[\"]").is_err());
+        assert!(Config::parse("[groups]\nwork = [\"a/[\"]").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let user = Config::parse(
+            r#"
+depth = 2
+jobs = 4
+[groups]
+work = ["work/*"]
+home = ["home/*"]
+"#,
+        )
+        .unwrap();
+        let project = Config::parse(
+            r#"
+depth = 1
+[groups]
+work = ["src/*"]
+"#,
+        )
+        .unwrap();
+        let layers = [
+            Layer::defaults(),
+            Layer::new(Source::User(PathBuf::from("user.toml")), user),
+            Layer::new(Source::Project(PathBuf::from("project.toml")), project),
+        ];
+
+        let config = merge(&layers);
+        assert_eq!(Some(1), config.depth);
+        assert_eq!(Some(4), config.jobs);
+        assert_eq!(Some("dark"), config.theme.as_deref());
+        assert_eq!(vec!["src/*".to_string()], config.groups["work"]);
+        assert_eq!(vec!["home/*".to_string()], config.groups["home"]);
+
+        let rendered = render(&resolve(&layers));
+        assert!(rendered.contains("depth = 1 # project config project.toml\n"));
+        assert!(rendered.contains("jobs = 4 # user config user.toml\n"));
+        assert!(rendered.contains("theme = \"dark\" # default\n"));
+        assert!(rendered.contains("groups.home = [\"home/*\"] # user config user.toml\n"));
+    }
+
+    #[test]
+    fn test_profile() {
+        let config = Config::parse(
+            r#"
+depth = 3
+[profile.work]
+root = "/work"
+depth = 2
+theme = "plain"
+"#,
+        )
+        .unwrap();
+        let mut user = Layer::new(Source::User(PathBuf::from("user.toml")), config);
+
+        assert!(Layer::profile(Some(&mut user), "home").is_err());
+
+        let profile = Layer::profile(Some(&mut user), "work").unwrap();
+        assert_eq!(1, profile.warnings.len());
+        let config = merge(&[user, profile]);
+        assert_eq!(Some(2), config.depth);
+        assert_eq!(Some(PathBuf::from("/work")), config.root);
+        assert_eq!(None, config.theme);
+    }
+
+    #[test]
+    fn test_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "# The depth\ndepth = 2\n").unwrap();
+
+        set(&path, "depth", "3").unwrap();
+        set(&path, "theme", "plain").unwrap();
+        set(&path, "groups.work", r#"["work/*"]"#).unwrap();
+        assert_eq!(
+            "# The depth\ndepth = 3\ntheme = \"plain\"\n\n[groups]\nwork = [\"work/*\"]\n",
+            std::fs::read_to_string(&path).unwrap()
+        );
+
+        assert!(set(&path, "depth", "deep").is_err());
+        assert!(set(&path, "theme", "blue").is_err());
+        assert!(set(&path, "colour", "never").is_err());
+        assert_eq!(
+            Some(3),
+            Config::load(&path).unwrap().and_then(|config| config.depth)
+        );
+    }
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::parse(DEFAULT_CONFIG).unwrap();
+        assert_eq!(None, config.depth);
+    }
+
+    #[test]
+    fn test_project_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "depth = 2\ngit = \"/tmp/evil\"\n",
+        )
+        .unwrap();
+
+        let layer = Layer::project(dir.path()).unwrap().unwrap();
+        assert_eq!(Some(2), layer.config.depth);
+        assert_eq!(None, layer.config.git);
+        assert_eq!(1, layer.warnings.len());
+    }
+}
@@ -0,0 +1,661 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
+use tracing::{debug, trace};
+
+use crate::gitmodules::GitModules;
+
+/// Controls where and how deep repositories are searched.
+#[derive(Debug, Clone)]
+pub struct DiscoverOptions {
+    /// The directories to search, repositories found under several roots are only returned once
+    pub roots: Vec<PathBuf>,
+    /// How many directory levels below each root are searched
+    pub depth: usize,
+    /// Glob patterns of the repositories to ignore, matched against their path relative to the root
+    pub excludes: Vec<String>,
+    /// Glob patterns of the only repositories to return, like the excludes; all of them if empty
+    pub includes: Vec<String>,
+    /// Whether the working trees of the repositories found are searched for other repositories,
+    /// the submodules are only returned with `submodules`
+    pub nested: bool,
+    /// Whether the submodules found are returned too, like any other repository
+    pub submodules: bool,
+}
+
+impl Default for DiscoverOptions {
+    /// Searches the current directory 3 levels deep, nested repositories included and submodules
+    /// excluded.
+    fn default() -> Self {
+        Self {
+            roots: vec![PathBuf::from(".")],
+            depth: 3,
+            excludes: Vec::new(),
+            includes: Vec::new(),
+            nested: true,
+            submodules: false,
+        }
+    }
+}
+
+/// A repository with this file in its working tree isn't discovered.
+pub const SKIP_MARKER: &str = ".gitjuggling-skip";
+/// A directory with this file isn't searched, neither it nor any of its subdirectories are
+/// discovered.
+pub const SKIP_ALL_MARKER: &str = ".gitjuggling-skip-all";
+
+/// A directory left out of a discovery by a marker file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marked {
+    /// The canonical path of the repository, or of the directory for [`SKIP_ALL_MARKER`]
+    pub path: PathBuf,
+    /// The marker file found
+    pub marker: &'static str,
+    /// The first line of the marker file, if it isn't empty
+    pub reason: Option<String>,
+}
+
+impl Marked {
+    fn read(path: &Path, marker: &'static str) -> Option<Self> {
+        let marker_path = path.join(marker);
+        if !marker_path.is_file() {
+            return None;
+        }
+        // An unreadable marker is still a marker
+        let reason = std::fs::read(&marker_path)
+            .ok()
+            .and_then(|contents| {
+                let contents = String::from_utf8_lossy(&contents);
+                contents.lines().next().map(|line| line.trim().to_string())
+            })
+            .filter(|line| !line.is_empty());
+
+        Some(Self {
+            path: path.to_path_buf(),
+            marker,
+            reason,
+        })
+    }
+}
+
+/// A repository found but not returned, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Excluded {
+    /// The canonical path of the repository
+    pub path: PathBuf,
+    /// Why it isn't returned, like "excluded by archive/*"
+    pub reason: String,
+}
+
+/// The result of a discovery: the repositories found and the ones left out.
+#[derive(Debug, Default)]
+pub struct Discovered {
+    /// The repositories with the submodules parsed from their .gitmodules file, like
+    /// [`discover_superprojects`] returns
+    pub superprojects: Vec<(PathBuf, Option<GitModules>)>,
+    /// The directories with a marker, sorted
+    pub marked: Vec<Marked>,
+    /// The submodules and the repositories left out by the excludes and includes, sorted
+    pub excluded: Vec<Excluded>,
+}
+
+/// How far a discovery went, reported while the roots are walked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiscoverProgress {
+    /// How many directories were visited
+    pub directories: usize,
+    /// How many repositories were found, before the excludes and includes are applied
+    pub repositories: usize,
+}
+
+/// Returns the canonical paths of the git repositories found under the roots of `options`.
+///
+/// Submodules are not returned unless [`DiscoverOptions::submodules`] is set, only the
+/// repositories containing them.
+pub fn discover_repositories(options: &DiscoverOptions) -> anyhow::Result<Vec<PathBuf>> {
+    Ok(discover_superprojects(options)?
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Like [`discover_repositories`], along with the submodules of each repository parsed from its
+/// .gitmodules file, if it has one.
+pub fn discover_superprojects(
+    options: &DiscoverOptions,
+) -> anyhow::Result<Vec<(PathBuf, Option<GitModules>)>> {
+    discover_superprojects_with_progress(options, &|_| {})
+}
+
+/// Like [`discover_superprojects`], calling `report` after every directory visited.
+///
+/// The directories are walked in parallel on the current rayon thread pool, `report` can be
+/// called from any of its threads.
+pub fn discover_superprojects_with_progress(
+    options: &DiscoverOptions,
+    report: &(dyn Fn(DiscoverProgress) + Sync),
+) -> anyhow::Result<Vec<(PathBuf, Option<GitModules>)>> {
+    Ok(discover_with_markers(options, report)?.superprojects)
+}
+
+/// Like [`discover_superprojects_with_progress`], along with the repositories and directories
+/// left out because they have a [`SKIP_MARKER`] or a [`SKIP_ALL_MARKER`] file, the submodules
+/// and the repositories the excludes and includes left out.
+///
+/// The excludes and includes apply to the marked ones too.
+pub fn discover_with_markers(
+    options: &DiscoverOptions,
+    report: &(dyn Fn(DiscoverProgress) + Sync),
+) -> anyhow::Result<Discovered> {
+    let walker = Walker {
+        depth: options.depth,
+        nested: options.nested,
+        include_submodules: options.submodules,
+        directories: AtomicUsize::new(0),
+        repositories: AtomicUsize::new(0),
+        marked: Mutex::new(Vec::new()),
+        submodules: Mutex::new(Vec::new()),
+        report,
+    };
+    let excludes = build_globs(&options.excludes)?;
+    let includes = build_globs(&options.includes)?;
+    let mut repositories_paths = Vec::new();
+    let mut marked = Vec::new();
+    let mut excluded = Vec::new();
+
+    for root in &options.roots {
+        let root = root
+            .canonicalize()
+            .map_err(|err| anyhow!("invalid root {}: {}", root.display(), err))?;
+        debug!(root = %root.display(), depth = options.depth, "discovering repositories");
+
+        let found = walker.walk_root(&root).map_err(|err| {
+            anyhow!(
+                "unable to discover the repositories under {}: {}",
+                root.display(),
+                err
+            )
+        })?;
+        // Returns why the repository at `path` is left out by the excludes or the includes
+        let filtered = |path: &Path| {
+            let relative = path.strip_prefix(&root).unwrap_or(path);
+            if let Some(&index) = excludes.matches(relative).first() {
+                debug!(path = %path.display(), pattern = options.excludes[index], "excluded");
+                Some(format!("excluded by {}", options.excludes[index]))
+            } else if !options.includes.is_empty() && !includes.is_match(relative) {
+                debug!(path = %path.display(), "not included");
+                Some(format!("not included by {}", options.includes.join(", ")))
+            } else {
+                None
+            }
+        };
+        for (path, gitmodules) in found {
+            if let Some(reason) = filtered(&path) {
+                excluded.push(Excluded { path, reason });
+            } else if repositories_paths.iter().any(|(known, _)| *known == path) {
+                debug!(path = %path.display(), "already discovered under another root");
+            } else {
+                repositories_paths.push((path, gitmodules));
+            }
+        }
+
+        let mut root_marked = std::mem::take(&mut *walker.marked.lock().unwrap());
+        root_marked.sort_by(|a, b| a.path.cmp(&b.path));
+        for found in root_marked {
+            if filtered(&found.path).is_none()
+                && !marked.iter().any(|known: &Marked| known.path == found.path)
+            {
+                marked.push(found);
+            }
+        }
+
+        for path in std::mem::take(&mut *walker.submodules.lock().unwrap()) {
+            if !excluded.iter().any(|known: &Excluded| known.path == path) {
+                excluded.push(Excluded {
+                    path,
+                    reason: "submodule".to_string(),
+                });
+            }
+        }
+    }
+    excluded.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(Discovered {
+        superprojects: repositories_paths,
+        marked,
+        excluded,
+    })
+}
+
+fn build_globs(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob =
+            Glob::new(pattern).map_err(|err| anyhow!("invalid pattern {}: {}", pattern, err))?;
+        builder.add(glob);
+    }
+
+    Ok(builder.build()?)
+}
+
+fn parse_gitmodules(path: &Path) -> anyhow::Result<GitModules> {
+    let contents = {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        contents
+    };
+
+    let gitmodules = GitModules::parse(&contents)?;
+
+    Ok(gitmodules)
+}
+
+fn is_submodule(path: &Path, gitmodules: Option<&GitModules>) -> bool {
+    match gitmodules {
+        Some(gitmodules) => {
+            // If this is a submodule:
+            // * path is the git submodule directory
+            // * parent path is the parent git repository containing the gitmodules
+
+            let parent_path = match path.parent().ok_or(anyhow!("no parent path")) {
+                Ok(path) => path,
+                Err(_) => return false,
+            };
+
+            let tmp = parent_path
+                .components()
+                .next_back()
+                .map(|p| PathBuf::from(p.as_os_str()))
+                .unwrap_or_default();
+
+            gitmodules.contains(&tmp)
+        }
+        None => false,
+    }
+}
+
+/// The repositories found with the submodules parsed from their .gitmodules file.
+type Found = Vec<(PathBuf, Option<GitModules>)>;
+
+/// Walks the roots of a discovery, the subdirectories of a directory are walked in parallel on
+/// the current rayon thread pool.
+struct Walker<'a> {
+    depth: usize,
+    nested: bool,
+    include_submodules: bool,
+    directories: AtomicUsize,
+    repositories: AtomicUsize,
+    marked: Mutex<Vec<Marked>>,
+    /// The submodules found, unless they're returned
+    submodules: Mutex<Vec<PathBuf>>,
+    report: &'a (dyn Fn(DiscoverProgress) + Sync),
+}
+
+impl Walker<'_> {
+    fn directory(&self) {
+        self.directories.fetch_add(1, Ordering::Relaxed);
+        (self.report)(DiscoverProgress {
+            directories: self.directories.load(Ordering::Relaxed),
+            repositories: self.repositories.load(Ordering::Relaxed),
+        });
+    }
+
+    fn repository(&self) {
+        self.repositories.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns true if `path` has the marker file `marker`, recording it.
+    fn is_marked(&self, path: &Path, marker: &'static str) -> bool {
+        let Some(marked) = Marked::read(path, marker) else {
+            return false;
+        };
+        debug!(path = %path.display(), marker, reason = ?marked.reason, "marked");
+        self.marked.lock().unwrap().push(marked);
+        true
+    }
+
+    /// Returns the repositories found under `root`.
+    fn walk_root(&self, root: &Path) -> anyhow::Result<Found> {
+        let mut repositories_paths = Vec::new();
+        if self.is_marked(root, SKIP_ALL_MARKER) {
+            return Ok(repositories_paths);
+        }
+
+        // The root is an entry like any other, except it's always a directory
+        let gitmodules = entry_gitmodules(root);
+        if let Some(path) = entry_repository(root) {
+            self.repository();
+            if !self.is_marked(&path, SKIP_MARKER) {
+                repositories_paths.push((path, None));
+            }
+        }
+        self.directory();
+        repositories_paths.extend(self.walk(root, 0, gitmodules.as_ref(), gitmodules.clone())?);
+
+        Ok(repositories_paths)
+    }
+
+    /// Returns the repositories found in `dir`, which is `dir_depth` levels below the root.
+    ///
+    /// `gitmodules` are the submodules of the closest directory with a .gitmodules file, `dir`
+    /// included, and `submodules` the ones of `dir` itself, returned with it if it's a
+    /// repository.
+    fn walk(
+        &self,
+        dir: &Path,
+        dir_depth: usize,
+        gitmodules: Option<&GitModules>,
+        mut submodules: Option<GitModules>,
+    ) -> anyhow::Result<Found> {
+        if dir_depth >= self.depth || self.is_marked(dir, SKIP_ALL_MARKER) {
+            return Ok(Vec::new());
+        }
+
+        let read_error = |err: io::Error| anyhow!("unable to read {}: {}", dir.display(), err);
+        let mut entries = std::fs::read_dir(dir)
+            .map_err(read_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error)?;
+        // The order of read_dir depends on the file system
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut repositories_paths = Vec::new();
+        let mut subdirectories = Vec::new();
+        let mut repository = false;
+        for entry in entries {
+            let file_type = entry.file_type().map_err(read_error)?;
+            let entry_path = entry.path();
+
+            // Only symlinks need to be resolved, dir is already canonical
+            let path = if file_type.is_symlink() {
+                match entry_path.canonicalize() {
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        trace!(path = %entry_path.display(), "ignoring dangling entry");
+                        continue;
+                    }
+                    Err(err) => {
+                        return Err(anyhow!(
+                            "unable to resolve {}: {}",
+                            entry_path.display(),
+                            err
+                        ))
+                    }
+                    Ok(path) => path,
+                }
+            } else {
+                entry_path
+            };
+
+            let child_gitmodules = entry_gitmodules(&path);
+            if let Some(found) = entry_repository(&path) {
+                self.repository();
+                if is_submodule(&path, gitmodules) && !self.include_submodules {
+                    debug!(path = %found.display(), "ignoring submodule");
+                    self.submodules.lock().unwrap().push(found);
+                } else if !self.is_marked(&found, SKIP_MARKER) {
+                    repositories_paths.push((found, submodules.take()));
+                }
+                repository = true;
+            }
+
+            // Symlinks aren't followed, and there are no repositories in a git directory
+            if file_type.is_dir() && entry.file_name() != ".git" {
+                subdirectories.push((path, child_gitmodules));
+            }
+        }
+        if repository && !self.nested {
+            trace!(path = %dir.display(), "not searching the working tree");
+            subdirectories.clear();
+        }
+
+        let walked: Vec<anyhow::Result<Found>> = subdirectories
+            .into_par_iter()
+            .map(|(path, child_gitmodules)| {
+                self.directory();
+                self.walk(
+                    &path,
+                    dir_depth + 1,
+                    child_gitmodules.as_ref().or(gitmodules),
+                    child_gitmodules.clone(),
+                )
+            })
+            .collect();
+        for walked in walked {
+            repositories_paths.extend(walked?);
+        }
+
+        Ok(repositories_paths)
+    }
+}
+
+/// Parses the .gitmodules file of the entry `path` if it has one.
+fn entry_gitmodules(path: &Path) -> Option<GitModules> {
+    let gitmodules_path = path.join(".gitmodules");
+    if !gitmodules_path.exists() {
+        return None;
+    }
+
+    match parse_gitmodules(&gitmodules_path) {
+        Ok(gitmodules) => {
+            debug!(
+                path = %gitmodules_path.display(),
+                submodules = gitmodules.submodules().len(),
+                "parsed .gitmodules"
+            );
+            Some(gitmodules)
+        }
+        Err(err) => {
+            debug!(
+                path = %gitmodules_path.display(),
+                %err,
+                "unable to parse .gitmodules"
+            );
+            None
+        }
+    }
+}
+
+/// Returns the repository of the entry `path` if it's a git directory.
+fn entry_repository(path: &Path) -> Option<PathBuf> {
+    let path_string = path.to_string_lossy();
+    trace!(path = %path_string, "visiting");
+
+    // Ignore directories that aren't a git repository, a bare repository like foo.git included:
+    // its parent isn't a repository
+    if path.file_name().is_none_or(|name| name != ".git") {
+        return None;
+    }
+    let path = path.parent()?.to_path_buf();
+    debug!(path = %path.display(), "found repository");
+
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_repositories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("foo/.git")).unwrap();
+        std::fs::create_dir_all(dir.path().join("bar/baz/.git")).unwrap();
+        std::fs::create_dir_all(dir.path().join("not-a-repo")).unwrap();
+
+        let root = dir.path().canonicalize().unwrap();
+        let options = DiscoverOptions {
+            // The same root twice must not return the repositories twice
+            roots: vec![root.clone(), root.join("foo")],
+            depth: 3,
+            ..DiscoverOptions::default()
+        };
+
+        let mut paths = discover_repositories(&options).unwrap();
+        paths.sort();
+
+        assert_eq!(vec![root.join("bar/baz"), root.join("foo")], paths);
+
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            depth: 3,
+            excludes: vec!["bar/*".to_string()],
+            ..DiscoverOptions::default()
+        };
+        assert_eq!(
+            vec![root.join("foo")],
+            discover_repositories(&options).unwrap()
+        );
+
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            depth: 3,
+            includes: vec!["bar/*".to_string()],
+            ..DiscoverOptions::default()
+        };
+        assert_eq!(
+            vec![root.join("bar/baz")],
+            discover_repositories(&options).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_discover_with_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for path in [
+            "foo/.git",
+            "mirror/.git",
+            "archive/old/.git",
+            "archive/older/.git",
+        ] {
+            std::fs::create_dir_all(root.join(path)).unwrap();
+        }
+        std::fs::write(
+            root.join("mirror").join(SKIP_MARKER),
+            "read-only mirror\nignored\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("archive").join(SKIP_ALL_MARKER), "").unwrap();
+
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            ..DiscoverOptions::default()
+        };
+        let discovered = discover_with_markers(&options, &|_| {}).unwrap();
+
+        let paths: Vec<&PathBuf> = discovered
+            .superprojects
+            .iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(vec![&root.join("foo")], paths);
+        assert_eq!(
+            vec![
+                Marked {
+                    path: root.join("archive"),
+                    marker: SKIP_ALL_MARKER,
+                    reason: None,
+                },
+                Marked {
+                    path: root.join("mirror"),
+                    marker: SKIP_MARKER,
+                    reason: Some("read-only mirror".to_string()),
+                },
+            ],
+            discovered.marked
+        );
+
+        // The excluded ones aren't reported
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            excludes: vec!["mirror".to_string()],
+            ..DiscoverOptions::default()
+        };
+        let discovered = discover_with_markers(&options, &|_| {}).unwrap();
+        assert_eq!(1, discovered.marked.len());
+
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            excludes: vec!["f*".to_string()],
+            ..DiscoverOptions::default()
+        };
+        let discovered = discover_with_markers(&options, &|_| {}).unwrap();
+        assert!(discovered.superprojects.is_empty());
+        assert_eq!(
+            vec![Excluded {
+                path: root.join("foo"),
+                reason: "excluded by f*".to_string(),
+            }],
+            discovered.excluded
+        );
+    }
+
+    #[test]
+    fn test_discover_superprojects() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("app/.git")).unwrap();
+        std::fs::create_dir_all(root.join("app/vendor/lib")).unwrap();
+        std::fs::write(
+            root.join("app/vendor/lib/.git"),
+            "gitdir: ../../.git/modules/lib\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("app/.gitmodules"),
+            "[submodule \"lib\"]\n\tpath = vendor/lib\n\turl = https://example.com/lib.git\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join("a/b/c/deep/.git")).unwrap();
+        std::fs::create_dir_all(root.join("zed/.git")).unwrap();
+
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            depth: 3,
+            ..DiscoverOptions::default()
+        };
+        let repositories = discover_superprojects(&options).unwrap();
+
+        // The submodule is only returned with its superproject, the deep repository is too deep
+        let paths: Vec<&PathBuf> = repositories.iter().map(|(path, _)| path).collect();
+        assert_eq!(vec![&root.join("app"), &root.join("zed")], paths);
+        assert_eq!(1, repositories[0].1.as_ref().unwrap().submodules().len());
+        assert!(repositories[1].1.is_none());
+    }
+
+    #[test]
+    fn test_discover_nested() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("outer/.git/objects")).unwrap();
+        // Nothing inside a git directory is a repository
+        std::fs::create_dir_all(root.join("outer/.git/modules/.git")).unwrap();
+        std::fs::create_dir_all(root.join("outer/tools/inner/.git")).unwrap();
+
+        let options = DiscoverOptions {
+            roots: vec![root.clone()],
+            depth: 4,
+            ..DiscoverOptions::default()
+        };
+        assert_eq!(
+            vec![root.join("outer"), root.join("outer/tools/inner")],
+            discover_repositories(&options).unwrap()
+        );
+
+        let options = DiscoverOptions {
+            nested: false,
+            ..options
+        };
+        assert_eq!(
+            vec![root.join("outer")],
+            discover_repositories(&options).unwrap()
+        );
+    }
+}
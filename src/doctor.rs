@@ -0,0 +1,226 @@
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process;
+
+use colored::Colorize;
+use gitjuggling::Git;
+
+use crate::config::Layer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Warn,
+    /// A hard failure, gitjuggling can't work like this
+    Fail,
+}
+
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub explanation: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, explanation: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            explanation: explanation.into(),
+        }
+    }
+}
+
+/// Runs all the checks of the environment, `root` is where repositories are searched.
+///
+/// `config` are the config files read, or the error reading them.
+pub fn run(git: &Git, root: &Path, config: &anyhow::Result<Vec<Layer>>) -> Vec<Check> {
+    vec![
+        check_config(config),
+        check_git(git),
+        check_root(root),
+        check_ssh_agent(),
+        check_ssh_control_master(),
+        check_colors(),
+    ]
+}
+
+pub fn render(checks: &[Check]) -> String {
+    let mut output = String::new();
+
+    for check in checks {
+        let status = match check.status {
+            Status::Pass => "pass".bright_green(),
+            Status::Warn => "warn".bright_yellow(),
+            Status::Fail => "fail".bright_red(),
+        };
+        writeln!(
+            &mut output,
+            "[{}] {}: {}",
+            status, check.name, check.explanation
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+fn check_config(config: &anyhow::Result<Vec<Layer>>) -> Check {
+    let layers = match config {
+        Ok(layers) => layers,
+        Err(err) => return Check::new("config", Status::Fail, err.to_string()),
+    };
+    if layers.is_empty() {
+        return Check::new(
+            "config",
+            Status::Pass,
+            "no config file is read, the defaults are used",
+        );
+    }
+
+    let warnings: Vec<&str> = layers
+        .iter()
+        .flat_map(|layer| &layer.warnings)
+        .map(String::as_str)
+        .collect();
+    if !warnings.is_empty() {
+        return Check::new("config", Status::Warn, warnings.join(", "));
+    }
+
+    let sources: Vec<String> = layers
+        .iter()
+        .map(|layer| layer.source.to_string())
+        .collect();
+    Check::new(
+        "config",
+        Status::Pass,
+        format!("read {}", sources.join(", ")),
+    )
+}
+
+fn check_git(git: &Git) -> Check {
+    match git.version() {
+        Ok(version) => Check::new("git", Status::Pass, version),
+        Err(err) => Check::new("git", Status::Fail, format!("{}, is git in the PATH?", err)),
+    }
+}
+
+fn check_root(root: &Path) -> Check {
+    match root.read_dir() {
+        Ok(_) => Check::new(
+            "root",
+            Status::Pass,
+            format!("{} is readable", root.display()),
+        ),
+        Err(err) => Check::new(
+            "root",
+            Status::Fail,
+            format!("unable to read {}: {}", root.display(), err),
+        ),
+    }
+}
+
+fn check_ssh_agent() -> Check {
+    match env::var_os("SSH_AUTH_SOCK") {
+        Some(socket) if Path::new(&socket).exists() => Check::new(
+            "ssh agent",
+            Status::Pass,
+            format!("running at {}", Path::new(&socket).display()),
+        ),
+        Some(socket) => Check::new(
+            "ssh agent",
+            Status::Warn,
+            format!(
+                "SSH_AUTH_SOCK points to {} which doesn't exist, commands using SSH remotes may prompt for passphrases",
+                Path::new(&socket).display()
+            ),
+        ),
+        None => Check::new(
+            "ssh agent",
+            Status::Warn,
+            "SSH_AUTH_SOCK isn't set, commands using SSH remotes may prompt for passphrases",
+        ),
+    }
+}
+
+/// Returns the ControlMaster setting from the output of `ssh -G`.
+fn parse_control_master(config: &str) -> Option<&str> {
+    config.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        key.eq_ignore_ascii_case("controlmaster")
+            .then_some(value.trim())
+    })
+}
+
+fn check_ssh_control_master() -> Check {
+    let output = match process::Command::new("ssh")
+        .args(["-G", "github.com"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            return Check::new(
+                "ssh multiplexing",
+                Status::Warn,
+                "unable to read the SSH configuration with ssh -G",
+            )
+        }
+    };
+
+    match parse_control_master(&String::from_utf8_lossy(&output.stdout)) {
+        Some("false") | Some("no") | None => Check::new(
+            "ssh multiplexing",
+            Status::Warn,
+            "ControlMaster is disabled, every repository opens its own SSH connection",
+        ),
+        Some(value) => Check::new(
+            "ssh multiplexing",
+            Status::Pass,
+            format!(
+                "ControlMaster is {}, connections to the same host are multiplexed",
+                value
+            ),
+        ),
+    }
+}
+
+fn check_colors() -> Check {
+    if env::var_os("NO_COLOR").is_some() {
+        return Check::new("colors", Status::Pass, "disabled by NO_COLOR");
+    }
+    if !std::io::stdout().is_terminal() {
+        return Check::new(
+            "colors",
+            Status::Pass,
+            "stdout isn't a terminal, colors are disabled",
+        );
+    }
+    if env::var("TERM").is_ok_and(|term| term == "dumb") {
+        return Check::new("colors", Status::Warn, "TERM is dumb, use --theme plain");
+    }
+
+    match env::var("COLORTERM").as_deref() {
+        Ok("truecolor") | Ok("24bit") => {
+            Check::new("colors", Status::Pass, "the terminal supports true colors")
+        }
+        _ => Check::new(
+            "colors",
+            Status::Pass,
+            "the terminal supports ANSI colors, the themes fall back to them",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_control_master() {
+        let config = "user git\nhostname github.com\ncontrolmaster auto\ncontrolpath /tmp/ssh-%C\n";
+        assert_eq!(Some("auto"), parse_control_master(config));
+        assert_eq!(None, parse_control_master("user git\n"));
+    }
+}
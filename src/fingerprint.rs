@@ -0,0 +1,219 @@
+//! Tell whether a repository changed since a command last succeeded in it, for commands too
+//! expensive to run again in the repositories nothing happened to.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::git::Git;
+
+/// Returns a fingerprint of the repository at `path`, it changes with HEAD, the index, and the
+/// modified and untracked files.
+///
+/// Only the files `git status` lists are looked at, with their size and modification time
+/// rather than their contents. The index itself isn't: git status rewrites it while some of its
+/// entries were written in the same second as the files. There's no fingerprint if git can't
+/// tell the status, like in a bare repository.
+pub fn fingerprint(git: &Git, path: &Path) -> Option<String> {
+    // Not trimmed, the first entry can start with a space
+    let output = git
+        .command(path)
+        .args(["status", "--porcelain=v1", "-z", "--untracked-files=all"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let status = String::from_utf8_lossy(&output.stdout);
+    // There's no HEAD commit in an unborn branch
+    let head = git
+        .stdout(path, &["rev-parse", "--quiet", "--verify", "HEAD"])
+        .unwrap_or_default();
+
+    let mut hasher = Fnv::new();
+    hasher.write(head.as_bytes());
+    hasher.write(status.as_bytes());
+
+    let mut entries = status.split('\0');
+    while let Some(entry) = entries.next() {
+        let Some(file) = entry.get(3..) else {
+            continue;
+        };
+        match metadata(&path.join(file)) {
+            Some((size, modified)) => {
+                hasher.write(&size.to_le_bytes());
+                hasher.write(&modified.to_le_bytes());
+            }
+            None => hasher.write(&[]),
+        }
+        // A rename or a copy is followed by the path it comes from
+        if entry.starts_with(['R', 'C']) {
+            entries.next();
+        }
+    }
+
+    Some(format!("{:016x}", hasher.0))
+}
+
+/// The 64-bit FNV-1a hash. Unlike the hasher of the standard library it never changes, the
+/// fingerprints recorded by another version of gitjuggling are still valid.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Hashes `bytes` preceded by their length, so that the fields can't run into each other.
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Returns the size and the modification time of the file at `path`, if it exists.
+fn metadata(path: &Path) -> Option<(u64, u128)> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+    Some((metadata.len(), modified.as_nanos()))
+}
+
+/// The fingerprints of the repositories the last time each command succeeded in them, persisted
+/// in a TOML file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastRuns {
+    /// The fingerprints, sorted by command and path
+    #[serde(default, rename = "run")]
+    pub runs: Vec<LastRun>,
+}
+
+/// A fingerprint recorded in [`LastRuns`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastRun {
+    /// The git arguments, joined with spaces
+    pub command: String,
+    /// The absolute path of the repository
+    pub path: PathBuf,
+    /// The fingerprint of the repository before the command ran, see [`fingerprint`]
+    pub fingerprint: String,
+}
+
+/// Returns the path of the default file of the last runs, in $XDG_STATE_HOME or ~/.local/state.
+pub fn last_runs_path() -> Option<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+
+    Some(state_home.join("gitjuggling").join("last-runs.toml"))
+}
+
+impl LastRuns {
+    /// Loads the file at `path`, a missing file records no runs.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(input) => toml::from_str(&input)
+                .map_err(|err| anyhow!("unable to parse {}: {}", path.display(), err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(anyhow!("unable to read {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Writes the file at `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|err| anyhow!("unable to create {}: {}", dir.display(), err))?;
+        }
+        // A run interrupted while writing never leaves half of the file
+        let temporary = path.with_extension("toml.tmp");
+        std::fs::write(&temporary, toml::to_string(self).unwrap())
+            .and_then(|_| std::fs::rename(&temporary, path))
+            .map_err(|err| anyhow!("unable to write {}: {}", path.display(), err))
+    }
+
+    /// Returns the fingerprint of the repository at `path` the last time `command` succeeded in
+    /// it.
+    pub fn get(&self, command: &str, path: &Path) -> Option<&str> {
+        self.runs
+            .iter()
+            .find(|run| run.command == command && run.path == path)
+            .map(|run| run.fingerprint.as_str())
+    }
+
+    /// Records that `command` succeeded in the repository at `path` with the fingerprint
+    /// `fingerprint`, or forgets the last time it did if there's none.
+    pub fn set(&mut self, command: &str, path: &Path, fingerprint: Option<String>) {
+        let index = self.runs.binary_search_by(|run| {
+            (run.command.as_str(), run.path.as_path()).cmp(&(command, path))
+        });
+        match (index, fingerprint) {
+            (Ok(index), Some(fingerprint)) => self.runs[index].fingerprint = fingerprint,
+            (Ok(index), None) => {
+                self.runs.remove(index);
+            }
+            (Err(index), Some(fingerprint)) => self.runs.insert(
+                index,
+                LastRun {
+                    command: command.to_string(),
+                    path: path.to_path_buf(),
+                    fingerprint,
+                },
+            ),
+            (Err(_), None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv() {
+        // The fingerprints must stay the same from one release to the other
+        let mut hasher = Fnv::new();
+        hasher.write(b"");
+        assert_eq!(0xa8c7_f832_281a_39c5, hasher.0);
+        let mut hasher = Fnv::new();
+        hasher.write(b"ab");
+        let mut other = Fnv::new();
+        other.write(b"a");
+        other.write(b"b");
+        assert_ne!(hasher.0, other.0);
+    }
+
+    #[test]
+    fn test_last_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/last-runs.toml");
+        let mut last_runs = LastRuns::load(&path).unwrap();
+        assert_eq!(LastRuns::default(), last_runs);
+
+        last_runs.set("test", Path::new("/src/foo"), Some("1".to_string()));
+        last_runs.set("test", Path::new("/src/bar"), Some("2".to_string()));
+        last_runs.set("fetch", Path::new("/src/foo"), Some("3".to_string()));
+        last_runs.set("test", Path::new("/src/foo"), Some("4".to_string()));
+        last_runs.set("fetch", Path::new("/src/bar"), None);
+        last_runs.save(&path).unwrap();
+
+        let last_runs = LastRuns::load(&path).unwrap();
+        assert_eq!(Some("4"), last_runs.get("test", Path::new("/src/foo")));
+        assert_eq!(Some("2"), last_runs.get("test", Path::new("/src/bar")));
+        assert_eq!(Some("3"), last_runs.get("fetch", Path::new("/src/foo")));
+        assert_eq!(None, last_runs.get("fetch", Path::new("/src/bar")));
+        assert_eq!("fetch", last_runs.runs[0].command);
+        assert!(!path.with_extension("toml.tmp").exists());
+
+        let mut forgotten = last_runs.clone();
+        forgotten.set("test", Path::new("/src/foo"), None);
+        assert_eq!(None, forgotten.get("test", Path::new("/src/foo")));
+        assert_eq!(2, forgotten.runs.len());
+    }
+}
@@ -0,0 +1,116 @@
+//! Run the git CLI.
+
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::anyhow;
+
+/// The git program and the environment it runs with.
+#[derive(Debug, Clone)]
+pub struct Git {
+    program: PathBuf,
+    ssh_command: Option<String>,
+}
+
+impl Default for Git {
+    /// Runs `git` from the PATH.
+    fn default() -> Self {
+        Self::new("git")
+    }
+}
+
+impl Git {
+    /// Creates a git running `program`, a path or a name looked up in the PATH.
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            ssh_command: None,
+        }
+    }
+
+    /// Sets GIT_SSH_COMMAND for every git process.
+    pub fn ssh_command(mut self, ssh_command: impl Into<String>) -> Self {
+        self.ssh_command = Some(ssh_command.into());
+        self
+    }
+
+    /// Returns the program run.
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    /// Returns a command running git in `path`, with the [`Git::env_overrides`].
+    pub fn command(&self, path: &Path) -> process::Command {
+        let mut command = process::Command::new(&self.program);
+        command.current_dir(path);
+        for (name, value) in self.env_overrides() {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+
+        command
+    }
+
+    /// Returns how the environment of git differs from the one of gitjuggling: the variables
+    /// set, and the ones removed with `None`.
+    ///
+    /// The variables git sets for external subcommands are removed, so that the command runs
+    /// in the repository it's started in and not the one gitjuggling was started from.
+    pub fn env_overrides(&self) -> Vec<(String, Option<String>)> {
+        let mut overrides: Vec<(String, Option<String>)> =
+            ["GIT_DIR", "GIT_WORK_TREE", "GIT_PREFIX"]
+                .into_iter()
+                .filter(|name| std::env::var_os(name).is_some())
+                .map(|name| (name.to_string(), None))
+                .collect();
+        if let Some(ssh_command) = &self.ssh_command {
+            overrides.push(("GIT_SSH_COMMAND".to_string(), Some(ssh_command.clone())));
+        }
+
+        overrides
+    }
+
+    /// Returns the output of `git --version`, this checks that git can run at all.
+    pub fn version(&self) -> anyhow::Result<String> {
+        let output = process::Command::new(&self.program)
+            .arg("--version")
+            .output()
+            .map_err(|err| self.spawn_error(err))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} --version failed: {}",
+                self.program.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Describes why git couldn't be spawned at all.
+    pub fn spawn_error(&self, err: std::io::Error) -> anyhow::Error {
+        let looked_up = self.program.components().count() == 1;
+        if looked_up && err.kind() == std::io::ErrorKind::NotFound {
+            return anyhow!("{} executable not found in PATH", self.program.display());
+        }
+
+        anyhow!("unable to run {}: {}", self.program.display(), err)
+    }
+
+    /// Returns the value of the git config `key` in the repository at `path`, if it's set.
+    pub fn config(&self, path: &Path, key: &str) -> Option<String> {
+        self.stdout(path, &["config", "--get", key])
+    }
+
+    /// Runs git in `path` and returns its trimmed stdout if it succeeded.
+    pub(crate) fn stdout(&self, path: &Path, args: &[&str]) -> Option<String> {
+        let output = self.command(path).args(args).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
@@ -1,10 +1,13 @@
+//! Parse .gitmodules files.
+
 use std::collections::HashMap;
 use std::io::Error as IoError;
 use std::path::{Path, PathBuf};
 
 use onlyerror::Error;
 
-#[derive(Debug)]
+/// A submodule declared in a .gitmodules file.
+#[derive(Debug, Clone)]
 pub struct GitSubmodule {
     name: String,
     path: PathBuf,
@@ -12,12 +15,36 @@ pub struct GitSubmodule {
     branch: Option<String>,
 }
 
-#[derive(Debug)]
+impl GitSubmodule {
+    /// The name of the submodule
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path of the submodule, relative to the repository containing it
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The URL of the submodule
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The branch tracked by the submodule, if any
+    pub fn branch(&self) -> Option<&str> {
+        self.branch.as_deref()
+    }
+}
+
+/// The submodules declared in a .gitmodules file.
+#[derive(Debug, Clone)]
 pub struct GitModules {
     submodules: Vec<GitSubmodule>,
 }
 
 impl GitModules {
+    /// Parses the contents of a .gitmodules file.
     pub fn parse(input: &str) -> anyhow::Result<Self> {
         let mut parser = GitModulesParser::new(input);
         let result = parser.parse()?;
@@ -25,12 +52,14 @@ impl GitModules {
         Ok(result)
     }
 
+    /// Returns the submodules in the order they're declared.
+    pub fn submodules(&self) -> &[GitSubmodule] {
+        &self.submodules
+    }
+
+    /// Returns true if a submodule is declared at `path`.
     pub fn contains(&self, path: &Path) -> bool {
         for submodule in &self.submodules {
-            let _ = submodule.name;
-            let _ = submodule.url;
-            let _ = submodule.branch;
-
             if submodule.path == path {
                 return true;
             }
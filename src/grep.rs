@@ -0,0 +1,112 @@
+use gitjuggling::ansi::strip_ansi;
+
+/// The git options taking their value as a separate argument.
+const OPTIONS_WITH_VALUE: &[&str] = &["-c", "-C", "--git-dir", "--work-tree", "--namespace"];
+
+/// Returns the index of the git subcommand in `git_args`, skipping the global options before it.
+pub fn subcommand_index(git_args: &[&str]) -> Option<usize> {
+    let mut index = 0;
+    while index < git_args.len() {
+        let arg = git_args[index];
+        if OPTIONS_WITH_VALUE.contains(&arg) {
+            index += 2;
+        } else if arg.starts_with('-') {
+            index += 1;
+        } else {
+            return Some(index);
+        }
+    }
+
+    None
+}
+
+/// Adds `--color=always` after the grep subcommand unless a color option is already given,
+/// git doesn't color its output otherwise since it's not written to a terminal.
+pub fn add_color(git_args: &mut Vec<&str>) {
+    let Some(index) = subcommand_index(git_args).filter(|&index| git_args[index] == "grep") else {
+        return;
+    };
+    if git_args[index..]
+        .iter()
+        .any(|arg| arg.starts_with("--color") || *arg == "--no-color")
+    {
+        return;
+    }
+
+    git_args.insert(index + 1, "--color=always");
+}
+
+fn is_separator(line: &str) -> bool {
+    strip_ansi(line) == "--"
+}
+
+/// Prefixes every line of the output of git grep with `name`, so that the file paths start with
+/// the repository they're in. The separators of the context lines are left untouched.
+pub fn prefix_paths(output: &str, name: &str) -> String {
+    let mut result = String::new();
+
+    for line in output.lines() {
+        if is_separator(line) {
+            result.push_str(line);
+        } else {
+            // Keep the color of the path if it starts with one
+            let split = if line.starts_with("\x1b[") {
+                line.find('m').map(|index| index + 1).unwrap_or(0)
+            } else {
+                0
+            };
+            result.push_str(&line[..split]);
+            result.push_str(name);
+            result.push('/');
+            result.push_str(&line[split..]);
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Returns the number of lines of the output of git grep, without the context separators.
+pub fn count_matches(output: &str) -> usize {
+    output.lines().filter(|line| !is_separator(line)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_color() {
+        let mut args = vec!["-c", "grep.lineNumber=true", "grep", "TODO"];
+        add_color(&mut args);
+        assert_eq!(
+            vec![
+                "-c",
+                "grep.lineNumber=true",
+                "grep",
+                "--color=always",
+                "TODO"
+            ],
+            args
+        );
+
+        let mut args = vec!["grep", "--no-color", "TODO"];
+        add_color(&mut args);
+        assert_eq!(vec!["grep", "--no-color", "TODO"], args);
+
+        let mut args = vec!["log", "--grep", "fix"];
+        add_color(&mut args);
+        assert_eq!(vec!["log", "--grep", "fix"], args);
+    }
+
+    #[test]
+    fn test_prefix_paths() {
+        let output =
+            "\x1b[35msrc/main.rs\x1b[m\x1b[36m:\x1b[m// TODO\n\x1b[36m--\x1b[m\nREADME:TODO";
+        assert_eq!(
+            "\x1b[35mfoo/src/main.rs\x1b[m\x1b[36m:\x1b[m// TODO\n\x1b[36m--\x1b[m\nfoo/README:TODO\n",
+            prefix_paths(output, "foo")
+        );
+        assert_eq!(2, count_matches(output));
+    }
+}
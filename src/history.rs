@@ -0,0 +1,268 @@
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+/// At most this many runs are kept, the oldest ones are removed first.
+pub const MAX_RUNS: usize = 200;
+/// The history file is kept under this size, the oldest runs are removed first.
+pub const MAX_BYTES: usize = 1024 * 1024;
+/// At most this many bytes of the output of a failed repository are kept.
+pub const MAX_OUTPUT: usize = 4096;
+
+/// A run of a git command recorded in the history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// Set when the run is recorded, one more than the last run
+    #[serde(default)]
+    pub id: u64,
+    /// When the run started, in RFC 3339
+    pub time: String,
+    /// The git arguments given on the command line
+    pub args: Vec<String>,
+    pub roots: Vec<PathBuf>,
+    pub duration_ms: u64,
+    /// Whether the run was stopped with Ctrl-C
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub interrupted: bool,
+    pub repositories: Vec<Entry>,
+}
+
+/// How the command ended in a repository of a recorded run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// ok, fail, skip or not-attempted, like the status of --porcelain
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub duration_ms: u64,
+    /// Why the command failed or the repository was skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Skipped because of an operation in progress
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub in_progress: bool,
+    /// The beginning of the output of a failed command, see [`truncate`]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Run {
+    /// Returns how many repositories have the status `status`.
+    pub fn count(&self, status: &str) -> usize {
+        self.repositories
+            .iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    }
+}
+
+/// Returns the first [`MAX_OUTPUT`] bytes of `output`, cut at a character boundary.
+pub fn truncate(output: &str) -> String {
+    if output.len() <= MAX_OUTPUT {
+        return output.to_string();
+    }
+    let mut end = MAX_OUTPUT;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    format!("{}\n[truncated]", &output[..end])
+}
+
+/// Returns the path of the history file, in $XDG_DATA_HOME or ~/.local/share.
+pub fn history_path() -> Option<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+
+    Some(data_home.join("gitjuggling").join("history.jsonl"))
+}
+
+/// Loads the runs of the history file at `path`, oldest first. There are none if it doesn't
+/// exist, and the lines that aren't a run are ignored.
+pub fn load(path: &Path) -> anyhow::Result<Vec<Run>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(anyhow!("unable to read {}: {}", path.display(), err)),
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Appends `run` to the history file at `path` and returns its ID. The oldest runs are removed
+/// to keep at most [`MAX_RUNS`] runs and [`MAX_BYTES`] bytes.
+pub fn record(path: &Path, mut run: Run) -> anyhow::Result<u64> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| anyhow!("unable to create {}: {}", dir.display(), err))?;
+    }
+    // The runs ending at the same time would each write the history without the other's run
+    let _lock = lock(path)?;
+
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(anyhow!("unable to read {}: {}", path.display(), err)),
+    };
+    let mut lines: Vec<&str> = contents.lines().collect();
+
+    run.id = lines
+        .iter()
+        .rev()
+        .find_map(|line| serde_json::from_str::<Run>(line).ok())
+        .map_or(1, |last| last.id + 1);
+    let line = serde_json::to_string(&run).unwrap();
+    lines.push(&line);
+
+    let mut size: usize = lines.iter().map(|line| line.len() + 1).sum();
+    let mut oldest = 0;
+    while lines.len() - oldest > 1 && (lines.len() - oldest > MAX_RUNS || size > MAX_BYTES) {
+        size -= lines[oldest].len() + 1;
+        oldest += 1;
+    }
+
+    let mut contents = lines[oldest..].join("\n");
+    contents.push('\n');
+    // Another run reading the file never sees half of it
+    let temporary = path.with_extension("jsonl.tmp");
+    std::fs::write(&temporary, contents)
+        .and_then(|_| std::fs::rename(&temporary, path))
+        .map_err(|err| anyhow!("unable to write {}: {}", path.display(), err))?;
+
+    Ok(run.id)
+}
+
+/// Takes an exclusive lock on the history at `path`, held until the file is dropped. The lock is
+/// on a file next to it: the history itself is replaced when it's written.
+fn lock(path: &Path) -> anyhow::Result<std::fs::File> {
+    let lock_path = path.with_extension("jsonl.lock");
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|err| anyhow!("unable to open {}: {}", lock_path.display(), err))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(anyhow!(
+                "unable to lock {}: {}",
+                lock_path.display(),
+                io::Error::last_os_error()
+            ));
+        }
+    }
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Run {
+        Run {
+            id: 0,
+            time: "2024-06-01T12:00:00Z".to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            roots: vec![PathBuf::from("/src")],
+            duration_ms: 1200,
+            interrupted: false,
+            repositories: vec![Entry {
+                path: PathBuf::from("/src/foo"),
+                status: "fail".to_string(),
+                exit_code: Some(1),
+                duration_ms: 1100,
+                reason: Some("exited with code 1".to_string()),
+                in_progress: false,
+                stdout: String::new(),
+                stderr: "fatal: no remote".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/history.jsonl");
+        assert!(load(&path).unwrap().is_empty());
+
+        assert_eq!(1, record(&path, run(&["fetch"])).unwrap());
+        assert_eq!(2, record(&path, run(&["pull"])).unwrap());
+
+        let runs = load(&path).unwrap();
+        assert_eq!(2, runs.len());
+        assert_eq!(vec!["pull"], runs[1].args);
+        assert_eq!(2, runs[1].id);
+        assert_eq!(1, runs[1].count("fail"));
+        assert_eq!(run(&["fetch"]).repositories, runs[0].repositories);
+    }
+
+    #[test]
+    fn test_record_concurrently() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..5 {
+                        record(&path, run(&["fetch"])).unwrap();
+                    }
+                });
+            }
+        });
+        let ids: Vec<u64> = load(&path).unwrap().iter().map(|run| run.id).collect();
+        assert_eq!((1..=40).collect::<Vec<u64>>(), ids);
+    }
+
+    #[test]
+    fn test_record_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+
+        for _ in 0..MAX_RUNS + 5 {
+            record(&path, run(&["fetch"])).unwrap();
+        }
+        let runs = load(&path).unwrap();
+        assert_eq!(MAX_RUNS, runs.len());
+        assert_eq!(6, runs[0].id);
+
+        // A single run bigger than the limit is still recorded
+        let mut big = run(&["log"]);
+        big.repositories = vec![big.repositories[0].clone(); MAX_BYTES / 100];
+        assert!(record(&path, big).is_ok());
+        let runs = load(&path).unwrap();
+        assert_eq!(1, runs.len());
+        assert_eq!(MAX_RUNS as u64 + 6, runs[0].id);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!("short", truncate("short"));
+
+        let long = "é".repeat(MAX_OUTPUT);
+        let truncated = truncate(&long);
+        assert!(truncated.len() <= MAX_OUTPUT + "\n[truncated]".len());
+        assert!(truncated.ends_with("é\n[truncated]"));
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::{BufWriter, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The version of the events schema, bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// At most this many events wait for the hook to read them, newer events are dropped.
+const BUFFER_CAP: usize = 4096;
+
+/// How long the hook has to read its last events and exit once the run is over, it's killed
+/// afterwards.
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the end of the hook is checked while waiting for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A program receiving the events of the run as NDJSON on its stdin.
+///
+/// The hook must never fail the run: all errors are reported as warnings.
+pub struct Hook {
+    command: String,
+    child: process::Child,
+    sender: Option<SyncSender<String>>,
+    writer: Option<thread::JoinHandle<()>>,
+    warned: AtomicBool,
+}
+
+impl Hook {
+    /// Spawns `command` with the shell, its stdout is discarded.
+    pub fn spawn(command: &str) -> std::io::Result<Self> {
+        #[cfg(unix)]
+        let mut shell = {
+            let mut shell = process::Command::new("sh");
+            shell.arg("-c").arg(command);
+            shell
+        };
+        #[cfg(not(unix))]
+        let mut shell = {
+            let mut shell = process::Command::new("cmd");
+            shell.arg("/C").arg(command);
+            shell
+        };
+
+        let mut child = shell
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+
+        let (sender, receiver) = mpsc::sync_channel::<String>(BUFFER_CAP);
+        let writer = thread::spawn(move || {
+            let mut stdin = BufWriter::new(stdin);
+            for event in receiver {
+                if stdin
+                    .write_all(event.as_bytes())
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    // The hook exited or closed its stdin, the remaining events are dropped
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            sender: Some(sender),
+            writer: Some(writer),
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    /// Sends an event to the hook, it must be a JSON object.
+    pub fn send(&self, event: serde_json::Value) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        match sender.try_send(Self::line(event)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.warn_once("it doesn't read its events fast enough, some were dropped")
+            }
+            Err(TrySendError::Disconnected(_)) => self.warn_once("it stopped reading its events"),
+        }
+    }
+
+    /// Returns the NDJSON line of `event`.
+    fn line(mut event: serde_json::Value) -> String {
+        event["version"] = SCHEMA_VERSION.into();
+        let mut line = event.to_string();
+        line.push('\n');
+
+        line
+    }
+
+    fn warn_once(&self, message: &str) {
+        if !self.warned.swap(true, Ordering::SeqCst) {
+            eprintln!("warning: hook {}: {}", self.command, message);
+        }
+    }
+
+    /// Sends the `last` event, waiting for room in the buffer unlike [`Hook::send`], then waits
+    /// for the hook to read all the events and exit. A hook still running after
+    /// [`FINISH_TIMEOUT`] is killed.
+    pub fn finish(self, last: serde_json::Value) {
+        self.finish_within(last, FINISH_TIMEOUT);
+    }
+
+    fn finish_within(mut self, last: serde_json::Value, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+
+        // Closing the channel afterwards ends the writer which closes the stdin of the hook
+        if let Some(sender) = self.sender.take() {
+            let mut line = Self::line(last);
+            loop {
+                match sender.try_send(line) {
+                    Ok(()) => break,
+                    Err(TrySendError::Full(unsent)) if Instant::now() < deadline => {
+                        line = unsent;
+                        thread::sleep(POLL_INTERVAL);
+                    }
+                    Err(TrySendError::Full(_)) => {
+                        self.warn_once("it doesn't read its events fast enough, some were dropped");
+                        break;
+                    }
+                    Err(TrySendError::Disconnected(_)) => {
+                        self.warn_once("it stopped reading its events");
+                        break;
+                    }
+                }
+            }
+        }
+
+        let exited = loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(_) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+                Ok(_) => {
+                    break Err(format!(
+                        "it didn't exit {}s after the run, it was killed",
+                        timeout.as_secs_f64()
+                    ))
+                }
+                Err(err) => break Err(err.to_string()),
+            }
+        };
+
+        match exited {
+            Ok(status) if status.success() => {}
+            Ok(status) => eprintln!("warning: hook {} failed: {}", self.command, status),
+            Err(err) => {
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                eprintln!("warning: hook {}: {}", self.command, err);
+            }
+        }
+        // The writer is left behind if it's still blocked, like on a pipe a child of the hook
+        // keeps open
+        if let Some(writer) = self.writer.take().filter(|writer| writer.is_finished()) {
+            let _ = writer.join();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events");
+
+        let hook = Hook::spawn(&format!("cat > {}", path.display())).unwrap();
+        hook.send(serde_json::json!({"event": "run_start"}));
+        hook.finish(serde_json::json!({"event": "run_end"}));
+
+        assert_eq!(
+            "{\"event\":\"run_start\",\"version\":1}\n{\"event\":\"run_end\",\"version\":1}\n",
+            std::fs::read_to_string(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_hook_not_reading() {
+        // More events than the buffer and the pipe hold, for a hook never reading them
+        let hook = Hook::spawn("sleep 60").unwrap();
+        let payload = "x".repeat(1024);
+        for _ in 0..BUFFER_CAP + 100 {
+            hook.send(serde_json::json!({"event": "result", "payload": payload}));
+        }
+
+        let start = Instant::now();
+        hook.finish_within(
+            serde_json::json!({"event": "run_end"}),
+            Duration::from_millis(200),
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,60 @@
+//! Discover git repositories and run a git command in all of them in parallel.
+//!
+//! This is the library behind the `gitjuggling` binary:
+//!
+//! ```no_run
+//! use gitjuggling::{discover_repositories, DiscoverOptions, Runner};
+//!
+//! let paths = discover_repositories(&DiscoverOptions::default()).unwrap();
+//!
+//! for result in Runner::new(&["fetch", "--all"]).run(&paths) {
+//!     println!("{}: {}", result.path.display(), result.success);
+//! }
+//! ```
+
+#![allow(clippy::uninlined_format_args)]
+#![warn(missing_docs)]
+
+pub mod ansi;
+pub mod audit;
+pub mod branches;
+mod capture;
+pub mod classify;
+pub mod clone;
+mod discover;
+pub mod fingerprint;
+pub mod git;
+pub mod gitmodules;
+pub mod limits;
+pub mod maintenance;
+pub mod manifest;
+pub mod order;
+pub mod patch;
+pub mod priority;
+pub mod probe;
+pub mod push;
+pub mod remotes;
+mod runner;
+pub mod sizes;
+pub mod stash;
+pub mod state;
+pub mod status;
+pub mod submodules;
+pub mod switch;
+pub mod sync;
+pub mod tag;
+pub mod timeline;
+pub mod transfer;
+pub mod verify;
+
+pub use capture::RawOutput;
+pub use discover::{
+    discover_repositories, discover_superprojects, discover_superprojects_with_progress,
+    discover_with_markers, DiscoverOptions, DiscoverProgress, Discovered, Excluded, Marked,
+    SKIP_ALL_MARKER, SKIP_MARKER,
+};
+pub use git::Git;
+pub use gitmodules::GitModules;
+pub use probe::Backend;
+pub use runner::{RunResult, Runner, Step};
+pub use status::RepoStatus;
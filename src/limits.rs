@@ -0,0 +1,79 @@
+//! Limit how many commands run at the same time under some directories, like a slow disk.
+
+use std::path::{Path, PathBuf};
+
+/// How many commands can run at the same time in the repositories under some directories.
+///
+/// The repositories under none of them are only limited by the number of threads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathLimits {
+    limits: Vec<(PathBuf, usize)>,
+}
+
+impl PathLimits {
+    /// Allows `limit` commands at the same time under the directory `prefix`, 0 is no limit.
+    ///
+    /// A repository under several directories gets the limit of the deepest one, or of the
+    /// first one added if the same directory has several limits.
+    pub fn add(&mut self, prefix: impl Into<PathBuf>, limit: usize) {
+        self.limits.push((prefix.into(), limit));
+    }
+
+    /// Returns true if no directory is limited.
+    pub fn is_empty(&self) -> bool {
+        self.limits.iter().all(|(_, limit)| *limit == 0)
+    }
+
+    /// Returns the index of the directory whose limit applies to the repository at `path`.
+    pub(crate) fn bucket_index(&self, path: &Path) -> Option<usize> {
+        self.limits
+            .iter()
+            .enumerate()
+            .filter(|(_, (prefix, _))| path.starts_with(prefix))
+            .min_by_key(|(index, (prefix, _))| (usize::MAX - prefix.components().count(), *index))
+            // A deeper directory without a limit lifts the one of its parent
+            .filter(|(_, (_, limit))| *limit > 0)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the directory whose limit applies to the repository at `path`, with the limit.
+    pub fn bucket(&self, path: &Path) -> Option<(&Path, usize)> {
+        let (prefix, limit) = &self.limits[self.bucket_index(path)?];
+        Some((prefix, *limit))
+    }
+
+    /// Returns how many commands can run at the same time for each index of
+    /// [`PathLimits::bucket_index`].
+    pub(crate) fn limits(&self) -> Vec<usize> {
+        self.limits.iter().map(|(_, limit)| *limit).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        let mut limits = PathLimits::default();
+        assert!(limits.is_empty());
+        limits.add("/mnt/nas", 2);
+        limits.add("/mnt/nas/fast", 4);
+        limits.add("/mnt/nas", 8);
+        limits.add("/mnt/nas/slow/fast", 0);
+        limits.add("/src", 0);
+        assert!(!limits.is_empty());
+
+        assert_eq!(
+            Some((Path::new("/mnt/nas"), 2)),
+            limits.bucket(Path::new("/mnt/nas/foo"))
+        );
+        assert_eq!(
+            Some((Path::new("/mnt/nas/fast"), 4)),
+            limits.bucket(Path::new("/mnt/nas/fast/foo"))
+        );
+        assert_eq!(None, limits.bucket(Path::new("/mnt/nas/slow/fast/foo")));
+        assert_eq!(None, limits.bucket(Path::new("/mnt/nasty/foo")));
+        assert_eq!(None, limits.bucket(Path::new("/src/foo")));
+    }
+}
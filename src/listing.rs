@@ -0,0 +1,162 @@
+//! The output of `--list --format json`: the discovered repositories with what an external tool
+//! needs to know about them, without running any command.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use gitjuggling::state;
+
+/// The version of the schema, bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A repository of the list.
+///
+/// A path that isn't valid UTF-8 is written with the invalid bytes replaced by U+FFFD, its raw
+/// bytes are in the field of the same name suffixed with `_hex`, in lowercase hexadecimal. That
+/// field is only there for such a path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    pub version: u32,
+    /// The canonical path
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_hex: Option<String>,
+    /// The path relative to the root it was discovered in
+    pub relative: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relative_hex: Option<String>,
+    /// The shortest trailing path telling it apart from the other repositories
+    pub name: String,
+    pub submodule: bool,
+    pub worktree: bool,
+    /// Always false as the bare repositories aren't discovered, kept for the schema
+    pub bare: bool,
+    /// The current branch, only with --enrich
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// The URL of the origin remote, only with --enrich
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+impl Entry {
+    /// Describes the repository at `path` from its files, without running git: `root` is the root
+    /// it was discovered in and `name` its short name.
+    pub fn new(path: &Path, root: Option<&Path>, name: String) -> Self {
+        let relative = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let (path_string, path_hex) = encode(path.as_os_str());
+        let (relative, relative_hex) = encode(relative.as_os_str());
+
+        // A worktree or a submodule has a .git file pointing to its git directory, in the
+        // repository it belongs to
+        let git_dir = state::git_dir(path).unwrap_or_else(|| path.join(".git"));
+        let linked = path.join(".git").is_file();
+        let in_dir = |dir: &str| {
+            git_dir
+                .components()
+                .any(|component| component.as_os_str() == dir)
+        };
+
+        Self {
+            version: SCHEMA_VERSION,
+            path: path_string,
+            path_hex,
+            relative,
+            relative_hex,
+            name,
+            submodule: linked && in_dir("modules"),
+            worktree: linked && in_dir("worktrees"),
+            bare: false,
+            branch: None,
+            origin: None,
+        }
+    }
+}
+
+/// Returns `path` as a string and, if it isn't valid UTF-8, its bytes in hexadecimal.
+fn encode(path: &OsStr) -> (String, Option<String>) {
+    match path.to_str() {
+        Some(path) => (path.to_string(), None),
+        None => {
+            let hex = path
+                .as_encoded_bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            (path.to_string_lossy().to_string(), Some(hex))
+        }
+    }
+}
+
+/// Returns the root of `roots` containing `path`, the deepest one if they're nested.
+pub fn root_of<'a>(path: &Path, roots: &'a [PathBuf]) -> Option<&'a Path> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .map(PathBuf::as_path)
+}
+
+/// Formats the entries as a JSON array, followed by a newline.
+pub fn render_json(entries: &[Entry]) -> String {
+    let mut output = serde_json::to_string(entries).unwrap();
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("work/foo/.git")).unwrap();
+        std::fs::create_dir_all(root.join("work/foo/lib")).unwrap();
+        std::fs::write(
+            root.join("work/foo/lib/.git"),
+            "gitdir: ../.git/modules/lib\n",
+        )
+        .unwrap();
+
+        let foo = Entry::new(&root.join("work/foo"), Some(root), "foo".to_string());
+        assert_eq!("work/foo", foo.relative);
+        assert!(!foo.submodule && !foo.worktree && !foo.bare);
+
+        let lib = Entry::new(&root.join("work/foo/lib"), Some(root), "lib".to_string());
+        assert!(lib.submodule && !lib.worktree);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(
+            ("/src/foo".to_string(), None),
+            encode(OsStr::new("/src/foo"))
+        );
+        assert_eq!(
+            (
+                "/src/\u{fffd}".to_string(),
+                Some("2f7372632fff".to_string())
+            ),
+            encode(OsStr::from_bytes(b"/src/\xff"))
+        );
+    }
+
+    #[test]
+    fn test_root_of() {
+        let roots = vec![PathBuf::from("/src"), PathBuf::from("/src/work")];
+        assert_eq!(
+            Some(Path::new("/src/work")),
+            root_of(Path::new("/src/work/foo"), &roots)
+        );
+        assert_eq!(None, root_of(Path::new("/tmp/foo"), &roots));
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use gitjuggling::ansi::strip_ansi_bytes;
+
+/// A transcript of the run written to a file.
+///
+/// Every line is timestamped and stripped of ANSI escapes. Each entry is flushed as soon as
+/// it's written so that a crashed run still leaves a useful log.
+pub struct LogFile {
+    file: Mutex<File>,
+}
+
+impl LogFile {
+    pub fn open(path: &Path, append: bool) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Writes `text` to the log file, prefixing every line with the current time.
+    ///
+    /// The output of the commands is written as is, it's not necessarily UTF-8. Errors are
+    /// ignored: the log file must never fail the run.
+    pub fn write(&self, text: &[u8]) {
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now());
+        let text = strip_ansi_bytes(text);
+        let text = text.strip_suffix(b"\n").unwrap_or(&text);
+
+        let mut entry = Vec::new();
+        if !text.is_empty() {
+            for line in text.split(|byte| *byte == b'\n') {
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                entry.extend_from_slice(format!("[{}] ", timestamp).as_bytes());
+                entry.extend_from_slice(line);
+                entry.push(b'\n');
+            }
+        }
+
+        let mut file = self.file.lock().unwrap();
+        let _ = file.write_all(&entry);
+        let _ = file.flush();
+    }
+}
+
+/// A directory containing one log file per repository.
+pub struct LogDir {
+    paths: Vec<PathBuf>,
+}
+
+impl LogDir {
+    /// Creates the directory if needed and assigns a log file to each repository.
+    ///
+    /// The log files are named after the path of the repository relative to `root`. Unless
+    /// `overwrite` is true this fails if any of the log files already exists.
+    pub fn create(
+        dir: &Path,
+        root: &Path,
+        repositories_paths: &[PathBuf],
+        overwrite: bool,
+    ) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let relative_paths: Vec<&Path> = repositories_paths
+            .iter()
+            .map(|path| path.strip_prefix(root).unwrap_or(path))
+            .collect();
+
+        let paths: Vec<PathBuf> = file_names(&relative_paths)
+            .into_iter()
+            .map(|name| dir.join(name))
+            .collect();
+
+        if !overwrite {
+            if let Some(path) = paths.iter().find(|path| path.exists()) {
+                return Err(anyhow::anyhow!(
+                    "log file {} already exists, use --log-overwrite to overwrite it",
+                    path.display()
+                ));
+            }
+        }
+
+        Ok(Self { paths })
+    }
+
+    /// Writes the log file of the repository at `index`.
+    pub fn write(&self, index: usize, contents: &[u8]) -> io::Result<()> {
+        fs::write(&self.paths[index], strip_ansi_bytes(contents))
+    }
+}
+
+/// Builds a unique file name for each path by replacing the path separators.
+fn file_names(paths: &[&Path]) -> Vec<String> {
+    let mut seen = HashSet::new();
+
+    paths
+        .iter()
+        .map(|path| {
+            let mut base: String = path
+                .to_string_lossy()
+                .chars()
+                .map(|ch| match ch {
+                    '/' | '\\' | ':' => '_',
+                    ch => ch,
+                })
+                .collect();
+            if base.is_empty() {
+                base = "root".to_string();
+            }
+
+            let mut name = format!("{}.log", base);
+            let mut n = 2;
+            while !seen.insert(name.clone()) {
+                name = format!("{}-{}.log", base, n);
+                n += 1;
+            }
+
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_names() {
+        let paths = [
+            Path::new("foo/bar"),
+            Path::new("foo_bar"),
+            Path::new(""),
+            Path::new("baz"),
+            Path::new("foo/bar_"),
+        ];
+
+        assert_eq!(
+            vec![
+                "foo_bar.log",
+                "foo_bar-2.log",
+                "root.log",
+                "baz.log",
+                "foo_bar_.log"
+            ],
+            file_names(&paths)
+        );
+    }
+}
@@ -1,268 +1,4504 @@
 #![allow(clippy::uninlined_format_args)]
 
-use anyhow::anyhow;
+use argsfile::ArgsFile;
+use ci::Ci;
+use clap::parser::ValueSource;
 use colored::Colorize;
-use gitmodules::GitModules;
+use config::{Config, Layer, RepoOverride};
+use gitjuggling::ansi;
+use gitjuggling::classify::Classifier;
+use gitjuggling::fingerprint::{self, LastRuns};
+use gitjuggling::limits::PathLimits;
+use gitjuggling::maintenance::{self, Task};
+use gitjuggling::order::Dependencies;
+use gitjuggling::priority::{self, IoClass, Priority};
+use gitjuggling::remotes;
+use gitjuggling::state::{self, RepoState};
+use gitjuggling::transfer::{self, Transfer};
+use gitjuggling::{
+    discover_with_markers, Backend, DiscoverOptions, Discovered, Git, GitModules, Marked,
+    RawOutput, RepoStatus, RunResult, Runner,
+};
+use indexmap::IndexMap;
+use logfile::{LogDir, LogFile};
+use output::{Format, OutputOrder, Printer, Stream, Tee};
+use paths::{Hyperlinks, PathDisplay};
 use rayon::prelude::*;
+use render::{
+    format_collapsed, format_counts, format_diagnostics, format_failure_details, format_grep_item,
+    format_header, format_item, format_log_entry, format_match_counts,
+    format_not_attempted_details, format_skipped_details, format_summary,
+};
+use script::Script;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::OsString;
 use std::fmt::Write as FmtWrite;
-use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::process;
-use walkdir::WalkDir;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use table::{Row, RowStatus, TableSort};
+use terminal::TerminalProgress;
+use theme::{Theme, ThemeName};
+use tracing::debug;
+use tracing_subscriber::EnvFilter;
+
+mod argsfile;
+mod ci;
+mod cmd;
+mod config;
+mod doctor;
+mod grep;
+mod history;
+mod hook;
+mod listing;
+mod logfile;
+mod names;
+mod notify;
+mod output;
+mod overview;
+mod paths;
+mod porcelain;
+mod render;
+mod report;
+mod safety;
+mod script;
+mod snapshot;
+mod spinner;
+mod stats;
+mod table;
+mod terminal;
+mod theme;
+mod tui;
+mod version;
+mod watch;
+
+/// Exit code when at least one command failed.
+const EXIT_FAILURE: i32 = 1;
+/// Exit code on usage errors, this is also what clap uses.
+const EXIT_USAGE: i32 = 2;
+/// Exit code if the repositories couldn't be discovered.
+const EXIT_DISCOVERY: i32 = 3;
+/// Exit code if git couldn't be run at all.
+const EXIT_GIT: i32 = 4;
+/// Exit code if the run stopped before the command was attempted in every repository, except
+/// with Ctrl-C.
+const EXIT_INCOMPLETE: i32 = 5;
+/// Exit code if the run was interrupted with Ctrl-C.
+const EXIT_INTERRUPTED: i32 = 130;
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0    all commands succeeded
+  1    at least one command failed (see --fail-threshold)
+  2    usage error
+  3    the repositories couldn't be discovered
+  4    git couldn't be run
+  5    the run stopped before the command was attempted everywhere, like with --fail-fast
+  130  the run was interrupted
+With --exit-zero every code but 2 and 130 is replaced by 0.";
+
+/// Set when Ctrl-C is pressed; no new command is started after that.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Set when a command fails with --fail-fast; no new command is started after that.
+static FAILED_FAST: AtomicBool = AtomicBool::new(false);
+/// Set with --exit-zero, see [`exit`].
+static EXIT_ZERO: AtomicBool = AtomicBool::new(false);
+
+/// Exits with `code`, or with 0 with --exit-zero unless the usage was wrong or the run was
+/// interrupted.
+fn exit(code: i32) -> ! {
+    if EXIT_ZERO.load(Ordering::SeqCst) && code != EXIT_USAGE && code != EXIT_INTERRUPTED {
+        process::exit(0);
+    }
+    process::exit(code)
+}
+
+/// The result of a repository along with how it's displayed.
+struct Item {
+    result: RunResult,
+    /// The path as it should be displayed
+    display: String,
+    /// The file:// URL of the repository if hyperlinks are enabled
+    link: Option<String>,
+    prefix: Option<String>,
+}
+
+/// How the command ended in a repository, or why it didn't run there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Status {
+    Succeeded,
+    Failed,
+    Skipped(String),
+    NotAttempted(String),
+}
+
+impl Status {
+    /// Returns the status of a repository the command ran in.
+    fn of(result: &RunResult) -> Self {
+        if result.success {
+            Status::Succeeded
+        } else {
+            Status::Failed
+        }
+    }
+
+    /// Returns the status field of --porcelain and of the hook events.
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Succeeded => "ok",
+            Status::Failed => "fail",
+            Status::Skipped(_) => "skip",
+            Status::NotAttempted(_) => "not-attempted",
+        }
+    }
+
+    /// Returns why the repository was skipped or not attempted.
+    fn reason(&self) -> Option<&str> {
+        match self {
+            Status::Skipped(reason) | Status::NotAttempted(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+/// A repository the command wasn't run in.
+struct Skipped {
+    path: PathBuf,
+    /// The path as it should be displayed
+    display: String,
+    reason: String,
+    /// Skipped because of an operation in progress, without being asked to
+    in_progress: bool,
+    /// Skipped because HEAD can't be resolved, with --skip-broken
+    broken: bool,
+}
+
+impl Skipped {
+    fn status(&self) -> Status {
+        Status::Skipped(self.reason.clone())
+    }
+}
+
+/// A repository the command was going to run in, the run stopped before it could start.
+struct NotAttempted {
+    path: PathBuf,
+    /// The path as it should be displayed
+    display: String,
+    reason: String,
+}
+
+impl NotAttempted {
+    fn status(&self) -> Status {
+        Status::NotAttempted(self.reason.clone())
+    }
+}
+
+/// Returns why no new command is started, if the run stopped.
+fn stop_reason() -> Option<&'static str> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        Some("interrupted with Ctrl-C")
+    } else if FAILED_FAST.load(Ordering::SeqCst) {
+        Some("stopped after a failure with --fail-fast")
+    } else {
+        None
+    }
+}
+
+impl Item {
+    fn status(&self) -> Status {
+        Status::of(&self.result)
+    }
+
+    fn report_entry(&self) -> report::ReportEntry {
+        report::ReportEntry {
+            name: self.display.clone(),
+            success: self.result.success,
+            failure_reason: self.result.failure_reason(),
+            duration: self.result.duration,
+            stdout: self.result.stdout.read_lossy().into_owned(),
+            stderr: self.result.stderr.read_lossy().into_owned(),
+        }
+    }
+
+    fn table_row(&self) -> Row {
+        let first_line = self
+            .result
+            .stdout
+            .to_str_lossy()
+            .lines()
+            .chain(self.result.stderr.to_str_lossy().lines())
+            .next()
+            .map(|line| ansi::strip_ansi(line).into_owned())
+            .or_else(|| self.result.error.clone())
+            .unwrap_or_default();
+
+        Row {
+            name: self.display.clone(),
+            status: if self.result.success {
+                RowStatus::Ok
+            } else {
+                RowStatus::Fail
+            },
+            exit_code: self.result.exit_code,
+            duration: self.result.duration,
+            first_line,
+        }
+    }
+
+    /// Formats the path of the repository followed by its branch if known, and how far it is
+    /// from its upstream with --counts.
+    fn display_path(&self, theme: &Theme) -> String {
+        let mut path = self.display.color(theme.path).to_string();
+        if let Some(url) = &self.link {
+            path = paths::hyperlink(url, &path);
+        }
+
+        let branch = match (&self.result.branch, self.result.ahead, self.result.behind) {
+            (Some(branch), Some(ahead), Some(behind)) if ahead > 0 || behind > 0 => {
+                Some(format!("{} {}", branch, format_counts(ahead, behind)))
+            }
+            (branch, _, _) => branch.clone(),
+        };
+        match (&branch, self.result.state) {
+            (Some(branch), Some(state)) => format!("{} ({}, {})", path, branch, state),
+            (Some(branch), None) => format!("{} ({})", path, branch),
+            (None, Some(state)) => format!("{} ({})", path, state),
+            (None, None) => path,
+        }
+    }
+}
+
+/// The name of the binary when it's installed as a git external subcommand.
+const GIT_SUBCOMMAND_NAME: &str = "git-juggle";
+
+/// Returns true if the binary was invoked as `git juggle` or `git-juggle`.
+fn is_git_subcommand() -> bool {
+    env::args_os()
+        .next()
+        .map(PathBuf::from)
+        .and_then(|path| path.file_stem().map(|stem| stem == GIT_SUBCOMMAND_NAME))
+        .unwrap_or(false)
+}
+
+/// Returns the directory searched when no --root is given: the working directory.
+///
+/// Git runs aliases from the toplevel of the repository and sets GIT_PREFIX to the directory
+/// they were started from, which is what the user means.
+fn default_root() -> PathBuf {
+    match env::var_os("GIT_PREFIX") {
+        Some(prefix) if !prefix.is_empty() => PathBuf::from(prefix),
+        _ => PathBuf::from("."),
+    }
+}
 
-mod gitmodules;
+/// Returns the arguments selecting the repositories, shared by the subcommands working on all of
+/// them like status or maintenance.
+fn discovery_args() -> Vec<clap::Arg> {
+    vec![
+        clap::Arg::new("root")
+            .long("root")
+            .help("Search for repositories under this directory instead of the current one")
+            .value_name("DIR")
+            .num_args(1)
+            .env("GITJUGGLING_ROOT")
+            .action(clap::ArgAction::Append)
+            .value_parser(clap::value_parser!(PathBuf)),
+        clap::Arg::new("depth")
+            .long("depth")
+            .short('d')
+            .help("How many directory levels below the roots are searched [default: 3]")
+            .num_args(1)
+            .env("GITJUGGLING_DEPTH")
+            .value_parser(clap::value_parser!(usize)),
+        clap::Arg::new("no_nested")
+            .long("no-nested")
+            .help("Don't search the working trees of the repositories found for other repositories")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("include_submodules")
+            .long("include-submodules")
+            .help("Use the submodules found like any other repository")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("include_self")
+            .long("include-self")
+            .help("Use the repository the root is in, even if the root is one of its subdirectories")
+            .overrides_with("exclude_self")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("exclude_self")
+            .long("exclude-self")
+            .help("Leave out the repository the root is in, only the repositories nested in it are used")
+            .overrides_with("include_self")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("exclude")
+            .long("exclude")
+            .help("Ignore the repositories whose path relative to the root matches this glob")
+            .value_name("GLOB")
+            .num_args(1)
+            .action(clap::ArgAction::Append)
+            .value_parser(parse_glob),
+        clap::Arg::new("group")
+            .long("group")
+            .help("Only use the repositories of this group of the config files")
+            .value_name("NAME")
+            .num_args(1)
+            .action(clap::ArgAction::Append),
+    ]
+    .into_iter()
+    .chain(name_args())
+    .chain(remote_args())
+    .collect()
+}
+
+/// The arguments selecting repositories by name: the end of their path, like foo or work/foo.
+fn name_args() -> Vec<clap::Arg> {
+    vec![
+        clap::Arg::new("only")
+            .long("only")
+            .visible_alias("limit-to")
+            .help("Only the repositories with these names, like foo or work/foo when there are several foo")
+            .long_help(
+                "Only the repositories with these names, separated by commas. \
+                A name is the end of a path: foo or work/foo when there are several foo, \
+                a name matching several repositories is an error listing them.",
+            )
+            .value_name("NAMES")
+            .num_args(1)
+            .value_delimiter(',')
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("exclude_name")
+            .long("exclude-name")
+            .help("Ignore the repositories with these names, like --only")
+            .value_name("NAMES")
+            .num_args(1)
+            .value_delimiter(',')
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("name")
+            .long("name")
+            .help("Only the repositories whose directory name matches this glob, like '*-service'")
+            .long_help(
+                "Only the repositories whose directory name, the last component of their path, matches \
+                this glob, like '*-service'. The case is ignored unless --case-sensitive is given, \
+                a repository matching any of several globs is used.",
+            )
+            .value_name("PATTERN")
+            .num_args(1)
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("case_sensitive")
+            .long("case-sensitive")
+            .help("Match --name with the case")
+            .requires("name")
+            .action(clap::ArgAction::SetTrue),
+    ]
+}
+
+/// The arguments using the remotes of the repositories, they're only probed if one is given.
+fn remote_args() -> Vec<clap::Arg> {
+    vec![
+        clap::Arg::new("with_remotes")
+            .long("with-remotes")
+            .help("Read the remotes of every repository once, to add them to the JSON output or throttle per host")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("remote_matches")
+            .long("remote-matches")
+            .help("Only the repositories with a remote URL matching this regex")
+            .value_name("REGEX")
+            .num_args(1),
+        clap::Arg::new("has_remote")
+            .long("has-remote")
+            .help("Only the repositories with a remote of this name, can be repeated")
+            .value_name("NAME")
+            .num_args(1)
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("exclude_host")
+            .long("exclude-host")
+            .help("Leave out the repositories whose origin is on this host, can be repeated")
+            .long_help(
+                "Leave out the repositories whose origin URL is on this host, like git.example.com, can be \
+                repeated. The host is compared without the case and the port, the repositories without an \
+                origin or with a local one are kept.",
+            )
+            .value_name("HOST")
+            .num_args(1)
+            .action(clap::ArgAction::Append),
+        clap::Arg::new("include_host")
+            .long("include-host")
+            .help("Only the repositories whose origin is on this host, can be repeated")
+            .long_help(
+                "Only the repositories whose origin URL is on this host or another one given, can be repeated. \
+                The host is compared without the case and the port, the repositories without an origin or with \
+                a local one are kept.",
+            )
+            .value_name("HOST")
+            .num_args(1)
+            .action(clap::ArgAction::Append),
+    ]
+}
+
+/// Returns the definition of the command line, it's also used to generate the completions.
+fn cli() -> clap::Command {
+    let command = if is_git_subcommand() {
+        clap::Command::new(GIT_SUBCOMMAND_NAME).bin_name("git juggle")
+    } else {
+        clap::Command::new("gitjuggling")
+    };
+
+    command
+        // --version is defined below to print the build metadata too
+        .disable_version_flag(true)
+        .arg(
+            clap::Arg::new("version")
+                .long("version")
+                .short('V')
+                .help("Print the version along with the commit and date it was built from")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("format")
+                .long("format")
+                .help("Format of --version and --list")
+                .num_args(1)
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .requires("formatted"),
+        )
+        .group(
+            clap::ArgGroup::new("formatted")
+                .args(["version", "list"])
+                .multiple(true),
+        )
+        .about("Git juggler")
+        .after_help(EXIT_CODES_HELP)
+        .disable_help_subcommand(true)
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            clap::Command::new("completions")
+                .about("Print the completion script of a shell on stdout")
+                .arg(
+                    clap::Arg::new("shell")
+                        .required(true)
+                        .value_parser(clap::value_parser!(clap_complete::Shell)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("doctor")
+                .about("Check the environment gitjuggling runs in, exits with 1 if a check fails")
+                .arg(
+                    clap::Arg::new("root")
+                        .long("root")
+                        .help("Check this directory instead of the current one")
+                        .value_name("DIR")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("status")
+                .about("Print the branch, the changes and the upstream of every repository, most interesting first")
+                .long_about(
+                    "Print the branch, the changes and the upstream of every repository, most interesting first. \
+                    Nothing is modified. Run gitjuggling -- status to run git status in every repository instead, \
+                    the arguments this subcommand doesn't know like --short are passed to git status too.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                )
+                .arg(
+                    clap::Arg::new("check")
+                        .long("check")
+                        .help("Exit with 1 if a repository has changes or its branch diverged from its upstream")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("save")
+                        .long("save")
+                        .help("Save the status of every repository as the snapshot NAME, replacing the previous one")
+                        .long_help(
+                            "Save the status of every repository as the snapshot NAME, replacing the previous one. \
+                            The snapshots are in $XDG_DATA_HOME/gitjuggling/snapshots, the ones older than 30 days \
+                            are removed.",
+                        )
+                        .value_name("NAME")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("diff")
+                        .long("diff")
+                        .help("Only print the repositories whose status changed since the snapshot NAME")
+                        .long_help(
+                            "Only print the repositories whose status changed since the snapshot NAME: their \
+                            branch, commit, changes or upstream. With --save the snapshot is compared before \
+                            it's replaced.",
+                        )
+                        .value_name("NAME")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("sync")
+                .about("Fetch all the remotes, then fast-forward the current branches that are clean and behind their upstream")
+                .long_about(
+                    "Fetch all the remotes with --prune, then fast-forward the current branches that are clean and behind their upstream. \
+                    Dirty working trees, detached HEADs and diverged branches are left untouched and reported as needing attention. \
+                    Exits with 1 only if a git command failed.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("jobs")
+                        .long("jobs")
+                        .short('j')
+                        .help("How many repositories are synced at the same time, 0 is one per CPU [default: 0]")
+                        .value_name("N")
+                        .num_args(1)
+                        .env("GITJUGGLING_JOBS")
+                        .value_parser(clap::value_parser!(usize)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("audit")
+                .about("Report the work that only exists locally, exits with 1 if there is any")
+                .long_about(
+                    "Report the work that only exists locally: uncommitted changes, untracked files, stashes, \
+                    branches without an upstream and branches ahead of their upstream. \
+                    Exits with 1 if there is any, or if a repository couldn't be audited.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("all")
+                        .long("all")
+                        .help("Also list the repositories where everything is committed and pushed")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("timeline")
+                .about("Print the commits of all the repositories in a single list, newest first")
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("since")
+                        .long("since")
+                        .help("Only the commits more recent than this date, like '1 week ago'")
+                        .value_name("DATE")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("until")
+                        .long("until")
+                        .help("Only the commits older than this date")
+                        .value_name("DATE")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("author")
+                        .long("author")
+                        .help("Only the commits whose author matches this pattern, me is the user.email of each repository")
+                        .value_name("PATTERN")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("all")
+                        .long("all")
+                        .help("List the commits of all the branches instead of the current one")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("group_by")
+                        .long("group-by")
+                        .help("Group the commits by repository or by day, only in the text format")
+                        .num_args(1)
+                        .value_parser(["none", "repo", "day"])
+                        .default_value("none"),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("branches")
+                .about("List the local branches of every repository with their upstream, age and whether they're merged")
+                .long_about(
+                    "List the local branches of every repository with their upstream, the age of their last commit \
+                    and whether they're merged into the default branch, the HEAD of origin or else main or master.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("stale")
+                        .long("stale")
+                        .help("Only the branches whose last commit is older than this, like 30days")
+                        .value_name("DURATION")
+                        .num_args(1)
+                        .value_parser(humantime::parse_duration),
+                )
+                .arg(
+                    clap::Arg::new("delete_merged")
+                        .long("delete-merged")
+                        .help("Delete the merged branches, except the current and the default ones")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("dry_run")
+                        .long("dry-run")
+                        .short('n')
+                        .help("Print the branches --delete-merged would delete, without deleting them")
+                        .requires("delete_merged")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("prune-branches")
+                .about("Delete the local branches merged into the default branch or into their upstream")
+                .long_about(
+                    "Delete the local branches of every repository whose commits are all in the default branch, \
+                    the HEAD of origin or else main or master, or in their upstream. \
+                    The current branch, the default branch and the protected ones are never deleted. \
+                    A branch that can't be deleted, like one checked out in a worktree, is reported and the others are still deleted. \
+                    Exits with 1 if a branch couldn't be deleted.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("protect")
+                        .long("protect")
+                        .help("Never delete the branches matching this glob, like 'release/*', can be repeated")
+                        .value_name("GLOB")
+                        .num_args(1)
+                        .action(clap::ArgAction::Append)
+                        .value_parser(parse_glob),
+                )
+                .arg(
+                    clap::Arg::new("gone")
+                        .long("gone")
+                        .help("Also delete the branches whose upstream is gone, even if their commits are nowhere else")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("dry_run")
+                        .long("dry-run")
+                        .short('n')
+                        .help("Print the branches that would be deleted, without deleting them")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("off-default")
+                .about("List the repositories not on their default branch")
+                .long_about(
+                    "List the repositories not on their default branch, the HEAD of origin or else the branch named by \
+                    init.defaultBranch, main or master, with how far HEAD diverged from it. Detached HEADs are listed last.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("switch_back")
+                        .long("switch-back")
+                        .help("Switch the repositories on another branch back to the default branch, except the dirty ones")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("diverged")
+                .about("List the repositories whose branch diverged from its upstream")
+                .long_about(
+                    "List the repositories whose current branch and its upstream both have commits the other doesn't, \
+                    with the upstream and the ahead and behind counts as of the last fetch. \
+                    Exits with 1 if a repository diverged or couldn't be probed.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("verify")
+                .about("Verify the signature of the HEAD commit of every repository")
+                .long_about(
+                    "Verify the signature of the HEAD commit of every repository with its GPG, SSH or X.509 settings. \
+                    The repositories whose repos override of the config has verify in its skip list are exempted. \
+                    Exits with 1 if a signature isn't trusted, unless it's allowed by the flags.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("allow_unknown_key")
+                        .long("allow-unknown-key")
+                        .help("Accept the good signatures made by a key that isn't trusted")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("allow_unsigned")
+                        .long("allow-unsigned")
+                        .help("Accept the commits that aren't signed")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("sizes")
+                .about("List the repositories by the space they take, largest first")
+                .long_about(
+                    "List the repositories by the space they take, largest first: the git directory, \
+                    the working tree without the nested repositories, and whether the .gitattributes file uses Git LFS.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("largest")
+                        .long("largest")
+                        .help("Also list the N largest blobs of the history of every repository, it walks the whole history")
+                        .value_name("N")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("submodules")
+                .about("Manage the submodules of every repository having some")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("update")
+                        .about("Sync the URLs of the submodules, then initialize and update them recursively")
+                        .long_about(
+                            "Run git submodule sync --recursive then git submodule update --init --recursive \
+                            in the repositories having a .gitmodules file, and report the outcome of each submodule. \
+                            Exits with 1 if a submodule couldn't be updated.",
+                        )
+                        .args(discovery_args())
+                        .arg(
+                            clap::Arg::new("jobs")
+                                .long("jobs")
+                                .short('j')
+                                .help("How many repositories are updated at the same time, 0 is one per CPU [default: 0]")
+                                .value_name("N")
+                                .num_args(1)
+                                .env("GITJUGGLING_JOBS")
+                                .value_parser(clap::value_parser!(usize)),
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("switch-all")
+                .about("Switch every repository having the branch to it")
+                .long_about(
+                    "Switch every repository having a local branch with this name to it. \
+                    Repositories without the branch and the ones with changes to tracked files are left untouched. \
+                    Exits with 1 only if a git command failed.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("branch")
+                        .help("The branch to switch to")
+                        .value_name("BRANCH")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("create_tracking")
+                        .long("create-tracking")
+                        .help("Create the branch from the remote-tracking branch if it only exists on a remote, origin first")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("force")
+                        .long("force")
+                        .help("Switch the repositories with changes to tracked files too, git still refuses if the changes conflict")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("stash-all")
+                .about("Stash the changes and the untracked files of the dirty repositories, unstash-all restores them")
+                .long_about(
+                    "Stash the changes and the untracked files of the dirty repositories, and record the stashes in a state file \
+                    so that unstash-all restores exactly these stashes. Clean repositories are left untouched. \
+                    Exits with 1 if a repository couldn't be stashed.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("state_file")
+                        .long("state-file")
+                        .help("Record the stashes in this file [default: $XDG_STATE_HOME/gitjuggling/stashes.toml]")
+                        .value_name("FILE")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("unstash-all")
+                .about("Pop the stashes recorded by stash-all")
+                .long_about(
+                    "Pop the stashes recorded by stash-all in the repositories found. \
+                    A stash is only popped if it's still at the top of the stashes of its repository, \
+                    the ones that couldn't be popped stay recorded. Exits with 1 if a stash couldn't be popped.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("state_file")
+                        .long("state-file")
+                        .help("Record the stashes in this file [default: $XDG_STATE_HOME/gitjuggling/stashes.toml]")
+                        .value_name("FILE")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("tag-all")
+                .about("Create the same tag on the HEAD of every repository")
+                .long_about(
+                    "Create the same tag on the HEAD of every repository. A tag already pointing to HEAD is left as is, \
+                    a tag pointing to another commit is a conflict unless --force moves it. \
+                    Exits with 1 if a tag is in conflict or a git command failed.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("tag")
+                        .help("The tag to create")
+                        .value_name("TAG")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("message")
+                        .long("message")
+                        .short('m')
+                        .help("Create an annotated tag with this message")
+                        .value_name("MESSAGE")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("sign")
+                        .long("sign")
+                        .short('s')
+                        .help("Create a signed tag, with the name of the tag as its message unless --message is given")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("force")
+                        .long("force")
+                        .help("Move the tag to HEAD where it points to another commit")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("push")
+                        .long("push")
+                        .help("Push the tag to this remote afterwards")
+                        .value_name("REMOTE")
+                        .num_args(0..=1)
+                        .default_missing_value("origin"),
+                )
+                .arg(
+                    clap::Arg::new("jobs")
+                        .long("jobs")
+                        .short('j')
+                        .help("How many repositories push at the same time, 0 is one per CPU [default: 0]")
+                        .value_name("N")
+                        .num_args(1)
+                        .env("GITJUGGLING_JOBS")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    clap::Arg::new("per_host")
+                        .long("per-host")
+                        .help("How many repositories push to the same host at the same time, 0 is no limit")
+                        .value_name("N")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("4"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("apply-all")
+                .about("Apply the same patch to every repository it applies cleanly to")
+                .long_about(
+                    "Apply the same patch to every repository with git apply --3way, or git am --3way with --am. \
+                    The patch is checked first: the repositories it doesn't apply cleanly to are left untouched, \
+                    and the ones it applies to in reverse already have it. Repositories with changes to tracked files are skipped. \
+                    Exits with 1 only if a git command failed.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("patch")
+                        .long("patch")
+                        .help("The patch to apply, made by git diff or git format-patch")
+                        .value_name("FILE")
+                        .num_args(1)
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    clap::Arg::new("am")
+                        .long("am")
+                        .help("Apply the commits of a patch made by git format-patch with git am, keeping their messages")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("branch")
+                        .long("branch")
+                        .help("Create this branch and switch to it before applying the patch")
+                        .value_name("BRANCH")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("message")
+                        .long("message")
+                        .short('m')
+                        .help("Commit the changes with this message, they're left staged otherwise")
+                        .value_name("MESSAGE")
+                        .num_args(1)
+                        .conflicts_with("am"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("push-all")
+                .about("Push every repository, except the ones where it isn't safe")
+                .long_about(
+                    "Push the current branch of every repository to its upstream, or the refspecs given. \
+                    The branches behind their upstream or without one aren't pushed, \
+                    and neither are the repositories where a refspec would force the push unless --allow-force is given. \
+                    Exits with 1 if a push failed or was rejected.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("remote")
+                        .long("remote")
+                        .help("Push to this remote instead of the one of the upstream of the branch, or origin")
+                        .value_name("REMOTE")
+                        .num_args(1),
+                )
+                .arg(
+                    clap::Arg::new("allow_force")
+                        .long("allow-force")
+                        .help("Push even if a refspec forces the push, given with a + or configured with remote.<name>.push")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("set_upstream")
+                        .long("set-upstream")
+                        .help("Push the branches without an upstream and set it")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("dry_run")
+                        .long("dry-run")
+                        .short('n')
+                        .help("Tell what would be pushed with git push --dry-run, without pushing")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("jobs")
+                        .long("jobs")
+                        .short('j')
+                        .help("How many repositories push at the same time, 0 is one per CPU [default: 0]")
+                        .value_name("N")
+                        .num_args(1)
+                        .env("GITJUGGLING_JOBS")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    clap::Arg::new("per_host")
+                        .long("per-host")
+                        .help("How many repositories push to the same host at the same time, 0 is no limit")
+                        .value_name("N")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("4"),
+                )
+                .arg(
+                    clap::Arg::new("refspecs")
+                        .help("The refspecs to push, HEAD to the upstream of the current branch if there are none")
+                        .value_name("REFSPEC")
+                        .num_args(0..),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("maintenance")
+                .about("Run the housekeeping tasks of git in every repository")
+                .long_about(
+                    "Run the housekeeping tasks of git in every repository, one after the other in a repository \
+                    and in a few repositories at the same time since they're heavy on the disk. \
+                    Exits with 1 if a task failed. Run gitjuggling -- maintenance to run git maintenance \
+                    instead, the arguments this subcommand doesn't know like run are passed to it too.",
+                )
+                .args(discovery_args())
+                .arg(
+                    clap::Arg::new("task")
+                        .long("task")
+                        .short('t')
+                        .help("Run this task, can be repeated [default: gc and prune, or maintenance_tasks in the config file]")
+                        .value_name("TASK")
+                        .num_args(1)
+                        .action(clap::ArgAction::Append)
+                        .value_parser(Task::NAMES.to_vec()),
+                )
+                .arg(
+                    clap::Arg::new("min_size")
+                        .long("min-size")
+                        .help("Skip the repositories whose .git directory is smaller than this, like 100M")
+                        .value_name("SIZE")
+                        .num_args(1)
+                        .value_parser(maintenance::parse_size),
+                )
+                .arg(
+                    clap::Arg::new("jobs")
+                        .long("jobs")
+                        .short('j')
+                        .help("How many repositories are maintained at the same time")
+                        .value_name("N")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("2"),
+                )
+                .arg(
+                    clap::Arg::new("schedule")
+                        .long("schedule")
+                        .help("Print the crontab line running this every night instead of running it")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("remotes")
+                .about("Manage the remotes of every repository")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("rewrite")
+                        .about("Rewrite the URLs of a remote, repositories whose URLs don't match are left untouched")
+                        .long_about(
+                            "Rewrite the URLs of a remote, or of all of them with --all-remotes. \
+                            A push URL set apart from the fetch URL is rewritten too, with a warning if only one of them matches. \
+                            Repositories whose URLs don't match are left untouched.",
+                        )
+                        .args(discovery_args())
+                        .arg(
+                            clap::Arg::new("from")
+                                .long("from")
+                                .help("The prefix of the URLs to replace, or the pattern with --regex")
+                                .value_name("PATTERN")
+                                .num_args(1)
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::new("to")
+                                .long("to")
+                                .help("What replaces the prefix, $1 or ${name} are the groups captured with --regex")
+                                .value_name("REPLACEMENT")
+                                .num_args(1)
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::new("regex")
+                                .long("regex")
+                                .help("--from is a regular expression, its first match is replaced")
+                                .action(clap::ArgAction::SetTrue),
+                        )
+                        .arg(
+                            clap::Arg::new("remote")
+                                .long("remote")
+                                .help("The remote to rewrite")
+                                .value_name("NAME")
+                                .num_args(1)
+                                .default_value("origin"),
+                        )
+                        .arg(
+                            clap::Arg::new("all_remotes")
+                                .long("all-remotes")
+                                .help("Rewrite all the remotes")
+                                .conflicts_with("remote")
+                                .action(clap::ArgAction::SetTrue),
+                        )
+                        .arg(
+                            clap::Arg::new("dry_run")
+                                .long("dry-run")
+                                .short('n')
+                                .help("Print the URLs that would be rewritten, without changing them")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("check")
+                        .about("Check that every remote answers, exits with 1 if one is unreachable")
+                        .long_about(
+                            "Check that every remote of every repository answers git ls-remote, \
+                            and report the remotes that are unreachable, refuse the credentials or redirect to another URL. \
+                            Exits with 1 if a remote is unreachable.",
+                        )
+                        .args(discovery_args())
+                        .arg(
+                            clap::Arg::new("timeout")
+                                .long("timeout")
+                                .help("How long a remote has to answer")
+                                .value_name("DURATION")
+                                .num_args(1)
+                                .value_parser(humantime::parse_duration)
+                                .default_value("10s"),
+                        )
+                        .arg(
+                            clap::Arg::new("per_host")
+                                .long("per-host")
+                                .help("How many remotes of the same host are checked at the same time, 0 is no limit")
+                                .value_name("N")
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(usize))
+                                .default_value("4"),
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("manifest")
+                .about("Export the repositories and their origin to a manifest, or clone them from it")
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("export")
+                        .about("Print the manifest of the repositories in TOML")
+                        .arg(
+                            clap::Arg::new("root")
+                                .long("root")
+                                .help("Search for repositories under this directory instead of the current one")
+                                .value_name("DIR")
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            clap::Arg::new("depth")
+                                .long("depth")
+                                .short('d')
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(usize))
+                                .default_value("3"),
+                        )
+                        .arg(
+                            clap::Arg::new("output")
+                                .long("output")
+                                .short('o')
+                                .help("Write the manifest to this file instead of stdout")
+                                .value_name("FILE")
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("clone")
+                        .about("Clone the repositories of a manifest that are missing, existing ones are never touched")
+                        .arg(
+                            clap::Arg::new("root")
+                                .long("root")
+                                .help("Clone the repositories under this directory instead of the current one")
+                                .value_name("DIR")
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            clap::Arg::new("file")
+                                .required(true)
+                                .value_name("FILE")
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("clone-all")
+                .about("Clone the repositories of a list of URLs that are missing")
+                .long_about(
+                    "Clone the repositories of a list of URLs, one per line, into directories named after the path of the URL: \
+                    git@github.com:foo/bar.git is cloned into foo/bar. Blank lines and lines starting with # are ignored. \
+                    The git repositories already there are left untouched. \
+                    Exits with 1 if a repository couldn't be cloned, the ones that could stay in place.",
+                )
+                .arg(
+                    clap::Arg::new("root")
+                        .long("into")
+                        .help("Clone the repositories under this directory instead of the root")
+                        .value_name("DIR")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    clap::Arg::new("jobs")
+                        .long("jobs")
+                        .short('j')
+                        .help("How many repositories are cloned at the same time, 0 is one per CPU [default: 0]")
+                        .value_name("N")
+                        .num_args(1)
+                        .env("GITJUGGLING_JOBS")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    clap::Arg::new("per_host")
+                        .long("per-host")
+                        .help("How many repositories of the same host are cloned at the same time, 0 is no limit")
+                        .value_name("N")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("4"),
+                )
+                .arg(
+                    clap::Arg::new("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("history")
+                .about("List the recent runs, or show one of them")
+                .long_about(
+                    "List the recent runs with how many repositories succeeded, failed and were skipped. \
+                    The runs are recorded in $XDG_DATA_HOME/gitjuggling/history.jsonl, the oldest ones are removed \
+                    past 200 runs or 1 MiB. Set history = false in the user config file to stop recording them.",
+                )
+                .arg(
+                    clap::Arg::new("limit")
+                        .long("limit")
+                        .short('n')
+                        .help("How many runs are listed, the most recent ones")
+                        .value_name("N")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("20"),
+                )
+                .subcommand(
+                    clap::Command::new("show")
+                        .about("Print the failure details and the summary of a run again")
+                        .arg(
+                            clap::Arg::new("id")
+                                .required(true)
+                                .value_name("ID")
+                                .help("The ID of the run, as listed by gitjuggling history")
+                                .value_parser(clap::value_parser!(u64)),
+                        ),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("config")
+                .about("Inspect the configuration")
+                .long_about(
+                    "Inspect the configuration of gitjuggling. Run gitjuggling -- config to run git config \
+                    in every repository instead, the arguments this subcommand doesn't know like --get are \
+                    passed to it too.",
+                )
+                .subcommand_required(true)
+                .subcommand(
+                    clap::Command::new("show")
+                        .about("Print the effective configuration, with the source of every value")
+                        .arg(
+                            clap::Arg::new("root")
+                                .long("root")
+                                .help("Read the project config file of this directory instead of the current one")
+                                .value_name("DIR")
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("get")
+                        .about("Print the effective value of a setting and its source, exits with 1 if it's not set")
+                        .arg(
+                            clap::Arg::new("root")
+                                .long("root")
+                                .help("Read the project config file of this directory instead of the current one")
+                                .value_name("DIR")
+                                .num_args(1)
+                                .value_parser(clap::value_parser!(PathBuf)),
+                        )
+                        .arg(
+                            clap::Arg::new("key")
+                                .required(true)
+                                .value_name("KEY")
+                                .help("The key of the setting, like jobs or groups.work"),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("set")
+                        .about("Set a setting in the user config file, or the one given with --config")
+                        .arg(
+                            clap::Arg::new("key")
+                                .required(true)
+                                .value_name("KEY")
+                                .help("The key of the setting, like jobs or groups.work"),
+                        )
+                        .arg(
+                            clap::Arg::new("value")
+                                .required(true)
+                                .value_name("VALUE")
+                                .help("A TOML value like 4 or '[\"archive/*\"]', or a string"),
+                        ),
+                )
+                .subcommand(
+                    clap::Command::new("edit").about(
+                        "Open the user config file, or the one given with --config, in $VISUAL or $EDITOR",
+                    ),
+                ),
+        )
+        .arg(
+            clap::Arg::new("no_config")
+                .long("no-config")
+                .help("Ignore the config files")
+                .global(true)
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("config")
+                .long("config")
+                .help("Read this config file instead of the .gitjuggling.toml of the root")
+                .value_name("FILE")
+                .num_args(1)
+                .global(true)
+                .conflicts_with("no_config")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("profile")
+                .long("profile")
+                .help("Use this profile of the user config file")
+                .value_name("NAME")
+                .num_args(1)
+                .env("GITJUGGLING_PROFILE")
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("depth")
+                .long("depth")
+                .short('d')
+                .help("How many directory levels below the roots are searched [default: 3]")
+                .num_args(1)
+                .env("GITJUGGLING_DEPTH")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            clap::Arg::new("no_nested")
+                .long("no-nested")
+                .help("Don't search the working trees of the repositories found for other repositories")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("include_submodules")
+                .long("include-submodules")
+                .help("Run the command in the submodules found too, like in any other repository")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("include_self")
+                .long("include-self")
+                .help("Run the command in the repository the root is in, even if the root is one of its subdirectories")
+                .overrides_with("exclude_self")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("exclude_self")
+                .long("exclude-self")
+                .help("Don't run the command in the repository the root is in, only in the repositories nested in it")
+                .overrides_with("include_self")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("root")
+                .long("root")
+                .help("Search for repositories under this directory instead of the current one")
+                .value_name("DIR")
+                .num_args(1)
+                .env("GITJUGGLING_ROOT")
+                .action(clap::ArgAction::Append)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("exclude")
+                .long("exclude")
+                .help("Ignore the repositories whose path relative to the root matches this glob, replaces the excludes of the config file")
+                .value_name("GLOB")
+                .num_args(1)
+                .action(clap::ArgAction::Append)
+                .value_parser(parse_glob),
+        )
+        .arg(
+            clap::Arg::new("group")
+                .long("group")
+                .help("Only run in the repositories of this group of the config files")
+                .value_name("NAME")
+                .num_args(1)
+                .action(clap::ArgAction::Append),
+        )
+        .args(name_args())
+        .args(remote_args())
+        .arg(
+            clap::Arg::new("jobs")
+                .long("jobs")
+                .short('j')
+                .help("How many commands run at the same time, 0 is one per CPU [default: 0]")
+                .value_name("N")
+                .num_args(1)
+                .env("GITJUGGLING_JOBS")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            clap::Arg::new("per_root_jobs")
+                .long("per-root-jobs")
+                .help("How many commands run at the same time under each root, on top of the path_jobs of the config files")
+                .long_help(
+                    "How many commands run at the same time in the repositories under each root, 0 is no limit. \
+                    The path_jobs table of the config files limits the repositories under other directories, \
+                    the deepest directory applies. The repositories elsewhere use every thread of --jobs. \
+                    With -v the limit of each repository is printed.",
+                )
+                .value_name("N")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            clap::Arg::new("nice")
+                .long("nice")
+                .help("Run git with the niceness N, from -20 to 19")
+                .long_help(
+                    "Run git with the niceness N, from -20 (highest priority) to 19. Unlike the one of nice(1) it's \
+                    the niceness itself, not an increment of the one of gitjuggling. \
+                    A niceness lower than the one of gitjuggling needs privileges, without them the command runs as is \
+                    with a warning. This does nothing outside of Unix. With -v the priority of each repository is printed.",
+                )
+                .value_name("N")
+                .num_args(1)
+                .allow_negative_numbers(true)
+                .value_parser(clap::value_parser!(i32).range(-20..=19)),
+        )
+        .arg(
+            clap::Arg::new("ionice")
+                .long("ionice")
+                .help("Run git with the IO scheduling class CLASS: idle, best-effort[:LEVEL] or realtime[:LEVEL]")
+                .long_help(
+                    "Run git with the IO scheduling class CLASS like ionice(1): idle, best-effort or realtime, \
+                    the last two with a level from 0 (highest priority) to 7, 4 by default, like best-effort:7. \
+                    A class that can't be set runs the command as is with a warning. This does nothing outside of Linux.",
+                )
+                .value_name("CLASS")
+                .num_args(1)
+                .value_parser(|s: &str| s.parse::<IoClass>().map_err(|err| err.to_string())),
+        )
+        .arg(
+            clap::Arg::new("relative")
+                .long("relative")
+                .help("Display the repository paths relative to the root (the default with a single --root)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("absolute")
+                .long("absolute")
+                .help("Display the absolute repository paths")
+                .conflicts_with("relative")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("short_names")
+                .long("short-names")
+                .help("Display the repositories by the shortest end of their path that is unique, like foo or work/foo")
+                .long_help(
+                    "Display the repositories by the shortest end of their path that is unique among the ones discovered, \
+                    like foo or work/foo when there are several foo. These names work with --only and --exclude-name. \
+                    The machine formats keep the full paths.",
+                )
+                .conflicts_with_all(["relative", "absolute", "tilde"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("tilde")
+                .long("tilde")
+                .help("Abbreviate the home directory with ~ in absolute paths")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("theme")
+                .long("theme")
+                .num_args(1)
+                .env("GITJUGGLING_THEME")
+                .value_parser(["light", "dark", "plain"])
+                .default_value("dark")
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("color")
+                .long("color")
+                .help("When to use colors, git's included")
+                .long_help(
+                    "When to use colors: auto when writing to a terminal, always or never. \
+                    With colors git colors its output as if it wrote to a terminal, like for diff or log --graph, \
+                    and that output is printed as is. With never git doesn't color it even if it's configured to.",
+                )
+                .value_name("WHEN")
+                .num_args(1)
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("prefix")
+                .long("prefix")
+                .help("Prefix every output line with the repository name, the default in CI")
+                .overrides_with("no_prefix")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_prefix")
+                .long("no-prefix")
+                .help("Don't prefix the output lines with the repository name, even in CI")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("timestamps")
+                .long("timestamps")
+                .help("Prefix every output line with the time its command completed, the default in CI")
+                .overrides_with("no_timestamps")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_timestamps")
+                .long("no-timestamps")
+                .help("Don't prefix the output lines with the time, even in CI")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("terminal_progress")
+                .long("terminal-progress")
+                .help("Show the progress in the title of the terminal and its progress indicator")
+                .long_help(
+                    "Show the progress of the run in the title of the terminal, like gitjuggling: 42/120 (3 failed), \
+                    and in its progress indicator on the terminals supporting OSC 9;4 like Windows Terminal. \
+                    The title is restored at the end of the run. With auto, only when stdout and stderr are a \
+                    terminal, not in CI nor with --watch.",
+                )
+                .value_name("WHEN")
+                .num_args(1)
+                .value_parser(["auto", "off"])
+                .default_value("auto"),
+        )
+        .arg(
+            clap::Arg::new("ci")
+                .long("ci")
+                .help("Use the output defaults of CI even if no CI system is detected")
+                .long_help(
+                    "Use the output defaults of CI even if no CI system is detected. \
+                    GitHub Actions, GitLab CI, Azure Pipelines, Buildkite and the systems setting CI are detected. \
+                    In CI there are no colors unless CLICOLOR_FORCE is set or --theme is given, no truecolor and no spinner, \
+                    the output lines are prefixed with the repository and the time, see --no-prefix and --no-timestamps, \
+                    and the output of each repository is folded if the system supports it, see --no-groups.",
+                )
+                .conflicts_with("no_ci")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_ci")
+                .long("no-ci")
+                .help("Use the usual output defaults even if a CI system is detected")
+                .env("GITJUGGLING_NO_CI")
+                .value_parser(clap::builder::BoolishValueParser::new())
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_groups")
+                .long("no-groups")
+                .help("Don't fold the output of each repository in CI")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("output_order")
+                .long("output-order")
+                .help("Print the output as commands complete or in sorted repository order")
+                .num_args(1)
+                .value_parser(["completion", "sorted"])
+                .default_value("completion"),
+        )
+        .arg(
+            clap::Arg::new("order")
+                .long("order")
+                .help("Run the repositories in any order, or the superprojects before their submodules with topo and after them with topo-reverse")
+                .long_help(
+                    "Run the repositories in any order, or the superprojects before their submodules with topo and after them with topo-reverse. \
+                    Only the submodules found with --include-submodules wait for their superproject or the other way around, \
+                    the repositories which don't depend on each other still run in parallel.",
+                )
+                .num_args(1)
+                .value_parser(["any", "topo", "topo-reverse"])
+                .default_value("any"),
+        )
+        .arg(
+            clap::Arg::new("collapse")
+                .long("collapse")
+                .help("Print each unique output once with the repositories that produced it")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("hide_empty")
+                .long("hide-empty")
+                .visible_alias("only-changed")
+                .help("Hide repositories whose command succeeded without any output")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("skip_diverged")
+                .long("skip-diverged")
+                .help("Skip the repositories whose branch diverged from its upstream, like before a pull")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("since_last_run")
+                .long("since-last-run")
+                .help("Skip the repositories that didn't change since the command last succeeded in them")
+                .long_help(
+                    "Skip the repositories that didn't change since the same command last succeeded in them: \
+                    neither HEAD, the index nor the files git status lists. The last runs are recorded in \
+                    $XDG_STATE_HOME/gitjuggling/last-runs.toml.",
+                )
+                .conflicts_with("watch")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("force")
+                .long("force")
+                .help("With --since-last-run, run in every repository and record the run")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Run a risky command in several repositories without asking for a confirmation")
+                .long_help(
+                    "Run a risky command like reset --hard or push --force in several repositories without \
+                    asking for a confirmation. Without a terminal to ask on, the risky commands only run with it.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_safety")
+                .long("no-safety")
+                .help("Don't look for risky commands, they run in every repository without asking")
+                .conflicts_with("yes")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("skip_states")
+                .long("skip-states")
+                .help("Skip the repositories in these states instead of running the command, like detached,rebasing")
+                .long_help(
+                    "Skip the repositories in these states instead of running the command and failing, \
+                    separated by commas: on-branch, detached, unborn, rebasing, applying, merging, \
+                    cherry-picking, reverting or bisecting.",
+                )
+                .value_name("STATES")
+                .num_args(1)
+                .value_delimiter(',')
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    RepoState::ALL.map(|state| state.name()),
+                ))
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("show_skipped")
+                .long("show-skipped")
+                .help("List the skipped repositories and why before the summary, which only counts them otherwise")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_skip_in_progress")
+                .long("no-skip-in-progress")
+                .help("Run the command in the repositories with a rebase, a merge or a bisection in progress")
+                .long_help(
+                    "Run the command in the repositories with an operation in progress: a rebase, git am, a merge, \
+                    a cherry-pick, a revert or a bisection. They are skipped by default unless the git subcommand \
+                    only reads the repository, like status, log, diff or grep.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("skip_broken")
+                .long("skip-broken")
+                .help("Skip the repositories whose HEAD can't be resolved instead of running the command")
+                .long_help(
+                    "Skip the repositories whose HEAD can't be resolved: it's neither a commit nor a ref, or it \
+                    points to a branch that doesn't exist while others do. By default the command runs in them \
+                    anyways, they're reported as broken in the summary.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("include_readonly")
+                .long("include-readonly")
+                .help("Run the command in the repositories that can't be written to, like on a read-only filesystem")
+                .long_help(
+                    "Run the command in the repositories whose git directory can't be written to, like on a \
+                    read-only filesystem. They are skipped by default unless the git subcommand only reads the \
+                    repository, like status, log, diff or grep.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .help("Print the directory, the git program and the environment of each command in its banner, and diagnostics on stderr")
+                .long_help(
+                    "Print the directory, the git program and the environment of each command in its banner. \
+                    Diagnostics are printed on stderr, stdout stays the same with --porcelain or --format json: \
+                    the effective settings and where each comes from, the repositories left out by a filter or skipped, \
+                    and how each command ran and ended. Twice, the submodules left out and every repository found are printed too.",
+                )
+                .action(clap::ArgAction::Count),
+        )
+        .arg(
+            clap::Arg::new("max_lines")
+                .long("max-lines")
+                .help("Only print the first and last N/2 lines of the output of each repository")
+                .value_name("N")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            clap::Arg::new("spill_threshold")
+                .long("spill-threshold")
+                .help("Spill the output of a repository bigger than SIZE to a temporary file, only a few lines around the cut are printed")
+                .long_help(
+                    "Spill the stdout or stderr of a repository bigger than SIZE to a temporary file, with a K, M or G suffix. \
+                    Only an excerpt of its beginning and of its end is kept in memory and printed with the repository, \
+                    the details of the failed repositories, the log files and the reports read all of it back.",
+                )
+                .value_name("SIZE")
+                .num_args(1)
+                .value_parser(maintenance::parse_size)
+                .default_value("8M"),
+        )
+        .arg(
+            clap::Arg::new("log_file")
+                .long("log-file")
+                .help("Write a complete, uncolored transcript of the run to a file")
+                .value_name("PATH")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("tee")
+                .long("tee")
+                .help("Also write the output to a file, uncolored")
+                .long_help(
+                    "Also write the output to a file, uncolored, while the terminal gets it as usual. \
+                    The file gets the repositories --hide-empty and --collapse leave out of the terminal. \
+                    It's replaced at once if it exists and written as the repositories complete.",
+                )
+                .value_name("FILE")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("log_append")
+                .long("log-append")
+                .help("Append to the log file instead of truncating it")
+                .requires("log_file")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("log_dir")
+                .long("log-dir")
+                .help("Write one log file per repository in a directory")
+                .value_name("DIR")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("log_overwrite")
+                .long("log-overwrite")
+                .help("Overwrite existing files in the log directory")
+                .requires("log_dir")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("no_branch")
+                .long("no-branch")
+                .help("Don't show the current branch of each repository")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("counts")
+                .long("counts")
+                .help("Show how many commits the branch of each repository is ahead and behind its upstream")
+                .long_help(
+                    "Show how many commits the branch of each repository is ahead and behind its upstream before the command, \
+                    like foo (main ↑2 ↓5) executing pull. Nothing is shown for a repository up to date or without an upstream. \
+                    The counts are in the ahead and behind fields of the results of --hook. \
+                    This runs git rev-list once more in every repository.",
+                )
+                .conflicts_with("no_branch")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("table")
+                .long("table")
+                .help("Print a table of all repositories at the end of the run")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("sort_table")
+                .long("sort-table")
+                .help("Sort the table by name, status or duration")
+                .num_args(1)
+                .value_parser(["name", "status", "duration"])
+                .default_value("name")
+                .requires("table"),
+        )
+        .arg(
+            clap::Arg::new("report_markdown")
+                .long("report-markdown")
+                .help("Write a Markdown report of the run to a file")
+                .value_name("FILE")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("notify")
+                .long("notify")
+                .help("Send a desktop notification when the run finishes")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("notify_after")
+                .long("notify-after")
+                .help("Only notify if the run took longer than this")
+                .value_name("DURATION")
+                .num_args(1)
+                .value_parser(humantime::parse_duration)
+                .default_value("10s")
+                .requires("notify"),
+        )
+        .arg(
+            clap::Arg::new("exit_zero")
+                .long("exit-zero")
+                .help("Always exit with code 0, for fire-and-forget runs like cron jobs")
+                .long_help(
+                    "Always exit with code 0, for fire-and-forget runs like cron jobs: even if commands failed, \
+                    the repositories couldn't be discovered, git couldn't be run or the run stopped before the \
+                    command was attempted everywhere. Only a usage error (2) and an interruption (130) still \
+                    exit with their code.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("fail_fast")
+                .long("fail-fast")
+                .help("Stop starting commands after the first failure")
+                .long_help(
+                    "Stop starting commands after the first failure, the commands already running complete. \
+                    The repositories left are listed as not attempted and the exit code is 5.",
+                )
+                .conflicts_with("watch")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("fail_threshold")
+                .long("fail-threshold")
+                .help("Only exit with a non-zero code if more than N repositories failed")
+                .value_name("N")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize))
+                .conflicts_with("exit_zero"),
+        )
+        .arg(
+            clap::Arg::new("fail_regex")
+                .long("fail-regex")
+                .help("Consider a command failed if its output matches this regex")
+                .value_name("RE")
+                .num_args(1),
+        )
+        .arg(
+            clap::Arg::new("ok_exit_codes")
+                .long("ok-exit-codes")
+                .help("Exit codes that are considered a success in addition to 0")
+                .value_name("CODES")
+                .num_args(1)
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(i32)),
+        )
+        .arg(
+            clap::Arg::new("fail_on_stderr")
+                .long("fail-on-stderr")
+                .help("Consider a command failed if it wrote to stderr, git progress lines excepted")
+                .long_help(
+                    "Consider a command failed if it wrote to stderr. \
+                    Git progress lines are ignored but anything else a command reports on stderr counts, \
+                    pass --quiet to git for commands that are chatty.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("print_failed")
+                .long("print-failed")
+                .help("Print the paths of the failed repositories on stdout, newline separated")
+                .long_help(
+                    "Print the paths of the failed repositories on stdout, newline separated. \
+                    All the other output goes to stderr.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("print_failed0")
+                .long("print-failed0")
+                .help("Print the paths of the failed repositories on stdout, NUL separated")
+                .long_help(
+                    "Print the paths of the failed repositories on stdout, NUL separated. \
+                    All the other output goes to stderr.",
+                )
+                .conflicts_with("print_failed")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("hyperlinks")
+                .long("hyperlinks")
+                .help("Make the repository paths clickable links in terminals supporting it")
+                .num_args(1)
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            clap::Arg::new("tui")
+                .long("tui")
+                .help("Browse the results in the terminal once the run is done")
+                .long_help(
+                    "Browse the results in the terminal once the run is done: the repositories grouped by status on the left, \
+                    the output of the selected one on the right. \
+                    The arrows select a repository, PgUp and PgDn scroll its output, / searches it and n or N go to the next \
+                    or the previous match, r runs the command again in the repository, y copies its path to the clipboard \
+                    of the terminals supporting OSC 52 and q quits. \
+                    The summary is printed after quitting, with the results of the repositories run again. \
+                    Ignored with a warning unless stdin and stdout are a terminal.",
+                )
+                .conflicts_with_all(["porcelain", "watch", "print_failed", "print_failed0"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("stats")
+                .long("stats")
+                .help("Print statistics about the duration of the run in the summary")
+                .long_help(
+                    "Print statistics about the duration of the run in the summary. \
+                    After a fetch, a pull, a clone or a push they also sum the objects and the bytes transferred, \
+                    read from the progress git writes. Git only writes it to a terminal so --progress is added to these commands, \
+                    unless --progress or --no-progress is given. Git writes it in English only: the repositories without it are counted as unknown.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("watch")
+                .long("watch")
+                .help("Keep running, re-run the command in a repository whenever its files change")
+                .long_help(
+                    "Keep running, re-run the command in a repository whenever its files change. \
+                    The output of all repositories is redrawn in place and Ctrl-C prints the summary of the last state.",
+                )
+                .conflicts_with_all([
+                    "porcelain",
+                    "collapse",
+                    "table",
+                    "stats",
+                    "print_failed",
+                    "print_failed0",
+                    "log_file",
+                    "log_dir",
+                    "tee",
+                    "report_markdown",
+                    "notify",
+                ])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("watch_git_exclude")
+                .long("watch-git-exclude")
+                .help("Ignore changes of this entry of the .git directory in watch mode, can be repeated")
+                .long_help(
+                    "Ignore changes of this entry of the .git directory in watch mode, can be repeated. \
+                    Giving it replaces the defaults. Lock files are always ignored.",
+                )
+                .value_name("NAME")
+                .num_args(1)
+                .action(clap::ArgAction::Append)
+                .default_values(watch::DEFAULT_GIT_EXCLUDES)
+                .requires("watch"),
+        )
+        .arg(
+            clap::Arg::new("git")
+                .long("git")
+                .help("The git program to run instead of the one in the PATH")
+                .value_name("PATH")
+                .num_args(1)
+                .env("GITJUGGLING_GIT")
+                .global(true)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("ssh_command")
+                .long("ssh-command")
+                .help("Set GIT_SSH_COMMAND to this command for every git process")
+                .value_name("CMD")
+                .num_args(1)
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("hook")
+                .long("hook")
+                .help("Stream the events of the run as NDJSON to the stdin of this shell command")
+                .long_help(
+                    "Stream the events of the run as NDJSON to the stdin of this shell command. \
+                    Every event is a JSON object with a version field, the version of the schema, and an event field: \
+                    run_start with the git arguments and the repositories, \
+                    result with the result of a repository as soon as it's known, \
+                    and run_end with the summary. \
+                    The hook failing never fails the run, it's killed if it's still running 5 seconds after the run.",
+                )
+                .value_name("COMMAND")
+                .num_args(1),
+        )
+        .arg(
+            clap::Arg::new("log_level")
+                .long("log-level")
+                .help("Log the discovery and execution to stderr at this level, RUST_LOG is used if not set")
+                .value_name("LEVEL")
+                .num_args(1)
+                .value_parser(["error", "warn", "info", "debug", "trace"]),
+        )
+        .arg(
+            clap::Arg::new("backend")
+                .long("backend")
+                .help("How the repositories are probed, libgit2 needs the libgit2 cargo feature")
+                .long_help(
+                    "How the state of the repositories, like their current branch, is probed. \
+                    cli spawns a git process for each query, libgit2 answers them in-process which is faster \
+                    with many repositories but needs gitjuggling to be built with the libgit2 cargo feature. \
+                    The command itself always runs with the git CLI.",
+                )
+                .num_args(1)
+                .value_parser(["cli", "libgit2"])
+                .default_value("cli"),
+        )
+        .arg(
+            clap::Arg::new("porcelain")
+                .long("porcelain")
+                .help("Print one stable, tab-separated line per repository meant for scripts")
+                .long_help(
+                    "Print one tab-separated line per repository: status, \
+                    exit code (- if the command didn't exit normally), duration in milliseconds, absolute path \
+                    and the reason the repository was skipped or not attempted, empty otherwise. \
+                    The status is one of ok (the command succeeded), fail (it failed), skip (the repository was skipped) \
+                    and not-attempted (the run stopped before the command was started in the repository, like with \
+                    --fail-fast), new values may be added. \
+                    The repositories not attempted come after the ones run, then the skipped ones. \
+                    Nothing else is printed, neither colors nor the command output nor the summary. \
+                    Fields will only ever be appended to this format so scripts can rely on it. \
+                    Paths containing a double quote, a backslash, a control character or a non-ASCII byte \
+                    are enclosed in double quotes and escaped in C style, like git does with core.quotePath.",
+                )
+                .conflicts_with_all(["collapse", "table", "stats", "print_failed", "print_failed0"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("alias")
+                .long("alias")
+                .help("Run the alias NAME of the config files, like @NAME")
+                .long_help(
+                    "Run the alias NAME of the config files, like @NAME as the first argument. \
+                    An alias expands to gitjuggling flags and git arguments, \
+                    the arguments following it are appended to its expansion.",
+                )
+                .value_name("NAME")
+                .num_args(1),
+        )
+        .arg(
+            clap::Arg::new("grep_mode")
+                .long("grep-mode")
+                .help("Print the output of git grep without banners, the paths starting with the repository")
+                .long_help(
+                    "Print the output of git grep without banners, the paths starting with the repository. \
+                    Repositories without matches print nothing, exiting with 1 isn't a failure and the summary counts the matching lines. \
+                    This is the default when the git subcommand is grep.",
+                )
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("dry_run")
+                .long("dry-run")
+                .short('n')
+                .help("Print the command that would run in every repository, without running it")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("args_file")
+                .long("args-file")
+                .help("Append arguments to the git arguments of the repositories mapped in a file")
+                .long_help(
+                    "Append arguments to the git arguments of the repositories mapped in a file. \
+                    Each line is the path or the glob of the repositories, relative to the root or absolute, \
+                    followed by their arguments split like a shell does, like `foo origin main:trunk`. \
+                    A * of a glob stays in a directory, ** goes into the subdirectories. \
+                    Empty lines and lines starting with # are ignored. \
+                    The placeholders are replaced in the arguments, --dry-run prints the command of each repository. \
+                    A pattern mapped twice or a repository mapped by several lines is an error.",
+                )
+                .value_name("FILE")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            clap::Arg::new("args_file_only")
+                .long("args-file-only")
+                .help("Only run in the repositories mapped by --args-file")
+                .requires("args_file")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("script")
+                .long("script")
+                .help("Run the git commands of a file one after the other in every repository, instead of the git arguments")
+                .long_help(
+                    "Run the git commands of a file one after the other in every repository, instead of the git arguments. \
+                    Each line is the git arguments of a command split like a shell does, optionally after git, like `fetch --prune`. \
+                    Empty lines and lines starting with # are ignored. \
+                    The commands of a repository stop at the first one failing, the failure names its step and the output of the \
+                    steps before it is printed too. \
+                    The placeholders are replaced in every line, --dry-run prints the commands of each repository. \
+                    The repos overrides of the config files, gitjuggling.extra-args and --args-file don't apply to them.",
+                )
+                .value_name("FILE")
+                .num_args(1)
+                .value_parser(clap::value_parser!(PathBuf))
+                .conflicts_with_all(["git_args", "args_file", "since_last_run", "grep_mode"]),
+        )
+        .arg(
+            clap::Arg::new("list")
+                .long("list")
+                .help("Print the absolute path of every repository the command would run in, sorted, and exit")
+                .long_help(
+                    "Print the absolute path of every repository the command would run in and exit, without running anything. \
+                    The paths are sorted, one per line, and quoted like the ones of --porcelain. \
+                    The filters and the skipped states apply, the git arguments are optional.\n\n\
+                    With --format json a JSON array is printed instead, an object per repository with the version of \
+                    the schema, the path, the path relative to its root, the short name and whether it's a submodule, \
+                    a worktree or a bare repository, always false as the bare repositories aren't discovered. With --enrich the branch and the origin URL are there too. \
+                    A path that isn't valid UTF-8 has its invalid bytes replaced, its bytes are in a field of the same \
+                    name suffixed with _hex, in hexadecimal.",
+                )
+                .conflicts_with_all(["dry_run", "porcelain", "watch"])
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("enrich")
+                .long("enrich")
+                .help("With --list --format json, also read the branch and the origin URL of every repository")
+                .requires("list")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("git_args")
+                .help("The git arguments, from the first one gitjuggling doesn't know. Put -- before them to pass everything to git")
+                .num_args(1..)
+                .required_unless_present_any(["version", "alias", "list", "script"])
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true)
+                .value_hint(clap::ValueHint::Other),
+        )
+}
+
+/// Builds the global rayon pool with `jobs` threads, 0 for one per CPU. Exits if it can't.
+fn build_thread_pool(jobs: usize) {
+    if let Err(err) = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+    {
+        eprintln!(
+            "{}",
+            format!("unable to start the threads: {}", err).bright_red()
+        );
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Checks that a glob pattern is valid, it's kept as a string.
+fn parse_glob(pattern: &str) -> Result<String, globset::Error> {
+    globset::Glob::new(pattern).map(|_| pattern.to_string())
+}
+
+/// Returns the value of `id` given on the command line or with its environment variable,
+/// then the one of the config file, then its default value.
+fn setting<T>(matches: &clap::ArgMatches, id: &str, config: Option<T>) -> Option<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable) => {
+            matches.get_one::<T>(id).cloned()
+        }
+        _ => config.or_else(|| matches.get_one::<T>(id).cloned()),
+    }
+}
+
+/// Like [`setting`] for the arguments taking several values.
+fn settings<T>(matches: &clap::ArgMatches, id: &str, config: Option<Vec<T>>) -> Option<Vec<T>>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    match matches.value_source(id) {
+        Some(ValueSource::CommandLine) | Some(ValueSource::EnvVariable) => matches
+            .get_many::<T>(id)
+            .map(|values| values.cloned().collect()),
+        _ => config.or_else(|| {
+            matches
+                .get_many::<T>(id)
+                .map(|values| values.cloned().collect())
+        }),
+    }
+}
+
+/// Loads the user config file, its profile selected with --profile, then the project config
+/// file of the root, unless --no-config is given.
+///
+/// `matches` are the ones of the command defining --root.
+fn load_config(matches: &clap::ArgMatches) -> anyhow::Result<Vec<Layer>> {
+    if matches.get_flag("no_config") {
+        return Ok(Vec::new());
+    }
+
+    let mut layers: Vec<Layer> = Layer::user()?.into_iter().collect();
+    if let Some(name) = matches.get_one::<String>("profile") {
+        let profile = Layer::profile(layers.first_mut(), name)?;
+        layers.push(profile);
+    }
+
+    match matches.get_one::<PathBuf>("config") {
+        Some(path) => layers.push(Layer::file(path)?),
+        None => {
+            let user = config::merge(&layers);
+            let roots = settings(matches, "root", user.root.map(|root| vec![root]))
+                .unwrap_or_else(|| vec![default_root()]);
+
+            match roots.as_slice() {
+                [root] => layers.extend(Layer::project(root)?),
+                _ => debug!("several roots, no project config file is read"),
+            }
+        }
+    }
+
+    for layer in &layers {
+        debug!(source = %layer.source, config = ?layer.config, "loaded config");
+    }
+
+    Ok(layers)
+}
+
+/// Loads and merges the config files like [`load_config`], exits if one of them is invalid.
+fn config_or_exit(matches: &clap::ArgMatches) -> Config {
+    match load_config(matches) {
+        Ok(layers) => {
+            for warning in layers.iter().flat_map(|layer| &layer.warnings) {
+                eprintln!("warning: {}", warning);
+            }
+            config::merge(&layers)
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_USAGE);
+        }
+    }
+}
+
+/// Returns how many times -v was given, 0 for the commands without it.
+fn verbosity(matches: &clap::ArgMatches) -> u8 {
+    matches
+        .try_get_one::<u8>("verbose")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Returns the settings given with flags on the command line, the environment variables of the
+/// flags are already in [`Layer::env`].
+fn command_line_config(matches: &clap::ArgMatches) -> Config {
+    fn given<T>(matches: &clap::ArgMatches, id: &str) -> Option<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        (matches.value_source(id) == Some(ValueSource::CommandLine))
+            .then(|| matches.get_one::<T>(id).cloned())
+            .flatten()
+    }
+
+    let roots: Vec<PathBuf> = matches
+        .get_many::<PathBuf>("root")
+        .filter(|_| matches.value_source("root") == Some(ValueSource::CommandLine))
+        .map(|roots| roots.cloned().collect())
+        .unwrap_or_default();
+    let excludes: Vec<String> = matches
+        .get_many::<String>("exclude")
+        .filter(|_| matches.value_source("exclude") == Some(ValueSource::CommandLine))
+        .map(|excludes| excludes.cloned().collect())
+        .unwrap_or_default();
+
+    let mut config = Config::default();
+    // The config only has a single root
+    if let [root] = roots.as_slice() {
+        config.root = Some(root.clone());
+    }
+    config.depth = given(matches, "depth");
+    config.jobs = given(matches, "jobs");
+    config.excludes = (!excludes.is_empty()).then_some(excludes);
+    config.theme = given(matches, "theme");
+    config.git = given(matches, "git");
+    config.ssh_command = given(matches, "ssh_command");
+
+    config
+}
+
+/// Prints the effective settings on stderr with where each comes from: the defaults, the config
+/// files, the environment and the command line.
+fn print_effective_config(matches: &clap::ArgMatches) {
+    let mut layers = vec![Layer::defaults()];
+    layers.extend(load_config(matches).unwrap_or_default());
+    layers.extend(Layer::env().ok());
+    layers.push(Layer::command_line(command_line_config(matches)));
+
+    for line in config::render(&config::resolve(&layers)).lines() {
+        eprintln!("config: {}", line);
+    }
+}
+
+/// Returns the git to run from the command line or the config file.
+fn git_from_matches(matches: &clap::ArgMatches, config: &Config) -> Git {
+    let mut git = match setting(matches, "git", config.git.clone()) {
+        Some(program) => Git::new(program),
+        None => Git::default(),
+    };
+    if let Some(ssh_command) = setting(matches, "ssh_command", config.ssh_command.clone()) {
+        git = git.ssh_command(ssh_command);
+    }
+
+    git
+}
+
+/// Exits if `git` can't run.
+fn check_git(git: &Git) {
+    match git.version() {
+        Ok(version) => debug!(program = %git.program().display(), %version, "using git"),
+        Err(err) => {
+            eprintln!("{}; nothing was executed", err);
+            exit(EXIT_GIT);
+        }
+    }
+}
+
+/// The repositories found with the discovery arguments.
+struct Discovery {
+    roots: Vec<PathBuf>,
+    /// Set if the roots were given on the command line, with the environment or in a config file
+    explicit_roots: bool,
+    paths: Vec<PathBuf>,
+    /// The submodules of the repositories having a .gitmodules file, parsed while discovering
+    gitmodules: HashMap<PathBuf, GitModules>,
+    /// The remotes of the repositories, only read with --with-remotes or a filter using them
+    remotes: Option<Remotes>,
+    /// The repositories and directories left out by a marker file
+    marked: Vec<Marked>,
+}
+
+/// Returns the repositories named by the argument `id`, like --only, or None if it isn't given.
+/// Exits if a name matches none or several of `paths`.
+fn selected_by_name(
+    matches: &clap::ArgMatches,
+    id: &str,
+    paths: &[PathBuf],
+) -> Option<Vec<PathBuf>> {
+    let names: Vec<&String> = matches.try_get_many::<String>(id).ok().flatten()?.collect();
+    let short_names = names::short_names(paths);
+
+    let mut selected = Vec::new();
+    for name in names {
+        match names::find(name, paths).as_slice() {
+            [index] => selected.push(paths[*index].clone()),
+            [] => {
+                eprintln!("no repository named {}", name);
+                process::exit(EXIT_USAGE);
+            }
+            indices => {
+                let candidates: Vec<&str> = indices
+                    .iter()
+                    .map(|index| short_names[*index].as_str())
+                    .collect();
+                eprintln!(
+                    "ambiguous name {}, it could be {}",
+                    name,
+                    candidates.join(", ")
+                );
+                process::exit(EXIT_USAGE);
+            }
+        }
+    }
+
+    Some(selected)
+}
+
+/// The remotes of every repository, with their name and fetch URL.
+type Remotes = HashMap<PathBuf, Vec<(String, String)>>;
+
+/// Reads the remotes of every repository in parallel. A repository whose remotes can't be read
+/// has none, with a warning.
+fn read_remotes(git: &Git, paths: &[PathBuf]) -> Remotes {
+    paths
+        .par_iter()
+        .map(|path| {
+            let remotes = remotes::list(git, path).unwrap_or_else(|err| {
+                eprintln!(
+                    "warning: unable to read the remotes of {}: {}",
+                    path.display(),
+                    err
+                );
+                Vec::new()
+            });
+            (path.clone(), remotes)
+        })
+        .collect()
+}
+
+/// Returns true if the output is meant for a program: a JSON format or --porcelain.
+fn machine_output(matches: &clap::ArgMatches) -> bool {
+    let json = matches
+        .try_get_one::<String>("format")
+        .ok()
+        .flatten()
+        .is_some_and(|format| format == "json");
+    let porcelain = matches
+        .try_get_one::<bool>("porcelain")
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false);
+
+    json || porcelain
+}
+
+/// Returns where to insert `--progress` in the git arguments `args` of a network command, right
+/// after the subcommand, or None if it isn't one or a progress option is already given.
+fn progress_index(args: &[&str]) -> Option<usize> {
+    let index = grep::subcommand_index(args).filter(|&index| transfer::is_network(args[index]))?;
+    let given = args[index..]
+        .iter()
+        .any(|arg| *arg == "--progress" || *arg == "--no-progress");
+
+    (!given).then_some(index + 1)
+}
+
+/// Discovers the repositories with --root, --depth, --no-nested, --exclude and --group, exits on
+/// errors.
+fn discover(matches: &clap::ArgMatches, config: &Config) -> Discovery {
+    let depth = setting(matches, "depth", config.depth).unwrap_or(3);
+
+    let mut includes = Vec::new();
+    for name in matches.get_many::<String>("group").unwrap_or_default() {
+        match config.groups.get(name) {
+            Some(patterns) => includes.extend(patterns.iter().cloned()),
+            None => {
+                let names: Vec<&str> = config.groups.keys().map(String::as_str).collect();
+                eprintln!(
+                    "unknown group {}, the groups are: {}",
+                    name,
+                    names.join(", ")
+                );
+                process::exit(EXIT_USAGE);
+            }
+        }
+    }
+
+    let explicit_roots = settings(matches, "root", config.root.clone().map(|root| vec![root]));
+    let roots: Vec<PathBuf> = explicit_roots
+        .clone()
+        .unwrap_or_else(|| vec![default_root()])
+        .into_iter()
+        .map(|root| match root.canonicalize() {
+            Ok(root) => root,
+            Err(err) => {
+                let error = format!("invalid root {}: {}", root.display(), err);
+                eprintln!("{}", error.bright_red());
+                exit(EXIT_DISCOVERY);
+            }
+        })
+        .collect();
+
+    let options = DiscoverOptions {
+        roots: roots.clone(),
+        depth,
+        excludes: settings(matches, "exclude", config.excludes.clone()).unwrap_or_default(),
+        includes,
+        nested: !matches.get_flag("no_nested"),
+        submodules: matches.get_flag("include_submodules"),
+    };
+    // A spinner on stderr, a slow walk would otherwise look like it hangs
+    let spinner = (io::stderr().is_terminal()
+        && !machine_output(matches)
+        && Ci::from_matches(matches).is_none())
+    .then(|| Mutex::new(spinner::Spinner::new(io::stderr())));
+    let discovered = discover_with_markers(&options, &|progress| {
+        // Another thread is drawing it, this update can be dropped
+        if let Some(Ok(mut spinner)) = spinner.as_ref().map(Mutex::try_lock) {
+            spinner.update(progress);
+        }
+    });
+    if let Some(spinner) = spinner {
+        spinner.into_inner().unwrap().finish();
+    }
+    let Discovered {
+        superprojects,
+        marked,
+        excluded,
+    } = match discovered {
+        Err(err) => {
+            eprintln!("{}", err.to_string().bright_red());
+            exit(EXIT_DISCOVERY);
+        }
+        Ok(discovered) => discovered,
+    };
+
+    // Why the repositories were or weren't discovered, on stderr
+    let verbosity = verbosity(matches);
+    let diagnostic = |path: &Path, what: &str| {
+        eprintln!("{}: {}", relative_to_root(&roots, path).display(), what);
+    };
+    if verbosity > 0 {
+        for excluded in &excluded {
+            if excluded.reason != "submodule" || verbosity > 1 {
+                diagnostic(&excluded.path, &format!("left out, {}", excluded.reason));
+            }
+        }
+    }
+    if verbosity > 1 {
+        for (path, _) in &superprojects {
+            diagnostic(path, "found");
+        }
+    }
+
+    let mut paths = Vec::with_capacity(superprojects.len());
+    let mut gitmodules = HashMap::new();
+    for (path, submodules) in superprojects {
+        if let Some(submodules) = submodules {
+            gitmodules.insert(path.clone(), submodules);
+        }
+        paths.push(path);
+    }
+
+    // A root in a repository finds it or nothing, rather than the projects next to it
+    let include_self = matches.get_flag("include_self");
+    let exclude_self = matches.get_flag("exclude_self");
+    for root in &roots {
+        let Some(repository) = state::enclosing_repository(root) else {
+            continue;
+        };
+        if exclude_self {
+            paths.retain(|path| *path != repository);
+        } else if include_self {
+            if !paths.contains(&repository) {
+                paths.insert(0, repository);
+            }
+        } else if !machine_output(matches) && paths.iter().all(|path| *path == repository) {
+            let projects = repository.parent().unwrap_or(&repository);
+            let note = if paths.is_empty() {
+                format!(
+                    "note: {} is in the repository {} and nothing was found below it. Pass --include-self \
+                     to use the repository, or --root {} to search the projects next to it",
+                    root.display(),
+                    repository.display(),
+                    projects.display()
+                )
+            } else {
+                format!(
+                    "note: {} is a repository, only it was found. Pass --root {} to search the projects \
+                     next to it",
+                    root.display(),
+                    projects.display()
+                )
+            };
+            eprintln!("{}", note.bright_yellow());
+        }
+    }
+
+    let remote_matches =
+        matches
+            .get_one::<String>("remote_matches")
+            .map(|re| match regex::Regex::new(re) {
+                Ok(re) => re,
+                Err(err) => {
+                    eprintln!("invalid --remote-matches: {}", err);
+                    process::exit(EXIT_USAGE);
+                }
+            });
+    let has_remotes: Vec<&String> = matches
+        .get_many::<String>("has_remote")
+        .unwrap_or_default()
+        .collect();
+
+    let exclude_hosts: Vec<&String> = matches
+        .get_many::<String>("exclude_host")
+        .unwrap_or_default()
+        .collect();
+    let include_hosts: Vec<&String> = matches
+        .get_many::<String>("include_host")
+        .unwrap_or_default()
+        .collect();
+
+    let remotes = (matches.get_flag("with_remotes")
+        || remote_matches.is_some()
+        || !has_remotes.is_empty()
+        || !exclude_hosts.is_empty()
+        || !include_hosts.is_empty())
+    .then(|| read_remotes(&git_from_matches(matches, config), &paths));
+    if let Some(remotes) = &remotes {
+        paths.retain(|path| {
+            let remotes = &remotes[path];
+            if let Some(re) = remote_matches
+                .as_ref()
+                .filter(|re| !remotes.iter().any(|(_, url)| re.is_match(url)))
+            {
+                if verbosity > 0 {
+                    diagnostic(path, &format!("left out by --remote-matches {}", re));
+                }
+                return false;
+            }
+            if let Some(name) = has_remotes
+                .iter()
+                .find(|name| !remotes.iter().any(|(remote, _)| remote == **name))
+            {
+                if verbosity > 0 {
+                    diagnostic(path, &format!("left out by --has-remote {}", name));
+                }
+                return false;
+            }
+
+            // The host filters only apply to the repositories with an origin on a host
+            let Some(host) = remotes
+                .iter()
+                .find(|(remote, _)| remote == "origin")
+                .and_then(|(_, url)| remotes::host(url))
+            else {
+                return true;
+            };
+            if let Some(excluded) = exclude_hosts
+                .iter()
+                .find(|excluded| excluded.eq_ignore_ascii_case(host))
+            {
+                if verbosity > 0 {
+                    diagnostic(path, &format!("left out by --exclude-host {}", excluded));
+                }
+                return false;
+            }
+            if !include_hosts.is_empty()
+                && !include_hosts
+                    .iter()
+                    .any(|included| included.eq_ignore_ascii_case(host))
+            {
+                if verbosity > 0 {
+                    diagnostic(
+                        path,
+                        &format!("left out by --include-host, its origin is on {}", host),
+                    );
+                }
+                return false;
+            }
+
+            true
+        });
+    }
+
+    let name_patterns: Vec<String> = matches
+        .try_get_many::<String>("name")
+        .ok()
+        .flatten()
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let name_globs = (!name_patterns.is_empty()).then(|| {
+        let case_sensitive = matches
+            .try_get_one::<bool>("case_sensitive")
+            .ok()
+            .flatten()
+            .copied()
+            .unwrap_or(false);
+        match names::name_globs(&name_patterns, case_sensitive) {
+            Ok(globs) => globs,
+            Err(err) => {
+                eprintln!("invalid --name: {}", err);
+                process::exit(EXIT_USAGE);
+            }
+        }
+    });
+
+    // The names are resolved against every repository discovered, the filters don't change them
+    let only = selected_by_name(matches, "only", &paths);
+    let exclude_names = selected_by_name(matches, "exclude_name", &paths);
+    paths.retain(|path| {
+        if only.as_ref().is_some_and(|only| !only.contains(path)) {
+            if verbosity > 0 {
+                diagnostic(path, "left out by --only");
+            }
+            return false;
+        }
+        if exclude_names
+            .as_ref()
+            .is_some_and(|excluded| excluded.contains(path))
+        {
+            if verbosity > 0 {
+                diagnostic(path, "left out by --exclude-name");
+            }
+            return false;
+        }
+        if name_globs
+            .as_ref()
+            .is_some_and(|globs| !names::matches_name(globs, path))
+        {
+            if verbosity > 0 {
+                diagnostic(path, "left out by --name");
+            }
+            return false;
+        }
+
+        true
+    });
+
+    Discovery {
+        roots,
+        explicit_roots: explicit_roots.is_some(),
+        paths,
+        gitmodules,
+        remotes,
+        marked,
+    }
+}
+
+/// Sets up the colors for --theme, --color, --porcelain and the `ci`, on top of NO_COLOR and
+/// whether stdout is a terminal that colored already follows. Returns the theme.
+fn setup_colors(matches: &clap::ArgMatches, config: &Config, ci: Option<Ci>) -> Theme {
+    let theme_name = setting(matches, "theme", config.theme.clone())
+        .map(|s| s.parse::<ThemeName>().unwrap())
+        .unwrap_or(ThemeName::Dark);
+    if let Some(ci) = ci {
+        // CI logs usually render colors but aren't terminals, asking for a theme forces them
+        let forced = env::var_os("CLICOLOR_FORCE").is_some_and(|value| value != "0");
+        if matches.value_source("theme") == Some(ValueSource::CommandLine) {
+            colored::control::set_override(true);
+        } else if !forced {
+            colored::control::set_override(false);
+        }
+        if verbosity(matches) > 0 {
+            eprintln!("ci: {}, using its output defaults", ci.name());
+        }
+    }
+    match matches.get_one::<String>("color").unwrap().as_str() {
+        "always" => colored::control::set_override(true),
+        "never" => colored::control::set_override(false),
+        _ => {}
+    }
+    if theme_name == ThemeName::Plain || matches.get_flag("porcelain") {
+        colored::control::set_override(false);
+    }
+
+    match ci {
+        Some(_) => Theme::ansi(theme_name),
+        None => Theme::new(theme_name),
+    }
+    .with_colors(&config.colors)
+}
+
+/// Records the run in the history file, warnings aside nothing stops the run.
+fn record_history(
+    start_time: SystemTime,
+    elapsed: Duration,
+    git_args: &[&str],
+    roots: &[PathBuf],
+    items: &[&Item],
+    skipped: &[Skipped],
+    not_attempted: &[NotAttempted],
+) {
+    let Some(path) = history::history_path() else {
+        return;
+    };
+
+    let mut repositories: Vec<history::Entry> = items
+        .iter()
+        .map(|item| {
+            let failed = !item.result.success;
+            let output = |output: &RawOutput| {
+                if failed {
+                    history::truncate(&output.read_lossy())
+                } else {
+                    String::new()
+                }
+            };
+            history::Entry {
+                path: item.result.path.clone(),
+                status: item.status().name().to_string(),
+                exit_code: item.result.exit_code,
+                duration_ms: item.result.duration.as_millis() as u64,
+                reason: failed.then(|| item.result.failure_reason()),
+                in_progress: false,
+                stdout: output(&item.result.stdout),
+                stderr: output(&item.result.stderr),
+            }
+        })
+        .collect();
+    repositories.extend(skipped.iter().map(|skipped| history::Entry {
+        path: skipped.path.clone(),
+        status: skipped.status().name().to_string(),
+        exit_code: None,
+        duration_ms: 0,
+        reason: Some(skipped.reason.clone()),
+        in_progress: skipped.in_progress,
+        stdout: String::new(),
+        stderr: String::new(),
+    }));
+    repositories.extend(not_attempted.iter().map(|not_attempted| history::Entry {
+        path: not_attempted.path.clone(),
+        status: not_attempted.status().name().to_string(),
+        exit_code: None,
+        duration_ms: 0,
+        reason: Some(not_attempted.reason.clone()),
+        in_progress: false,
+        stdout: String::new(),
+        stderr: String::new(),
+    }));
+
+    let run = history::Run {
+        id: 0,
+        time: humantime::format_rfc3339_seconds(start_time).to_string(),
+        args: git_args.iter().map(|arg| arg.to_string()).collect(),
+        roots: roots.to_vec(),
+        duration_ms: elapsed.as_millis() as u64,
+        interrupted: INTERRUPTED.load(Ordering::SeqCst),
+        repositories,
+    };
+    match history::record(&path, run) {
+        Ok(id) => debug!(id, path = %path.display(), "recorded the run"),
+        Err(err) => debug!(%err, "unable to record the run"),
+    }
+}
+
+/// Logs to stderr so that the logs never mix with the output.
+///
+/// --log-level takes precedence over RUST_LOG, nothing but warnings and errors are logged by default.
+fn setup_logging(level: Option<&str>) {
+    let filter = match level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+    };
+
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .with_env_filter(filter)
+        .init();
+}
+
+/// Returns the path of a repository relative to the root it's under.
+fn relative_to_root<'a>(roots: &[PathBuf], path: &'a Path) -> &'a Path {
+    roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+}
+
+/// Returns the globs of the repos overrides of the config skipping `subcommand`.
+fn config_skips(overrides: &IndexMap<String, RepoOverride>, subcommand: &str) -> globset::GlobSet {
+    let mut builder = globset::GlobSetBuilder::new();
+    for (glob, repo) in overrides {
+        if repo.skip.iter().any(|skip| skip == subcommand) {
+            builder.add(
+                globset::Glob::new(glob).expect("the globs are checked when the config is parsed"),
+            );
+        }
+    }
+
+    builder
+        .build()
+        .expect("the globs are checked when the config is parsed")
+}
+
+/// Asks for a confirmation before running a risky command of the safety catalogue in `paths`,
+/// one of the `commands` of a repository, the command isn't run without it. There's no one to
+/// ask if stdin or stderr isn't a terminal: the run stops, --yes must be passed instead.
+fn confirm_risky(paths: &[PathBuf], commands: impl Fn(&Path) -> Vec<Vec<String>>) {
+    // The extra arguments of a repository can make its command risky
+    let mut findings: IndexMap<safety::Finding, usize> = IndexMap::new();
+    for path in paths {
+        let mut found = HashSet::new();
+        for command in commands(path) {
+            let args: Vec<&str> = command.iter().map(String::as_str).collect();
+            found.extend(safety::check(&args));
+        }
+        for finding in found {
+            *findings.entry(finding).or_default() += 1;
+        }
+    }
+    if findings.is_empty() {
+        return;
+    }
+    findings.sort_by(|a, _, b, _| b.risk.cmp(&a.risk));
+
+    for (finding, count) in &findings {
+        eprintln!("{}, in {} repositories", finding, count);
+    }
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        eprintln!(
+            "refusing to run a risky command without a confirmation, pass --yes to run it anyway"
+        );
+        process::exit(EXIT_USAGE);
+    }
+
+    eprint!("Run it in {} repositories? [y/N] ", paths.len());
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err()
+        || !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    {
+        eprintln!("not running");
+        process::exit(EXIT_USAGE);
+    }
+}
+
+/// The placeholder replaced by the host of the origin remote in the git arguments.
+const ORIGIN_HOST: &str = "{origin_host}";
+
+/// Returns the git arguments of every repository: `git_args` changed by the `overrides` whose
+/// glob matches, followed by the gitjuggling.extra-args git config of the repository and by its
+/// `mapped` arguments of --args-file.
+///
+/// With the `remotes`, [`ORIGIN_HOST`] is replaced by the host of the origin remote.
+fn repository_args(
+    git: &Git,
+    roots: &[PathBuf],
+    paths: &[PathBuf],
+    overrides: &IndexMap<String, RepoOverride>,
+    mapped: &HashMap<PathBuf, Vec<String>>,
+    remotes: Option<&Remotes>,
+    git_args: &[&str],
+) -> HashMap<PathBuf, Vec<String>> {
+    let overrides: Vec<(&str, globset::GlobMatcher, &RepoOverride)> = overrides
+        .iter()
+        .map(|(glob, repo)| {
+            let matcher = globset::Glob::new(glob)
+                .expect("the globs are checked when the config is parsed")
+                .compile_matcher();
+            (glob.as_str(), matcher, repo)
+        })
+        .collect();
+
+    paths
+        .par_iter()
+        .map(|path| {
+            let relative = relative_to_root(roots, path);
+            let mut args: Vec<String> = git_args.iter().map(|arg| arg.to_string()).collect();
+
+            let matching: Vec<_> = overrides
+                .iter()
+                .filter(|(_, matcher, _)| matcher.is_match(relative))
+                .collect();
+            if matching.len() > 1 {
+                let globs: Vec<&str> = matching.iter().map(|(glob, _, _)| *glob).collect();
+                debug!(
+                    path = %path.display(),
+                    ?globs,
+                    "several repos overrides apply, in the order they're declared"
+                );
+            }
+            for (_, _, repo) in matching {
+                if let Some(replace) = &repo.replace {
+                    args = replace.clone();
+                }
+                args.extend(repo.append.iter().cloned());
+            }
+
+            if let Some(extra_args) = git.config(path, "gitjuggling.extra-args") {
+                match shlex::split(&extra_args) {
+                    Some(extra_args) => args.extend(extra_args),
+                    None => eprintln!(
+                        "warning: invalid gitjuggling.extra-args in {}: {}",
+                        path.display(),
+                        extra_args
+                    ),
+                }
+            }
+            if let Some(mapped) = mapped.get(path) {
+                args.extend(mapped.iter().cloned());
+            }
+
+            if let Some(remotes) = remotes {
+                replace_origin_host(path, remotes, &mut args);
+            }
+
+            (path.clone(), args)
+        })
+        .collect()
+}
+
+/// Replaces [`ORIGIN_HOST`] in the `args` of the repository at `path` by the host of its origin
+/// remote, found in the `remotes`.
+fn replace_origin_host<'a>(
+    path: &Path,
+    remotes: &Remotes,
+    args: impl IntoIterator<Item = &'a mut String>,
+) {
+    let args: Vec<&mut String> = args
+        .into_iter()
+        .filter(|arg| arg.contains(ORIGIN_HOST))
+        .collect();
+    if args.is_empty() {
+        return;
+    }
+
+    let host = remotes[path]
+        .iter()
+        .find(|(name, _)| name == "origin")
+        .and_then(|(_, url)| remotes::host(url));
+    if host.is_none() {
+        eprintln!(
+            "warning: {} has no origin with a host, {} is replaced by nothing",
+            path.display(),
+            ORIGIN_HOST
+        );
+    }
+    for arg in args {
+        *arg = arg.replace(ORIGIN_HOST, host.unwrap_or(""));
+    }
+}
+
+/// The subcommands named like a git command, `gitjuggling status --short` means git status.
+const GIT_NAMED_SUBCOMMANDS: &[&str] = &["status", "config", "maintenance"];
+
+/// Parses the command line, the arguments given to a subcommand named like a git command that it
+/// doesn't know are passed to git instead, as if they were after a `--`.
+fn parse_args() -> (Vec<OsString>, clap::ArgMatches) {
+    let args: Vec<OsString> = env::args_os().collect();
+    let err = match cli().try_get_matches_from(&args) {
+        Ok(matches) => return (args, matches),
+        Err(err) => err,
+    };
+    let unknown = matches!(
+        err.kind(),
+        clap::error::ErrorKind::UnknownArgument
+            | clap::error::ErrorKind::InvalidSubcommand
+            | clap::error::ErrorKind::TooManyValues
+    );
+    // The subcommand is always the first argument, the options can't come before it
+    let shadowed = args
+        .get(1)
+        .and_then(|arg| arg.to_str())
+        .filter(|arg| GIT_NAMED_SUBCOMMANDS.contains(arg));
+    if !unknown || shadowed.is_none() {
+        err.exit();
+    }
+
+    let mut fallback = args;
+    fallback.insert(1, OsString::from("--"));
+    match cli().try_get_matches_from(&fallback) {
+        Ok(matches) => (fallback, matches),
+        Err(_) => err.exit(),
+    }
+}
+
+/// An alias of the config files and the arguments it expanded to.
+struct Alias {
+    name: String,
+    args: Vec<String>,
+}
+
+/// Expands the alias given with @NAME or --alias, the command line is parsed again with the
+/// arguments of the alias in its place.
+fn expand_alias(
+    args: Vec<OsString>,
+    matches: clap::ArgMatches,
+) -> (clap::ArgMatches, Option<Alias>) {
+    let git_args: Vec<&String> = matches
+        .get_many::<String>("git_args")
+        .unwrap_or_default()
+        .collect();
+    // The git arguments are always the last ones
+    let mut position = args.len() - git_args.len();
+
+    let (name, mut skip) = match (matches.get_one::<String>("alias"), git_args.first()) {
+        (Some(name), _) => (name.clone(), 0),
+        (None, Some(first)) if first.starts_with('@') => (first[1..].to_string(), 1),
+        _ => return (matches, None),
+    };
+    // The arguments following the alias are appended to its expansion, a -- separating them
+    // from the flags would end up in the middle of the git arguments
+    if position > 0 && args[position - 1] == "--" {
+        position -= 1;
+        skip += 1;
+    }
+
+    let config = match load_config(&matches) {
+        Ok(layers) => config::merge(&layers),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(EXIT_USAGE);
+        }
+    };
+    let Some(expansion) = config.aliases.get(&name) else {
+        let names: Vec<&str> = config.aliases.keys().map(String::as_str).collect();
+        eprintln!(
+            "unknown alias {}, the aliases are: {}",
+            name,
+            names.join(", ")
+        );
+        process::exit(EXIT_USAGE);
+    };
+
+    let mut expanded = args[..position].to_vec();
+    expanded.extend(expansion.iter().map(OsString::from));
+    expanded.extend_from_slice(&args[position + skip..]);
+
+    let matches = cli().get_matches_from(expanded);
+    let nested = expansion
+        .iter()
+        .any(|arg| arg == "--alias" || arg.starts_with("--alias="))
+        || matches
+            .get_many::<String>("git_args")
+            .and_then(|mut git_args| git_args.next())
+            .is_some_and(|first| first.starts_with('@'));
+    if nested {
+        eprintln!("alias {} uses another alias, aliases can't be nested", name);
+        process::exit(EXIT_USAGE);
+    }
+
+    let alias = Alias {
+        name,
+        args: expansion.clone(),
+    };
+    (matches, Some(alias))
+}
+
+fn main() {
+    let (args, matches) = parse_args();
+    let (matches, alias) = expand_alias(args, matches);
+
+    setup_logging(matches.get_one::<String>("log_level").map(String::as_str));
+    EXIT_ZERO.store(matches.get_flag("exit_zero"), Ordering::SeqCst);
+    // NO_COLOR wins over CLICOLOR_FORCE, see https://no-color.org
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        colored::control::set_override(false);
+    }
+    if let Some(alias) = &alias {
+        debug!(name = %alias.name, args = ?alias.args, "expanded alias");
+    }
+
+    if let Some(subcommand) = matches.subcommand() {
+        match subcommand {
+            ("completions", sub_matches) => cmd::completions::run(sub_matches),
+            ("doctor", sub_matches) => cmd::doctor::run(sub_matches),
+            ("status", sub_matches) => cmd::status::run(sub_matches),
+            ("sync", sub_matches) => cmd::sync::run(sub_matches),
+            ("audit", sub_matches) => cmd::audit::run(sub_matches),
+            ("timeline", sub_matches) => cmd::timeline::run(sub_matches),
+            ("branches", sub_matches) => cmd::branches::run(sub_matches),
+            ("prune-branches", sub_matches) => cmd::prune_branches::run(sub_matches),
+            ("off-default", sub_matches) => cmd::off_default::run(sub_matches),
+            ("diverged", sub_matches) => cmd::diverged::run(sub_matches),
+            ("verify", sub_matches) => cmd::verify::run(sub_matches),
+            ("sizes", sub_matches) => cmd::sizes::run(sub_matches),
+            ("apply-all", sub_matches) => cmd::apply_all::run(sub_matches),
+            ("push-all", sub_matches) => cmd::push_all::run(sub_matches),
+            ("submodules", sub_matches) => cmd::submodules::run(sub_matches),
+            ("switch-all", sub_matches) => cmd::switch_all::run(sub_matches),
+            ("tag-all", sub_matches) => cmd::tag_all::run(sub_matches),
+            ("stash-all", sub_matches) => cmd::stash_all::run(sub_matches),
+            ("unstash-all", sub_matches) => cmd::unstash_all::run(sub_matches),
+            ("config", sub_matches) => cmd::config::run(sub_matches),
+            ("history", sub_matches) => cmd::history::run(&matches, sub_matches),
+            ("maintenance", sub_matches) => cmd::maintenance::run(sub_matches),
+            ("remotes", sub_matches) => cmd::remotes::run(sub_matches),
+            ("clone-all", sub_matches) => cmd::clone_all::run(sub_matches),
+            ("manifest", sub_matches) => cmd::manifest::run(sub_matches),
+            (name, _) => unreachable!("unknown subcommand {}", name),
+        }
+        return;
+    }
+
+    if matches.get_flag("version") {
+        let format = matches
+            .get_one::<String>("format")
+            .map(|s| s.parse::<Format>().unwrap())
+            .unwrap_or(Format::Text);
+        let version = version::Version::current();
+
+        match format {
+            Format::Text => print!("{}", version.render()),
+            Format::Json => print!("{}", version.render_json()),
+        }
+        return;
+    }
+
+    let config = config_or_exit(&matches);
+    if verbosity(&matches) > 0 {
+        print_effective_config(&matches);
+    }
+
+    // The steps of --script run instead of the git arguments, the messages name the script
+    let mut script = matches
+        .get_one::<PathBuf>("script")
+        .map(|path| match Script::load(path) {
+            Ok(script) => (path.to_string_lossy().to_string(), script),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+        });
+    // git only writes the progress --stats reads to a terminal
+    let stats = matches.get_flag("stats");
+    if stats {
+        for step in script.iter_mut().flat_map(|(_, script)| &mut script.steps) {
+            let step_args: Vec<&str> = step.iter().map(String::as_str).collect();
+            if let Some(index) = progress_index(&step_args) {
+                step.insert(index, "--progress".to_string());
+            }
+        }
+    }
+    let mut git_args: Vec<&str> = match &script {
+        Some((path, _)) => vec!["--script", path],
+        None => matches
+            .get_many::<String>("git_args")
+            .unwrap_or_default()
+            .map(String::as_str)
+            .collect(),
+    };
 
-struct GitOutput {
-    output: std::process::Output,
-}
+    let start_time = SystemTime::now();
+    let start = Instant::now();
 
-fn do_git_command(path: &Path, args: &[&str]) -> anyhow::Result<GitOutput> {
-    match process::Command::new("git")
-        .args(args)
-        .current_dir(path)
-        .output()
-    {
-        Ok(output) => Ok(GitOutput { output }),
-        Err(err) => Err(anyhow!(err)),
-    }
-}
+    // Setup the colors.
 
-fn parse_gitmodules(path: &Path) -> anyhow::Result<GitModules> {
-    let contents = {
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        contents
+    let porcelain = matches.get_flag("porcelain");
+    let ci = Ci::from_matches(&matches);
+    let theme = setup_colors(&matches, &config, ci);
+    let color = matches.get_one::<String>("color").unwrap().as_str();
+    // git only colors its output for a terminal, forcing it passes the colors through
+    let git_color = if color == "never" {
+        Some("false")
+    } else if colored::control::SHOULD_COLORIZE.should_colorize() && !machine_output(&matches) {
+        Some("always")
+    } else {
+        None
     };
 
-    let gitmodules = GitModules::parse(&contents)?;
+    let grep_mode = matches.get_flag("grep_mode")
+        || (script.is_none()
+            && grep::subcommand_index(&git_args).is_some_and(|index| git_args[index] == "grep"));
+    if grep_mode && colored::control::SHOULD_COLORIZE.should_colorize() {
+        grep::add_color(&mut git_args);
+    }
 
-    Ok(gitmodules)
-}
+    if stats {
+        if let Some(index) = progress_index(&git_args) {
+            git_args.insert(index, "--progress");
+        }
+    }
 
-fn is_submodule(path: &Path, gitmodules: Option<&GitModules>) -> bool {
-    match gitmodules {
-        Some(gitmodules) => {
-            // If this is a submodule:
-            // * path is the git submodule directory
-            // * parent path is the parent git repository containing the gitmodules
+    // Setup rayon.
 
-            let parent_path = match path.parent().ok_or(anyhow!("no parent path")) {
-                Ok(path) => path,
-                Err(_) => return false,
-            };
+    // Can't use to many threads due to SSH multiplexing
+    build_thread_pool(setting(&matches, "jobs", config.jobs).unwrap_or(0));
 
-            let tmp = parent_path
-                .components()
-                .last()
-                .map(|p| PathBuf::from(p.as_os_str()))
-                .unwrap_or_default();
+    // Open the log file if needed
 
-            gitmodules.contains(&tmp)
+    let log_file = matches.get_one::<PathBuf>("log_file").map(|path| {
+        match LogFile::open(path, matches.get_flag("log_append")) {
+            Ok(log_file) => log_file,
+            Err(err) => {
+                eprintln!("unable to open log file {}: {}", path.display(), err);
+                process::exit(EXIT_USAGE);
+            }
         }
-        None => false,
+    });
+
+    // Collect all local git repositories
+
+    let Discovery {
+        roots,
+        explicit_roots,
+        paths: mut repositories_paths,
+        gitmodules,
+        remotes,
+        marked,
+    } = discover(&matches, &config);
+
+    // Relative paths are the default if there's a single root given explicitly
+    let relative = if matches.get_flag("absolute") {
+        false
+    } else {
+        matches.get_flag("relative") || (explicit_roots && roots.len() == 1)
+    };
+    let mut path_display = PathDisplay::new(&roots, relative, matches.get_flag("tilde"));
+    if matches.get_flag("short_names") {
+        path_display = path_display.short_names(&repositories_paths);
     }
-}
 
-fn get_repositories_paths(depth: usize) -> anyhow::Result<Vec<PathBuf>> {
-    let mut repositories_paths = Vec::<PathBuf>::new();
+    // The arguments of --args-file are added to the others with the config overrides
+    let mut mapped_args = HashMap::new();
+    if let Some(path) = matches.get_one::<PathBuf>("args_file") {
+        let args_file = match ArgsFile::load(path) {
+            Ok(args_file) => args_file,
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(EXIT_USAGE);
+            }
+        };
+        let mapped = match args_file.resolve(&repositories_paths, &roots) {
+            Ok(mapped) => mapped,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                process::exit(EXIT_USAGE);
+            }
+        };
+        for mapping in &args_file.mappings {
+            if !mapped.values().any(|mapped| mapped.line == mapping.line) {
+                eprintln!(
+                    "warning: {}:{}: {} maps no repository",
+                    path.display(),
+                    mapping.line,
+                    mapping.pattern
+                );
+            }
+        }
 
-    let walker = WalkDir::new(".").max_depth(depth);
+        if matches.get_flag("args_file_only") {
+            repositories_paths.retain(|repository| {
+                let listed = mapped.contains_key(repository);
+                if !listed && verbosity(&matches) > 0 {
+                    eprintln!(
+                        "{}: left out by --args-file-only",
+                        path_display.display(repository)
+                    );
+                }
+                listed
+            });
+        }
+        mapped_args = mapped
+            .into_iter()
+            .map(|(path, mapping)| (path, mapping.args.clone()))
+            .collect();
+    }
 
-    let mut gitmodules: Option<GitModules> = None;
+    let output_order = matches
+        .get_one::<String>("output_order")
+        .map(|s| s.parse::<OutputOrder>().unwrap())
+        .unwrap_or(OutputOrder::Completion);
+    if output_order == OutputOrder::Sorted {
+        repositories_paths.sort();
+    }
 
-    for entry in walker {
-        let entry = entry?;
-        let entry_path = entry.into_path();
+    let git = git_from_matches(&matches, &config);
+    check_git(&git);
 
-        let mut path = match entry_path.canonicalize() {
-            Err(err) => match err.kind() {
-                io::ErrorKind::NotFound => continue,
-                _ => return Err(anyhow!(err)),
+    // The repositories in the states to skip are neither run nor counted as failed
+    let skip_states: Vec<RepoState> = matches
+        .get_many::<String>("skip_states")
+        .into_iter()
+        .flatten()
+        .map(|state| state.parse().unwrap())
+        .collect();
+    let mut skipped: Vec<Skipped> = marked
+        .iter()
+        .map(|marked| Skipped {
+            path: marked.path.clone(),
+            display: path_display.display(&marked.path),
+            reason: match &marked.reason {
+                Some(reason) => format!("{}: {}", marked.marker, reason),
+                None => marked.marker.to_string(),
             },
-            Ok(v) => v,
+            in_progress: false,
+            broken: false,
+        })
+        .collect();
+    if !skip_states.is_empty() {
+        let states: Vec<(PathBuf, RepoState)> = repositories_paths
+            .par_iter()
+            .filter_map(|path| {
+                let state = state::probe(&git, path).ok()?;
+                skip_states.contains(&state).then(|| (path.clone(), state))
+            })
+            .collect();
+        repositories_paths.retain(|path| !states.iter().any(|(skipped, _)| skipped == path));
+
+        skipped.extend(states.into_iter().map(|(path, state)| Skipped {
+            display: path_display.display(&path),
+            path,
+            reason: state.to_string(),
+            in_progress: false,
+            broken: false,
+        }));
+    }
+
+    if matches.get_flag("skip_broken") {
+        let broken: Vec<(PathBuf, String)> = repositories_paths
+            .par_iter()
+            .filter_map(|path| {
+                let reason = state::broken_head(&state::git_dir(path)?)?;
+                Some((path.clone(), reason))
+            })
+            .collect();
+        repositories_paths.retain(|path| !broken.iter().any(|(skipped, _)| skipped == path));
+
+        skipped.extend(broken.into_iter().map(|(path, reason)| Skipped {
+            display: path_display.display(&path),
+            path,
+            reason: format!("broken, {}", reason),
+            in_progress: false,
+            broken: true,
+        }));
+    }
+
+    // A command that writes could make an operation in progress worse, only the files git leaves
+    // behind are looked at
+    let is_write = |args: &[&str]| {
+        grep::subcommand_index(args).is_some_and(|index| !state::is_read_only(args[index]))
+    };
+    let writes = match &script {
+        Some((_, script)) => script.steps.iter().any(|step| {
+            let args: Vec<&str> = step.iter().map(String::as_str).collect();
+            is_write(&args)
+        }),
+        None => is_write(&git_args),
+    };
+    if writes && !matches.get_flag("no_skip_in_progress") {
+        let states: Vec<(PathBuf, RepoState)> = repositories_paths
+            .par_iter()
+            .filter_map(|path| {
+                let state = state::in_progress(&state::git_dir(path)?)?;
+                Some((path.clone(), state))
+            })
+            .collect();
+        repositories_paths.retain(|path| !states.iter().any(|(skipped, _)| skipped == path));
+
+        skipped.extend(states.into_iter().map(|(path, state)| Skipped {
+            display: path_display.display(&path),
+            path,
+            reason: format!(
+                "{} in progress, pass --no-skip-in-progress to run anyway",
+                state
+            ),
+            in_progress: true,
+            broken: false,
+        }));
+    }
+
+    // Every command that writes would fail in these, with a lot of noise
+    if writes && !matches.get_flag("include_readonly") {
+        let read_only: Vec<(PathBuf, &str)> = repositories_paths
+            .par_iter()
+            .filter_map(|path| {
+                let reason = state::not_writable(&state::git_dir(path)?)?;
+                Some((path.clone(), reason))
+            })
+            .collect();
+        repositories_paths.retain(|path| !read_only.iter().any(|(read_only, _)| read_only == path));
+
+        skipped.extend(read_only.into_iter().map(|(path, reason)| Skipped {
+            display: path_display.display(&path),
+            reason: format!("{}, pass --include-readonly to run anyway", reason),
+            path,
+            in_progress: false,
+            broken: false,
+        }));
+    }
+
+    // The diverged repositories are neither run nor counted as failed, a pull would stop on them
+    if matches.get_flag("skip_diverged") {
+        let diverged: Vec<(PathBuf, RepoStatus)> = repositories_paths
+            .par_iter()
+            .filter_map(|path| {
+                let status = RepoStatus::probe(&git, path).ok()?;
+                status.is_diverged().then(|| (path.clone(), status))
+            })
+            .collect();
+        repositories_paths.retain(|path| !diverged.iter().any(|(diverged, _)| diverged == path));
+
+        skipped.extend(diverged.into_iter().map(|(path, status)| Skipped {
+            display: path_display.display(&path),
+            path,
+            reason: format!(
+                "diverged, ahead {} and behind {} of {}",
+                status.ahead.unwrap_or(0),
+                status.behind.unwrap_or(0),
+                status.upstream.unwrap_or_default()
+            ),
+            in_progress: false,
+            broken: false,
+        }));
+    }
+
+    if verbosity(&matches) > 0 {
+        for skipped in &skipped {
+            eprintln!("{}: skipped, {}", skipped.display, skipped.reason);
+        }
+    }
+
+    if matches.get_flag("list") {
+        let mut paths = repositories_paths;
+        paths.sort();
+        if matches
+            .get_one::<String>("format")
+            .is_some_and(|format| format == "json")
+        {
+            let names = names::short_names(&paths);
+            let entries: Vec<listing::Entry> = paths
+                .par_iter()
+                .zip(names)
+                .map(|(path, name)| {
+                    let mut entry = listing::Entry::new(path, listing::root_of(path, &roots), name);
+                    if matches.get_flag("enrich") {
+                        entry.branch = Backend::Cli.current_branch(&git, path);
+                        entry.origin = remotes::list(&git, path).ok().and_then(|remotes| {
+                            remotes
+                                .into_iter()
+                                .find(|(name, _)| name == "origin")
+                                .map(|(_, url)| url)
+                        });
+                    }
+                    entry
+                })
+                .collect();
+            print!("{}", listing::render_json(&entries));
+            return;
+        }
+        for path in &paths {
+            print!("{}", porcelain::format_path(path.as_os_str()));
+        }
+        return;
+    }
+
+    // The placeholders are the only consumer of the remotes that doesn't ask for them explicitly
+    let remotes = remotes.or_else(|| {
+        git_args
+            .iter()
+            .copied()
+            .chain(mapped_args.values().flatten().map(String::as_str))
+            .chain(
+                script
+                    .iter()
+                    .flat_map(|(_, script)| script.steps.iter().flatten().map(String::as_str)),
+            )
+            .any(|arg| arg.contains(ORIGIN_HOST))
+            .then(|| read_remotes(&git, &repositories_paths))
+    });
+    let (repository_args, script_steps) = match &script {
+        Some((_, script)) => {
+            let steps: HashMap<PathBuf, Vec<Vec<String>>> = repositories_paths
+                .par_iter()
+                .map(|path| {
+                    let mut steps = script.steps.clone();
+                    if let Some(remotes) = &remotes {
+                        replace_origin_host(path, remotes, steps.iter_mut().flatten());
+                    }
+                    (path.clone(), steps)
+                })
+                .collect();
+            (HashMap::new(), steps)
+        }
+        None => {
+            let args = repository_args(
+                &git,
+                &roots,
+                &repositories_paths,
+                &config.repos,
+                &mapped_args,
+                remotes.as_ref(),
+                &git_args,
+            );
+            (args, HashMap::new())
+        }
+    };
+
+    // The command runs again where the fingerprint changed, or where it never succeeded
+    let mut last_runs = None;
+    let mut fingerprints = HashMap::new();
+    if matches.get_flag("since_last_run") {
+        let Some(path) = fingerprint::last_runs_path() else {
+            eprintln!("unable to find the state directory, neither XDG_STATE_HOME nor HOME is set");
+            process::exit(EXIT_USAGE);
+        };
+        let runs = match LastRuns::load(&path) {
+            Ok(runs) => runs,
+            Err(err) => {
+                eprintln!("{}", err);
+                exit(EXIT_FAILURE);
+            }
+        };
+
+        fingerprints = repositories_paths
+            .par_iter()
+            .map(|path| {
+                let command = repository_args[path].join(" ");
+                (
+                    path.clone(),
+                    (command, fingerprint::fingerprint(&git, path)),
+                )
+            })
+            .collect();
+        let unchanged: Vec<PathBuf> = if matches.get_flag("force") {
+            Vec::new()
+        } else {
+            repositories_paths
+                .iter()
+                .filter(|path| {
+                    let (command, fingerprint) = &fingerprints[*path];
+                    fingerprint.is_some() && runs.get(command, path) == fingerprint.as_deref()
+                })
+                .cloned()
+                .collect()
         };
-        let path_string = path.to_string_lossy();
+        repositories_paths.retain(|path| !unchanged.contains(path));
 
-        // Parse the gitmodules file if it exists
-        let gitmodules_path = path.join(".gitmodules");
-        if gitmodules_path.exists() {
-            if let Ok(tmp) = parse_gitmodules(&gitmodules_path) {
-                gitmodules = Some(tmp)
+        for path in unchanged {
+            let entry = Skipped {
+                display: path_display.display(&path),
+                path,
+                reason: "unchanged since the last successful run, pass --force to run anyway"
+                    .to_string(),
+                in_progress: false,
+                broken: false,
+            };
+            if verbosity(&matches) > 0 {
+                eprintln!("{}: skipped, {}", entry.display, entry.reason);
             }
+            skipped.push(entry);
         }
+        last_runs = Some((path, runs));
+    }
 
-        // Ignore directories that aren't a git repository
-        if !path_string.ends_with(".git") {
-            continue;
+    if matches.get_flag("dry_run") {
+        if let Some(alias) = &alias {
+            println!("@{} expands to {}", alias.name, alias.args.join(" "));
         }
-        // Ignore repositories that are a submoduile
-        if is_submodule(&path, gitmodules.as_ref()) {
-            continue;
+        for path in &repositories_paths {
+            let Some(steps) = script_steps.get(path) else {
+                println!(
+                    "{}: git {}",
+                    path_display.display(path),
+                    repository_args[path].join(" ")
+                );
+                continue;
+            };
+            for (index, step) in steps.iter().enumerate() {
+                println!(
+                    "{}: git {} (step {} of {})",
+                    path_display.display(path),
+                    step.join(" "),
+                    index + 1,
+                    steps.len()
+                );
+            }
         }
+        for skipped in &skipped {
+            println!("{}: skipped, {}", skipped.display, skipped.reason);
+        }
+        return;
+    }
 
-        path.pop();
-
-        repositories_paths.push(path);
+    // A command losing work runs in a single repository, or after a confirmation
+    if repositories_paths.len() > 1 && !matches.get_flag("yes") && !matches.get_flag("no_safety") {
+        confirm_risky(&repositories_paths, |path| match script_steps.get(path) {
+            Some(steps) => steps.clone(),
+            None => vec![repository_args[path].clone()],
+        });
     }
 
-    Ok(repositories_paths)
-}
+    // With --print-failed the failed repositories are printed on stdout, everything else goes to stderr
+    let print_failed = matches.get_flag("print_failed") || matches.get_flag("print_failed0");
+    let stream = if print_failed {
+        Stream::Stderr
+    } else {
+        Stream::Stdout
+    };
+    let mut printer = Printer::new(output_order, stream);
+    if let Some(path) = matches.get_one::<PathBuf>("tee") {
+        // The command could remove or reset the file while it's written
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .canonicalize()
+            .unwrap_or_default();
+        if let Some(repository) = repositories_paths
+            .iter()
+            .find(|repository| writes && dir.starts_with(repository))
+        {
+            eprintln!(
+                "refusing to write {} inside {}, git {} changes the repository",
+                path.display(),
+                path_display.display(repository),
+                git_args.join(" ")
+            );
+            process::exit(EXIT_USAGE);
+        }
 
-struct Item {
-    path: PathBuf,
-    success: bool,
-    stdout: String,
-    stderr: String,
-    err: Option<anyhow::Error>,
-}
+        match Tee::create(path) {
+            Ok(tee) => printer = printer.tee(tee),
+            Err(err) => {
+                eprintln!("unable to create {}: {}", path.display(), err);
+                process::exit(EXIT_USAGE);
+            }
+        }
+    }
 
-const STDOUT_COLOR: colored::Color = colored::Color::TrueColor {
-    r: 176,
-    g: 176,
-    b: 176,
-};
+    let hyperlinks = matches
+        .get_one::<String>("hyperlinks")
+        .map(|s| s.parse::<Hyperlinks>().unwrap())
+        .unwrap_or(Hyperlinks::Auto)
+        .enabled(stream);
 
-const STDERR_COLOR: colored::Color = colored::Color::TrueColor {
-    r: 219,
-    g: 154,
-    b: 154,
-};
+    let log_dir = matches.get_one::<PathBuf>("log_dir").map(|dir| {
+        let root = match roots.as_slice() {
+            [root] => root.clone(),
+            _ => Path::new(".").canonicalize().unwrap_or_default(),
+        };
 
-fn main() {
-    let matches = clap::Command::new("gitjuggling")
-        .disable_version_flag(true)
-        .about("Git juggler")
-        .arg(clap::Arg::new("depth").long("depth").short('d').num_args(1))
-        .arg(
-            clap::Arg::new("git_args")
-                .num_args(1..)
-                .required(true)
-                .trailing_var_arg(true),
-        )
-        .get_matches();
+        match LogDir::create(
+            dir,
+            &root,
+            &repositories_paths,
+            matches.get_flag("log_overwrite"),
+        ) {
+            Ok(log_dir) => log_dir,
+            Err(err) => {
+                eprintln!("unable to use log directory {}: {}", dir.display(), err);
+                process::exit(EXIT_USAGE);
+            }
+        }
+    });
 
-    let git_args: Vec<&str> = matches
-        .get_many::<String>("git_args")
-        .unwrap_or_default()
-        .map(String::as_str)
-        .collect();
+    if let Some(log_file) = &log_file {
+        let mut entry = format!(
+            "running git {} in {} repositories\n",
+            git_args.join(" "),
+            repositories_paths.len()
+        );
+        for path in &repositories_paths {
+            writeln!(&mut entry, "discovered {}", path.to_string_lossy()).unwrap();
+        }
 
-    // Setup rayon.
+        log_file.write(entry.as_bytes());
+    }
 
-    // Can't use to many threads due to SSH multiplexing
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(0)
-        .build_global()
-        .unwrap();
+    // Compute the prefixes if needed
 
-    // Collect all local git repositories
+    let prefix = matches.get_flag("prefix") || (ci.is_some() && !matches.get_flag("no_prefix"));
+    let timestamps =
+        matches.get_flag("timestamps") || (ci.is_some() && !matches.get_flag("no_timestamps"));
+    let prefixes: Vec<Option<String>> = if prefix {
+        let names = names::short_names(&repositories_paths);
+        let width = names
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0);
 
-    let depth = matches.get_one::<usize>("depth").copied().unwrap_or(3);
+        names
+            .iter()
+            .map(|name| {
+                Some(
+                    format!("{:width$} |", name, width = width)
+                        .color(theme.path)
+                        .to_string(),
+                )
+            })
+            .collect()
+    } else {
+        vec![None; repositories_paths.len()]
+    };
 
-    let repositories_paths = match get_repositories_paths(depth) {
-        Err(err) => panic!("unable to get repositories paths: {}", err),
-        Ok(v) => v,
+    // The time is the one the command completed at, its output is only printed then
+    let item_prefix = |index: usize| {
+        if !timestamps {
+            return prefixes[index].clone();
+        }
+        let time = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        Some(match &prefixes[index] {
+            Some(prefix) => format!("{} {}", time, prefix),
+            None => time,
+        })
     };
+    let groups = ci.filter(|_| !matches.get_flag("no_groups"));
 
     //
 
-    let results: Vec<Item> = repositories_paths
-        .into_par_iter()
-        .map(|path| {
-            let mut output = String::new();
+    let fail_regex = matches.get_one::<String>("fail_regex").map(|re| {
+        match regex::RegexBuilder::new(re).multi_line(true).build() {
+            Ok(re) => re,
+            Err(err) => {
+                eprintln!("invalid --fail-regex: {}", err);
+                process::exit(EXIT_USAGE);
+            }
+        }
+    });
+    let mut ok_exit_codes: Vec<i32> = matches
+        .get_many::<i32>("ok_exit_codes")
+        .map(|codes| codes.copied().collect())
+        .unwrap_or_default();
+    // git grep exits with 1 if nothing matched
+    if grep_mode {
+        ok_exit_codes.push(1);
+    }
+    let classifier = Classifier::new(
+        fail_regex,
+        ok_exit_codes,
+        matches.get_flag("fail_on_stderr"),
+    );
 
-            writeln!(
-                &mut output,
-                "{} executing {}",
-                &path.to_string_lossy().to_string().green(),
-                &git_args.join(" ").yellow()
-            )
-            .unwrap();
+    let collapse = matches.get_flag("collapse");
+    let show_branch = !matches.get_flag("no_branch");
+    let hide_empty = matches.get_flag("hide_empty");
+    let max_lines = matches.get_one::<usize>("max_lines").copied();
+    let verbose = matches.get_count("verbose") > 0;
+    let fail_fast = matches.get_flag("fail_fast");
 
-            match do_git_command(&path, &git_args) {
-                Err(err) => Item {
-                    path: path.clone(),
-                    success: false,
-                    stdout: String::new(),
-                    stderr: String::new(),
-                    err: Some(err),
-                },
-                Ok(go) => {
-                    let stdout = String::from_utf8_lossy(&go.output.stdout)
-                        .trim()
-                        .to_string();
-                    let stderr = String::from_utf8_lossy(&go.output.stderr)
-                        .trim()
-                        .to_string();
-
-                    if !stdout.is_empty() {
-                        writeln!(&mut output, "{}", stdout.color(STDOUT_COLOR)).unwrap();
-                    }
-                    if !stderr.is_empty() {
-                        writeln!(&mut output, "{}", stderr.color(STDERR_COLOR)).unwrap();
+    if let Err(err) = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst)) {
+        eprintln!("unable to set the Ctrl-C handler: {}", err);
+    }
+
+    let backend = match matches
+        .get_one::<String>("backend")
+        .map(|s| s.parse::<Backend>())
+    {
+        Some(Ok(backend)) => backend,
+        Some(Err(err)) => {
+            eprintln!("invalid --backend: {}", err);
+            process::exit(EXIT_USAGE);
+        }
+        None => Backend::Cli,
+    };
+
+    let hook =
+        matches
+            .get_one::<String>("hook")
+            .and_then(|command| match hook::Hook::spawn(command) {
+                Ok(hook) => Some(hook),
+                Err(err) => {
+                    eprintln!("warning: unable to start hook {}: {}", command, err);
+                    None
+                }
+            });
+    if let Some(hook) = &hook {
+        hook.send(serde_json::json!({
+            "event": "run_start",
+            "args": git_args,
+            "repositories": repositories_paths,
+        }));
+        for skipped in &skipped {
+            let status = skipped.status();
+            hook.send(serde_json::json!({
+                "event": "skipped",
+                "path": skipped.path,
+                "status": status.name(),
+                "reason": status.reason(),
+            }));
+        }
+    }
+    let send_result = |index: usize, result: &RunResult| {
+        if let Some(broken) = &result.broken {
+            eprintln!(
+                "warning: {}: broken, {}",
+                path_display.display(&result.path),
+                broken
+            );
+        }
+        for warning in &result.warnings {
+            eprintln!(
+                "warning: {}: {}",
+                path_display.display(&result.path),
+                warning
+            );
+        }
+        if verbosity(&matches) > 0 {
+            eprint!(
+                "{}",
+                format_diagnostics(&path_display.display(&result.path), result)
+            );
+        }
+        if let Some(hook) = &hook {
+            hook.send(serde_json::json!({
+                "event": "result",
+                "index": index,
+                "status": Status::of(result).name(),
+                "result": result,
+            }));
+        }
+    };
+
+    let mut runner = Runner::new(&git_args)
+        .git(git)
+        .repository_args(repository_args)
+        .steps(script_steps)
+        .backend(backend)
+        .classifier(classifier)
+        .show_branch(show_branch)
+        .counts(matches.get_flag("counts"))
+        .spill_threshold(*matches.get_one::<u64>("spill_threshold").unwrap())
+        .stop_flag(&INTERRUPTED)
+        .stop_flag(&FAILED_FAST);
+    if let Some(value) = git_color {
+        runner = runner.git_config("color.ui", value);
+    }
+    let priority = Priority {
+        nice: matches.get_one::<i32>("nice").copied(),
+        io_class: matches.get_one::<IoClass>("ionice").copied(),
+    };
+    if priority.nice.is_some() && !priority::NICE_SUPPORTED {
+        eprintln!("warning: --nice isn't supported on this platform, it's ignored");
+    }
+    if priority.io_class.is_some() && !priority::IO_CLASS_SUPPORTED {
+        eprintln!("warning: --ionice is only supported on Linux, it's ignored");
+    }
+    runner = runner.priority(priority);
+
+    // The config files limit directories, --per-root-jobs the roots
+    let mut path_limits = PathLimits::default();
+    for (prefix, jobs) in &config.path_jobs {
+        path_limits.add(prefix.canonicalize().unwrap_or(prefix.clone()), *jobs);
+    }
+    if let Some(jobs) = matches.get_one::<usize>("per_root_jobs") {
+        for root in &roots {
+            path_limits.add(root.clone(), *jobs);
+        }
+    }
+    if !path_limits.is_empty() {
+        if verbosity(&matches) > 0 {
+            for path in &repositories_paths {
+                let bucket = match path_limits.bucket(path) {
+                    Some((prefix, jobs)) => {
+                        format!("{} at a time under {}", jobs, prefix.display())
                     }
-                    print!("{}", output);
-
-                    Item {
-                        path: path.clone(),
-                        success: go.output.status.success(),
-                        stdout,
-                        stderr,
-                        err: None,
+                    None => "no limit".to_string(),
+                };
+                eprintln!("{}: jobs, {}", path_display.display(path), bucket);
+            }
+        }
+        runner = runner.path_limits(path_limits);
+    }
+
+    let order = matches.get_one::<String>("order").map(String::as_str);
+    if let Some(order @ ("topo" | "topo-reverse")) = order {
+        let mut dependencies = Dependencies::submodules(&repositories_paths, &gitmodules);
+        if order == "topo-reverse" {
+            dependencies = dependencies.reversed();
+        }
+        // A submodule can only be its own superproject through symlinks
+        for (path, dependency) in dependencies.break_cycles(&repositories_paths) {
+            eprintln!(
+                "warning: dependency cycle, {} runs without waiting for {}",
+                path_display.display(&path),
+                path_display.display(&dependency)
+            );
+        }
+        runner = runner.dependencies(dependencies);
+    }
+
+    let tui = matches.get_flag("tui")
+        && if tui::SUPPORTED && io::stdin().is_terminal() && io::stdout().is_terminal() {
+            true
+        } else {
+            eprintln!("warning: --tui needs a terminal, it's ignored");
+            false
+        };
+
+    // A watch never ends, the title would be left behind
+    let terminal_progress = (ci.is_none()
+        && !matches.get_flag("watch")
+        && matches
+            .get_one::<String>("terminal_progress")
+            .map(String::as_str)
+            == Some("auto"))
+    .then(|| TerminalProgress::start(repositories_paths.len()))
+    .flatten();
+
+    let results: Vec<Item> = if matches.get_flag("watch") {
+        let filter = watch::Filter {
+            git_excludes: matches
+                .get_many::<String>("watch_git_exclude")
+                .map(|excludes| excludes.cloned().collect())
+                .unwrap_or_default(),
+        };
+        let make_item = |index: usize, result: RunResult| {
+            send_result(index, &result);
+
+            Item {
+                display: path_display.display(&result.path),
+                link: hyperlinks.then(|| paths::file_url(&result.path)),
+                prefix: item_prefix(index),
+                result,
+            }
+        };
+        let render = |items: &[&Item]| {
+            items
+                .iter()
+                .filter(|item| !(hide_empty && item.result.is_quiet()))
+                .map(|item| format_item(item, &theme, max_lines, verbose))
+                .collect::<String>()
+        };
+
+        let results = watch::run(
+            &runner,
+            &repositories_paths,
+            &filter,
+            &INTERRUPTED,
+            make_item,
+            render,
+        );
+
+        // Ctrl-C is how watch mode ends, it's not an interruption
+        INTERRUPTED.store(false, Ordering::SeqCst);
+
+        match results {
+            Ok(results) => results,
+            Err(err) => {
+                eprintln!("unable to watch the repositories: {}", err);
+                exit(EXIT_FAILURE);
+            }
+        }
+    } else {
+        runner.run_with(&repositories_paths, |index, result| {
+            let Some(result) = result else {
+                printer.print(index, String::new()).unwrap();
+                return None;
+            };
+            send_result(index, &result);
+            if let Some(terminal_progress) = &terminal_progress {
+                terminal_progress.update(result.success);
+            }
+            if fail_fast && !result.success {
+                FAILED_FAST.store(true, Ordering::SeqCst);
+            }
+
+            let item = Item {
+                display: path_display.display(&result.path),
+                link: hyperlinks.then(|| paths::file_url(&result.path)),
+                prefix: item_prefix(index),
+                result,
+            };
+
+            if log_file.is_some() || log_dir.is_some() {
+                let entry = format_log_entry(&item);
+
+                if let Some(log_file) = &log_file {
+                    log_file.write(&entry);
+                }
+                if let Some(log_dir) = &log_dir {
+                    if let Err(err) = log_dir.write(index, &entry) {
+                        eprintln!(
+                            "unable to write log file of {}: {}",
+                            item.result.path.display(),
+                            err
+                        );
                     }
                 }
             }
+
+            if porcelain {
+                let line = porcelain::format_line(
+                    item.status().name(),
+                    item.result.exit_code,
+                    item.result.duration,
+                    item.result.path.as_os_str(),
+                    "",
+                );
+                printer.print(index, line).unwrap();
+            } else {
+                let hidden = collapse || (hide_empty && item.result.is_quiet());
+                if hidden && !collapse {
+                    debug!(path = %item.result.path.display(), "hiding quiet repository");
+                }
+                // The tee file gets the repositories hidden in the terminal too
+                let output = if hidden && !printer.tees() {
+                    String::new()
+                } else if grep_mode && item.result.success {
+                    format_grep_item(&item, &theme)
+                } else {
+                    format_item(&item, &theme, max_lines, verbose)
+                };
+                let output = match groups {
+                    Some(ci) if !output.is_empty() => ci.group(&item.display, index, &output),
+                    _ => output,
+                };
+                if hidden {
+                    printer.print_split(index, String::new(), Some(output))
+                } else {
+                    printer.print(index, output)
+                }
+                .unwrap();
+            }
+
+            Some(item)
         })
-        .collect();
+    };
+    if let Some(terminal_progress) = terminal_progress {
+        terminal_progress.finish();
+    }
+
+    // The error would be the same in every repository, it's reported only once
+    if let Some(err) = runner.git_error() {
+        eprintln!(
+            "{}; {} of {} repositories were not run",
+            err,
+            repositories_paths.len() - results.len(),
+            repositories_paths.len()
+        );
+        exit(EXIT_GIT);
+    }
+
+    if collapse {
+        printer.write_stream(&format_collapsed(&results, &theme, max_lines));
+    }
+
+    // The run stopped before reaching the repositories without a result
+    let not_attempted: Vec<NotAttempted> = match stop_reason() {
+        Some(reason) if !matches.get_flag("watch") => {
+            let attempted: HashSet<&Path> = results
+                .iter()
+                .map(|item| item.result.path.as_path())
+                .collect();
+            repositories_paths
+                .iter()
+                .filter(|path| !attempted.contains(path.as_path()))
+                .map(|path| NotAttempted {
+                    path: path.clone(),
+                    display: path_display.display(path),
+                    reason: reason.to_string(),
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    if let Some(hook) = &hook {
+        for not_attempted in &not_attempted {
+            hook.send(serde_json::json!({
+                "event": "not_attempted",
+                "path": not_attempted.path,
+                "status": not_attempted.status().name(),
+                "reason": not_attempted.reason,
+            }));
+        }
+    }
+
+    // The repositories run again in the browser replace their first result
+    let mut results = results;
+    if tui {
+        let entries = results
+            .iter()
+            .map(|item| tui::Entry::ran(item.display.clone(), &item.result))
+            .chain(skipped.iter().map(|skipped| {
+                tui::Entry::not_run(
+                    tui::Group::Skipped,
+                    skipped.display.clone(),
+                    skipped.path.clone(),
+                    &skipped.reason,
+                )
+            }))
+            .chain(not_attempted.iter().map(|not_attempted| {
+                tui::Entry::not_run(
+                    tui::Group::NotAttempted,
+                    not_attempted.display.clone(),
+                    not_attempted.path.clone(),
+                    &not_attempted.reason,
+                )
+            }))
+            .collect();
+        // A rerun is asked for, what stopped the run doesn't stop it. The exit code still tells
+        // the run was interrupted
+        let interrupted = INTERRUPTED.load(Ordering::SeqCst);
+        let rerun = |path: &Path| {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+            FAILED_FAST.store(false, Ordering::SeqCst);
+            runner.run(&[path.to_path_buf()]).pop()
+        };
+
+        let browsed = tui::browse(entries, rerun);
+        if interrupted {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+        match browsed {
+            Ok(reruns) => {
+                for result in reruns {
+                    if let Some(item) = results
+                        .iter_mut()
+                        .find(|item| item.result.path == result.path)
+                    {
+                        item.result = result;
+                    }
+                }
+            }
+            Err(err) => eprintln!("warning: unable to browse the results: {}", err),
+        }
+    }
+
+    // Then come the repositories not attempted and the skipped ones, in the order they were
+    // skipped
+    if porcelain {
+        for not_attempted in &not_attempted {
+            let status = not_attempted.status();
+            printer.write(&porcelain::format_line(
+                status.name(),
+                None,
+                Duration::ZERO,
+                not_attempted.path.as_os_str(),
+                status.reason().unwrap_or_default(),
+            ));
+        }
+        for skipped in &skipped {
+            let status = skipped.status();
+            printer.write(&porcelain::format_line(
+                status.name(),
+                None,
+                Duration::ZERO,
+                skipped.path.as_os_str(),
+                status.reason().unwrap_or_default(),
+            ));
+        }
+    }
+
+    if let Some(path) = matches.get_one::<PathBuf>("report_markdown") {
+        let entries: Vec<_> = results.iter().map(Item::report_entry).collect();
+        let markdown =
+            report::render_markdown(&format!("git {}", git_args.join(" ")), start_time, &entries);
+
+        if let Err(err) = std::fs::write(path, markdown) {
+            let error = format!("unable to write report {}: {}", path.display(), err);
+            eprintln!("{}", error.bright_red());
+        }
+    }
 
-    let (succeeded, failed): (Vec<_>, Vec<_>) = results.into_iter().partition(|item| item.success);
+    let table = if matches.get_flag("table") {
+        let sort = matches
+            .get_one::<String>("sort_table")
+            .map(|s| s.parse::<TableSort>().unwrap())
+            .unwrap_or(TableSort::Name);
+        let rows: Vec<Row> = results.iter().map(Item::table_row).collect();
+
+        Some(table::render(rows, sort, table::terminal_width()))
+    } else {
+        None
+    };
+
+    let stats = if stats {
+        let durations: Vec<Duration> = results.iter().map(|item| item.result.duration).collect();
+        let with_output = results
+            .iter()
+            .filter(|item| !item.result.stdout.is_empty() || !item.result.stderr.is_empty())
+            .count();
+
+        let mut stats = stats::Stats::compute(start.elapsed(), &durations, with_output);
+
+        // A network command git wrote no progress for transferred an unknown amount
+        let transfers: Vec<Option<&Transfer>> = results
+            .iter()
+            .filter(|item| {
+                item.result.transfer.is_some()
+                    || std::iter::once(&item.result.args)
+                        .chain(item.result.steps.iter().map(|step| &step.args))
+                        .any(|args| {
+                            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                            grep::subcommand_index(&args)
+                                .is_some_and(|index| transfer::is_network(args[index]))
+                        })
+            })
+            .map(|item| item.result.transfer.as_ref())
+            .collect();
+        if !transfers.is_empty() {
+            stats.transferred = Some(stats::Transferred::sum(&transfers));
+        }
+
+        Some(stats)
+    } else {
+        None
+    };
+
+    let (succeeded, failed): (Vec<_>, Vec<_>) =
+        results.into_iter().partition(|item| item.result.success);
+    let (quiet, succeeded): (Vec<_>, Vec<_>) = succeeded
+        .into_iter()
+        .partition(|item| hide_empty && item.result.is_quiet());
 
     //
 
-    if !failed.is_empty() {
-        println!(
-            "\n\n{}{}{}\n",
-            "=== ".bright_white(),
-            "Details of failed items".bright_red(),
-            " ===".bright_white()
-        );
+    if !failed.is_empty() && !porcelain {
+        printer.write(&format_failure_details(&failed, &theme, max_lines));
+    }
+    if !skipped.is_empty() && !porcelain && matches.get_flag("show_skipped") {
+        printer.write(&format_skipped_details(&skipped, &theme));
+    }
+    if !not_attempted.is_empty() && !porcelain {
+        printer.write(&format_not_attempted_details(&not_attempted, &theme));
+    }
+
+    if let Some(table) = table {
+        printer.write(&format_header("Repositories".color(theme.summary)));
+        printer.write(&table);
+    }
+
+    if let Some(stats) = stats {
+        printer.write(&format_header("Statistics".color(theme.summary)));
+        printer.write(&stats.render());
+    }
+
+    if !porcelain {
+        printer.write(&format_summary(
+            &succeeded,
+            hide_empty.then_some(quiet.as_slice()),
+            &failed,
+            &skipped,
+            &not_attempted,
+            &theme,
+        ));
+        if grep_mode {
+            printer.write(&format_match_counts(&succeeded, &theme));
+        }
+    }
 
+    if print_failed {
+        let separator = if matches.get_flag("print_failed0") {
+            '\0'
+        } else {
+            '\n'
+        };
+
+        let mut paths = Vec::new();
         for item in &failed {
-            println!("{}", &item.path.to_string_lossy().to_string().green());
+            paths.extend_from_slice(item.result.path.as_os_str().as_encoded_bytes());
+            paths.push(separator as u8);
+        }
 
-            if !item.stdout.is_empty() {
-                println!("{}", item.stdout);
-            }
+        if let Err(err) = Stream::Stdout.write_bytes(&paths) {
+            eprintln!("unable to print the failed repositories: {}", err);
+        }
+    }
 
-            if let Some(err) = &item.err {
-                println!("error: {}", err);
-            } else {
-                println!("{}", item.stderr.color(STDERR_COLOR));
-            }
+    // A failed command runs again next time, even if nothing changed
+    if let Some((path, mut runs)) = last_runs {
+        for item in succeeded.iter().chain(&quiet).chain(&failed) {
+            let path = &item.result.path;
+            let Some((command, fingerprint)) = fingerprints.remove(path) else {
+                continue;
+            };
+            runs.set(&command, path, fingerprint.filter(|_| item.result.success));
+        }
+        if let Err(err) = runs.save(&path) {
+            eprintln!("warning: {}", err);
         }
     }
 
-    println!(
-        "\n\n{}{}{}\n",
-        "=== ".bright_white(),
-        "Summary".bright_cyan(),
-        " ===".bright_white()
-    );
+    if config.history != Some(false) && !matches.get_flag("watch") {
+        let items: Vec<&Item> = succeeded.iter().chain(&quiet).chain(&failed).collect();
+        record_history(
+            start_time,
+            start.elapsed(),
+            &git_args,
+            &roots,
+            &items,
+            &skipped,
+            &not_attempted,
+        );
+    }
 
-    println!(
-        "{} {}",
-        "Succeeded: ".blue(),
-        format!("{}", succeeded.len()).bright_green()
-    );
-    println!(
-        "{} {}",
-        "Failed:    ".blue(),
-        format!("{}", failed.len()).bright_red()
-    );
+    if let Some(log_file) = &log_file {
+        let mut summary = format!(
+            "summary: {} succeeded, {} failed",
+            succeeded.len() + quiet.len(),
+            failed.len()
+        );
+        if !skipped.is_empty() {
+            write!(&mut summary, ", {} skipped", skipped.len()).unwrap();
+        }
+        if !not_attempted.is_empty() {
+            write!(&mut summary, ", {} not attempted", not_attempted.len()).unwrap();
+        }
+        log_file.write(summary.as_bytes());
+    }
+
+    if let Some(hook) = hook {
+        hook.finish(serde_json::json!({
+            "event": "run_end",
+            "succeeded": succeeded.len() + quiet.len(),
+            "failed": failed.len(),
+            "skipped": skipped.len(),
+            "not_attempted": not_attempted.len(),
+            "duration_ms": start.elapsed().as_millis() as u64,
+            "interrupted": INTERRUPTED.load(Ordering::SeqCst),
+        }));
+    }
+
+    if matches.get_flag("notify") {
+        let elapsed = start.elapsed();
+
+        let threshold = matches
+            .get_one::<Duration>("notify_after")
+            .copied()
+            .unwrap_or_default();
+        if elapsed >= threshold {
+            notify::run_finished(succeeded.len() + quiet.len(), failed.len(), elapsed);
+        }
+    }
+
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        process::exit(EXIT_INTERRUPTED);
+    }
+    // Scripts can tell a run cut short from one where some commands failed
+    if !not_attempted.is_empty() {
+        exit(EXIT_INCOMPLETE);
+    }
 
-    if !failed.is_empty() {
-        process::exit(1);
+    let fail_threshold = matches
+        .get_one::<usize>("fail_threshold")
+        .copied()
+        .unwrap_or(0);
+    if failed.len() > fail_threshold {
+        exit(EXIT_FAILURE);
     }
 }
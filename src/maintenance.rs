@@ -0,0 +1,221 @@
+//! Run the housekeeping tasks of git in a repository.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use walkdir::WalkDir;
+
+use crate::git::Git;
+
+/// A housekeeping task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// `git gc --auto`, only collects garbage when there's enough of it
+    Gc,
+    /// `git maintenance run --auto`, the tasks git schedules itself
+    Maintenance,
+    /// `git remote prune origin`, deletes the remote-tracking branches gone from origin
+    Prune,
+    /// `git repack -d`, packs the loose objects
+    Repack,
+    /// `git commit-graph write --reachable`, speeds up walking the history
+    CommitGraph,
+}
+
+/// The tasks run when none are given.
+pub const DEFAULT_TASKS: &[Task] = &[Task::Gc, Task::Prune];
+
+impl Task {
+    /// The names of the tasks, as parsed by [`Task::from_str`].
+    pub const NAMES: &'static [&'static str] =
+        &["gc", "maintenance", "prune", "repack", "commit-graph"];
+
+    /// Returns the name of the task.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task::Gc => "gc",
+            Task::Maintenance => "maintenance",
+            Task::Prune => "prune",
+            Task::Repack => "repack",
+            Task::CommitGraph => "commit-graph",
+        }
+    }
+
+    fn args(&self) -> &'static [&'static str] {
+        match self {
+            Task::Gc => &["gc", "--auto", "--quiet"],
+            Task::Maintenance => &["maintenance", "run", "--auto", "--quiet"],
+            Task::Prune => &["remote", "prune", "origin"],
+            Task::Repack => &["repack", "-d", "--quiet"],
+            Task::CommitGraph => &["commit-graph", "write", "--reachable", "--no-progress"],
+        }
+    }
+}
+
+impl FromStr for Task {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gc" => Ok(Task::Gc),
+            "maintenance" => Ok(Task::Maintenance),
+            "prune" => Ok(Task::Prune),
+            "repack" => Ok(Task::Repack),
+            "commit-graph" => Ok(Task::CommitGraph),
+            _ => Err(anyhow!(
+                "unknown task {}, the tasks are: {}",
+                s,
+                Task::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// What running a task did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    /// The task succeeded
+    Done,
+    /// The task doesn't apply to the repository, or the repository is too small
+    Skipped {
+        /// Why it was skipped
+        reason: String,
+    },
+    /// The task failed
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl TaskOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskOutcome::Done => "done",
+            TaskOutcome::Skipped { .. } => "skipped",
+            TaskOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for TaskOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskOutcome::Done => f.write_str("done"),
+            TaskOutcome::Skipped { reason } => write!(f, "skipped, {}", reason),
+            TaskOutcome::Failed { error } => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// Runs the `tasks` one after the other in the repository at `path`, a failed task doesn't stop
+/// the next ones.
+pub fn run(git: &Git, path: &Path, tasks: &[Task]) -> Vec<(Task, TaskOutcome)> {
+    tasks
+        .iter()
+        .map(|&task| (task, run_task(git, path, task)))
+        .collect()
+}
+
+fn run_task(git: &Git, path: &Path, task: Task) -> TaskOutcome {
+    if task == Task::Prune && git.config(path, "remote.origin.url").is_none() {
+        return TaskOutcome::Skipped {
+            reason: "no origin".to_string(),
+        };
+    }
+
+    match git.command(path).args(task.args()).output() {
+        Ok(output) if output.status.success() => TaskOutcome::Done,
+        Ok(output) => TaskOutcome::Failed {
+            error: String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .last()
+                .unwrap_or("")
+                .trim()
+                .to_string(),
+        },
+        Err(err) => TaskOutcome::Failed {
+            error: format!("unable to run {}: {}", git.program().display(), err),
+        },
+    }
+}
+
+/// Returns the size in bytes of the git directory of the repository at `path`.
+pub fn git_dir_size(git: &Git, path: &Path) -> anyhow::Result<u64> {
+    let git_dir = git
+        .stdout(path, &["rev-parse", "--absolute-git-dir"])
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("git rev-parse --absolute-git-dir failed"))?;
+
+    Ok(WalkDir::new(git_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum())
+}
+
+const UNITS: &[(&str, u64)] = &[
+    ("G", 1024 * 1024 * 1024),
+    ("M", 1024 * 1024),
+    ("K", 1024),
+    ("", 1),
+];
+
+/// Parses a size in bytes like `500M`, with an optional K, M or G suffix in powers of 1024.
+pub fn parse_size(s: &str) -> anyhow::Result<u64> {
+    let s = s.trim();
+    let digits = s.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = s[digits.len()..].to_ascii_uppercase();
+    let suffix = suffix
+        .strip_suffix("IB")
+        .or_else(|| suffix.strip_suffix('B'))
+        .unwrap_or(&suffix);
+
+    let (_, multiplier) = UNITS
+        .iter()
+        .find(|(unit, _)| *unit == suffix)
+        .ok_or_else(|| anyhow!("invalid size {}, the units are K, M and G", s))?;
+    let count: u64 = digits
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid size {}", s))?;
+
+    Ok(count * multiplier)
+}
+
+/// Formats a size in bytes with the largest unit it has at least one of, like `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    match UNITS.iter().find(|(_, multiplier)| bytes >= *multiplier) {
+        Some((unit, multiplier)) if !unit.is_empty() => {
+            format!("{:.1} {}iB", bytes as f64 / *multiplier as f64, unit)
+        }
+        _ => format!("{} B", bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(500 * 1024 * 1024, parse_size("500M").unwrap());
+        assert_eq!(2 * 1024 * 1024 * 1024, parse_size("2GiB").unwrap());
+        assert_eq!(10 * 1024, parse_size("10kb").unwrap());
+        assert_eq!(42, parse_size("42").unwrap());
+        assert!(parse_size("12T").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!("512 B", format_size(512));
+        assert_eq!("1.5 MiB", format_size(1024 * 1024 * 3 / 2));
+        assert_eq!("2.0 GiB", format_size(2 * 1024 * 1024 * 1024));
+    }
+}
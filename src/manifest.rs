@@ -0,0 +1,132 @@
+//! Export the list of repositories to a file and clone them back from it.
+
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::git::Git;
+
+/// The repositories under a directory along with where they come from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The repositories, sorted by path
+    #[serde(default, rename = "repository")]
+    pub repositories: Vec<Entry>,
+}
+
+/// A repository in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The path of the repository relative to the root of the manifest
+    pub path: PathBuf,
+    /// The URL of the origin remote, if there's one
+    pub url: Option<String>,
+    /// The branch checked out, unless HEAD is detached
+    pub branch: Option<String>,
+}
+
+/// What happened to an entry when cloning a manifest.
+#[derive(Debug)]
+pub enum CloneOutcome {
+    /// The repository was cloned
+    Cloned,
+    /// Something already exists at the path of the repository, it was left untouched
+    Exists,
+    /// The repository has no URL in the manifest
+    NoUrl,
+    /// git clone failed with this error
+    Failed(String),
+}
+
+impl Manifest {
+    /// Creates the manifest of the repositories at `paths`, which must be under `root`.
+    pub fn export(git: &Git, root: &Path, paths: &[PathBuf]) -> Self {
+        let mut repositories: Vec<Entry> = paths
+            .par_iter()
+            .map(|path| Entry {
+                path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+                url: git.stdout(path, &["config", "--get", "remote.origin.url"]),
+                branch: git.stdout(path, &["symbolic-ref", "--short", "-q", "HEAD"]),
+            })
+            .collect();
+        repositories.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Self { repositories }
+    }
+
+    /// Parses a manifest in TOML.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(input)?)
+    }
+
+    /// Formats the manifest in TOML.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    /// Clones in parallel the repositories of the manifest that are missing under `root`.
+    ///
+    /// Returns the outcome of each entry, in the order of the manifest.
+    pub fn clone_missing(&self, git: &Git, root: &Path) -> Vec<(&Entry, CloneOutcome)> {
+        self.repositories
+            .par_iter()
+            .map(|entry| (entry, clone_entry(git, root, entry)))
+            .collect()
+    }
+}
+
+fn clone_entry(git: &Git, root: &Path, entry: &Entry) -> CloneOutcome {
+    let path = root.join(&entry.path);
+    if path.symlink_metadata().is_ok() {
+        return CloneOutcome::Exists;
+    }
+    let Some(url) = &entry.url else {
+        return CloneOutcome::NoUrl;
+    };
+
+    let mut command = git.command(Path::new("."));
+    command.args(["clone", "-q"]);
+    if let Some(branch) = &entry.branch {
+        command.args(["--branch", branch]);
+    }
+    command.arg(url).arg(&path);
+
+    match command.output() {
+        Ok(output) if output.status.success() => CloneOutcome::Cloned,
+        Ok(output) => {
+            CloneOutcome::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+        Err(err) => CloneOutcome::Failed(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let manifest = Manifest {
+            repositories: vec![
+                Entry {
+                    path: PathBuf::from("a/foo"),
+                    url: Some("git@github.com:foo/bar.git".to_string()),
+                    branch: Some("main".to_string()),
+                },
+                Entry {
+                    path: PathBuf::from("baz"),
+                    url: None,
+                    branch: None,
+                },
+            ],
+        };
+
+        let toml = manifest.to_toml();
+        assert_eq!(
+            "[[repository]]\npath = \"a/foo\"\nurl = \"git@github.com:foo/bar.git\"\nbranch = \"main\"\n\n[[repository]]\npath = \"baz\"\n",
+            toml
+        );
+        assert_eq!(manifest, Manifest::parse(&toml).unwrap());
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::anyhow;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Computes the shortest unique trailing path of each path in `paths`.
+///
+/// For example with `/src/foo`, `/src/bar` and `/work/bar` this returns `foo`, `src/bar` and `work/bar`.
+pub fn short_names(paths: &[PathBuf]) -> Vec<String> {
+    let all_components: Vec<Vec<Component>> =
+        paths.iter().map(|p| p.components().collect()).collect();
+
+    all_components
+        .iter()
+        .enumerate()
+        .map(|(i, components)| {
+            let mut n = 1;
+            while n < components.len() {
+                let suffix = &components[components.len() - n..];
+
+                let ambiguous = all_components
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| j != i && other.ends_with(suffix));
+                if !ambiguous {
+                    break;
+                }
+
+                n += 1;
+            }
+
+            let start = components.len().saturating_sub(n);
+
+            components[start..]
+                .iter()
+                .collect::<PathBuf>()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Returns the indices of the paths in `paths` ending with the components of `name`, like `foo`
+/// or `work/foo`. A short name from [`short_names`] always matches a single path.
+pub fn find(name: &str, paths: &[PathBuf]) -> Vec<usize> {
+    let name = Path::new(name);
+    if name.as_os_str().is_empty() {
+        return Vec::new();
+    }
+
+    paths
+        .iter()
+        .enumerate()
+        .filter(|(_, path)| path.ends_with(name))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Builds the globs matching the directory name of a repository with one of `patterns`, case
+/// insensitive unless `case_sensitive`. See [`matches_name`].
+pub fn name_globs(patterns: &[String], case_sensitive: bool) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .literal_separator(true)
+            .build()
+            .map_err(|err| anyhow!("invalid pattern {}: {}", pattern, err))?;
+        builder.add(glob);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Returns true if the last component of `path`, the directory name of the repository, matches
+/// one of `globs`.
+pub fn matches_name(globs: &GlobSet, path: &Path) -> bool {
+    path.file_name().is_some_and(|name| globs.is_match(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_name() {
+        let patterns = vec!["*-service".to_string(), "api*".to_string()];
+        let globs = name_globs(&patterns, false).unwrap();
+        assert!(matches_name(&globs, Path::new("/src/billing-service")));
+        assert!(matches_name(&globs, Path::new("/src/API-gateway")));
+        assert!(!matches_name(
+            &globs,
+            Path::new("/src/billing-service/worker")
+        ));
+        assert!(!matches_name(&globs, Path::new("/api/worker")));
+
+        let globs = name_globs(&patterns, true).unwrap();
+        assert!(!matches_name(&globs, Path::new("/src/API-gateway")));
+        assert!(name_globs(&["a[".to_string()], false).is_err());
+    }
+
+    #[test]
+    fn test_short_names() {
+        let paths = vec![
+            PathBuf::from("/src/foo"),
+            PathBuf::from("/src/bar"),
+            PathBuf::from("/work/bar"),
+            PathBuf::from("/work/a/baz"),
+            PathBuf::from("/work/b/a/baz"),
+        ];
+
+        let names = short_names(&paths);
+        assert_eq!(
+            vec!["foo", "src/bar", "work/bar", "work/a/baz", "b/a/baz"],
+            names
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let paths = vec![
+            PathBuf::from("/src/foo"),
+            PathBuf::from("/src/bar"),
+            PathBuf::from("/work/bar"),
+        ];
+
+        assert_eq!(vec![0], find("foo", &paths));
+        assert_eq!(vec![1, 2], find("bar", &paths));
+        assert_eq!(vec![2], find("work/bar", &paths));
+        assert_eq!(vec![2], find("work/bar/", &paths));
+        assert!(find("oo", &paths).is_empty());
+        assert!(find("", &paths).is_empty());
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::output::format_duration;
+
+/// Sends a desktop notification saying the run finished.
+///
+/// If no notification daemon is reachable this rings the terminal bell instead.
+pub fn run_finished(succeeded: usize, failed: usize, elapsed: Duration) {
+    let summary = if failed > 0 {
+        "gitjuggling finished with failures"
+    } else {
+        "gitjuggling finished"
+    };
+    let body = format!(
+        "{} succeeded, {} failed in {}",
+        succeeded,
+        failed,
+        format_duration(elapsed)
+    );
+
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("gitjuggling")
+        .summary(summary)
+        .body(&body);
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.urgency(if failed > 0 {
+        notify_rust::Urgency::Critical
+    } else {
+        notify_rust::Urgency::Normal
+    });
+
+    if notification.show().is_err() {
+        let mut stderr = io::stderr();
+        let _ = stderr.write_all(b"\x07");
+        let _ = stderr.flush();
+    }
+}
@@ -0,0 +1,163 @@
+//! Order the repositories so that a superproject runs before or after its submodules.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::gitmodules::GitModules;
+
+/// The repositories each repository waits for before it's run, the others run in parallel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    waits_for: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl Dependencies {
+    /// Makes every submodule in `paths` wait for its superproject, `gitmodules` are the
+    /// submodules of the superprojects parsed from their .gitmodules file.
+    ///
+    /// The submodules not in `paths` are ignored.
+    pub fn submodules(paths: &[PathBuf], gitmodules: &HashMap<PathBuf, GitModules>) -> Self {
+        let mut dependencies = Self::default();
+        for superproject in paths {
+            let Some(gitmodules) = gitmodules.get(superproject) else {
+                continue;
+            };
+            for submodule in gitmodules.submodules() {
+                // The paths are canonical, the submodule may be behind a symlink
+                let path = superproject.join(submodule.path());
+                let path = path.canonicalize().unwrap_or(path);
+                if paths.contains(&path) {
+                    dependencies.add(&path, superproject);
+                }
+            }
+        }
+
+        dependencies
+    }
+
+    /// Makes `path` wait for `dependency`.
+    pub fn add(&mut self, path: &Path, dependency: &Path) {
+        let waits_for = self.waits_for.entry(path.to_path_buf()).or_default();
+        if !waits_for.iter().any(|known| known == dependency) {
+            waits_for.push(dependency.to_path_buf());
+        }
+    }
+
+    /// Returns the same dependencies the other way around: the superprojects wait for their
+    /// submodules.
+    pub fn reversed(&self) -> Self {
+        let mut reversed = Self::default();
+        for (path, waits_for) in &self.waits_for {
+            for dependency in waits_for {
+                reversed.add(dependency, path);
+            }
+        }
+
+        reversed
+    }
+
+    /// Returns true if no repository waits for another.
+    pub fn is_empty(&self) -> bool {
+        self.waits_for.values().all(Vec::is_empty)
+    }
+
+    /// Returns the repositories `path` waits for.
+    pub fn waits_for(&self, path: &Path) -> &[PathBuf] {
+        self.waits_for.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Removes the dependencies making repositories wait for each other, otherwise none of them
+    /// would ever run. Returns them, as the repository and the one it no longer waits for.
+    ///
+    /// The dependencies are walked in the order of `paths` so the same ones are always removed.
+    pub fn break_cycles(&mut self, paths: &[PathBuf]) -> Vec<(PathBuf, PathBuf)> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Mark {
+            Visiting,
+            Visited,
+        }
+
+        fn visit(
+            dependencies: &mut Dependencies,
+            path: &Path,
+            marks: &mut HashMap<PathBuf, Mark>,
+            broken: &mut Vec<(PathBuf, PathBuf)>,
+        ) {
+            marks.insert(path.to_path_buf(), Mark::Visiting);
+            for dependency in dependencies.waits_for(path).to_vec() {
+                match marks.get(&dependency) {
+                    Some(Mark::Visiting) => {
+                        if let Some(waits_for) = dependencies.waits_for.get_mut(path) {
+                            waits_for.retain(|known| *known != dependency);
+                        }
+                        broken.push((path.to_path_buf(), dependency));
+                    }
+                    Some(Mark::Visited) => {}
+                    None => visit(dependencies, &dependency, marks, broken),
+                }
+            }
+            marks.insert(path.to_path_buf(), Mark::Visited);
+        }
+
+        let mut marks = HashMap::new();
+        let mut broken = Vec::new();
+        for path in paths {
+            if !marks.contains_key(path) {
+                visit(self, path, &mut marks, &mut broken);
+            }
+        }
+
+        broken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_submodules() {
+        let gitmodules = GitModules::parse(
+            "[submodule \"lib\"]\n\
+             \tpath = lib\n\
+             \turl = ../lib\n\
+             [submodule \"vendor\"]\n\
+             \tpath = vendor/foo\n\
+             \turl = ../foo\n",
+        )
+        .unwrap();
+        let paths = vec![
+            PathBuf::from("/nonexistent/super"),
+            PathBuf::from("/nonexistent/super/lib"),
+            PathBuf::from("/nonexistent/other"),
+        ];
+        let gitmodules = HashMap::from([(paths[0].clone(), gitmodules)]);
+
+        let dependencies = Dependencies::submodules(&paths, &gitmodules);
+        assert_eq!(&paths[..1], dependencies.waits_for(&paths[1]));
+        assert!(dependencies.waits_for(&paths[0]).is_empty());
+        assert!(dependencies.waits_for(&paths[2]).is_empty());
+
+        let reversed = dependencies.reversed();
+        assert_eq!(&paths[1..2], reversed.waits_for(&paths[0]));
+        assert!(reversed.waits_for(&paths[1]).is_empty());
+    }
+
+    #[test]
+    fn test_break_cycles() {
+        let paths: Vec<PathBuf> = ["/a", "/b", "/c", "/d"].iter().map(PathBuf::from).collect();
+        let mut dependencies = Dependencies::default();
+        dependencies.add(&paths[0], &paths[1]);
+        dependencies.add(&paths[1], &paths[2]);
+        dependencies.add(&paths[2], &paths[0]);
+        dependencies.add(&paths[3], &paths[2]);
+
+        assert_eq!(
+            vec![(paths[2].clone(), paths[0].clone())],
+            dependencies.break_cycles(&paths)
+        );
+        assert!(dependencies.waits_for(&paths[2]).is_empty());
+        assert_eq!(&paths[2..3], dependencies.waits_for(&paths[3]));
+        assert!(dependencies.break_cycles(&paths).is_empty());
+    }
+}
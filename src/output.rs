@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use gitjuggling::ansi::strip_ansi;
+
+/// Chunks bigger than this are spilled to a temporary file while they wait for their turn.
+const SPILL_THRESHOLD: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputOrder {
+    /// Print the output of a repository as soon as its command completes
+    Completion,
+    /// Print the output of the repositories in sorted order
+    Sorted,
+}
+
+impl FromStr for OutputOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "completion" => Ok(OutputOrder::Completion),
+            "sorted" => Ok(OutputOrder::Sorted),
+            _ => Err(anyhow::anyhow!("unknown output order {}", s)),
+        }
+    }
+}
+
+/// The format of the output meant for other programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(anyhow::anyhow!("unknown format {}", s)),
+        }
+    }
+}
+
+/// The stream the output is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn lock(&self) -> Box<dyn Write + '_> {
+        match self {
+            Stream::Stdout => Box::new(io::stdout().lock()),
+            Stream::Stderr => Box::new(io::stderr().lock()),
+        }
+    }
+
+    pub fn write_bytes(&self, data: &[u8]) -> io::Result<()> {
+        let mut writer = self.lock();
+        writer.write_all(data)?;
+        writer.flush()
+    }
+}
+
+enum Chunk {
+    Memory(String),
+    Spilled(File),
+}
+
+struct ReorderBuffer {
+    next: usize,
+    /// The chunks waiting for their turn, with what goes to the tee file if it's not the chunk
+    pending: BTreeMap<usize, (Chunk, Option<String>)>,
+}
+
+/// A file getting a copy of the output, without the colors.
+///
+/// Every chunk is flushed as soon as it's written so that a crashed run still leaves the output
+/// of the repositories which completed.
+pub struct Tee {
+    file: Mutex<File>,
+}
+
+impl Tee {
+    /// Creates the file at `path`, an existing file is replaced at once rather than truncated.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(".gitjuggling-tee");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(std::fs::Permissions::from_mode(0o644));
+        }
+        let file = builder
+            .tempfile_in(dir)?
+            .persist(path)
+            .map_err(|err| err.error)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Writes `text` without its escape sequences.
+    ///
+    /// Errors are ignored: like the log file, the tee file must never fail the run.
+    pub fn write(&self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let mut file = self.file.lock().unwrap();
+        let _ = file.write_all(strip_ansi(text).as_bytes());
+        let _ = file.flush();
+    }
+}
+
+/// Prints the output chunk of each repository.
+///
+/// In sorted order a chunk is held until the chunks of all the repositories before it have been printed.
+/// The tee file gets the chunks in the same order.
+pub struct Printer {
+    order: OutputOrder,
+    stream: Stream,
+    tee: Option<Tee>,
+    buffer: Mutex<ReorderBuffer>,
+}
+
+impl Printer {
+    pub fn new(order: OutputOrder, stream: Stream) -> Self {
+        Self {
+            order,
+            stream,
+            tee: None,
+            buffer: Mutex::new(ReorderBuffer {
+                next: 0,
+                pending: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Copies the output to `tee`.
+    pub fn tee(mut self, tee: Tee) -> Self {
+        self.tee = Some(tee);
+        self
+    }
+
+    /// Returns true if the output is copied to a tee file.
+    pub fn tees(&self) -> bool {
+        self.tee.is_some()
+    }
+
+    /// Prints `text` right away, outside of any repository chunk.
+    ///
+    /// Errors are ignored, like a closed pipe.
+    pub fn write(&self, text: &str) {
+        let _ = self.stream.write_bytes(text.as_bytes());
+        self.write_tee(text);
+    }
+
+    /// Prints `text` right away like [`Printer::write`], but not to the tee file.
+    pub fn write_stream(&self, text: &str) {
+        let _ = self.stream.write_bytes(text.as_bytes());
+    }
+
+    fn write_tee(&self, text: &str) {
+        if let Some(tee) = &self.tee {
+            tee.write(text);
+        }
+    }
+
+    /// Prints the chunk of the repository at `index`.
+    pub fn print(&self, index: usize, chunk: String) -> io::Result<()> {
+        self.print_split(index, chunk, None)
+    }
+
+    /// Prints the chunk of the repository at `index`, with `tee` instead of the chunk in the
+    /// tee file if it's given, like a repository hidden in the terminal.
+    pub fn print_split(&self, index: usize, chunk: String, tee: Option<String>) -> io::Result<()> {
+        if self.order == OutputOrder::Completion {
+            // Held so that the tee file gets the chunks in the same order
+            let _buffer = self.buffer.lock().unwrap();
+            self.write_tee(tee.as_deref().unwrap_or(&chunk));
+            return self.stream.write_bytes(chunk.as_bytes());
+        }
+
+        let mut buffer = self.buffer.lock().unwrap();
+
+        if index != buffer.next {
+            let chunk = if chunk.len() > SPILL_THRESHOLD {
+                let mut file = tempfile::tempfile()?;
+                file.write_all(chunk.as_bytes())?;
+                Chunk::Spilled(file)
+            } else {
+                Chunk::Memory(chunk)
+            };
+
+            buffer.pending.insert(index, (chunk, tee));
+            return Ok(());
+        }
+
+        let mut stdout = self.stream.lock();
+
+        stdout.write_all(chunk.as_bytes())?;
+        self.write_tee(tee.as_deref().unwrap_or(&chunk));
+        buffer.next += 1;
+
+        // Flush all the chunks that were waiting on this one
+
+        loop {
+            let next = buffer.next;
+            let Some((chunk, tee)) = buffer.pending.remove(&next) else {
+                break;
+            };
+
+            match chunk {
+                Chunk::Memory(data) => {
+                    stdout.write_all(data.as_bytes())?;
+                    self.write_tee(tee.as_deref().unwrap_or(&data));
+                }
+                Chunk::Spilled(mut file) => {
+                    file.seek(SeekFrom::Start(0))?;
+                    io::copy(&mut file, &mut stdout)?;
+                    match tee {
+                        Some(tee) => self.write_tee(&tee),
+                        None if self.tees() => {
+                            let mut data = String::new();
+                            file.seek(SeekFrom::Start(0))?;
+                            file.read_to_string(&mut data)?;
+                            self.write_tee(&data);
+                        }
+                        None => {}
+                    }
+                }
+            }
+
+            buffer.next += 1;
+        }
+
+        stdout.flush()
+    }
+}
+
+/// Keeps only the first and last `max_lines / 2` lines of `text`, with a marker in between
+/// saying how many lines were omitted.
+pub fn truncate_lines(text: &str, max_lines: Option<usize>) -> Cow<'_, str> {
+    let Some(max_lines) = max_lines else {
+        return Cow::Borrowed(text);
+    };
+
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() <= max_lines {
+        return Cow::Borrowed(text);
+    }
+
+    let tail = max_lines / 2;
+    let head = max_lines - tail;
+    let omitted = lines.len() - max_lines;
+
+    let mut result = lines[..head].join("\n");
+    if !result.is_empty() {
+        result.push('\n');
+    }
+    result.push_str(&format!("… {} lines omitted …", omitted));
+    for line in &lines[lines.len() - tail..] {
+        result.push('\n');
+        result.push_str(line);
+    }
+
+    Cow::Owned(result)
+}
+
+/// Formats a duration compactly, for example `20ms`, `1.50s` or `2m05s`.
+pub fn format_duration(duration: Duration) -> String {
+    let millis = duration.as_millis();
+
+    if millis < 1000 {
+        format!("{}ms", millis)
+    } else if millis < 60_000 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        let secs = duration.as_secs();
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_lines() {
+        let text = "1\n2\n3\n4\n5\n6\n7";
+
+        assert_eq!(text, truncate_lines(text, None));
+        assert_eq!(text, truncate_lines(text, Some(7)));
+        assert_eq!(
+            "1\n2\n3\n… 2 lines omitted …\n6\n7",
+            truncate_lines(text, Some(5))
+        );
+        assert_eq!("… 7 lines omitted …", truncate_lines(text, Some(0)));
+    }
+}
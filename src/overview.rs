@@ -0,0 +1,1672 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as FmtWrite};
+use std::str::FromStr;
+
+use colored::Colorize;
+use gitjuggling::audit::Audit;
+use gitjuggling::branches::{Branches, OffDefault, Pruned};
+use gitjuggling::clone::CloneUrlOutcome;
+use gitjuggling::maintenance::{format_size, TaskOutcome};
+use gitjuggling::patch::PatchOutcome;
+use gitjuggling::push::PushOutcome;
+use gitjuggling::remotes::RemoteHealth;
+use gitjuggling::sizes::Sizes;
+use gitjuggling::stash::{StashOutcome, UnstashOutcome};
+use gitjuggling::submodules::SubmoduleOutcome;
+use gitjuggling::switch::SwitchOutcome;
+use gitjuggling::sync::SyncOutcome;
+use gitjuggling::tag::TagOutcome;
+use gitjuggling::timeline::Commit;
+use gitjuggling::verify::Signature;
+use gitjuggling::RepoStatus;
+
+/// The status of a repository, or why it couldn't be probed.
+pub struct Entry {
+    pub name: String,
+    pub status: Result<RepoStatus, String>,
+    /// The remotes with their URL, if they were read
+    pub remotes: Option<Vec<(String, String)>>,
+}
+
+impl Entry {
+    /// Lower is more interesting: failed probes first, then diverged branches, dirty working
+    /// trees, branches ahead or behind, and the clean repositories last.
+    fn rank(&self) -> u8 {
+        match &self.status {
+            Err(_) => 0,
+            Ok(status) if status.is_diverged() => 1,
+            Ok(status) if status.is_dirty() => 2,
+            Ok(status) if status.ahead.unwrap_or(0) > 0 || status.behind.unwrap_or(0) > 0 => 3,
+            Ok(_) => 4,
+        }
+    }
+}
+
+/// Sorts `entries` with the most interesting first, then by name.
+pub fn sort(entries: &mut [Entry]) {
+    entries.sort_by(|a, b| a.rank().cmp(&b.rank()).then(a.name.cmp(&b.name)));
+}
+
+fn format_changes(status: &RepoStatus) -> String {
+    match (status.modified, status.untracked) {
+        (0, 0) => "clean".to_string(),
+        (modified, 0) => format!("{} modified", modified),
+        (0, untracked) => format!("{} untracked", untracked),
+        (modified, untracked) => format!("{} modified, {} untracked", modified, untracked),
+    }
+}
+
+fn format_upstream(status: &RepoStatus) -> String {
+    if status.unborn {
+        return "no commits".to_string();
+    }
+    match (status.ahead, status.behind) {
+        (Some(0), Some(0)) => "up to date".to_string(),
+        (Some(ahead), Some(0)) => format!("ahead {}", ahead),
+        (Some(0), Some(behind)) => format!("behind {}", behind),
+        (Some(ahead), Some(behind)) => format!("ahead {}, behind {}", ahead, behind),
+        _ if status.upstream.is_some() => "upstream gone".to_string(),
+        _ => "no upstream".to_string(),
+    }
+}
+
+/// Renders one aligned line per entry: name, branch, working tree changes and upstream.
+pub fn render(entries: &[Entry]) -> String {
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let statuses = entries
+        .iter()
+        .filter_map(|entry| entry.status.as_ref().ok());
+    let branch_width = statuses
+        .clone()
+        .map(|status| status.branch.chars().count())
+        .max()
+        .unwrap_or(0);
+    let changes_width = statuses
+        .map(|status| format_changes(status).chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+
+    for entry in entries {
+        let status = match &entry.status {
+            Ok(status) => status,
+            Err(err) => {
+                writeln!(
+                    &mut output,
+                    "{:name_width$} {}",
+                    entry.name,
+                    err.bright_red(),
+                    name_width = name_width
+                )
+                .unwrap();
+                continue;
+            }
+        };
+
+        let changes = format_changes(status);
+        let changes = if status.is_dirty() {
+            changes.bright_yellow()
+        } else {
+            changes.normal()
+        };
+        let upstream = if status.is_diverged() {
+            format_upstream(status).bright_red()
+        } else {
+            format_upstream(status).normal()
+        };
+
+        writeln!(
+            &mut output,
+            "{:name_width$} {:branch_width$} {:changes_width$} {}",
+            entry.name,
+            status.branch,
+            changes,
+            upstream,
+            name_width = name_width,
+            branch_width = branch_width,
+            changes_width = changes_width,
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Renders the entries as a JSON array, the changes counts are always present and the upstream
+/// fields are null without an upstream.
+pub fn render_json(entries: &[Entry]) -> String {
+    let entries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|entry| match &entry.status {
+            Ok(status) => {
+                let mut value = serde_json::to_value(status).unwrap();
+                value["name"] = entry.name.clone().into();
+                value["dirty"] = status.is_dirty().into();
+                if let Some(remotes) = &entry.remotes {
+                    value["remotes"] = remotes
+                        .iter()
+                        .map(|(name, url)| serde_json::json!({"name": name, "url": url}))
+                        .collect();
+                }
+                value
+            }
+            Err(err) => serde_json::json!({"name": entry.name, "error": err}),
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&entries).unwrap();
+    json.push('\n');
+    json
+}
+
+/// A repository whose status changed since a snapshot, `None` if it wasn't discovered then or
+/// isn't anymore.
+pub struct StatusChange {
+    pub name: String,
+    pub before: Option<Result<RepoStatus, String>>,
+    pub after: Option<Result<RepoStatus, String>>,
+}
+
+fn format_status(status: Option<&Result<RepoStatus, String>>) -> String {
+    let status = match status {
+        None => return "not discovered".to_string(),
+        Some(Err(err)) => return err.clone(),
+        Some(Ok(status)) => status,
+    };
+    // A detached HEAD already shows the commit
+    let commit = match &status.commit {
+        Some(commit) if !status.is_detached() => {
+            format!(" at {}", commit.get(..7).unwrap_or(commit))
+        }
+        _ => String::new(),
+    };
+
+    format!(
+        "{}{}, {}, {}",
+        status.branch,
+        commit,
+        format_changes(status),
+        format_upstream(status)
+    )
+}
+
+/// Renders the repositories whose status changed: the name, then the status before and after.
+pub fn render_status_diff(changes: &[StatusChange]) -> String {
+    let mut output = String::new();
+    for change in changes {
+        writeln!(&mut output, "{}", change.name).unwrap();
+        writeln!(
+            &mut output,
+            "  before {}",
+            format_status(change.before.as_ref()).dimmed()
+        )
+        .unwrap();
+        writeln!(
+            &mut output,
+            "  after  {}",
+            format_status(change.after.as_ref()).bright_yellow()
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Renders the repositories whose status changed as a JSON array, the status before and after
+/// are like in [`render_json`], or null.
+pub fn render_status_diff_json(changes: &[StatusChange]) -> String {
+    let value = |status: &Option<Result<RepoStatus, String>>| match status {
+        None => serde_json::Value::Null,
+        Some(Ok(status)) => {
+            let mut value = serde_json::to_value(status).unwrap();
+            value["dirty"] = status.is_dirty().into();
+            value
+        }
+        Some(Err(err)) => serde_json::json!({ "error": err }),
+    };
+    let changes: Vec<serde_json::Value> = changes
+        .iter()
+        .map(|change| {
+            serde_json::json!({
+                "name": change.name,
+                "before": value(&change.before),
+                "after": value(&change.after),
+            })
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&changes).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Renders the diverged repositories, the ones that couldn't be probed first: name, upstream and
+/// the ahead and behind counts, followed by how many diverged.
+pub fn render_diverged(entries: &[Entry]) -> String {
+    let name_width = entries
+        .iter()
+        .map(|entry| entry.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let upstream_width = entries
+        .iter()
+        .filter_map(|entry| entry.status.as_ref().ok())
+        .map(|status| status.upstream.as_deref().unwrap_or("").chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    let mut diverged = 0;
+    for entry in entries {
+        match &entry.status {
+            Ok(status) => {
+                diverged += 1;
+                writeln!(
+                    &mut output,
+                    "{:name_width$} {:upstream_width$} {}",
+                    entry.name,
+                    status.upstream.as_deref().unwrap_or(""),
+                    format_upstream(status).bright_red(),
+                    name_width = name_width,
+                    upstream_width = upstream_width,
+                )
+                .unwrap();
+            }
+            Err(err) => writeln!(
+                &mut output,
+                "{:name_width$} {}",
+                entry.name,
+                err.bright_red(),
+                name_width = name_width
+            )
+            .unwrap(),
+        }
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    writeln!(&mut output, "{} diverged", diverged).unwrap();
+
+    output
+}
+
+/// Where the outcome of a subcommand changing the repositories goes in its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// A git command failed
+    Failed,
+    /// The repository was left untouched for a reason the user should look at
+    Attention,
+    /// The repository was changed
+    Changed,
+    /// There was nothing to do
+    Unchanged,
+}
+
+/// The outcome of a subcommand in a repository, displayed with its details.
+pub trait Outcome: fmt::Display {
+    /// Returns the name of the outcome without its details, counted in the summary.
+    fn label(&self) -> &'static str;
+
+    /// Returns where the outcome goes in the output.
+    fn kind(&self) -> Kind;
+}
+
+impl Outcome for SyncOutcome {
+    fn label(&self) -> &'static str {
+        SyncOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            SyncOutcome::Failed { .. } => Kind::Failed,
+            SyncOutcome::FastForwarded { .. } => Kind::Changed,
+            outcome if outcome.needs_attention() => Kind::Attention,
+            _ => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for SwitchOutcome {
+    fn label(&self) -> &'static str {
+        SwitchOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            SwitchOutcome::Failed { .. } => Kind::Failed,
+            SwitchOutcome::Switched | SwitchOutcome::Created { .. } => Kind::Changed,
+            SwitchOutcome::AlreadyOn => Kind::Unchanged,
+            _ => Kind::Attention,
+        }
+    }
+}
+
+impl Outcome for StashOutcome {
+    fn label(&self) -> &'static str {
+        StashOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            StashOutcome::Failed { .. } => Kind::Failed,
+            StashOutcome::AlreadyStashed { .. } => Kind::Attention,
+            StashOutcome::Stashed { .. } => Kind::Changed,
+            StashOutcome::Clean => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for UnstashOutcome {
+    fn label(&self) -> &'static str {
+        UnstashOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            UnstashOutcome::Failed { .. } => Kind::Failed,
+            UnstashOutcome::Mismatch { .. } => Kind::Attention,
+            UnstashOutcome::Restored => Kind::Changed,
+        }
+    }
+}
+
+impl Outcome for TagOutcome {
+    fn label(&self) -> &'static str {
+        TagOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            TagOutcome::Failed { .. } => Kind::Failed,
+            TagOutcome::Conflict { .. } => Kind::Attention,
+            TagOutcome::Created | TagOutcome::Moved { .. } => Kind::Changed,
+            TagOutcome::AlreadyPresent => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for RemoteHealth {
+    fn label(&self) -> &'static str {
+        RemoteHealth::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            health if health.is_unreachable() => Kind::Failed,
+            RemoteHealth::Redirected { .. } => Kind::Attention,
+            _ => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for TaskOutcome {
+    fn label(&self) -> &'static str {
+        TaskOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            TaskOutcome::Failed { .. } => Kind::Failed,
+            TaskOutcome::Done => Kind::Changed,
+            TaskOutcome::Skipped { .. } => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for SubmoduleOutcome {
+    fn label(&self) -> &'static str {
+        SubmoduleOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            SubmoduleOutcome::Failed { .. } => Kind::Failed,
+            SubmoduleOutcome::CheckedOut { .. } => Kind::Changed,
+            SubmoduleOutcome::UpToDate => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for CloneUrlOutcome {
+    fn label(&self) -> &'static str {
+        CloneUrlOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            CloneUrlOutcome::Failed { .. } => Kind::Failed,
+            CloneUrlOutcome::Cloned => Kind::Changed,
+            CloneUrlOutcome::Present => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for Signature {
+    fn label(&self) -> &'static str {
+        Signature::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Signature::Error { .. } => Kind::Failed,
+            Signature::UnknownKey { .. } | Signature::Unsigned { .. } => Kind::Attention,
+            Signature::Trusted { .. } | Signature::Exempted => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for PatchOutcome {
+    fn label(&self) -> &'static str {
+        PatchOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            PatchOutcome::Failed { .. } => Kind::Failed,
+            PatchOutcome::Conflict { .. } | PatchOutcome::Dirty => Kind::Attention,
+            PatchOutcome::Applied { .. } => Kind::Changed,
+            PatchOutcome::AlreadyApplied => Kind::Unchanged,
+        }
+    }
+}
+
+impl Outcome for PushOutcome {
+    fn label(&self) -> &'static str {
+        PushOutcome::label(self)
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            PushOutcome::Rejected { .. } | PushOutcome::Failed { .. } => Kind::Failed,
+            PushOutcome::Pushed { .. } | PushOutcome::WouldPush { .. } => Kind::Changed,
+            PushOutcome::UpToDate => Kind::Unchanged,
+            _ => Kind::Attention,
+        }
+    }
+}
+
+/// Sorts the outcomes with the most interesting first: failures, then the repositories needing
+/// attention, then by name.
+pub fn sort_outcomes<T: Outcome>(entries: &mut [(String, T)]) {
+    entries.sort_by(|a, b| a.1.kind().cmp(&b.1.kind()).then(a.0.cmp(&b.0)));
+}
+
+/// Renders one line per repository with its outcome and a summary counting each outcome.
+pub fn render_outcomes<T: Outcome>(entries: &[(String, T)]) -> String {
+    let name_width = entries
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    let mut counts: BTreeMap<(Kind, &str), usize> = BTreeMap::new();
+
+    for (name, outcome) in entries {
+        let text = outcome.to_string();
+        let text = match outcome.kind() {
+            Kind::Failed => text.bright_red(),
+            Kind::Attention => text.bright_yellow(),
+            Kind::Changed => text.bright_green(),
+            Kind::Unchanged => text.normal(),
+        };
+        *counts.entry((outcome.kind(), outcome.label())).or_default() += 1;
+
+        writeln!(
+            &mut output,
+            "{:name_width$} {}",
+            name,
+            text,
+            name_width = name_width
+        )
+        .unwrap();
+    }
+
+    let count = |kinds: &[Kind]| -> Vec<String> {
+        counts
+            .iter()
+            .filter(|((kind, _), _)| kinds.contains(kind))
+            .map(|((_, label), count)| format!("{} {}", count, label))
+            .collect()
+    };
+
+    let mut summary = count(&[Kind::Changed, Kind::Unchanged]);
+    let attention = count(&[Kind::Attention]);
+    if !attention.is_empty() {
+        let total: usize = counts
+            .iter()
+            .filter(|((kind, _), _)| *kind == Kind::Attention)
+            .map(|(_, count)| count)
+            .sum();
+        summary.push(format!(
+            "{} need attention ({})",
+            total,
+            attention.join(", ")
+        ));
+    }
+    let failed: usize = counts
+        .iter()
+        .filter(|((kind, _), _)| *kind == Kind::Failed)
+        .map(|(_, count)| count)
+        .sum();
+    // Only break the failures down when they're of different natures
+    let failures = count(&[Kind::Failed]);
+    if counts
+        .keys()
+        .any(|(kind, label)| *kind == Kind::Failed && *label != "failed")
+    {
+        summary.push(format!("{} failed ({})", failed, failures.join(", ")));
+    } else {
+        summary.push(format!("{} failed", failed));
+    }
+
+    writeln!(&mut output, "\n{}", summary.join(", ")).unwrap();
+
+    output
+}
+
+/// Describes what needs attention in an audited repository, one item per line.
+fn audit_items(audit: &Audit) -> Vec<String> {
+    let mut items = Vec::new();
+
+    if audit.modified > 0 {
+        items.push(match &audit.diffstat {
+            Some(diffstat) => format!("uncommitted changes: {}", diffstat),
+            None => format!("uncommitted changes: {} files", audit.modified),
+        });
+    }
+    if audit.untracked > 0 {
+        items.push(format!("untracked files: {}", audit.untracked));
+    }
+    if audit.stashes > 0 {
+        items.push(format!("stashes: {}", audit.stashes));
+    }
+    if !audit.no_upstream.is_empty() {
+        items.push(format!("no upstream: {}", audit.no_upstream.join(", ")));
+    }
+    if !audit.ahead.is_empty() {
+        let branches: Vec<String> = audit
+            .ahead
+            .iter()
+            .map(|ahead| format!("{} by {}", ahead.branch, ahead.commits))
+            .collect();
+        items.push(format!("ahead: {}", branches.join(", ")));
+    }
+
+    items
+}
+
+/// Renders the repositories needing attention with what they have locally, then the clean ones
+/// if `show_clean` is set, then a summary.
+pub fn render_audit(entries: &[(String, Result<Audit, String>)], show_clean: bool) -> String {
+    let mut output = String::new();
+    let mut clean = Vec::new();
+
+    for (name, audit) in entries {
+        match audit {
+            Ok(audit) if audit.is_clean() => clean.push(name.as_str()),
+            Ok(audit) => {
+                writeln!(&mut output, "{}", name.bright_yellow()).unwrap();
+                for item in audit_items(audit) {
+                    writeln!(&mut output, "  {}", item).unwrap();
+                }
+            }
+            Err(err) => {
+                writeln!(&mut output, "{}", name.bright_red()).unwrap();
+                writeln!(&mut output, "  {}", err).unwrap();
+            }
+        }
+    }
+
+    if show_clean && !clean.is_empty() {
+        writeln!(&mut output, "{}", "clean".bright_green()).unwrap();
+        for name in &clean {
+            writeln!(&mut output, "  {}", name).unwrap();
+        }
+    }
+
+    let attention = entries.len() - clean.len();
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    writeln!(
+        &mut output,
+        "{} need attention, {} clean",
+        attention,
+        clean.len()
+    )
+    .unwrap();
+
+    output
+}
+
+/// Renders the audited repositories as a JSON array, the clean ones only if `show_clean` is set.
+pub fn render_audit_json(entries: &[(String, Result<Audit, String>)], show_clean: bool) -> String {
+    let entries: Vec<serde_json::Value> = entries
+        .iter()
+        .filter_map(|(name, audit)| match audit {
+            Ok(audit) if audit.is_clean() && !show_clean => None,
+            Ok(audit) => {
+                let mut value = serde_json::to_value(audit).unwrap();
+                value["name"] = name.clone().into();
+                value["clean"] = audit.is_clean().into();
+                Some(value)
+            }
+            Err(err) => Some(serde_json::json!({"name": name, "error": err})),
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&entries).unwrap();
+    json.push('\n');
+    json
+}
+
+/// How the commits of the timeline are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineGroup {
+    /// A single list
+    None,
+    /// By repository, the one with the most recent commit first
+    Repository,
+    /// By day, the most recent first
+    Day,
+}
+
+impl FromStr for TimelineGroup {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(TimelineGroup::None),
+            "repo" => Ok(TimelineGroup::Repository),
+            "day" => Ok(TimelineGroup::Day),
+            _ => Err(anyhow::anyhow!("unknown timeline group {}", s)),
+        }
+    }
+}
+
+/// Renders the commits of all the repositories, which must be sorted newest first.
+pub fn render_timeline(commits: &[(String, Commit)], group: TimelineGroup) -> String {
+    let name_width = commits
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+
+    match group {
+        TimelineGroup::None => {
+            for (name, commit) in commits {
+                writeln!(
+                    &mut output,
+                    "{} {} {} {}",
+                    commit.date,
+                    format!("{:name_width$}", name, name_width = name_width).bright_blue(),
+                    commit.short_hash().bright_yellow(),
+                    commit.subject
+                )
+                .unwrap();
+            }
+        }
+        TimelineGroup::Repository => {
+            // The repositories are in the order of their most recent commit
+            let mut names: Vec<&str> = Vec::new();
+            for (name, _) in commits {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+
+            for name in names {
+                writeln!(&mut output, "{}", name.bright_blue()).unwrap();
+                for (_, commit) in commits.iter().filter(|(other, _)| other == name) {
+                    writeln!(
+                        &mut output,
+                        "  {} {} {}",
+                        commit.date,
+                        commit.short_hash().bright_yellow(),
+                        commit.subject
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        TimelineGroup::Day => {
+            let mut day = "";
+            for (name, commit) in commits {
+                let (commit_day, time) = commit.date.split_once(' ').unwrap_or((&commit.date, ""));
+                if commit_day != day {
+                    writeln!(&mut output, "{}", commit_day.bright_white()).unwrap();
+                    day = commit_day;
+                }
+                writeln!(
+                    &mut output,
+                    "  {} {} {} {}",
+                    time,
+                    format!("{:name_width$}", name, name_width = name_width).bright_blue(),
+                    commit.short_hash().bright_yellow(),
+                    commit.subject
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    output
+}
+
+/// Renders the commits of all the repositories as a JSON array, whatever the grouping.
+pub fn render_timeline_json(commits: &[(String, Commit)]) -> String {
+    let commits: Vec<serde_json::Value> = commits
+        .iter()
+        .map(|(name, commit)| {
+            let mut value = serde_json::to_value(commit).unwrap();
+            value["repository"] = name.clone().into();
+            value
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&commits).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Formats `seconds` as a compact age like `3d` or `2mo`.
+fn format_age(seconds: i64) -> String {
+    const HOUR: i64 = 60 * 60;
+    const DAY: i64 = 24 * HOUR;
+
+    match seconds.max(0) {
+        seconds if seconds < HOUR => format!("{}m", seconds / 60),
+        seconds if seconds < DAY => format!("{}h", seconds / HOUR),
+        seconds if seconds < 14 * DAY => format!("{}d", seconds / DAY),
+        seconds if seconds < 60 * DAY => format!("{}w", seconds / (7 * DAY)),
+        seconds if seconds < 365 * DAY => format!("{}mo", seconds / (30 * DAY)),
+        seconds => format!("{}y", seconds / (365 * DAY)),
+    }
+}
+
+/// Renders the branches of every repository with at least one, `now` is the current time in
+/// seconds since the Unix epoch.
+pub fn render_branches(repositories: &[(String, Result<Branches, String>)], now: i64) -> String {
+    let mut output = String::new();
+
+    for (name, branches) in repositories {
+        let branches = match branches {
+            Ok(branches) if branches.branches.is_empty() => continue,
+            Ok(branches) => branches,
+            Err(err) => {
+                writeln!(&mut output, "{} {}", name.bright_blue(), err.bright_red()).unwrap();
+                continue;
+            }
+        };
+
+        match &branches.default {
+            Some(default) => {
+                writeln!(&mut output, "{} (default {})", name.bright_blue(), default).unwrap()
+            }
+            None => writeln!(&mut output, "{}", name.bright_blue()).unwrap(),
+        }
+
+        let rows: Vec<(String, String)> = branches
+            .branches
+            .iter()
+            .map(|branch| {
+                let upstream = branch
+                    .upstream
+                    .clone()
+                    .unwrap_or_else(|| "no upstream".to_string());
+                let track = match (branch.ahead, branch.behind) {
+                    (Some(0), Some(0)) => "up to date".to_string(),
+                    (Some(ahead), Some(0)) => format!("ahead {}", ahead),
+                    (Some(0), Some(behind)) => format!("behind {}", behind),
+                    (Some(ahead), Some(behind)) => format!("ahead {}, behind {}", ahead, behind),
+                    _ => String::new(),
+                };
+                (upstream, track)
+            })
+            .collect();
+        let name_width = branches
+            .branches
+            .iter()
+            .map(|branch| branch.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let upstream_width = rows
+            .iter()
+            .map(|(upstream, _)| upstream.chars().count())
+            .max()
+            .unwrap_or(0);
+        let track_width = rows
+            .iter()
+            .map(|(_, track)| track.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (branch, (upstream, track)) in branches.branches.iter().zip(rows) {
+            let line = format!(
+                "  {} {:name_width$} {:upstream_width$} {:track_width$} {:>4}",
+                if branch.current { "*" } else { " " },
+                branch.name,
+                upstream,
+                track,
+                format_age(now - branch.timestamp),
+                name_width = name_width,
+                upstream_width = upstream_width,
+                track_width = track_width,
+            );
+            if branch.merged {
+                writeln!(&mut output, "{} {}", line, "merged".bright_green()).unwrap();
+            } else {
+                writeln!(&mut output, "{}", line).unwrap();
+            }
+        }
+    }
+
+    output
+}
+
+/// Renders the branches of every repository as a JSON array.
+pub fn render_branches_json(repositories: &[(String, Result<Branches, String>)]) -> String {
+    let repositories: Vec<serde_json::Value> = repositories
+        .iter()
+        .map(|(name, branches)| match branches {
+            Ok(branches) => {
+                let mut value = serde_json::to_value(branches).unwrap();
+                value["name"] = name.clone().into();
+                value
+            }
+            Err(err) => serde_json::json!({"name": name, "error": err}),
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&repositories).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Renders the repositories not on their default branch: the ones that couldn't be compared
+/// first, then the ones on another branch and the detached ones last, each sorted by name.
+pub fn render_off_default(repositories: &[(String, Result<OffDefault, String>)]) -> String {
+    let mut repositories: Vec<&(String, Result<OffDefault, String>)> =
+        repositories.iter().collect();
+    repositories.sort_by_key(|(name, off)| {
+        let rank = match off {
+            Err(_) => 0,
+            Ok(off) if off.branch.is_some() => 1,
+            Ok(_) => 2,
+        };
+        (rank, name.clone())
+    });
+
+    let rows: Vec<(&str, String, String)> = repositories
+        .iter()
+        .map(|(name, off)| match off {
+            Ok(off) => {
+                let head = match &off.branch {
+                    Some(branch) => branch.clone(),
+                    None => format!("detached {}", off.commit),
+                };
+                let track = match (off.ahead, off.behind) {
+                    (0, 0) => format!("even with {}", off.default),
+                    (ahead, 0) => format!("ahead {} of {}", ahead, off.default),
+                    (0, behind) => format!("behind {} of {}", behind, off.default),
+                    (ahead, behind) => {
+                        format!("ahead {}, behind {} of {}", ahead, behind, off.default)
+                    }
+                };
+                (name.as_str(), head, track)
+            }
+            Err(err) => (name.as_str(), err.clone(), String::new()),
+        })
+        .collect();
+
+    let name_width = rows
+        .iter()
+        .map(|(name, _, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let head_width = rows
+        .iter()
+        .filter(|(_, _, track)| !track.is_empty())
+        .map(|(_, head, _)| head.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    let (mut branches, mut detached) = (0, 0);
+    for ((name, head, track), (_, off)) in rows.iter().zip(&repositories) {
+        match off {
+            Ok(off) => {
+                let head = format!("{:head_width$}", head, head_width = head_width);
+                let head = if off.branch.is_some() {
+                    branches += 1;
+                    head.bright_yellow()
+                } else {
+                    detached += 1;
+                    head.bright_red()
+                };
+                writeln!(
+                    &mut output,
+                    "{:name_width$} {} {}",
+                    name,
+                    head,
+                    track,
+                    name_width = name_width
+                )
+                .unwrap();
+            }
+            Err(_) => writeln!(
+                &mut output,
+                "{:name_width$} {}",
+                name,
+                head.bright_red(),
+                name_width = name_width
+            )
+            .unwrap(),
+        }
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    writeln!(
+        &mut output,
+        "{} on another branch, {} detached",
+        branches, detached
+    )
+    .unwrap();
+
+    output
+}
+
+/// Renders the repositories not on their default branch as a JSON array.
+pub fn render_off_default_json(repositories: &[(String, Result<OffDefault, String>)]) -> String {
+    let repositories: Vec<serde_json::Value> = repositories
+        .iter()
+        .map(|(name, off)| match off {
+            Ok(off) => {
+                let mut value = serde_json::to_value(off).unwrap();
+                value["name"] = name.clone().into();
+                value
+            }
+            Err(err) => serde_json::json!({"name": name, "error": err}),
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&repositories).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Renders the signatures as a JSON array, with the signer and the key of the signed commits.
+pub fn render_signatures_json(entries: &[(String, Signature)]) -> String {
+    let entries: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|(name, signature)| {
+            let mut value = serde_json::to_value(signature).unwrap();
+            value["name"] = name.clone().into();
+            value
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&entries).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Renders the sizes of the repositories, which must be sorted already: name, total, git
+/// directory, working tree and whether LFS is used, with the largest blobs below each
+/// repository, followed by the total of all of them.
+pub fn render_sizes(repositories: &[(String, Result<Sizes, String>)]) -> String {
+    let rows: Vec<(&str, [String; 3])> = repositories
+        .iter()
+        .filter_map(|(name, sizes)| {
+            let sizes = sizes.as_ref().ok()?;
+            Some((
+                name.as_str(),
+                [
+                    format_size(sizes.total()),
+                    format!("git {},", format_size(sizes.git_dir)),
+                    format!("work tree {})", format_size(sizes.work_tree)),
+                ],
+            ))
+        })
+        .collect();
+    let name_width = repositories
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let widths: Vec<usize> = (0..3)
+        .map(|column| {
+            rows.iter()
+                .map(|(_, columns)| columns[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut output = String::new();
+    let mut rows = rows.iter();
+    let mut total = 0;
+    for (name, sizes) in repositories {
+        let sizes = match sizes {
+            Ok(sizes) => sizes,
+            Err(err) => {
+                writeln!(
+                    &mut output,
+                    "{:name_width$} {}",
+                    name,
+                    err.bright_red(),
+                    name_width = name_width
+                )
+                .unwrap();
+                continue;
+            }
+        };
+        let (_, columns) = rows.next().unwrap();
+        total += sizes.total();
+
+        let line = format!(
+            "{:name_width$} {:>total_width$} ({:git_width$} {:work_tree_width$}",
+            name,
+            columns[0],
+            columns[1],
+            columns[2],
+            name_width = name_width,
+            total_width = widths[0],
+            git_width = widths[1],
+            work_tree_width = widths[2],
+        );
+        if sizes.lfs {
+            writeln!(&mut output, "{} {}", line, "LFS".bright_yellow()).unwrap();
+        } else {
+            writeln!(&mut output, "{}", line.trim_end()).unwrap();
+        }
+
+        for object in &sizes.largest {
+            writeln!(
+                &mut output,
+                "  {:>10} {} {}",
+                format_size(object.size),
+                object.path,
+                object.hash.get(..7).unwrap_or(&object.hash).dimmed()
+            )
+            .unwrap();
+        }
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    writeln!(&mut output, "{} in total", format_size(total)).unwrap();
+
+    output
+}
+
+/// Renders the branches pruned in every repository, or that would be with `dry_run`, followed by
+/// how many per repository and in total.
+pub fn render_pruned(
+    repositories: &[(String, Result<Vec<Pruned>, String>)],
+    dry_run: bool,
+) -> String {
+    let mut output = String::new();
+    let mut counts = Vec::new();
+    let mut total = 0;
+    let mut failed = 0;
+
+    for (name, pruned) in repositories {
+        let pruned = match pruned {
+            Ok(pruned) if pruned.is_empty() => continue,
+            Ok(pruned) => pruned,
+            Err(err) => {
+                writeln!(&mut output, "{} {}", name.bright_blue(), err.bright_red()).unwrap();
+                failed += 1;
+                continue;
+            }
+        };
+        writeln!(&mut output, "{}", name.bright_blue()).unwrap();
+
+        let reasons: Vec<String> = pruned
+            .iter()
+            .map(|branch| branch.reason.to_string())
+            .collect();
+        let name_width = pruned
+            .iter()
+            .map(|branch| branch.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let reason_width = reasons
+            .iter()
+            .map(|reason| reason.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut deleted = 0;
+        for (branch, reason) in pruned.iter().zip(reasons) {
+            let result = match &branch.error {
+                Some(error) => {
+                    failed += 1;
+                    error.bright_red().to_string()
+                }
+                None if dry_run => {
+                    deleted += 1;
+                    "would delete".to_string()
+                }
+                None => {
+                    deleted += 1;
+                    "deleted".bright_green().to_string()
+                }
+            };
+            writeln!(
+                &mut output,
+                "  {:name_width$} {:reason_width$} {}",
+                branch.name,
+                reason,
+                result,
+                name_width = name_width,
+                reason_width = reason_width
+            )
+            .unwrap();
+        }
+        total += deleted;
+        if deleted > 0 {
+            counts.push(format!("{} {}", name, deleted));
+        }
+    }
+
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    let mut summary = format!(
+        "{} {} {}",
+        total,
+        if total == 1 { "branch" } else { "branches" },
+        if dry_run {
+            "would be deleted"
+        } else {
+            "deleted"
+        }
+    );
+    if !counts.is_empty() {
+        write!(&mut summary, " ({})", counts.join(", ")).unwrap();
+    }
+    if failed > 0 {
+        write!(&mut summary, ", {} failed", failed).unwrap();
+    }
+    writeln!(&mut output, "{}", summary).unwrap();
+
+    output
+}
+
+/// Renders the branches pruned in every repository as a JSON array.
+pub fn render_pruned_json(repositories: &[(String, Result<Vec<Pruned>, String>)]) -> String {
+    let repositories: Vec<serde_json::Value> = repositories
+        .iter()
+        .map(|(name, pruned)| match pruned {
+            Ok(pruned) => serde_json::json!({"name": name, "branches": pruned}),
+            Err(err) => serde_json::json!({"name": name, "error": err}),
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&repositories).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Renders the sizes of the repositories as a JSON array, in bytes.
+pub fn render_sizes_json(repositories: &[(String, Result<Sizes, String>)]) -> String {
+    let repositories: Vec<serde_json::Value> = repositories
+        .iter()
+        .map(|(name, sizes)| match sizes {
+            Ok(sizes) => {
+                let mut value = serde_json::to_value(sizes).unwrap();
+                value["name"] = name.clone().into();
+                value["total"] = sizes.total().into();
+                value
+            }
+            Err(err) => serde_json::json!({"name": name, "error": err}),
+        })
+        .collect();
+
+    let mut json = serde_json::to_string(&repositories).unwrap();
+    json.push('\n');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gitjuggling::audit::AheadBranch;
+    use gitjuggling::branches::{Branch, PruneReason};
+    use gitjuggling::sizes::LargeObject;
+
+    #[test]
+    fn test_render() {
+        colored::control::set_override(false);
+
+        let status = |modified, ahead, behind| RepoStatus {
+            branch: "main".to_string(),
+            commit: None,
+            upstream: Some("origin/main".to_string()),
+            ahead: Some(ahead),
+            behind: Some(behind),
+            modified,
+            untracked: 0,
+            unborn: false,
+        };
+        let mut entries = vec![
+            Entry {
+                name: "clean".to_string(),
+                status: Ok(status(0, 0, 0)),
+                remotes: None,
+            },
+            Entry {
+                name: "dirty".to_string(),
+                status: Ok(status(2, 0, 0)),
+                remotes: None,
+            },
+            Entry {
+                name: "diverged".to_string(),
+                status: Ok(status(0, 1, 3)),
+                remotes: None,
+            },
+            Entry {
+                name: "local".to_string(),
+                status: Ok(RepoStatus {
+                    branch: "detached 3f2c1a9".to_string(),
+                    ..RepoStatus::default()
+                }),
+                remotes: None,
+            },
+            Entry {
+                name: "broken".to_string(),
+                status: Err("git status failed: fatal: bad object HEAD".to_string()),
+                remotes: None,
+            },
+        ];
+        sort(&mut entries);
+
+        assert_eq!(
+            "broken   git status failed: fatal: bad object HEAD\n\
+             diverged main             clean      ahead 1, behind 3\n\
+             dirty    main             2 modified up to date\n\
+             clean    main             clean      up to date\n\
+             local    detached 3f2c1a9 clean      no upstream\n",
+            render(&entries)
+        );
+    }
+
+    #[test]
+    fn test_render_status_diff() {
+        colored::control::set_override(false);
+
+        let before = RepoStatus {
+            branch: "main".to_string(),
+            commit: Some("3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a".to_string()),
+            upstream: Some("origin/main".to_string()),
+            ahead: Some(0),
+            behind: Some(0),
+            ..RepoStatus::default()
+        };
+        let after = RepoStatus {
+            commit: Some("0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a3f2c1a9d".to_string()),
+            ahead: Some(1),
+            modified: 2,
+            ..before.clone()
+        };
+        let changes = vec![
+            StatusChange {
+                name: "foo".to_string(),
+                before: Some(Ok(before.clone())),
+                after: Some(Ok(after)),
+            },
+            StatusChange {
+                name: "new".to_string(),
+                before: None,
+                after: Some(Ok(before)),
+            },
+        ];
+
+        assert_eq!(
+            "foo\n  \
+             before main at 3f2c1a9, clean, up to date\n  \
+             after  main at 0b7e4c5, 2 modified, ahead 1\n\
+             new\n  \
+             before not discovered\n  \
+             after  main at 3f2c1a9, clean, up to date\n",
+            render_status_diff(&changes)
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_status_diff_json(&changes)).unwrap();
+        assert_eq!(serde_json::Value::Null, json[1]["before"]);
+        assert_eq!(2, json[0]["after"]["modified"]);
+        assert_eq!(true, json[0]["after"]["dirty"]);
+    }
+
+    #[test]
+    fn test_render_diverged() {
+        colored::control::set_override(false);
+
+        let mut entries = vec![
+            Entry {
+                name: "foo".to_string(),
+                status: Ok(RepoStatus {
+                    branch: "dev".to_string(),
+                    upstream: Some("upstream/dev".to_string()),
+                    ahead: Some(2),
+                    behind: Some(1),
+                    ..RepoStatus::default()
+                }),
+                remotes: None,
+            },
+            Entry {
+                name: "broken".to_string(),
+                status: Err("git status failed: fatal: bad object HEAD".to_string()),
+                remotes: None,
+            },
+        ];
+        sort(&mut entries);
+
+        assert_eq!(
+            "broken git status failed: fatal: bad object HEAD\n\
+             foo    upstream/dev ahead 2, behind 1\n\
+             \n\
+             1 diverged\n",
+            render_diverged(&entries)
+        );
+        assert_eq!("0 diverged\n", render_diverged(&[]));
+    }
+
+    #[test]
+    fn test_render_sizes() {
+        colored::control::set_override(false);
+
+        let repositories = vec![
+            (
+                "assets".to_string(),
+                Ok(Sizes {
+                    git_dir: 3 * 1024 * 1024,
+                    work_tree: 1024 * 1024,
+                    lfs: true,
+                    largest: vec![LargeObject {
+                        hash: "0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a3f2c1a9d".to_string(),
+                        size: 2 * 1024 * 1024,
+                        path: "video.mp4".to_string(),
+                    }],
+                }),
+            ),
+            (
+                "foo".to_string(),
+                Ok(Sizes {
+                    git_dir: 20 * 1024,
+                    work_tree: 512,
+                    ..Sizes::default()
+                }),
+            ),
+            (
+                "broken".to_string(),
+                Err("git rev-parse --absolute-git-dir failed".to_string()),
+            ),
+        ];
+
+        assert_eq!(
+            "assets  4.0 MiB (git 3.0 MiB,  work tree 1.0 MiB) LFS\n\
+             \x20    2.0 MiB video.mp4 0b7e4c5\n\
+             foo    20.5 KiB (git 20.0 KiB, work tree 512 B)\n\
+             broken git rev-parse --absolute-git-dir failed\n\
+             \n\
+             4.0 MiB in total\n",
+            render_sizes(&repositories)
+        );
+    }
+
+    #[test]
+    fn test_render_audit() {
+        colored::control::set_override(false);
+
+        let entries = vec![
+            (
+                "foo".to_string(),
+                Ok(Audit {
+                    modified: 2,
+                    diffstat: Some("2 files changed, 3 insertions(+)".to_string()),
+                    stashes: 1,
+                    ahead: vec![AheadBranch {
+                        branch: "main".to_string(),
+                        commits: 3,
+                    }],
+                    ..Audit::default()
+                }),
+            ),
+            ("bar".to_string(), Ok(Audit::default())),
+        ];
+
+        assert_eq!(
+            "foo\n  \
+             uncommitted changes: 2 files changed, 3 insertions(+)\n  \
+             stashes: 1\n  \
+             ahead: main by 3\n\
+             \n\
+             1 need attention, 1 clean\n",
+            render_audit(&entries, false)
+        );
+        assert!(render_audit(&entries, true).contains("clean\n  bar\n"));
+    }
+
+    #[test]
+    fn test_render_timeline() {
+        colored::control::set_override(false);
+
+        let commit = |date: &str, subject: &str| Commit {
+            hash: "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a".to_string(),
+            timestamp: 0,
+            date: date.to_string(),
+            author: "Jane Doe".to_string(),
+            subject: subject.to_string(),
+        };
+        let commits = vec![
+            (
+                "foo".to_string(),
+                commit("2024-05-30 14:02", "Fix the parser"),
+            ),
+            (
+                "bar/baz".to_string(),
+                commit("2024-05-30 09:45", "Add a test"),
+            ),
+            (
+                "foo".to_string(),
+                commit("2024-05-29 18:30", "Bump the version"),
+            ),
+        ];
+
+        assert_eq!(
+            "2024-05-30 14:02 foo     3f2c1a9 Fix the parser\n\
+             2024-05-30 09:45 bar/baz 3f2c1a9 Add a test\n\
+             2024-05-29 18:30 foo     3f2c1a9 Bump the version\n",
+            render_timeline(&commits, TimelineGroup::None)
+        );
+        assert_eq!(
+            "foo\n  \
+             2024-05-30 14:02 3f2c1a9 Fix the parser\n  \
+             2024-05-29 18:30 3f2c1a9 Bump the version\n\
+             bar/baz\n  \
+             2024-05-30 09:45 3f2c1a9 Add a test\n",
+            render_timeline(&commits, TimelineGroup::Repository)
+        );
+        assert_eq!(
+            "2024-05-30\n  \
+             14:02 foo     3f2c1a9 Fix the parser\n  \
+             09:45 bar/baz 3f2c1a9 Add a test\n\
+             2024-05-29\n  \
+             18:30 foo     3f2c1a9 Bump the version\n",
+            render_timeline(&commits, TimelineGroup::Day)
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!("5m", format_age(300));
+        assert_eq!("3d", format_age(3 * 24 * 3600 + 100));
+        assert_eq!("2mo", format_age(65 * 24 * 3600));
+        assert_eq!("0m", format_age(-10));
+    }
+
+    #[test]
+    fn test_render_branches() {
+        colored::control::set_override(false);
+
+        let branch = |name: &str, upstream: Option<&str>, ahead, timestamp| Branch {
+            name: name.to_string(),
+            upstream: upstream.map(str::to_string),
+            ahead: upstream.map(|_| ahead),
+            behind: upstream.map(|_| 0),
+            timestamp,
+            current: name == "main",
+            merged: name == "done",
+            gone: false,
+        };
+        let repositories = vec![
+            (
+                "foo".to_string(),
+                Ok(Branches {
+                    default: Some("origin/main".to_string()),
+                    branches: vec![
+                        branch("done", None, 0, 0),
+                        branch("main", Some("origin/main"), 0, 7200),
+                        branch("wip", Some("origin/wip"), 2, 3600),
+                    ],
+                }),
+            ),
+            (
+                "empty".to_string(),
+                Ok(Branches {
+                    default: None,
+                    branches: Vec::new(),
+                }),
+            ),
+        ];
+
+        assert_eq!(
+            "foo (default origin/main)\n  \
+             \x20 done no upstream              3h merged\n  \
+             * main origin/main up to date   1h\n  \
+             \x20 wip  origin/wip  ahead 2      2h\n",
+            render_branches(&repositories, 7200 + 3600)
+        );
+    }
+
+    #[test]
+    fn test_render_pruned() {
+        colored::control::set_override(false);
+
+        let pruned = |name: &str, reason, error: Option<&str>| Pruned {
+            name: name.to_string(),
+            reason,
+            error: error.map(str::to_string),
+        };
+        let repositories = vec![
+            (
+                "api".to_string(),
+                Ok(vec![
+                    pruned("done", PruneReason::Merged, None),
+                    pruned("old", PruneReason::Gone, None),
+                    pruned(
+                        "review",
+                        PruneReason::MergedUpstream,
+                        Some("checked out in a worktree"),
+                    ),
+                ]),
+            ),
+            ("docs".to_string(), Ok(Vec::new())),
+            (
+                "web".to_string(),
+                Ok(vec![pruned("fix", PruneReason::Merged, None)]),
+            ),
+        ];
+
+        assert_eq!(
+            "api\n  \
+             done   merged                   deleted\n  \
+             old    [gone]                   deleted\n  \
+             review merged into its upstream checked out in a worktree\n\
+             web\n  \
+             fix merged deleted\n\
+             \n\
+             3 branches deleted (api 2, web 1), 1 failed\n",
+            render_pruned(&repositories, false)
+        );
+        assert_eq!(
+            "web\n  \
+             fix merged would delete\n\
+             \n\
+             1 branch would be deleted (web 1)\n",
+            render_pruned(&repositories[1..], true)
+        );
+    }
+
+    #[test]
+    fn test_render_off_default() {
+        colored::control::set_override(false);
+
+        let off = |branch: Option<&str>, ahead, behind| OffDefault {
+            branch: branch.map(str::to_string),
+            commit: "3f2c1a9".to_string(),
+            default: "origin/main".to_string(),
+            ahead,
+            behind,
+        };
+        let repositories = vec![
+            ("web".to_string(), Ok(off(None, 0, 2))),
+            ("api".to_string(), Ok(off(Some("feature/login"), 3, 1))),
+            ("docs".to_string(), Ok(off(Some("wip"), 0, 0))),
+            (
+                "broken".to_string(),
+                Err("unable to find the default branch".to_string()),
+            ),
+        ];
+
+        assert_eq!(
+            "broken unable to find the default branch\n\
+             api    feature/login    ahead 3, behind 1 of origin/main\n\
+             docs   wip              even with origin/main\n\
+             web    detached 3f2c1a9 behind 2 of origin/main\n\
+             \n\
+             2 on another branch, 1 detached\n",
+            render_off_default(&repositories)
+        );
+    }
+
+    #[test]
+    fn test_render_outcomes() {
+        colored::control::set_override(false);
+
+        let mut entries = vec![
+            ("foo".to_string(), SyncOutcome::UpToDate),
+            ("bar".to_string(), SyncOutcome::FastForwarded { commits: 1 }),
+            ("baz".to_string(), SyncOutcome::Dirty),
+            ("qux".to_string(), SyncOutcome::Detached),
+            ("quux".to_string(), SyncOutcome::Dirty),
+        ];
+        sort_outcomes(&mut entries);
+
+        assert_eq!(
+            "baz  dirty\n\
+             quux dirty\n\
+             qux  detached\n\
+             bar  fast-forwarded by 1 commit\n\
+             foo  up to date\n\
+             \n\
+             1 fast-forwarded, 1 up to date, 3 need attention (1 detached, 2 dirty), 0 failed\n",
+            render_outcomes(&entries)
+        );
+    }
+}
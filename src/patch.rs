@@ -0,0 +1,168 @@
+//! Apply a patch to a repository only if it applies cleanly.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::Path;
+
+use crate::git::Git;
+use crate::status::RepoStatus;
+
+/// How a patch is applied.
+#[derive(Debug, Clone, Default)]
+pub struct PatchOptions {
+    /// Apply a mailbox of commits made by git format-patch with git am, keeping their messages
+    pub am: bool,
+    /// Create this branch and switch to it before applying the patch
+    pub branch: Option<String>,
+    /// Commit the changes of the patch with this message, they're left staged otherwise
+    pub message: Option<String>,
+}
+
+/// What applying a patch did, or why the repository was left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOutcome {
+    /// The patch was applied
+    Applied {
+        /// Whether the changes were committed
+        committed: bool,
+    },
+    /// The patch applies in reverse, its changes are already there
+    AlreadyApplied,
+    /// The patch doesn't apply
+    Conflict {
+        /// Why git refused the patch
+        error: String,
+    },
+    /// Tracked files have changes, they would get mixed with the ones of the patch
+    Dirty,
+    /// A git command failed
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl PatchOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            PatchOutcome::Applied { .. } => "applied",
+            PatchOutcome::AlreadyApplied => "already applied",
+            PatchOutcome::Conflict { .. } => "conflict",
+            PatchOutcome::Dirty => "dirty",
+            PatchOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for PatchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchOutcome::Applied { committed: true } => f.write_str("applied and committed"),
+            PatchOutcome::Applied { committed: false } => f.write_str("applied, changes staged"),
+            PatchOutcome::Conflict { error } => write!(f, "conflict, left untouched: {}", error),
+            PatchOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+/// Runs git with `args` in `path`, returns the first line of its stderr if it fails.
+fn run<I, S>(git: &Git, path: &Path, args: I) -> Result<(), String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = git
+        .command(path)
+        .args(args)
+        .output()
+        .map_err(|err| format!("unable to run {}: {}", git.program().display(), err))?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error = stderr
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("git failed");
+    Err(error.to_string())
+}
+
+/// Applies the patch at `patch`, which must be an absolute path, to the repository at `path`.
+///
+/// The patch is checked first: the repository is left untouched if it doesn't apply cleanly or
+/// if it's already applied.
+pub fn apply(git: &Git, path: &Path, patch: &Path, options: &PatchOptions) -> PatchOutcome {
+    let status = match RepoStatus::probe(git, path) {
+        Ok(status) => status,
+        Err(err) => {
+            return PatchOutcome::Failed {
+                error: err.to_string(),
+            }
+        }
+    };
+
+    let patch = patch.as_os_str();
+    if let Err(error) = run(
+        git,
+        path,
+        [OsStr::new("apply"), OsStr::new("--check"), patch],
+    ) {
+        let reverse = [
+            OsStr::new("apply"),
+            OsStr::new("--check"),
+            OsStr::new("--reverse"),
+            patch,
+        ];
+        return match run(git, path, reverse) {
+            Ok(()) => PatchOutcome::AlreadyApplied,
+            Err(_) => PatchOutcome::Conflict { error },
+        };
+    }
+    if status.modified > 0 {
+        return PatchOutcome::Dirty;
+    }
+
+    let failed = |error| PatchOutcome::Failed { error };
+    if let Some(branch) = &options.branch {
+        if let Err(error) = run(git, path, ["switch", "--quiet", "--create", branch]) {
+            return failed(error);
+        }
+    }
+
+    if options.am {
+        if let Err(error) = run(
+            git,
+            path,
+            [
+                OsStr::new("am"),
+                OsStr::new("--quiet"),
+                OsStr::new("--3way"),
+                patch,
+            ],
+        ) {
+            // Don't leave the repository in the middle of git am
+            let _ = run(git, path, ["am", "--abort"]);
+            return failed(error);
+        }
+        return PatchOutcome::Applied { committed: true };
+    }
+
+    if let Err(error) = run(
+        git,
+        path,
+        [OsStr::new("apply"), OsStr::new("--3way"), patch],
+    ) {
+        return failed(error);
+    }
+    match &options.message {
+        Some(message) => match run(git, path, ["commit", "--quiet", "--message", message]) {
+            Ok(()) => PatchOutcome::Applied { committed: true },
+            Err(error) => failed(error),
+        },
+        None => PatchOutcome::Applied { committed: false },
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::env;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::names;
+use crate::output::Stream;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hyperlinks {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for Hyperlinks {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Hyperlinks::Auto),
+            "always" => Ok(Hyperlinks::Always),
+            "never" => Ok(Hyperlinks::Never),
+            _ => Err(anyhow::anyhow!("unknown hyperlinks mode {}", s)),
+        }
+    }
+}
+
+impl Hyperlinks {
+    /// Decides if hyperlinks should be written to `stream`.
+    ///
+    /// In auto mode they're only enabled if the stream is a TTY and the terminal is known to support them.
+    pub fn enabled(&self, stream: Stream) -> bool {
+        match self {
+            Hyperlinks::Always => true,
+            Hyperlinks::Never => false,
+            Hyperlinks::Auto => {
+                let is_terminal = match stream {
+                    Stream::Stdout => std::io::stdout().is_terminal(),
+                    Stream::Stderr => std::io::stderr().is_terminal(),
+                };
+
+                is_terminal && terminal_supports_hyperlinks()
+            }
+        }
+    }
+}
+
+fn terminal_supports_hyperlinks() -> bool {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+
+    if matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty" | "Hyper"
+    ) {
+        return true;
+    }
+    if var("TERM").contains("kitty") || var("TERM").contains("alacritty") || var("TERM") == "foot" {
+        return true;
+    }
+    if var("VTE_VERSION").parse::<u32>().unwrap_or(0) >= 5000 {
+        return true;
+    }
+
+    [
+        "WT_SESSION",
+        "KONSOLE_VERSION",
+        "KITTY_WINDOW_ID",
+        "DOMTERM",
+    ]
+    .iter()
+    .any(|name| env::var_os(name).is_some())
+}
+
+/// Wraps `text` in an OSC 8 hyperlink to `url`.
+pub fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Returns the file:// URL of `path`.
+pub fn file_url(path: &Path) -> String {
+    let mut url = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    url
+}
+
+/// Controls how repository paths are displayed.
+pub struct PathDisplay {
+    /// Display the paths relative to this root
+    root: Option<PathBuf>,
+    /// Abbreviate this home directory with a ~
+    home: Option<PathBuf>,
+    /// The short names of the repositories, displayed instead of their path
+    names: HashMap<PathBuf, String>,
+}
+
+impl PathDisplay {
+    /// Creates a path display.
+    ///
+    /// Paths are only displayed relative if there's a single root, anything else would be ambiguous.
+    pub fn new(roots: &[PathBuf], relative: bool, tilde: bool) -> Self {
+        let root = match roots {
+            [root] if relative => Some(root.clone()),
+            _ => None,
+        };
+
+        let home = if tilde {
+            env::var_os("HOME")
+                .map(PathBuf::from)
+                .and_then(|home| home.canonicalize().ok())
+        } else {
+            None
+        };
+
+        Self {
+            root,
+            home,
+            names: HashMap::new(),
+        }
+    }
+
+    /// Displays `paths` by their shortest unique trailing path, like `foo` or `work/foo`.
+    pub fn short_names(mut self, paths: &[PathBuf]) -> Self {
+        self.names = paths
+            .iter()
+            .cloned()
+            .zip(names::short_names(paths))
+            .collect();
+        self
+    }
+
+    pub fn display(&self, path: &Path) -> String {
+        if let Some(name) = self.names.get(path) {
+            return name.clone();
+        }
+        if let Some(root) = &self.root {
+            if let Ok(relative) = path.strip_prefix(root) {
+                if relative.as_os_str().is_empty() {
+                    return ".".to_string();
+                }
+                return relative.to_string_lossy().to_string();
+            }
+        }
+
+        if let Some(home) = &self.home {
+            if let Ok(relative) = path.strip_prefix(home) {
+                return Path::new("~").join(relative).to_string_lossy().to_string();
+            }
+        }
+
+        path.to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let path = Path::new("/home/me/src/foo");
+
+        let display = PathDisplay {
+            root: None,
+            home: None,
+            names: HashMap::new(),
+        };
+        assert_eq!("/home/me/src/foo", display.display(path));
+
+        let display = PathDisplay {
+            root: Some(PathBuf::from("/home/me/src")),
+            home: None,
+            names: HashMap::new(),
+        };
+        assert_eq!("foo", display.display(path));
+        assert_eq!(".", display.display(Path::new("/home/me/src")));
+
+        let display = PathDisplay {
+            root: None,
+            home: Some(PathBuf::from("/home/me")),
+            names: HashMap::new(),
+        };
+        assert_eq!("~/src/foo", display.display(path));
+
+        let display = PathDisplay::new(&[], false, false).short_names(&[
+            PathBuf::from("/home/me/src/foo"),
+            PathBuf::from("/home/me/work/foo"),
+        ]);
+        assert_eq!("src/foo", display.display(path));
+        assert_eq!("/home/me/bar", display.display(Path::new("/home/me/bar")));
+    }
+
+    #[test]
+    fn test_file_url() {
+        assert_eq!(
+            "file:///home/me/my%20repo",
+            file_url(Path::new("/home/me/my repo"))
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::ffi::OsStr;
+use std::fmt::Write as FmtWrite;
+use std::time::Duration;
+
+/// Formats the --porcelain line of a repository.
+///
+/// The fields are tab-separated: status (ok, fail, skip or not-attempted), exit code,
+/// duration in milliseconds, path and the reason a repository was skipped or not attempted,
+/// empty otherwise.
+/// This format is stable, fields will only ever be appended.
+pub fn format_line(
+    status: &str,
+    exit_code: Option<i32>,
+    duration: Duration,
+    path: &OsStr,
+    reason: &str,
+) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}\n",
+        status,
+        exit_code
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        duration.as_millis(),
+        quote_path(path.as_encoded_bytes()),
+        reason.replace(|c: char| c.is_control(), " ")
+    )
+}
+
+/// Formats the --list line of a repository: its path, quoted like in the --porcelain lines.
+pub fn format_path(path: &OsStr) -> String {
+    format!("{}\n", quote_path(path.as_encoded_bytes()))
+}
+
+/// Quotes a path like git does with core.quotePath: if the path contains a double quote, a
+/// backslash, a control character or a non-ASCII byte it's enclosed in double quotes and these
+/// bytes are escaped.
+fn quote_path(path: &[u8]) -> String {
+    let needs_quoting = path
+        .iter()
+        .any(|&b| b == b'"' || b == b'\\' || !(0x20..0x7f).contains(&b));
+    if !needs_quoting {
+        return String::from_utf8_lossy(path).to_string();
+    }
+
+    let mut result = String::from("\"");
+    for &b in path {
+        match b {
+            b'"' => result.push_str("\\\""),
+            b'\\' => result.push_str("\\\\"),
+            b'\t' => result.push_str("\\t"),
+            b'\n' => result.push_str("\\n"),
+            b'\r' => result.push_str("\\r"),
+            0x07 => result.push_str("\\a"),
+            0x08 => result.push_str("\\b"),
+            0x0b => result.push_str("\\v"),
+            0x0c => result.push_str("\\f"),
+            0x20..=0x7e => result.push(b as char),
+            _ => write!(&mut result, "\\{:03o}", b).unwrap(),
+        }
+    }
+    result.push('"');
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_path() {
+        assert_eq!("/src/foo bar", quote_path(b"/src/foo bar"));
+        assert_eq!("\"/src/a\\tb\\\"c\\\\\"", quote_path(b"/src/a\tb\"c\\"));
+        assert_eq!(
+            "\"/src/\\303\\251t\\303\\251\"",
+            quote_path("/src/été".as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            "fail\t128\t1500\t/src/foo\t\n",
+            format_line(
+                "fail",
+                Some(128),
+                Duration::from_millis(1500),
+                OsStr::new("/src/foo"),
+                ""
+            )
+        );
+        assert_eq!(
+            "skip\t-\t0\t/src/foo\tmerging in progress, see git status\n",
+            format_line(
+                "skip",
+                None,
+                Duration::ZERO,
+                OsStr::new("/src/foo"),
+                "merging in progress, see\ngit status"
+            )
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Lower the CPU and IO priority of the git processes, so that heavy maintenance like `gc` runs
+//! in the background.
+//!
+//! The niceness is set on Unix and the IO class on Linux only, elsewhere they're not set at all:
+//! the commands run as usual.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::process::Command;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+/// Whether the niceness can be set on this platform.
+pub const NICE_SUPPORTED: bool = cfg!(unix);
+/// Whether the IO class can be set on this platform.
+pub const IO_CLASS_SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// An IO scheduling class, like the ones of ionice(1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    /// Ahead of everything else, with a level from 0 (highest) to 7
+    Realtime(u8),
+    /// The default class, with a level from 0 (highest) to 7
+    BestEffort(u8),
+    /// Only when no other process needs the disk
+    Idle,
+}
+
+impl FromStr for IoClass {
+    type Err = anyhow::Error;
+
+    /// Parses `idle`, `best-effort` or `realtime`, the last two optionally followed by `:LEVEL`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (class, level) = match s.split_once(':') {
+            Some((class, level)) => {
+                let level = level
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|level| *level <= 7)
+                    .ok_or_else(|| anyhow!("invalid level {}, it must be from 0 to 7", level))?;
+                (class, Some(level))
+            }
+            None => (s, None),
+        };
+
+        match (class, level) {
+            ("idle", None) => Ok(IoClass::Idle),
+            ("idle", Some(_)) => Err(anyhow!("the idle class has no level")),
+            ("best-effort", level) => Ok(IoClass::BestEffort(level.unwrap_or(4))),
+            ("realtime", level) => Ok(IoClass::Realtime(level.unwrap_or(4))),
+            _ => Err(anyhow!(
+                "unknown IO class {}, expected idle, best-effort or realtime",
+                class
+            )),
+        }
+    }
+}
+
+impl fmt::Display for IoClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoClass::Realtime(level) => write!(f, "realtime:{}", level),
+            IoClass::BestEffort(level) => write!(f, "best-effort:{}", level),
+            IoClass::Idle => f.write_str("idle"),
+        }
+    }
+}
+
+/// The priority of the git processes, nothing is changed by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Priority {
+    /// The niceness, from -20 (highest priority) to 19
+    pub nice: Option<i32>,
+    /// The IO class
+    pub io_class: Option<IoClass>,
+}
+
+/// What [`Priority::prepare`] set in a process.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Applied {
+    /// The priorities set, like `nice 10`
+    pub set: Vec<String>,
+    /// Why the others couldn't be set
+    pub errors: Vec<String>,
+}
+
+/// A priority prepared in a command, the process reports what it couldn't set through a pipe
+/// closed when it runs git.
+pub struct Prepared {
+    priority: Priority,
+    errors: Option<(io::PipeReader, io::PipeWriter)>,
+}
+
+impl Prepared {
+    /// Returns what was set in the process, once it's spawned.
+    pub fn applied(self) -> Applied {
+        let mut applied = Applied::default();
+        let Some((mut reader, writer)) = self.errors else {
+            return applied;
+        };
+
+        // The copy of the process is closed when it runs git
+        drop(writer);
+        let mut reports = Vec::new();
+        if let Err(err) = reader.read_to_end(&mut reports) {
+            applied
+                .errors
+                .push(format!("unable to read what the priority set: {}", err));
+            return applied;
+        }
+        let error = |kind: u8| {
+            reports
+                .chunks_exact(5)
+                .find(|report| report[0] == kind)
+                .map(|report| {
+                    let errno = i32::from_ne_bytes([report[1], report[2], report[3], report[4]]);
+                    io::Error::from_raw_os_error(errno)
+                })
+        };
+
+        if let Some(nice) = self.priority.nice.filter(|_| NICE_SUPPORTED) {
+            match error(NICE_REPORT) {
+                None => applied.set.push(format!("nice {}", nice)),
+                Some(err) => applied
+                    .errors
+                    .push(format!("unable to set the niceness to {}: {}", nice, err)),
+            }
+        }
+        if let Some(io_class) = self.priority.io_class.filter(|_| IO_CLASS_SUPPORTED) {
+            match error(IO_CLASS_REPORT) {
+                None => applied.set.push(format!("IO class {}", io_class)),
+                Some(err) => applied.errors.push(format!(
+                    "unable to set the IO class to {}: {}",
+                    io_class, err
+                )),
+            }
+        }
+
+        applied
+    }
+}
+
+/// The first byte of the report of a niceness that couldn't be set, the errno follows.
+const NICE_REPORT: u8 = b'n';
+/// The first byte of the report of an IO class that couldn't be set.
+const IO_CLASS_REPORT: u8 = b'i';
+
+impl Priority {
+    /// Returns true if the priority is left as is.
+    pub fn is_empty(&self) -> bool {
+        self.nice.is_none() && self.io_class.is_none()
+    }
+
+    /// Sets the priority in the process of `command` before it runs the program, as far as this
+    /// platform supports it: the processes the program starts have it from the beginning.
+    ///
+    /// A priority that can't be set, like a negative niceness without the privileges, is
+    /// reported in the [`Applied::errors`] of [`Prepared::applied`] and the process runs as is.
+    pub fn prepare(&self, command: &mut Command) -> io::Result<Prepared> {
+        let nice = self.nice.filter(|_| NICE_SUPPORTED);
+        let io_class = self.io_class.filter(|_| IO_CLASS_SUPPORTED);
+        if nice.is_none() && io_class.is_none() {
+            return Ok(Prepared {
+                priority: *self,
+                errors: None,
+            });
+        }
+
+        let (reader, writer) = io::pipe()?;
+        pre_exec(command, &writer, nice, io_class);
+
+        Ok(Prepared {
+            priority: *self,
+            errors: Some((reader, writer)),
+        })
+    }
+}
+
+#[cfg(unix)]
+fn pre_exec(
+    command: &mut Command,
+    writer: &io::PipeWriter,
+    nice: Option<i32>,
+    io_class: Option<IoClass>,
+) {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::process::CommandExt;
+
+    let fd = writer.as_raw_fd();
+    // Only the async-signal-safe system calls run between fork and exec: the errors are written
+    // as is
+    let report = move |kind: u8, err: io::Error| {
+        let errno = err.raw_os_error().unwrap_or(0).to_ne_bytes();
+        let report = [kind, errno[0], errno[1], errno[2], errno[3]];
+        // SAFETY: write only reads the buffer, the pipe stays open until exec
+        unsafe { libc::write(fd, report.as_ptr().cast(), report.len()) };
+    };
+
+    // SAFETY: the closure only makes system calls, it doesn't allocate nor lock
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(nice) = nice {
+                if let Err(err) = set_nice(nice) {
+                    report(NICE_REPORT, err);
+                }
+            }
+            if let Some(io_class) = io_class {
+                if let Err(err) = set_io_class(io_class) {
+                    report(IO_CLASS_REPORT, err);
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn pre_exec(
+    _command: &mut Command,
+    _writer: &io::PipeWriter,
+    _nice: Option<i32>,
+    _io_class: Option<IoClass>,
+) {
+}
+
+/// Sets the niceness of the calling process.
+#[cfg(unix)]
+fn set_nice(nice: i32) -> io::Result<()> {
+    // SAFETY: setpriority only reads its arguments
+    let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Sets the IO class of the calling process.
+#[cfg(target_os = "linux")]
+fn set_io_class(io_class: IoClass) -> io::Result<()> {
+    // From linux/ioprio.h, there's no wrapper in the C library
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: u32 = 13;
+    let (class, level) = match io_class {
+        IoClass::Realtime(level) => (1, level),
+        IoClass::BestEffort(level) => (2, level),
+        IoClass::Idle => (3, 0),
+    };
+    let value = (class << IOPRIO_CLASS_SHIFT) | u32::from(level);
+
+    // SAFETY: ioprio_set only reads its arguments
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            value as libc::c_int,
+        )
+    };
+    if ret == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_io_class(_io_class: IoClass) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_io_class() {
+        assert_eq!(IoClass::Idle, "idle".parse::<IoClass>().unwrap());
+        assert_eq!(
+            IoClass::BestEffort(4),
+            "best-effort".parse::<IoClass>().unwrap()
+        );
+        assert_eq!(
+            IoClass::Realtime(0),
+            "realtime:0".parse::<IoClass>().unwrap()
+        );
+        assert!("best-effort:8".parse::<IoClass>().is_err());
+        assert!("idle:3".parse::<IoClass>().is_err());
+        assert!("low".parse::<IoClass>().is_err());
+
+        assert_eq!("best-effort:7", IoClass::BestEffort(7).to_string());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prepare() {
+        // The niceness is the one of the processes git starts too
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", "nice"]);
+        let priority = Priority {
+            nice: Some(19),
+            io_class: Some(IoClass::Idle),
+        };
+        let prepared = priority.prepare(&mut command).unwrap();
+        let output = command.output().unwrap();
+        let applied = prepared.applied();
+
+        assert!(applied.errors.is_empty(), "{:?}", applied);
+        assert_eq!("19", String::from_utf8_lossy(&output.stdout).trim());
+        assert_eq!("nice 19", applied.set[0]);
+        assert_eq!(IO_CLASS_SUPPORTED, applied.set.len() == 2);
+
+        // Without the privileges the niceness can't be lowered
+        let mut command = std::process::Command::new("true");
+        let priority = Priority {
+            nice: Some(-20),
+            io_class: None,
+        };
+        let prepared = priority.prepare(&mut command).unwrap();
+        command.status().unwrap();
+        let applied = prepared.applied();
+        if unsafe { libc::geteuid() } != 0 {
+            assert!(applied.set.is_empty(), "{:?}", applied);
+            assert!(
+                applied.errors[0].starts_with("unable to set the niceness to -20: "),
+                "{:?}",
+                applied
+            );
+        }
+    }
+}
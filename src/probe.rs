@@ -0,0 +1,167 @@
+//! Query the state of a repository.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::git::Git;
+
+/// How the state of a repository is probed.
+///
+/// The command given by the user always runs with the git CLI, the backend only answers
+/// queries like the current branch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Backend {
+    /// Spawn a git process per query
+    #[default]
+    Cli,
+    /// Answer the queries in-process with libgit2, only available with the `libgit2` feature
+    Libgit2,
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cli" => Ok(Backend::Cli),
+            "libgit2" if cfg!(feature = "libgit2") => Ok(Backend::Libgit2),
+            "libgit2" => Err(anyhow::anyhow!(
+                "gitjuggling was built without the libgit2 feature"
+            )),
+            _ => Err(anyhow::anyhow!("unknown backend {}", s)),
+        }
+    }
+}
+
+impl Backend {
+    /// Returns the current branch of the repository at `path`, or a detached HEAD marker with
+    /// the abbreviated commit.
+    pub fn current_branch(&self, git: &Git, path: &Path) -> Option<String> {
+        match self {
+            Backend::Cli => cli::current_branch(git, path),
+            #[cfg(feature = "libgit2")]
+            Backend::Libgit2 => libgit2::current_branch(path).ok().flatten(),
+            #[cfg(not(feature = "libgit2"))]
+            Backend::Libgit2 => None,
+        }
+    }
+
+    /// Returns true if the repository at `path` has modified, staged or untracked files.
+    pub fn is_dirty(&self, git: &Git, path: &Path) -> Option<bool> {
+        match self {
+            Backend::Cli => cli::is_dirty(git, path),
+            #[cfg(feature = "libgit2")]
+            Backend::Libgit2 => libgit2::is_dirty(path).ok(),
+            #[cfg(not(feature = "libgit2"))]
+            Backend::Libgit2 => None,
+        }
+    }
+
+    /// Returns how many commits the current branch is ahead and behind its upstream.
+    ///
+    /// This is `None` if there's no upstream.
+    pub fn ahead_behind(&self, git: &Git, path: &Path) -> Option<(usize, usize)> {
+        match self {
+            Backend::Cli => cli::ahead_behind(git, path),
+            #[cfg(feature = "libgit2")]
+            Backend::Libgit2 => libgit2::ahead_behind(path).ok().flatten(),
+            #[cfg(not(feature = "libgit2"))]
+            Backend::Libgit2 => None,
+        }
+    }
+}
+
+mod cli {
+    use super::*;
+
+    pub(super) fn current_branch(git: &Git, path: &Path) -> Option<String> {
+        if let Some(branch) = git.stdout(path, &["symbolic-ref", "--short", "-q", "HEAD"]) {
+            return Some(branch);
+        }
+
+        git.stdout(path, &["rev-parse", "--short", "HEAD"])
+            .map(|commit| format!("detached {}", commit))
+    }
+
+    pub(super) fn is_dirty(git: &Git, path: &Path) -> Option<bool> {
+        git.stdout(path, &["status", "--porcelain"])
+            .map(|status| !status.is_empty())
+    }
+
+    pub(super) fn ahead_behind(git: &Git, path: &Path) -> Option<(usize, usize)> {
+        let counts = git.stdout(
+            path,
+            &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+        )?;
+
+        let mut counts = counts.split_whitespace().map(|count| count.parse().ok());
+        match (counts.next(), counts.next()) {
+            (Some(Some(ahead)), Some(Some(behind))) => Some((ahead, behind)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "libgit2")]
+mod libgit2 {
+    use super::*;
+
+    use git2::{Repository, StatusOptions};
+
+    pub(super) fn current_branch(path: &Path) -> Result<Option<String>, git2::Error> {
+        let repository = Repository::open(path)?;
+
+        // Read HEAD itself rather than resolving it, it may point to an unborn branch
+        let head = repository.find_reference("HEAD")?;
+        if let Some(target) = head.symbolic_target() {
+            return Ok(Some(
+                target
+                    .strip_prefix("refs/heads/")
+                    .unwrap_or(target)
+                    .to_string(),
+            ));
+        }
+
+        let commit = head.peel_to_commit()?;
+        let short_id = commit.as_object().short_id()?;
+
+        Ok(short_id
+            .as_str()
+            .map(|short_id| format!("detached {}", short_id)))
+    }
+
+    pub(super) fn is_dirty(path: &Path) -> Result<bool, git2::Error> {
+        let repository = Repository::open(path)?;
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).include_ignored(false);
+        let statuses = repository.statuses(Some(&mut options))?;
+
+        Ok(!statuses.is_empty())
+    }
+
+    pub(super) fn ahead_behind(path: &Path) -> Result<Option<(usize, usize)>, git2::Error> {
+        let repository = Repository::open(path)?;
+
+        let head = repository.head()?;
+        let Some(name) = head.name() else {
+            return Ok(None);
+        };
+        let Ok(upstream_name) = repository.branch_upstream_name(name) else {
+            return Ok(None);
+        };
+        let Some(upstream_name) = upstream_name.as_str() else {
+            return Ok(None);
+        };
+
+        let local = head.peel_to_commit()?.id();
+        let upstream = repository
+            .find_reference(upstream_name)?
+            .peel_to_commit()?
+            .id();
+
+        repository.graph_ahead_behind(local, upstream).map(Some)
+    }
+}
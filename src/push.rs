@@ -0,0 +1,299 @@
+//! Push a repository only when it's safe to.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::git::Git;
+use crate::remotes;
+use crate::status::RepoStatus;
+
+/// What is pushed and which guards can be lifted.
+#[derive(Debug, Clone, Default)]
+pub struct PushOptions {
+    /// The remote to push to, the one of the upstream of the branch or origin by default
+    pub remote: Option<String>,
+    /// The refspecs to push, HEAD to the upstream of the current branch if there are none
+    pub refspecs: Vec<String>,
+    /// Push even if a refspec configured with `remote.<name>.push` forces the push
+    pub allow_force: bool,
+    /// Push the branches without an upstream and set it
+    pub set_upstream: bool,
+    /// Only tell what would be pushed, with `git push --dry-run`
+    pub dry_run: bool,
+}
+
+/// The push to run in a repository that passed the guards.
+#[derive(Debug, Clone)]
+pub struct PushPlan {
+    /// The remote pushed to
+    pub remote: String,
+    /// The URL pushed to, its host limits how many pushes run at the same time
+    pub url: String,
+    args: Vec<String>,
+    dry_run: bool,
+}
+
+/// What pushing a repository did, or why it was left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// The refs were pushed
+    Pushed {
+        /// The refs updated on the remote, with how they changed
+        refs: Vec<String>,
+    },
+    /// The refs would be pushed without --dry-run
+    WouldPush {
+        /// The refs that would be updated on the remote, with how they would change
+        refs: Vec<String>,
+    },
+    /// The remote already has everything
+    UpToDate,
+    /// The branch has no upstream and setting it wasn't asked
+    NoUpstream,
+    /// The upstream has commits the branch doesn't, the push would be rejected
+    Behind {
+        /// How many commits the branch is missing
+        commits: usize,
+    },
+    /// HEAD doesn't point to a branch and no refspec was given
+    Detached,
+    /// The current branch has no commits yet and no refspec was given
+    NoCommits,
+    /// A refspec configured for the remote forces the push and it wasn't allowed
+    Forced {
+        /// The forcing refspec
+        refspec: String,
+    },
+    /// The remote refused some refs
+    Rejected {
+        /// The refused refs, with the reason
+        refs: Vec<String>,
+    },
+    /// A git command failed
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl PushOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            PushOutcome::Pushed { .. } => "pushed",
+            PushOutcome::WouldPush { .. } => "would push",
+            PushOutcome::UpToDate => "up to date",
+            PushOutcome::NoUpstream => "no upstream",
+            PushOutcome::Behind { .. } => "behind",
+            PushOutcome::Detached => "detached",
+            PushOutcome::NoCommits => "no commits",
+            PushOutcome::Forced { .. } => "forced",
+            PushOutcome::Rejected { .. } => "rejected",
+            PushOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for PushOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushOutcome::Pushed { refs } => write!(f, "pushed {}", refs.join(", ")),
+            PushOutcome::WouldPush { refs } => write!(f, "would push {}", refs.join(", ")),
+            PushOutcome::NoUpstream => {
+                f.write_str("no upstream, pass --set-upstream to push and set it")
+            }
+            PushOutcome::Behind { commits } => write!(
+                f,
+                "behind by {} {}, the push would be rejected",
+                commits,
+                if *commits == 1 { "commit" } else { "commits" }
+            ),
+            PushOutcome::Forced { refspec } => write!(
+                f,
+                "{} forces the push, pass --allow-force to push anyway",
+                refspec
+            ),
+            PushOutcome::Rejected { refs } => write!(f, "rejected {}", refs.join(", ")),
+            PushOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+/// Returns true if `refspec` forces the push, like `+refs/heads/*:refs/heads/*`.
+pub fn is_forced(refspec: &str) -> bool {
+    refspec.starts_with('+')
+}
+
+/// Checks the guards of the repository at `path` and decides what to push.
+///
+/// Returns the outcome instead if the repository is left untouched.
+pub fn plan(git: &Git, path: &Path, options: &PushOptions) -> Result<PushPlan, PushOutcome> {
+    let failed = |error: String| PushOutcome::Failed { error };
+
+    let status = RepoStatus::probe(git, path).map_err(|err| failed(err.to_string()))?;
+    let branch = (!status.is_detached()).then_some(status.branch.as_str());
+    if branch.is_none() && options.refspecs.is_empty() {
+        return Err(PushOutcome::Detached);
+    }
+    if status.unborn && options.refspecs.is_empty() {
+        return Err(PushOutcome::NoCommits);
+    }
+
+    let remote = options
+        .remote
+        .clone()
+        .or_else(|| git.config(path, &format!("branch.{}.remote", branch?)))
+        .unwrap_or_else(|| "origin".to_string());
+    let url = git
+        .config(path, &format!("remote.{}.pushurl", remote))
+        .or_else(|| git.config(path, &format!("remote.{}.url", remote)))
+        .ok_or_else(|| failed(format!("no remote {}", remote)))?;
+
+    let mut args = vec!["push".to_string(), "--porcelain".to_string()];
+    if options.dry_run {
+        args.push("--dry-run".to_string());
+    }
+
+    if options.refspecs.is_empty() {
+        // The refspecs configured for the remote tell how it's meant to be pushed, a forcing one
+        // isn't overridden without asking
+        if !options.allow_force {
+            let configured = git
+                .stdout(
+                    path,
+                    &["config", "--get-all", &format!("remote.{}.push", remote)],
+                )
+                .unwrap_or_default();
+            if let Some(refspec) = configured.lines().find(|refspec| is_forced(refspec)) {
+                return Err(PushOutcome::Forced {
+                    refspec: refspec.to_string(),
+                });
+            }
+        }
+
+        // The branch is pushed to its upstream explicitly, whatever push.default says
+        let branch = branch.unwrap_or_default();
+        match (status.ahead, status.behind) {
+            (Some(_), Some(behind)) if behind > 0 => {
+                return Err(PushOutcome::Behind { commits: behind })
+            }
+            (Some(0), Some(_)) => return Err(PushOutcome::UpToDate),
+            (Some(_), Some(_)) => {
+                let merge = git
+                    .config(path, &format!("branch.{}.merge", branch))
+                    .ok_or_else(|| failed(format!("no upstream branch for {}", branch)))?;
+                args.extend([remote.clone(), format!("HEAD:{}", merge)]);
+            }
+            _ if options.set_upstream => args.extend([
+                "--set-upstream".to_string(),
+                remote.clone(),
+                format!("HEAD:refs/heads/{}", branch),
+            ]),
+            _ => return Err(PushOutcome::NoUpstream),
+        }
+    } else {
+        args.push(remote.clone());
+        args.extend(options.refspecs.iter().cloned());
+    }
+
+    Ok(PushPlan {
+        remote,
+        url,
+        args,
+        dry_run: options.dry_run,
+    })
+}
+
+/// Runs the push planned for the repository at `path`, without prompting for credentials.
+pub fn run(git: &Git, path: &Path, plan: &PushPlan) -> PushOutcome {
+    let output = match git
+        .command(path)
+        .args(&plan.args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            return PushOutcome::Failed {
+                error: format!("unable to run {}: {}", git.program().display(), err),
+            }
+        }
+    };
+
+    let (updated, rejected) = parse_porcelain(&String::from_utf8_lossy(&output.stdout));
+    if !rejected.is_empty() {
+        return PushOutcome::Rejected { refs: rejected };
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error = remotes::error_lines(&stderr)
+            .next()
+            .unwrap_or("git push failed");
+        return PushOutcome::Failed {
+            error: error.to_string(),
+        };
+    }
+
+    match (updated.is_empty(), plan.dry_run) {
+        (true, _) => PushOutcome::UpToDate,
+        (false, true) => PushOutcome::WouldPush { refs: updated },
+        (false, false) => PushOutcome::Pushed { refs: updated },
+    }
+}
+
+/// Parses the output of `git push --porcelain` into the refs updated and the ones rejected.
+fn parse_porcelain(output: &str) -> (Vec<String>, Vec<String>) {
+    let mut updated = Vec::new();
+    let mut rejected = Vec::new();
+
+    for line in output.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(flag), Some(refs), Some(summary)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let to = refs.rsplit(':').next().unwrap_or(refs);
+        let to = to
+            .strip_prefix("refs/heads/")
+            .or_else(|| to.strip_prefix("refs/tags/"))
+            .unwrap_or(to);
+
+        match flag {
+            "=" => {}
+            "!" => rejected.push(format!("{} {}", to, summary)),
+            "*" => updated.push(format!("{} (new)", to)),
+            "-" => updated.push(format!("{} (deleted)", to)),
+            _ => updated.push(format!("{} {}", to, summary)),
+        }
+    }
+
+    (updated, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = "To git@github.com:foo/bar.git\n\
+                      \x20\trefs/heads/main:refs/heads/main\t3f2c1a9..8b1d2e0\n\
+                      *\trefs/heads/dev:refs/heads/dev\t[new branch]\n\
+                      =\trefs/tags/v1:refs/tags/v1\t[up to date]\n\
+                      +\trefs/heads/wip:refs/heads/wip\t1b2a3f4...5d6c7b8 (forced update)\n\
+                      !\trefs/heads/old:refs/heads/old\t[rejected] (non-fast-forward)\n\
+                      Done\n";
+
+        let (updated, rejected) = parse_porcelain(output);
+        assert_eq!(
+            vec![
+                "main 3f2c1a9..8b1d2e0",
+                "dev (new)",
+                "wip 1b2a3f4...5d6c7b8 (forced update)"
+            ],
+            updated
+        );
+        assert_eq!(vec!["old [rejected] (non-fast-forward)"], rejected);
+    }
+}
@@ -0,0 +1,502 @@
+//! Rewrite the URLs of the remotes of a repository, and check that they're reachable.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use regex::Regex;
+
+use crate::git::Git;
+
+/// How the URLs are rewritten.
+#[derive(Debug, Clone)]
+pub enum Rewrite {
+    /// Replace the prefix `from` with `to`
+    Prefix {
+        /// The prefix to replace
+        from: String,
+        /// What replaces the prefix
+        to: String,
+    },
+    /// Replace the first match of `from` with `to`, where `$1` or `${name}` are the groups
+    /// captured by `from`
+    Regex {
+        /// The pattern to replace
+        from: Regex,
+        /// What replaces the match
+        to: String,
+    },
+}
+
+impl Rewrite {
+    /// Returns the rewritten URL, `None` if the URL doesn't match or is unchanged.
+    pub fn apply(&self, url: &str) -> Option<String> {
+        let new = match self {
+            Rewrite::Prefix { from, to } => format!("{}{}", to, url.strip_prefix(from.as_str())?),
+            Rewrite::Regex { from, to } => {
+                if !from.is_match(url) {
+                    return None;
+                }
+                from.replace(url, to.as_str()).into_owned()
+            }
+        };
+
+        Some(new).filter(|new| new != url)
+    }
+}
+
+/// A change of the URL of a remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlChange {
+    /// The name of the remote
+    pub remote: String,
+    /// Whether this is the push URL, set apart from the fetch URL with `remote.<name>.pushurl`
+    pub push: bool,
+    /// The current URL
+    pub old: String,
+    /// The rewritten URL
+    pub new: String,
+}
+
+/// The URL changes of a repository, and what couldn't be rewritten.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewritePlan {
+    /// The URLs to change
+    pub changes: Vec<UrlChange>,
+    /// The URLs left unchanged that should likely change too
+    pub warnings: Vec<String>,
+}
+
+/// Plans the rewrite of the URLs of the remote `remote` of the repository at `path`, or of all
+/// its remotes.
+///
+/// A push URL set apart from the fetch URL is rewritten too. If only one of them matches, the
+/// other one is left unchanged with a warning.
+pub fn plan(
+    git: &Git,
+    path: &Path,
+    rewrite: &Rewrite,
+    remote: Option<&str>,
+) -> anyhow::Result<RewritePlan> {
+    let remotes: Vec<String> = match remote {
+        Some(remote) => vec![remote.to_string()],
+        None => git
+            .stdout(path, &["remote"])
+            .ok_or_else(|| anyhow!("git remote failed"))?
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    };
+
+    let mut plan = RewritePlan::default();
+    for remote in remotes {
+        let Some(url) = git.config(path, &format!("remote.{}.url", remote)) else {
+            continue;
+        };
+        let push_url = git.config(path, &format!("remote.{}.pushurl", remote));
+
+        let new_url = rewrite.apply(&url);
+        let new_push_url = push_url.as_deref().and_then(|url| rewrite.apply(url));
+
+        match (&push_url, &new_url, &new_push_url) {
+            (Some(push_url), Some(_), None) => plan.warnings.push(format!(
+                "the push URL {} of {} doesn't match, it's left unchanged",
+                push_url, remote
+            )),
+            (Some(_), None, Some(_)) => plan.warnings.push(format!(
+                "the URL {} of {} doesn't match, it's left unchanged",
+                url, remote
+            )),
+            _ => {}
+        }
+
+        if let Some(new) = new_url {
+            plan.changes.push(UrlChange {
+                remote: remote.clone(),
+                push: false,
+                old: url,
+                new,
+            });
+        }
+        if let (Some(old), Some(new)) = (push_url, new_push_url) {
+            plan.changes.push(UrlChange {
+                remote,
+                push: true,
+                old,
+                new,
+            });
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Changes the URL of a remote of the repository at `path`.
+pub fn apply(git: &Git, path: &Path, change: &UrlChange) -> anyhow::Result<()> {
+    let mut command = git.command(path);
+    command.args(["remote", "set-url"]);
+    if change.push {
+        command.arg("--push");
+    }
+    command.args([&change.remote, &change.new, &change.old]);
+
+    let output = command
+        .output()
+        .map_err(|err| anyhow!("unable to run {}: {}", git.program().display(), err))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git remote set-url {} failed: {}",
+            change.remote,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Lists the remotes of the repository at `path` with their fetch URL.
+pub fn list(git: &Git, path: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let names = git
+        .stdout(path, &["remote"])
+        .ok_or_else(|| anyhow!("git remote failed"))?;
+
+    Ok(names
+        .lines()
+        .filter_map(|name| {
+            let url = git.config(path, &format!("remote.{}.url", name))?;
+            Some((name.to_string(), url))
+        })
+        .collect())
+}
+
+/// Whether a remote answered `git ls-remote`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteHealth {
+    /// The remote has a HEAD
+    Reachable,
+    /// The remote answered but has no HEAD, it's likely empty
+    NoHead,
+    /// The remote answered from another URL
+    Redirected {
+        /// The URL the remote redirects to
+        to: String,
+    },
+    /// The remote refused the credentials, or asked for some
+    AuthFailed {
+        /// The last line of the error of git
+        error: String,
+    },
+    /// The remote doesn't exist anymore or its host can't be reached
+    Unreachable {
+        /// The last line of the error of git
+        error: String,
+    },
+    /// The remote didn't answer in time
+    TimedOut {
+        /// How long it was given
+        timeout: Duration,
+    },
+}
+
+impl RemoteHealth {
+    /// Returns the name of the health, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            RemoteHealth::Reachable => "reachable",
+            RemoteHealth::NoHead => "no HEAD",
+            RemoteHealth::Redirected { .. } => "redirected",
+            RemoteHealth::AuthFailed { .. } => "authentication failed",
+            RemoteHealth::Unreachable { .. } => "unreachable",
+            RemoteHealth::TimedOut { .. } => "timed out",
+        }
+    }
+
+    /// Returns true if the remote can't be fetched from.
+    pub fn is_unreachable(&self) -> bool {
+        matches!(
+            self,
+            RemoteHealth::AuthFailed { .. }
+                | RemoteHealth::Unreachable { .. }
+                | RemoteHealth::TimedOut { .. }
+        )
+    }
+}
+
+impl fmt::Display for RemoteHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteHealth::Redirected { to } => write!(f, "redirected to {}", to),
+            RemoteHealth::AuthFailed { error } | RemoteHealth::Unreachable { error } => {
+                write!(f, "{}: {}", self.label(), error)
+            }
+            RemoteHealth::TimedOut { timeout } => write!(
+                f,
+                "timed out after {}",
+                humantime::format_duration(*timeout)
+            ),
+            health => f.write_str(health.label()),
+        }
+    }
+}
+
+/// Checks that the remote `remote` of the repository at `path` answers `git ls-remote` within
+/// `timeout`. Prompting for credentials is disabled, it would never answer.
+pub fn check(git: &Git, path: &Path, remote: &str, timeout: Duration) -> RemoteHealth {
+    let child = git
+        .command(path)
+        .args(["ls-remote", "--exit-code", remote, "HEAD"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            return RemoteHealth::Unreachable {
+                error: format!("unable to run {}: {}", git.program().display(), err),
+            }
+        }
+    };
+
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return RemoteHealth::TimedOut { timeout };
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+            Err(err) => {
+                return RemoteHealth::Unreachable {
+                    error: err.to_string(),
+                }
+            }
+        }
+    };
+
+    let mut stderr = String::new();
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr);
+    }
+
+    classify(status.code(), &stderr)
+}
+
+/// The errors meaning the remote refused the credentials or wanted some.
+const AUTH_ERRORS: &[&str] = &[
+    "Permission denied",
+    "Authentication failed",
+    "could not read Username",
+    "could not read Password",
+    "terminal prompts disabled",
+    "The requested URL returned error: 401",
+    "The requested URL returned error: 403",
+];
+
+/// The lines git adds to every error of an SSH remote, they don't tell what failed.
+const BOILERPLATE: &[&str] = &[
+    "warning:",
+    "fatal: Could not read from remote repository",
+    "Please make sure you have the correct access rights",
+    "and the repository exists",
+];
+
+/// Returns the lines of the stderr of a git command talking to a remote that tell what failed.
+pub(crate) fn error_lines(stderr: &str) -> impl Iterator<Item = &str> + Clone {
+    stderr.lines().map(str::trim).filter(|line| {
+        !line.is_empty() && !BOILERPLATE.iter().any(|prefix| line.starts_with(prefix))
+    })
+}
+
+/// Classifies the outcome of `git ls-remote --exit-code` from its exit code and its stderr.
+fn classify(exit_code: Option<i32>, stderr: &str) -> RemoteHealth {
+    let redirect = stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("warning: redirecting to "));
+
+    match exit_code {
+        Some(0) => match redirect {
+            Some(to) => RemoteHealth::Redirected { to: to.to_string() },
+            None => RemoteHealth::Reachable,
+        },
+        // --exit-code exits with 2 when the remote has no matching ref
+        Some(2) => RemoteHealth::NoHead,
+        _ => {
+            let mut lines = error_lines(stderr);
+
+            match lines
+                .clone()
+                .find(|line| AUTH_ERRORS.iter().any(|pattern| line.contains(pattern)))
+            {
+                Some(line) => RemoteHealth::AuthFailed {
+                    error: line.to_string(),
+                },
+                None => RemoteHealth::Unreachable {
+                    error: lines.next().unwrap_or("git ls-remote failed").to_string(),
+                },
+            }
+        }
+    }
+}
+
+/// Returns the host of a remote URL, `None` for local paths.
+pub fn host(url: &str) -> Option<&str> {
+    if let Some((scheme, rest)) = url.split_once("://") {
+        if scheme == "file" {
+            return None;
+        }
+        let authority = rest.split('/').next()?;
+        let host = authority.rsplit('@').next()?;
+        // An IPv6 address is in brackets, its colons aren't the one of the port
+        if let Some(address) = host.strip_prefix('[') {
+            return address.split(']').next();
+        }
+        return Some(host.split(':').next().unwrap_or(host));
+    }
+
+    // The scp-like syntax, user@host:path, a colon after a slash is a local path
+    let (authority, _) = url.split_once(':')?;
+    if authority.contains('/') {
+        return None;
+    }
+    Some(authority.rsplit('@').next().unwrap_or(authority))
+}
+
+/// Limits how many commands run at the same time against the same host.
+pub struct HostLimiter {
+    limit: usize,
+    running: Mutex<HashMap<String, usize>>,
+    released: Condvar,
+}
+
+impl HostLimiter {
+    /// Creates a limiter allowing `limit` commands per host at the same time, 0 is no limit.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            running: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Calls `f` once fewer than the limit of commands run against `host`, `None` runs it
+    /// right away.
+    pub fn run<T>(&self, host: Option<&str>, f: impl FnOnce() -> T) -> T {
+        let Some(host) = host.filter(|_| self.limit > 0) else {
+            return f();
+        };
+
+        {
+            let mut running = self.running.lock().unwrap();
+            while running.get(host).copied().unwrap_or(0) >= self.limit {
+                running = self.released.wait(running).unwrap();
+            }
+            *running.entry(host.to_string()).or_default() += 1;
+        }
+
+        let result = f();
+
+        *self.running.lock().unwrap().get_mut(host).unwrap() -= 1;
+        self.released.notify_all();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let prefix = Rewrite::Prefix {
+            from: "git@github.com:".to_string(),
+            to: "git@github.example.com:".to_string(),
+        };
+        assert_eq!(
+            Some("git@github.example.com:foo/bar.git".to_string()),
+            prefix.apply("git@github.com:foo/bar.git")
+        );
+        assert_eq!(None, prefix.apply("https://github.com/foo/bar.git"));
+
+        let regex = Rewrite::Regex {
+            from: Regex::new(r"^https://github\.com/([^/]+)/(.+)$").unwrap(),
+            to: "git@github.example.com:$1/$2".to_string(),
+        };
+        assert_eq!(
+            Some("git@github.example.com:foo/bar.git".to_string()),
+            regex.apply("https://github.com/foo/bar.git")
+        );
+        assert_eq!(None, regex.apply("git@github.com:foo/bar.git"));
+
+        let unchanged = Rewrite::Prefix {
+            from: "git@".to_string(),
+            to: "git@".to_string(),
+        };
+        assert_eq!(None, unchanged.apply("git@github.com:foo/bar.git"));
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(RemoteHealth::Reachable, classify(Some(0), ""));
+        assert_eq!(
+            RemoteHealth::Redirected {
+                to: "https://github.com/acme/renamed/".to_string()
+            },
+            classify(
+                Some(0),
+                "warning: redirecting to https://github.com/acme/renamed/\n"
+            )
+        );
+        assert_eq!(RemoteHealth::NoHead, classify(Some(2), ""));
+        assert_eq!(
+            RemoteHealth::AuthFailed {
+                error: "git@github.com: Permission denied (publickey).".to_string()
+            },
+            classify(
+                Some(128),
+                "git@github.com: Permission denied (publickey).\n\
+                 fatal: Could not read from remote repository.\n\n\
+                 Please make sure you have the correct access rights\n"
+            )
+        );
+        assert_eq!(
+            RemoteHealth::Unreachable {
+                error: "ERROR: Repository not found.".to_string()
+            },
+            classify(
+                Some(128),
+                "ERROR: Repository not found.\n\
+                 fatal: Could not read from remote repository.\n\n\
+                 Please make sure you have the correct access rights\n\
+                 and the repository exists.\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(Some("github.com"), host("git@github.com:acme/foo.git"));
+        assert_eq!(Some("github.com"), host("https://github.com/acme/foo.git"));
+        assert_eq!(
+            Some("git.example.com"),
+            host("ssh://git@git.example.com:2222/acme/foo.git")
+        );
+        assert_eq!(
+            Some("git.example.com"),
+            host("https://git.example.com:8443/acme/foo.git")
+        );
+        assert_eq!(Some("::1"), host("ssh://git@[::1]:22/acme/foo.git"));
+        assert_eq!(None, host("/srv/git/foo.git"));
+        assert_eq!(None, host("../foo"));
+        assert_eq!(None, host("file:///srv/git/foo.git"));
+    }
+}
@@ -0,0 +1,605 @@
+//! Renders the output of a run: the live output of each repository, the log file entries, the
+//! failures and the summary.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+
+use colored::Colorize;
+use gitjuggling::classify::Policy;
+use gitjuggling::state::RepoState;
+use gitjuggling::{ansi, RunResult};
+
+use crate::output::truncate_lines;
+use crate::theme::Theme;
+use crate::{grep, Item, NotAttempted, Skipped};
+
+/// Formats how many commits a branch is ahead and behind its upstream, like `↑2 ↓5`, without
+/// the counts that are 0.
+pub fn format_counts(ahead: usize, behind: usize) -> String {
+    let mut counts = Vec::new();
+    if ahead > 0 {
+        counts.push(format!("↑{}", ahead));
+    }
+    if behind > 0 {
+        counts.push(format!("↓{}", behind));
+    }
+
+    counts.join(" ")
+}
+
+/// Formats the output of a command like [`format_lines`], with `color` unless it has colors of
+/// its own. They're kept, but not the sequences moving the cursor.
+fn format_output(text: &str, color: colored::Color, prefix: Option<&str>) -> String {
+    if text.contains('\x1b') {
+        format_lines(&ansi::sanitize(text), None, prefix)
+    } else {
+        format_lines(text, Some(color), prefix)
+    }
+}
+
+/// Formats `text` with `color`, starting each line with `prefix` if there is one.
+fn format_lines(text: &str, color: Option<colored::Color>, prefix: Option<&str>) -> String {
+    let paint = |text: &str| match color {
+        Some(color) => text.color(color).to_string(),
+        None => text.to_string(),
+    };
+
+    match prefix {
+        Some(prefix) => text
+            .lines()
+            .map(|line| format!("{} {}\n", prefix, paint(line)))
+            .collect(),
+        None => format!("{}\n", paint(text)),
+    }
+}
+
+/// Formats the banner of a repository with the git arguments exactly as they were run. With
+/// `verbose` the directory, the git program and the environment it ran with follow.
+fn format_banner(item: &Item, theme: &Theme, verbose: bool) -> String {
+    let mut output = String::new();
+
+    write!(
+        &mut output,
+        "{} executing {}",
+        item.display_path(theme),
+        item.result
+            .command_line(&item.result.args)
+            .color(theme.command)
+    )
+    .unwrap();
+    match item.result.step {
+        Some((step, count)) => writeln!(&mut output, " (step {} of {})", step, count).unwrap(),
+        None => output.push('\n'),
+    }
+    if verbose {
+        writeln!(&mut output, "  cwd: {}", item.result.path.display()).unwrap();
+        writeln!(&mut output, "  git: {}", item.result.program.display()).unwrap();
+        for (name, value) in &item.result.env {
+            match value {
+                Some(value) => writeln!(&mut output, "  env: {}={}", name, value).unwrap(),
+                None => writeln!(&mut output, "  env: {} unset", name).unwrap(),
+            }
+        }
+    }
+
+    output
+}
+
+/// Formats the live output of a repository: the banner followed by the command's output, or
+/// why it couldn't be spawned. The steps of a script that ran before come first, each with its
+/// banner and its output.
+pub fn format_item(item: &Item, theme: &Theme, max_lines: Option<usize>, verbose: bool) -> String {
+    let mut output = String::new();
+
+    let prefix = item.prefix.as_deref();
+    let count = item.result.step.map_or(0, |(_, count)| count);
+    for (index, step) in item.result.steps.iter().enumerate() {
+        writeln!(
+            &mut output,
+            "{} executing {} (step {} of {})",
+            item.display_path(theme),
+            item.result.command_line(&step.args).color(theme.command),
+            index + 1,
+            count
+        )
+        .unwrap();
+        for (text, color) in [(&step.stdout, theme.stdout), (&step.stderr, theme.stderr)] {
+            if !text.is_empty() {
+                output.push_str(&format_output(
+                    &truncate_lines(&text.to_str_lossy(), max_lines),
+                    color,
+                    prefix,
+                ));
+            }
+        }
+    }
+    output.push_str(&format_banner(item, theme, verbose));
+
+    if item.result.error.is_some() {
+        let error = format!("failed: {}", item.result.failure_reason()).bright_red();
+        output.push_str(&format_lines(&error.to_string(), None, prefix));
+        return output;
+    }
+
+    if !item.result.stdout.is_empty() {
+        output.push_str(&format_output(
+            &truncate_lines(&item.result.stdout.to_str_lossy(), max_lines),
+            theme.stdout,
+            prefix,
+        ));
+    }
+    if !item.result.stderr.is_empty() {
+        output.push_str(&format_output(
+            &truncate_lines(&item.result.stderr.to_str_lossy(), max_lines),
+            theme.stderr,
+            prefix,
+        ));
+    }
+    if let Some(reason) = &item.result.reason {
+        let note = if item.result.success {
+            format!("note: {}", reason).bright_yellow()
+        } else {
+            format!("failed: {}", reason).bright_red()
+        };
+        output.push_str(&format_lines(&note.to_string(), None, prefix));
+    }
+
+    output
+}
+
+/// Formats the output of git grep in a repository without a banner, the paths starting with the
+/// repository.
+pub fn format_grep_item(item: &Item, theme: &Theme) -> String {
+    let mut output = grep::prefix_paths(&item.result.stdout.to_str_lossy(), &item.display);
+    if !item.result.stderr.is_empty() {
+        output.push_str(&format_output(
+            &item.result.stderr.to_str_lossy(),
+            theme.stderr,
+            item.prefix.as_deref(),
+        ));
+    }
+
+    output
+}
+
+/// Formats the complete, uncolored, log file entry of a repository, with the output of the
+/// command as it wrote it.
+pub fn format_log_entry(item: &Item) -> Vec<u8> {
+    let mut entry = String::new();
+
+    let path = item.result.path.to_string_lossy();
+
+    writeln!(
+        &mut entry,
+        "{} executing {} {}",
+        path,
+        item.result.program.display(),
+        item.result.command_line(&item.result.args)
+    )
+    .unwrap();
+    for (name, value) in &item.result.env {
+        match value {
+            Some(value) => writeln!(&mut entry, "env: {}={}", name, value).unwrap(),
+            None => writeln!(&mut entry, "env: {} unset", name).unwrap(),
+        }
+    }
+    if let Some(branch) = &item.result.branch {
+        writeln!(&mut entry, "branch: {}", branch).unwrap();
+    }
+    if let Some(state) = &item.result.state {
+        writeln!(&mut entry, "state: {}", state).unwrap();
+    }
+    if item.result.success {
+        match &item.result.reason {
+            Some(reason) => writeln!(&mut entry, "{} succeeded, {}", path, reason).unwrap(),
+            None => writeln!(&mut entry, "{} succeeded", path).unwrap(),
+        }
+    } else {
+        writeln!(
+            &mut entry,
+            "{} failed, {}",
+            path,
+            item.result.failure_reason()
+        )
+        .unwrap();
+    }
+    writeln!(&mut entry, "duration: {:?}", item.result.duration).unwrap();
+
+    let mut entry = entry.into_bytes();
+    for (name, output) in [
+        ("stdout", &item.result.stdout),
+        ("stderr", &item.result.stderr),
+    ] {
+        if !output.is_empty() {
+            entry.extend_from_slice(format!("{}:\n", name).as_bytes());
+            match output.read() {
+                Ok(bytes) => entry.extend_from_slice(&bytes),
+                Err(_) => entry.extend_from_slice(output.to_str_lossy().as_bytes()),
+            }
+            entry.push(b'\n');
+        }
+    }
+
+    entry
+}
+
+/// Above this number of repositories a collapsed group only shows the count of repositories.
+const COLLAPSE_LIST_LIMIT: usize = 10;
+
+/// Groups the items by their exact output, successes and failures are never grouped together.
+///
+/// The groups are sorted by size, largest last.
+fn group_items(items: &[Item]) -> Vec<Vec<&Item>> {
+    let mut groups: Vec<Vec<&Item>> = Vec::new();
+
+    for item in items {
+        let group = groups.iter_mut().find(|group| {
+            let other = group[0];
+
+            other.result.success == item.result.success
+                && other.result.exit_code == item.result.exit_code
+                && other.result.stdout == item.result.stdout
+                && other.result.stderr == item.result.stderr
+                && other.result.error == item.result.error
+        });
+
+        match group {
+            Some(group) => group.push(item),
+            None => groups.push(vec![item]),
+        }
+    }
+
+    groups.sort_by_key(|group| group.len());
+
+    groups
+}
+
+pub fn format_collapsed(items: &[Item], theme: &Theme, max_lines: Option<usize>) -> String {
+    let mut output = String::new();
+
+    for group in group_items(items) {
+        let first = group[0];
+
+        let status = if first.result.success {
+            "succeeded".bright_green()
+        } else {
+            format!("failed, {}", first.result.failure_reason()).bright_red()
+        };
+
+        if group.len() > COLLAPSE_LIST_LIMIT {
+            writeln!(
+                &mut output,
+                "{} repositories {}",
+                format!("{}", group.len()).color(theme.path),
+                status
+            )
+            .unwrap();
+        } else {
+            for item in &group {
+                writeln!(&mut output, "{} {}", item.display_path(theme), status).unwrap();
+            }
+        }
+
+        if !first.result.stdout.is_empty() {
+            output.push_str(&format_output(
+                &truncate_lines(&first.result.stdout.to_str_lossy(), max_lines),
+                theme.stdout,
+                None,
+            ));
+        }
+        if !first.result.stderr.is_empty() {
+            output.push_str(&format_output(
+                &truncate_lines(&first.result.stderr.to_str_lossy(), max_lines),
+                theme.stderr,
+                None,
+            ));
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Formats a section header like `=== Summary ===`.
+pub fn format_header(title: colored::ColoredString) -> String {
+    format!(
+        "\n\n{}{}{}\n\n",
+        "=== ".bright_white(),
+        title,
+        " ===".bright_white()
+    )
+}
+
+/// Formats the section of --show-skipped, every repository skipped and why.
+pub fn format_skipped_details(skipped: &[Skipped], theme: &Theme) -> String {
+    let mut output = format_header("Skipped repositories".bright_yellow());
+
+    for skipped in skipped {
+        writeln!(
+            &mut output,
+            "{} {}",
+            skipped.display.color(theme.path),
+            skipped.reason.bright_yellow()
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+pub fn format_not_attempted_details(not_attempted: &[NotAttempted], theme: &Theme) -> String {
+    let mut output = format_header("Repositories not attempted".bright_yellow());
+
+    for not_attempted in not_attempted {
+        writeln!(
+            &mut output,
+            "{} {}",
+            not_attempted.display.color(theme.path),
+            not_attempted.reason.bright_yellow()
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+pub fn format_failure_details(failed: &[Item], theme: &Theme, max_lines: Option<usize>) -> String {
+    let mut output = format_header("Details of failed items".bright_red());
+
+    for item in failed {
+        writeln!(
+            &mut output,
+            "{} {}",
+            item.display_path(theme),
+            item.result.failure_reason().bright_red()
+        )
+        .unwrap();
+
+        let prefix = item.prefix.as_deref();
+
+        if !item.result.stdout.is_empty() {
+            output.push_str(&format_lines(
+                &truncate_lines(&item.result.stdout.read_lossy(), max_lines),
+                None,
+                prefix,
+            ));
+        }
+
+        if item.result.error.is_none() && !item.result.stderr.is_empty() {
+            output.push_str(&format_output(
+                &truncate_lines(&item.result.stderr.read_lossy(), max_lines),
+                theme.stderr,
+                prefix,
+            ));
+        }
+    }
+
+    output
+}
+
+pub fn format_summary(
+    succeeded: &[Item],
+    quiet: Option<&[Item]>,
+    failed: &[Item],
+    skipped: &[Skipped],
+    not_attempted: &[NotAttempted],
+    theme: &Theme,
+) -> String {
+    let mut output = format_header("Summary".color(theme.summary));
+
+    writeln!(
+        &mut output,
+        "{} {}",
+        "Succeeded: ".blue(),
+        format!("{}", succeeded.len()).bright_green()
+    )
+    .unwrap();
+    if let Some(quiet) = quiet {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "Quiet:     ".blue(),
+            format!("{}", quiet.len()).bright_white()
+        )
+        .unwrap();
+    }
+    writeln!(
+        &mut output,
+        "{} {}",
+        "Failed:    ".blue(),
+        format!("{}", failed.len()).bright_red()
+    )
+    .unwrap();
+    if !skipped.is_empty() {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "Skipped:   ".blue(),
+            format!("{}", skipped.len()).bright_yellow()
+        )
+        .unwrap();
+        let in_progress = skipped.iter().filter(|skipped| skipped.in_progress).count();
+        if in_progress > 0 {
+            writeln!(
+                &mut output,
+                "{} {}",
+                "  in progress:  ".blue(),
+                format!("{}", in_progress).bright_yellow()
+            )
+            .unwrap();
+        }
+    }
+
+    let stderr_policy = failed
+        .iter()
+        .filter(|item| item.result.policy == Some(Policy::Stderr))
+        .count();
+    let regex_policy = failed
+        .iter()
+        .filter(|item| item.result.policy == Some(Policy::FailRegex))
+        .count();
+    // The command usually had nothing to work with when the repository has no commits yet
+    let no_commits = failed
+        .iter()
+        .filter(|item| item.result.policy.is_none() && item.result.state == Some(RepoState::Unborn))
+        .count();
+    if stderr_policy > 0 || regex_policy > 0 || no_commits > 0 {
+        let mut codes: BTreeMap<i32, usize> = BTreeMap::new();
+        for item in failed {
+            let unborn = item.result.state == Some(RepoState::Unborn);
+            match item.result.exit_code {
+                Some(code) if code != 0 && item.result.policy.is_none() && !unborn => {
+                    *codes.entry(code).or_default() += 1
+                }
+                _ => {}
+            }
+        }
+        for (code, count) in codes {
+            writeln!(
+                &mut output,
+                "{} {}",
+                format!("{:16}", format!("  exit code {}:", code)).blue(),
+                format!("{}", count).bright_red()
+            )
+            .unwrap();
+        }
+    }
+    if no_commits > 0 {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "  no commits:   ".blue(),
+            format!("{}", no_commits).bright_yellow()
+        )
+        .unwrap();
+    }
+    if stderr_policy > 0 {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "  stderr policy:".blue(),
+            format!("{}", stderr_policy).bright_red()
+        )
+        .unwrap();
+    }
+    if regex_policy > 0 {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "  regex policy: ".blue(),
+            format!("{}", regex_policy).bright_red()
+        )
+        .unwrap();
+    }
+    // After the breakdown of the failures, the run stopped because of them with --fail-fast
+    if !not_attempted.is_empty() {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "Not run:   ".blue(),
+            format!("{}", not_attempted.len()).bright_yellow()
+        )
+        .unwrap();
+    }
+    // Whether the command succeeded in them or not, they need a look
+    let broken = succeeded
+        .iter()
+        .chain(quiet.into_iter().flatten())
+        .chain(failed)
+        .filter(|item| item.result.broken.is_some())
+        .count()
+        + skipped.iter().filter(|skipped| skipped.broken).count();
+    if broken > 0 {
+        writeln!(
+            &mut output,
+            "{} {}",
+            "Broken:    ".blue(),
+            format!("{}", broken).bright_red()
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Formats the number of lines git grep found in each repository with matches, and overall.
+pub fn format_match_counts(items: &[Item], theme: &Theme) -> String {
+    let counts: Vec<(&str, usize)> = items
+        .iter()
+        .map(|item| {
+            (
+                item.display.as_str(),
+                grep::count_matches(&item.result.stdout.to_str_lossy()),
+            )
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    let width = counts
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    writeln!(
+        &mut output,
+        "{} {}",
+        "Matches:   ".blue(),
+        format!("{}", counts.iter().map(|(_, count)| count).sum::<usize>()).bright_white()
+    )
+    .unwrap();
+    for (name, count) in counts {
+        writeln!(
+            &mut output,
+            "  {} {}",
+            format!("{:width$}", name, width = width).color(theme.path),
+            count
+        )
+        .unwrap();
+    }
+
+    output
+}
+
+/// Formats what ran in a repository for -v on stderr: where, how, and how it ended.
+pub fn format_diagnostics(display: &str, result: &RunResult) -> String {
+    let mut output = String::new();
+    writeln!(
+        &mut output,
+        "{}: ran {} {} in {}",
+        display,
+        result.program.display(),
+        result.args.join(" "),
+        result.path.display()
+    )
+    .unwrap();
+    for (name, value) in &result.env {
+        match value {
+            Some(value) => writeln!(&mut output, "{}: env {}={}", display, name, value).unwrap(),
+            None => writeln!(&mut output, "{}: env {} unset", display, name).unwrap(),
+        }
+    }
+    if !result.priority.is_empty() {
+        writeln!(
+            &mut output,
+            "{}: priority {}",
+            display,
+            result.priority.join(", ")
+        )
+        .unwrap();
+    }
+    let status = if result.success {
+        "succeeded".to_string()
+    } else {
+        result.failure_reason()
+    };
+    writeln!(
+        &mut output,
+        "{}: {} after {:?}",
+        display, status, result.duration
+    )
+    .unwrap();
+
+    output
+}
@@ -0,0 +1,189 @@
+use std::fmt::Write as FmtWrite;
+use std::time::{Duration, SystemTime};
+
+use gitjuggling::ansi::strip_ansi;
+
+use crate::output::format_duration;
+
+pub struct ReportEntry {
+    pub name: String,
+    pub success: bool,
+    pub failure_reason: String,
+    pub duration: Duration,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Renders the run as a Markdown document: a header, a results table and the output of each
+/// failed repository in a collapsible block.
+pub fn render_markdown(command: &str, timestamp: SystemTime, entries: &[ReportEntry]) -> String {
+    let mut result = String::new();
+
+    let failed = entries.iter().filter(|entry| !entry.success).count();
+
+    writeln!(&mut result, "# gitjuggling report\n").unwrap();
+    writeln!(&mut result, "* Command: {}", code_span(command)).unwrap();
+    writeln!(
+        &mut result,
+        "* Date: {}",
+        humantime::format_rfc3339_seconds(timestamp)
+    )
+    .unwrap();
+    writeln!(
+        &mut result,
+        "* Results: {} succeeded, {} failed\n",
+        entries.len() - failed,
+        failed
+    )
+    .unwrap();
+
+    writeln!(&mut result, "| Repository | Status | Duration |").unwrap();
+    writeln!(&mut result, "| --- | --- | --- |").unwrap();
+    for entry in entries {
+        writeln!(
+            &mut result,
+            "| {} | {} | {} |",
+            escape_table_cell(&entry.name),
+            if entry.success { "OK" } else { "FAIL" },
+            format_duration(entry.duration)
+        )
+        .unwrap();
+    }
+
+    if failed > 0 {
+        writeln!(&mut result, "\n## Failed repositories").unwrap();
+    }
+
+    for entry in entries.iter().filter(|entry| !entry.success) {
+        let output = strip_ansi(&format!("{}\n{}", entry.stdout, entry.stderr))
+            .trim()
+            .to_string();
+        let fence = code_fence(&output);
+
+        writeln!(&mut result, "\n<details>").unwrap();
+        writeln!(
+            &mut result,
+            "<summary>{} ({})</summary>\n",
+            escape_html(&entry.name),
+            escape_html(&entry.failure_reason)
+        )
+        .unwrap();
+        writeln!(&mut result, "{}\n{}\n{}", fence, output, fence).unwrap();
+        writeln!(&mut result, "\n</details>").unwrap();
+    }
+
+    result
+}
+
+fn escape_table_cell(s: &str) -> String {
+    escape_html(s).replace('|', "\\|")
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns `s` as a code span, delimited by more backticks than any run of them in `s`. It's
+/// padded with a space on each side when it starts or ends with a backtick or a space, the
+/// padding is stripped when it's rendered.
+fn code_span(s: &str) -> String {
+    let delimiter = "`".repeat(longest_backticks(s) + 1);
+    let padding = if s.starts_with(['`', ' ']) || s.ends_with(['`', ' ']) {
+        " "
+    } else {
+        ""
+    };
+
+    format!("{}{}{}{}{}", delimiter, padding, s, padding, delimiter)
+}
+
+/// Returns a code fence longer than any run of backticks in `s`.
+fn code_fence(s: &str) -> String {
+    "`".repeat(longest_backticks(s).max(2) + 1)
+}
+
+/// Returns the length of the longest run of backticks in `s`.
+fn longest_backticks(s: &str) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for ch in s.chars() {
+        if ch == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_markdown() {
+        let entries = vec![
+            ReportEntry {
+                name: "foo".to_string(),
+                success: true,
+                failure_reason: String::new(),
+                duration: Duration::from_millis(20),
+                stdout: "Already up to date.".to_string(),
+                stderr: String::new(),
+            },
+            ReportEntry {
+                name: "bar|baz".to_string(),
+                success: false,
+                failure_reason: "exited with code 1".to_string(),
+                duration: Duration::from_millis(30),
+                stdout: String::new(),
+                stderr: "error: ```oops```".to_string(),
+            },
+        ];
+
+        let markdown = render_markdown("git pull", SystemTime::UNIX_EPOCH, &entries);
+
+        assert_eq!(
+            "# gitjuggling report
+
+* Command: `git pull`
+* Date: 1970-01-01T00:00:00Z
+* Results: 1 succeeded, 1 failed
+
+| Repository | Status | Duration |
+| --- | --- | --- |
+| foo | OK | 20ms |
+| bar\\|baz | FAIL | 30ms |
+
+## Failed repositories
+
+<details>
+<summary>bar|baz (exited with code 1)</summary>
+
+````
+error: ```oops```
+````
+
+</details>
+",
+            markdown
+        );
+    }
+
+    #[test]
+    fn test_code_span() {
+        assert_eq!("`git pull`", code_span("git pull"));
+        assert_eq!("``git log -S`foo` x``", code_span("git log -S`foo` x"));
+        assert_eq!(
+            "`` git log --format=`%h` ``",
+            code_span("git log --format=`%h`")
+        );
+        assert_eq!("```git log ``%h`` x```", code_span("git log ``%h`` x"));
+        assert_eq!("`` `foo` ``", code_span("`foo`"));
+        assert_eq!("`  foo `", code_span(" foo"));
+    }
+}
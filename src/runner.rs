@@ -0,0 +1,772 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::capture::{RawOutput, DEFAULT_SPILL_THRESHOLD};
+use crate::classify::{Classifier, Policy};
+use crate::git::Git;
+use crate::limits::PathLimits;
+use crate::order::Dependencies;
+use crate::priority::{Applied, Priority};
+use crate::probe::Backend;
+use crate::state::{self, RepoState};
+use crate::transfer::{self, Transfer};
+
+/// The outcome of a command in a repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunResult {
+    /// The path of the repository
+    pub path: PathBuf,
+    /// The current branch of the repository, or `detached <commit>`
+    pub branch: Option<String>,
+    /// The operation in progress in the repository, like a rebase, probed along with the branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<RepoState>,
+    /// Why HEAD can't be resolved, see [`state::broken_head`]. The command still runs, its
+    /// outcome is its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broken: Option<String>,
+    /// How many commits the current branch was ahead of its upstream before the command, see
+    /// [`Runner::counts`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ahead: Option<usize>,
+    /// How many commits the current branch was behind its upstream before the command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub behind: Option<usize>,
+    /// The git program the command ran
+    #[serde(default)]
+    pub program: PathBuf,
+    /// The git arguments the command ran with, after the placeholders were replaced
+    pub args: Vec<String>,
+    /// The configuration variables set with `-c` before the arguments, see
+    /// [`Runner::git_config`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config: Vec<String>,
+    /// The variables set for the command, and the ones removed with `None`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<(String, Option<String>)>,
+    /// Whether the command succeeded, as decided by the [`Classifier`]
+    pub success: bool,
+    /// The exit code of the command, `None` if it was killed by a signal or couldn't be spawned
+    pub exit_code: Option<i32>,
+    /// The signal that killed the command, only ever set on Unix
+    pub signal: Option<i32>,
+    /// The trimmed standard output of the command
+    pub stdout: RawOutput,
+    /// The trimmed standard error of the command
+    pub stderr: RawOutput,
+    /// How long the command ran
+    pub duration: Duration,
+    /// Explains why the outcome differs from what the exit code says
+    pub reason: Option<String>,
+    /// The policy that made the command fail, if any
+    pub policy: Option<Policy>,
+    /// Set if the command could not be spawned at all
+    pub error: Option<String>,
+    /// The priorities the command ran with, see [`Runner::priority`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority: Vec<String>,
+    /// What went wrong without failing the command, like a priority that couldn't be set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// What a network command received or sent, from the progress git wrote on stderr. It's
+    /// unknown without it, see [`transfer::parse`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<Transfer>,
+    /// For a script, the number of the step of this result from 1 and how many steps there are,
+    /// see [`Runner::steps`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<(usize, usize)>,
+    /// The steps of the script that ran before the one of this result, they all succeeded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<Step>,
+}
+
+/// A step of a script that succeeded before the one of a [`RunResult`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    /// The git arguments of the step
+    pub args: Vec<String>,
+    /// The exit code of git, the step succeeded
+    pub exit_code: Option<i32>,
+    /// The output of git on stdout
+    pub stdout: RawOutput,
+    /// The output of git on stderr
+    pub stderr: RawOutput,
+    /// How long the step ran
+    pub duration: Duration,
+}
+
+impl RunResult {
+    /// Returns the git arguments as they were spawned, the `-c` of the configuration first.
+    pub fn command_line(&self, args: &[String]) -> String {
+        self.config
+            .iter()
+            .flat_map(|config| ["-c", config.as_str()])
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Describes why the command failed.
+    pub fn failure_reason(&self) -> String {
+        let reason = if let Some(err) = &self.error {
+            format!("could not be spawned: {}", err)
+        } else if let Some(reason) = &self.reason {
+            reason.clone()
+        } else {
+            match (self.exit_code, self.signal) {
+                (Some(code), _) => format!("exited with code {}", code),
+                (None, Some(signal)) => format!("killed by signal {}", signal),
+                (None, None) => "exited with an unknown status".to_string(),
+            }
+        };
+
+        match self.step {
+            Some((step, count)) => format!(
+                "step {} of {}, {}: {}",
+                step,
+                count,
+                self.args.join(" "),
+                reason
+            ),
+            None => reason,
+        }
+    }
+
+    /// Returns true if the command succeeded without printing anything.
+    pub fn is_quiet(&self) -> bool {
+        self.success && self.stdout.is_empty() && self.stderr.is_empty()
+    }
+}
+
+/// Runs a git command in many repositories in parallel, on the global rayon thread pool.
+pub struct Runner {
+    git: Git,
+    git_args: Vec<String>,
+    repository_args: HashMap<PathBuf, Vec<String>>,
+    steps: HashMap<PathBuf, Vec<Vec<String>>>,
+    classifier: Classifier,
+    show_branch: bool,
+    counts: bool,
+    backend: Backend,
+    stop: Vec<&'static AtomicBool>,
+    spill_threshold: u64,
+    dependencies: Dependencies,
+    path_limits: PathLimits,
+    git_config: Vec<String>,
+    priority: Priority,
+    git_error: OnceLock<String>,
+}
+
+impl Runner {
+    /// Creates a runner executing `git <git_args>`.
+    ///
+    /// By default only the exit code decides the outcome and the current branch is probed.
+    pub fn new<S: AsRef<str>>(git_args: &[S]) -> Self {
+        Self {
+            git: Git::default(),
+            git_args: git_args
+                .iter()
+                .map(|arg| arg.as_ref().to_string())
+                .collect(),
+            repository_args: HashMap::new(),
+            steps: HashMap::new(),
+            classifier: Classifier::default(),
+            show_branch: true,
+            counts: false,
+            backend: Backend::default(),
+            stop: Vec::new(),
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            dependencies: Dependencies::default(),
+            path_limits: PathLimits::default(),
+            git_config: Vec::new(),
+            priority: Priority::default(),
+            git_error: OnceLock::new(),
+        }
+    }
+
+    /// Sets the git running the command and the probes.
+    pub fn git(mut self, git: Git) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Sets the git arguments of some repositories, instead of the ones given to [`Runner::new`].
+    pub fn repository_args(mut self, args: HashMap<PathBuf, Vec<String>>) -> Self {
+        self.repository_args = args;
+        self
+    }
+
+    /// Sets the script of some repositories: their commands run one after the other, until one of
+    /// them fails. The result is the one of the last command that ran, with the ones before it in
+    /// [`RunResult::steps`].
+    pub fn steps(mut self, steps: HashMap<PathBuf, Vec<Vec<String>>>) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Sets the classifier deciding whether a command succeeded.
+    pub fn classifier(mut self, classifier: Classifier) -> Self {
+        self.classifier = classifier;
+        self
+    }
+
+    /// Sets whether the current branch of each repository is probed.
+    pub fn show_branch(mut self, show_branch: bool) -> Self {
+        self.show_branch = show_branch;
+        self
+    }
+
+    /// Sets whether how many commits the current branch is ahead and behind its upstream is
+    /// probed before the command, it's not by default.
+    pub fn counts(mut self, counts: bool) -> Self {
+        self.counts = counts;
+        self
+    }
+
+    /// Sets the backend probing the state of the repositories.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Adds a flag which, once true, prevents any new command from being started.
+    pub fn stop_flag(mut self, stop: &'static AtomicBool) -> Self {
+        self.stop.push(stop);
+        self
+    }
+
+    /// Sets how many bytes of output a command can write to stdout or stderr before it's
+    /// spilled to a temporary file, 8 MiB by default. See [`RawOutput`].
+    pub fn spill_threshold(mut self, spill_threshold: u64) -> Self {
+        self.spill_threshold = spill_threshold;
+        self
+    }
+
+    /// Sets the repositories each repository waits for, the command runs in a repository once it
+    /// finished in all of them. The dependencies must not have cycles, see
+    /// [`Dependencies::break_cycles`].
+    pub fn dependencies(mut self, dependencies: Dependencies) -> Self {
+        self.dependencies = dependencies;
+        self
+    }
+
+    /// Sets how many commands run at the same time under some directories, the threads left
+    /// run the repositories elsewhere.
+    pub fn path_limits(mut self, path_limits: PathLimits) -> Self {
+        self.path_limits = path_limits;
+        self
+    }
+
+    /// Sets the configuration variable `key` to `value` for the command, with `-c` before its
+    /// arguments. It's in the `config` of the results, not in their `args`.
+    pub fn git_config(mut self, key: &str, value: &str) -> Self {
+        self.git_config.push(format!("{}={}", key, value));
+        self
+    }
+
+    /// Sets the priority of the git processes, it's set before git runs so that the processes it
+    /// starts have it too.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Returns why git itself couldn't be spawned, like when it's not installed.
+    ///
+    /// Once it's set no new command is started: every repository would fail the same way.
+    pub fn git_error(&self) -> Option<&str> {
+        self.git_error.get().map(String::as_str)
+    }
+
+    /// Runs the command in all `paths` and returns the results in the order of `paths`.
+    ///
+    /// Repositories skipped because a stop flag was set, or because git couldn't be spawned
+    /// (see [`Runner::git_error`]), have no result.
+    pub fn run(&self, paths: &[PathBuf]) -> Vec<RunResult> {
+        self.run_with(paths, |_, result| result)
+    }
+
+    /// Like [`Runner::run`] but calls `f` with the index of the repository in `paths` as soon
+    /// as its command finished, or with `None` if it was skipped.
+    ///
+    /// This returns what `f` returned, in the order of `paths`.
+    pub fn run_with<T, F>(&self, paths: &[PathBuf], f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize, Option<RunResult>) -> Option<T> + Sync,
+    {
+        if !self.dependencies.is_empty() || !self.path_limits.is_empty() {
+            return self.run_scheduled(paths, &f);
+        }
+
+        paths
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, path)| self.run_index(index, path, &f))
+            .collect()
+    }
+
+    fn run_index<T, F>(&self, index: usize, path: &Path, f: &F) -> Option<T>
+    where
+        F: Fn(usize, Option<RunResult>) -> Option<T>,
+    {
+        if self.stop.iter().any(|stop| stop.load(Ordering::SeqCst)) {
+            debug!(path = %path.display(), "stopped, not running");
+            return f(index, None);
+        }
+        if self.git_error.get().is_some() {
+            debug!(path = %path.display(), "git can't be spawned, not running");
+            return f(index, None);
+        }
+
+        f(index, Some(self.run_one(path)))
+    }
+
+    /// Runs the repositories once the ones they wait for finished and there's room under their
+    /// limit, the others in parallel. No thread waits: a repository that can't run yet is
+    /// started by the one it waits for.
+    fn run_scheduled<T, F>(&self, paths: &[PathBuf], f: &F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize, Option<RunResult>) -> Option<T> + Sync,
+    {
+        let indices: HashMap<&Path, usize> = paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| (path.as_path(), index))
+            .collect();
+        // The repositories waiting for each repository, and how many each one still waits for
+        let mut dependents = vec![Vec::new(); paths.len()];
+        let mut waiting = vec![0; paths.len()];
+        for (index, path) in paths.iter().enumerate() {
+            for dependency in self.dependencies.waits_for(path) {
+                if let Some(&dependency) = indices.get(dependency.as_path()) {
+                    dependents[dependency].push(index);
+                    waiting[index] += 1;
+                }
+            }
+        }
+
+        let scheduler = Scheduler {
+            runner: self,
+            paths,
+            f,
+            dependents,
+            waiting: waiting
+                .iter()
+                .map(|&count| AtomicUsize::new(count))
+                .collect(),
+            buckets: paths
+                .iter()
+                .map(|path| self.path_limits.bucket_index(path))
+                .collect(),
+            limits: self.path_limits.limits(),
+            slots: Mutex::new(
+                self.path_limits
+                    .limits()
+                    .iter()
+                    .map(|_| (0, VecDeque::new()))
+                    .collect(),
+            ),
+            results: paths.iter().map(|_| Mutex::new(None)).collect(),
+        };
+        rayon::scope(|scope| {
+            for (index, &count) in waiting.iter().enumerate() {
+                if count == 0 {
+                    scheduler.ready(scope, index);
+                }
+            }
+        });
+
+        scheduler
+            .results
+            .into_iter()
+            .filter_map(|result| result.into_inner().unwrap())
+            .collect()
+    }
+
+    fn run_one(&self, path: &Path) -> RunResult {
+        // Only files are looked at, it's cheap enough to do everywhere
+        let broken = state::git_dir(path).and_then(|git_dir| state::broken_head(&git_dir));
+        let (branch, state) = if self.show_branch {
+            (
+                self.backend.current_branch(&self.git, path),
+                state::git_dir(path).and_then(|git_dir| {
+                    state::in_progress(&git_dir).or_else(|| {
+                        (broken.is_none() && state::is_unborn(&git_dir))
+                            .then_some(RepoState::Unborn)
+                    })
+                }),
+            )
+        } else {
+            (None, None)
+        };
+        let (ahead, behind) = match self
+            .counts
+            .then(|| self.backend.ahead_behind(&self.git, path))
+            .flatten()
+        {
+            Some((ahead, behind)) => (Some(ahead), Some(behind)),
+            None => (None, None),
+        };
+
+        let mut result = match self.steps.get(path) {
+            Some(steps) => self.run_steps(path, steps),
+            None => {
+                let args = self
+                    .repository_args
+                    .get(path)
+                    .unwrap_or(&self.git_args)
+                    .clone();
+                self.run_command(path, args)
+            }
+        };
+        result.branch = branch;
+        result.state = state;
+        result.broken = broken;
+        result.ahead = ahead;
+        result.behind = behind;
+
+        result
+    }
+
+    /// Runs the `steps` of a script in the repository at `path` until one of them fails.
+    fn run_steps(&self, path: &Path, steps: &[Vec<String>]) -> RunResult {
+        let mut done = Vec::new();
+        let mut warnings = Vec::new();
+        let mut duration = Duration::ZERO;
+        let mut transferred: Option<Transfer> = None;
+        for (index, args) in steps.iter().enumerate() {
+            let mut result = self.run_command(path, args.clone());
+            duration += result.duration;
+            warnings.append(&mut result.warnings);
+            if let Some(transfer) = &result.transfer {
+                transferred
+                    .get_or_insert_with(Transfer::default)
+                    .add(transfer);
+            }
+
+            if !result.success || index + 1 == steps.len() {
+                result.step = Some((index + 1, steps.len()));
+                result.steps = done;
+                result.warnings = warnings;
+                result.duration = duration;
+                result.transfer = transferred;
+                return result;
+            }
+            done.push(Step {
+                args: result.args,
+                exit_code: result.exit_code,
+                stdout: result.stdout,
+                stderr: result.stderr,
+                duration: result.duration,
+            });
+        }
+
+        // A script without steps runs git alone, like an empty command line
+        self.run_command(path, Vec::new())
+    }
+
+    /// Runs `git <args>` in the repository at `path`, without the probes.
+    fn run_command(&self, path: &Path, args: Vec<String>) -> RunResult {
+        debug!(path = %path.display(), ?args, "spawning git");
+
+        let spawned: Vec<&str> = self
+            .git_config
+            .iter()
+            .flat_map(|config| ["-c", config.as_str()])
+            .chain(args.iter().map(String::as_str))
+            .collect();
+        let start = Instant::now();
+        let result = do_git_command(
+            &self.git,
+            path,
+            &spawned,
+            self.spill_threshold,
+            &self.priority,
+        );
+        let duration = start.elapsed();
+
+        match &result {
+            Ok(go) => debug!(
+                path = %path.display(),
+                status = %go.status,
+                ?duration,
+                "git exited"
+            ),
+            Err(err) => {
+                debug!(path = %path.display(), %err, "unable to spawn git");
+
+                // A spawn error can be specific to the repository, like a directory that can't
+                // be entered. It isn't if even git --version can't run.
+                if let Err(err) = self.git.version() {
+                    let _ = self.git_error.set(err.to_string());
+                }
+            }
+        }
+
+        match result {
+            Err(err) => RunResult {
+                path: path.to_path_buf(),
+                branch: None,
+                state: None,
+                broken: None,
+                ahead: None,
+                behind: None,
+                program: self.git.program().to_path_buf(),
+                args,
+                config: self.git_config.clone(),
+                env: self.git.env_overrides(),
+                success: false,
+                exit_code: None,
+                signal: None,
+                stdout: RawOutput::default(),
+                stderr: RawOutput::default(),
+                duration,
+                reason: None,
+                policy: None,
+                error: Some(err.to_string()),
+                priority: Vec::new(),
+                warnings: Vec::new(),
+                transfer: None,
+                step: None,
+                steps: Vec::new(),
+            },
+            Ok(go) => {
+                let exit_code = go.status.code();
+                let GitOutput {
+                    status,
+                    stdout,
+                    stderr,
+                    applied,
+                } = go;
+
+                // The fail regex and the stderr policy only need a lossy view of the output, but
+                // all of it
+                let verdict =
+                    self.classifier
+                        .classify(exit_code, &stdout.read_lossy(), &stderr.read_lossy());
+                let transfer = transfer::parse(&stderr.read_lossy());
+
+                RunResult {
+                    path: path.to_path_buf(),
+                    branch: None,
+                    state: None,
+                    broken: None,
+                    ahead: None,
+                    behind: None,
+                    program: self.git.program().to_path_buf(),
+                    args,
+                    config: self.git_config.clone(),
+                    env: self.git.env_overrides(),
+                    success: verdict.success,
+                    exit_code,
+                    signal: exit_signal(&status),
+                    stdout,
+                    stderr,
+                    duration,
+                    reason: verdict.reason,
+                    policy: verdict.policy,
+                    error: None,
+                    priority: applied.set,
+                    warnings: applied.errors,
+                    transfer,
+                    step: None,
+                    steps: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+/// The state of [`Runner::run_scheduled`] shared by the repositories running.
+struct Scheduler<'a, T, F> {
+    runner: &'a Runner,
+    paths: &'a [PathBuf],
+    f: &'a F,
+    dependents: Vec<Vec<usize>>,
+    waiting: Vec<AtomicUsize>,
+    /// The limit of each repository, an index of `limits`
+    buckets: Vec<Option<usize>>,
+    limits: Vec<usize>,
+    /// How many repositories run under each limit, and the ones ready to run once there's room
+    slots: Mutex<Vec<(usize, VecDeque<usize>)>>,
+    results: Vec<Mutex<Option<T>>>,
+}
+
+impl<'a, T, F> Scheduler<'a, T, F>
+where
+    T: Send,
+    F: Fn(usize, Option<RunResult>) -> Option<T> + Sync,
+{
+    /// Runs the repository at `index` which waits for nothing anymore, or queues it if its limit
+    /// is reached.
+    fn ready<'s>(&'s self, scope: &rayon::Scope<'s>, index: usize) {
+        if let Some(bucket) = self.buckets[index] {
+            let mut slots = self.slots.lock().unwrap();
+            let (running, queued) = &mut slots[bucket];
+            if *running >= self.limits[bucket] {
+                debug!(path = %self.paths[index].display(), "limit reached, queued");
+                queued.push_back(index);
+                return;
+            }
+            *running += 1;
+        }
+
+        self.spawn(scope, index);
+    }
+
+    /// Runs the repository at `index`, then the next one queued under its limit and the ones
+    /// waiting only for it.
+    fn spawn<'s>(&'s self, scope: &rayon::Scope<'s>, index: usize) {
+        scope.spawn(move |scope| {
+            let result = self.runner.run_index(index, &self.paths[index], self.f);
+            *self.results[index].lock().unwrap() = result;
+
+            if let Some(bucket) = self.buckets[index] {
+                let mut slots = self.slots.lock().unwrap();
+                let (running, queued) = &mut slots[bucket];
+                match queued.pop_front() {
+                    // The slot goes to the next one
+                    Some(next) => self.spawn(scope, next),
+                    None => *running -= 1,
+                }
+            }
+            for &dependent in &self.dependents[index] {
+                if self.waiting[dependent].fetch_sub(1, Ordering::SeqCst) == 1 {
+                    self.ready(scope, dependent);
+                }
+            }
+        });
+    }
+}
+
+struct GitOutput {
+    status: process::ExitStatus,
+    stdout: RawOutput,
+    stderr: RawOutput,
+    applied: Applied,
+}
+
+/// Runs git with `args` in `path`, capturing stdout and stderr at the same time so that neither
+/// pipe fills up.
+fn do_git_command<S: AsRef<str>>(
+    git: &Git,
+    path: &Path,
+    args: &[S],
+    spill_threshold: u64,
+    priority: &Priority,
+) -> anyhow::Result<GitOutput> {
+    let mut command = git.command(path);
+    command
+        .args(args.iter().map(AsRef::as_ref))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let prepared = priority.prepare(&mut command)?;
+    let mut child = command.spawn().map_err(|err| anyhow!(err))?;
+    let applied = prepared.applied();
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    let (stdout, stderr) = std::thread::scope(|scope| {
+        let stderr = scope.spawn(|| RawOutput::capture(stderr, spill_threshold));
+        let stdout = RawOutput::capture(stdout, spill_threshold);
+        (
+            stdout,
+            stderr.join().expect("capturing stderr doesn't panic"),
+        )
+    });
+    let status = child.wait()?;
+
+    Ok(GitOutput {
+        status,
+        stdout: stdout?,
+        stderr: stderr?,
+        applied,
+    })
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &process::ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &process::ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(success: bool, exit_code: Option<i32>, signal: Option<i32>) -> RunResult {
+        RunResult {
+            path: PathBuf::from("/src/foo"),
+            branch: None,
+            state: None,
+            broken: None,
+            ahead: None,
+            behind: None,
+            program: PathBuf::from("git"),
+            args: vec!["status".to_string()],
+            config: Vec::new(),
+            env: Vec::new(),
+            success,
+            exit_code,
+            signal,
+            stdout: RawOutput::default(),
+            stderr: RawOutput::default(),
+            duration: Duration::ZERO,
+            reason: None,
+            policy: None,
+            error: None,
+            priority: Vec::new(),
+            warnings: Vec::new(),
+            transfer: None,
+            step: None,
+            steps: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_failure_reason() {
+        assert_eq!(
+            "exited with code 128",
+            result(false, Some(128), None).failure_reason()
+        );
+        assert_eq!(
+            "killed by signal 9",
+            result(false, None, Some(9)).failure_reason()
+        );
+
+        let mut spawn_error = result(false, None, None);
+        spawn_error.error = Some("No such file or directory".to_string());
+        assert_eq!(
+            "could not be spawned: No such file or directory",
+            spawn_error.failure_reason()
+        );
+
+        assert!(result(true, Some(0), None).is_quiet());
+
+        let mut step = result(false, Some(1), None);
+        step.step = Some((2, 3));
+        assert_eq!(
+            "step 2 of 3, status: exited with code 1",
+            step.failure_reason()
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Recognize the git commands losing work or rewriting history, before they run in many
+//! repositories at once.
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use crate::grep;
+
+/// How much a risky command can lose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Risk {
+    /// What's lost can still be recovered, from the reflog or another clone
+    Medium,
+    /// What's lost is gone for good
+    High,
+}
+
+impl fmt::Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Risk::Medium => f.write_str("medium"),
+            Risk::High => f.write_str("high"),
+        }
+    }
+}
+
+/// A risky command of the catalogue.
+struct Rule {
+    subcommand: &'static str,
+    /// The combinations of arguments making the command risky, any of them matches. An empty
+    /// combination always does.
+    args: &'static [&'static [&'static str]],
+    /// The arguments making the command safe again
+    unless: &'static [&'static str],
+    risk: Risk,
+    reason: &'static str,
+}
+
+const CATALOGUE: &[Rule] = &[
+    Rule {
+        subcommand: "reset",
+        args: &[&["--hard"]],
+        unless: &[],
+        risk: Risk::High,
+        reason: "discards the uncommitted changes",
+    },
+    Rule {
+        subcommand: "clean",
+        args: &[&["-f"], &["--force"]],
+        unless: &["-n", "--dry-run"],
+        risk: Risk::High,
+        reason: "deletes the untracked files",
+    },
+    Rule {
+        subcommand: "push",
+        args: &[&["-f"], &["--force"]],
+        unless: &["-n", "--dry-run"],
+        risk: Risk::High,
+        reason: "overwrites the remote branches, the commits only they have are lost",
+    },
+    Rule {
+        subcommand: "push",
+        args: &[&["--force-with-lease"], &["--mirror"]],
+        unless: &["-n", "--dry-run"],
+        risk: Risk::Medium,
+        reason: "overwrites the remote branches",
+    },
+    Rule {
+        subcommand: "push",
+        args: &[&["-d"], &["--delete"], &["--prune"]],
+        unless: &["-n", "--dry-run"],
+        risk: Risk::Medium,
+        reason: "deletes remote branches",
+    },
+    Rule {
+        subcommand: "checkout",
+        args: &[&["--"], &["."], &["-f"], &["--force"]],
+        unless: &[],
+        risk: Risk::High,
+        reason: "discards the uncommitted changes of the files",
+    },
+    Rule {
+        subcommand: "restore",
+        args: &[&[]],
+        unless: &["-S", "--staged"],
+        risk: Risk::High,
+        reason: "discards the uncommitted changes of the files",
+    },
+    Rule {
+        subcommand: "switch",
+        args: &[&["-f"], &["--force"], &["--discard-changes"]],
+        unless: &[],
+        risk: Risk::High,
+        reason: "discards the uncommitted changes",
+    },
+    Rule {
+        subcommand: "branch",
+        args: &[&["-D"], &["-d", "-f"], &["--delete", "--force"]],
+        unless: &[],
+        risk: Risk::High,
+        reason: "deletes the branches even if they aren't merged, their commits are lost",
+    },
+    Rule {
+        subcommand: "stash",
+        args: &[&["clear"]],
+        unless: &[],
+        risk: Risk::High,
+        reason: "deletes every stash",
+    },
+    Rule {
+        subcommand: "stash",
+        args: &[&["drop"]],
+        unless: &[],
+        risk: Risk::Medium,
+        reason: "deletes a stash",
+    },
+    Rule {
+        subcommand: "gc",
+        args: &[&["--prune=now"]],
+        unless: &[],
+        risk: Risk::Medium,
+        reason: "deletes the unreachable commits at once, without waiting for them to expire",
+    },
+];
+
+/// A risky command of the catalogue recognized by its operands, the arguments that aren't
+/// options.
+struct OperandRule {
+    subcommand: &'static str,
+    /// Returns the operands making the command risky
+    operands: for<'a> fn(&[&'a str]) -> Option<Vec<&'a str>>,
+    /// The arguments making the command safe again
+    unless: &'static [&'static str],
+    risk: Risk,
+    reason: &'static str,
+}
+
+const OPERAND_CATALOGUE: &[OperandRule] = &[
+    OperandRule {
+        subcommand: "push",
+        operands: forced_refspecs,
+        unless: &["-n", "--dry-run"],
+        risk: Risk::High,
+        reason: "overwrites the remote branches, the commits only they have are lost",
+    },
+    OperandRule {
+        subcommand: "checkout",
+        operands: revision_and_paths,
+        unless: &["-b", "-B", "--orphan"],
+        risk: Risk::High,
+        reason: "discards the uncommitted changes of the files",
+    },
+];
+
+/// Returns the refspecs starting with `+` like `+main`, pushed even if it's not a fast-forward.
+fn forced_refspecs<'a>(operands: &[&'a str]) -> Option<Vec<&'a str>> {
+    let forced: Vec<&str> = operands
+        .iter()
+        .skip(1)
+        .filter(|operand| operand.starts_with('+'))
+        .copied()
+        .collect();
+
+    (!forced.is_empty()).then_some(forced)
+}
+
+/// Returns the operands of `checkout <revision> <path>…`, the paths given without `--`.
+fn revision_and_paths<'a>(operands: &[&'a str]) -> Option<Vec<&'a str>> {
+    (operands.len() > 1).then(|| operands.to_vec())
+}
+
+/// Why a command is risky.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Finding {
+    /// The subcommand and the arguments making it risky, like `reset --hard`
+    pub command: String,
+    pub risk: Risk,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} risk, {} {}", self.risk, self.command, self.reason)
+    }
+}
+
+/// Returns true if `arg` is `wanted` itself or, for a short option, a group of short options
+/// containing it like `-fdx` for `-f`. The subcommands like `clear` are matched as is.
+fn is_arg(arg: &str, wanted: &str) -> bool {
+    if arg == wanted {
+        return true;
+    }
+
+    match (wanted.strip_prefix('-'), arg.strip_prefix('-')) {
+        (Some(flag), Some(group)) if flag.len() == 1 && flag != "-" => {
+            !group.starts_with('-')
+                && group.chars().all(|c| c.is_ascii_alphabetic())
+                && group.contains(flag)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the entries of the catalogue `git_args` matches, the riskiest first. A command
+/// matching several entries for the same reason, like `push -f +main`, is reported once.
+///
+/// Only the arguments before `--` are looked at, the ones after it being paths, but `--` itself
+/// counts.
+pub fn check(git_args: &[&str]) -> Vec<Finding> {
+    let Some(index) = grep::subcommand_index(git_args) else {
+        return Vec::new();
+    };
+    let subcommand = git_args[index];
+    let args = &git_args[index + 1..];
+    let options = match args.iter().position(|arg| *arg == "--") {
+        Some(separator) => &args[..=separator],
+        None => args,
+    };
+    let has = |wanted: &str| options.iter().any(|arg| is_arg(arg, wanted));
+
+    let mut findings: Vec<Finding> = CATALOGUE
+        .iter()
+        .filter(|rule| rule.subcommand == subcommand)
+        .filter(|rule| !rule.unless.iter().any(|unless| has(unless)))
+        .filter_map(|rule| {
+            let combination = rule
+                .args
+                .iter()
+                .find(|combination| combination.iter().all(|wanted| has(wanted)))?;
+            let command = std::iter::once(subcommand)
+                .chain(combination.iter().copied())
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(Finding {
+                command,
+                risk: rule.risk,
+                reason: rule.reason,
+            })
+        })
+        .collect();
+
+    let operands: Vec<&str> = options
+        .iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .copied()
+        .collect();
+    for rule in OPERAND_CATALOGUE
+        .iter()
+        .filter(|rule| rule.subcommand == subcommand)
+        .filter(|rule| !rule.unless.iter().any(|unless| has(unless)))
+    {
+        if findings.iter().any(|finding| finding.reason == rule.reason) {
+            continue;
+        }
+        if let Some(matched) = (rule.operands)(&operands) {
+            let command = std::iter::once(subcommand)
+                .chain(matched)
+                .collect::<Vec<_>>()
+                .join(" ");
+            findings.push(Finding {
+                command,
+                risk: rule.risk,
+                reason: rule.reason,
+            });
+        }
+    }
+    findings.sort_by_key(|finding| Reverse(finding.risk));
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(git_args: &[&str]) -> Vec<String> {
+        check(git_args)
+            .into_iter()
+            .map(|finding| format!("{} {}", finding.risk, finding.command))
+            .collect()
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(vec!["high reset --hard"], commands(&["reset", "--hard"]));
+        assert_eq!(vec!["high clean -f"], commands(&["clean", "-fdx"]));
+        assert!(commands(&["clean", "-fdn"]).is_empty());
+        assert_eq!(
+            vec!["high push --force"],
+            commands(&["-c", "a.b=c", "push", "--force", "origin"])
+        );
+        assert_eq!(
+            vec!["medium push --force-with-lease"],
+            commands(&["push", "--force-with-lease"])
+        );
+        assert_eq!(
+            vec!["high checkout --"],
+            commands(&["checkout", "--", "src"])
+        );
+        assert_eq!(
+            vec!["high branch -d -f"],
+            commands(&["branch", "-df", "old"])
+        );
+        assert_eq!(vec!["high restore"], commands(&["restore", "README.md"]));
+        assert_eq!(vec!["high stash clear"], commands(&["stash", "clear"]));
+        assert_eq!(
+            vec!["high push +main"],
+            commands(&["push", "origin", "+main"])
+        );
+        assert_eq!(
+            vec!["high push +refs/heads/*:refs/heads/*"],
+            commands(&["push", "origin", "main", "+refs/heads/*:refs/heads/*"])
+        );
+        assert_eq!(
+            vec!["high push --force"],
+            commands(&["push", "--force", "origin", "+main"])
+        );
+        assert_eq!(
+            vec!["high checkout HEAD~1 src/main.rs"],
+            commands(&["checkout", "HEAD~1", "src/main.rs"])
+        );
+        assert_eq!(
+            vec!["high checkout ."],
+            commands(&["checkout", "main", "."])
+        );
+
+        assert!(commands(&["reset", "HEAD~1"]).is_empty());
+        assert!(commands(&["checkout", "main"]).is_empty());
+        assert!(commands(&["restore", "--staged", "README.md"]).is_empty());
+        assert!(commands(&["branch", "-d", "old"]).is_empty());
+        assert!(commands(&["push", "--dry-run", "--force"]).is_empty());
+        assert!(commands(&["push", "origin", "main"]).is_empty());
+        assert!(commands(&["push", "-n", "origin", "+main"]).is_empty());
+        // The remote itself can't be a forced refspec
+        assert!(commands(&["push", "+origin"]).is_empty());
+        assert!(commands(&["checkout", "-b", "fix", "origin/main"]).is_empty());
+        assert!(commands(&["checkout", "-q", "main"]).is_empty());
+        // After -- the arguments are paths
+        assert!(commands(&["log", "--", "--hard"]).is_empty());
+        assert!(commands(&["clean", "-n", "--", "-f"]).is_empty());
+        assert!(commands(&["status"]).is_empty());
+    }
+
+    #[test]
+    fn test_finding() {
+        let findings = check(&["reset", "--hard"]);
+        assert_eq!(
+            "high risk, reset --hard discards the uncommitted changes",
+            findings[0].to_string()
+        );
+    }
+}
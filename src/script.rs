@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+
+/// The commands of the file of --script, they run one after the other in each repository.
+pub struct Script {
+    /// The git arguments of every step, in the order of the file
+    pub steps: Vec<Vec<String>>,
+}
+
+impl Script {
+    /// Reads the file at `path`, the errors name the file and the line.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let input = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("unable to read {}: {}", path.display(), err))?;
+
+        Self::parse(&input).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+
+    /// Parses one step per line: the git arguments split like a shell does, with an optional
+    /// `git` before them. The empty lines and the ones starting with `#` are ignored.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut steps = Vec::new();
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some(mut args) = shlex::split(line) else {
+                return Err(anyhow!("{}: unbalanced quotes", index + 1));
+            };
+            if args.first().is_some_and(|arg| arg == "git") {
+                args.remove(0);
+            }
+            if args.is_empty() {
+                return Err(anyhow!("{}: no git arguments after git", index + 1));
+            }
+            steps.push(args);
+        }
+        if steps.is_empty() {
+            return Err(anyhow!("no command to run"));
+        }
+
+        Ok(Self { steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let script = Script::parse(
+            "# update everything\n\
+             fetch --prune\n\
+             \n\
+             git pull --ff-only\n\
+             config user.email 'me@{origin_host}'\n",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                vec!["fetch", "--prune"],
+                vec!["pull", "--ff-only"],
+                vec!["config", "user.email", "me@{origin_host}"],
+            ],
+            script.steps
+        );
+
+        let err = Script::parse("fetch\ncommit -m 'oops\n").err().unwrap();
+        assert_eq!("2: unbalanced quotes", err.to_string());
+        assert!(Script::parse("git\n").is_err());
+        assert!(Script::parse("# nothing\n\n").is_err());
+    }
+}
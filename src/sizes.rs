@@ -0,0 +1,179 @@
+//! Measure how much space a repository takes.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use anyhow::anyhow;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::git::Git;
+use crate::maintenance::git_dir_size;
+
+/// A blob of the history of a repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LargeObject {
+    /// The hash of the blob
+    pub hash: String,
+    /// The size of the blob in bytes, uncompressed
+    pub size: u64,
+    /// The first path the blob was found at
+    pub path: String,
+}
+
+/// The space a repository takes, in bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Sizes {
+    /// The git directory
+    pub git_dir: u64,
+    /// The files of the working tree, without the nested repositories
+    pub work_tree: u64,
+    /// Whether the .gitattributes file at the root of the working tree uses Git LFS
+    pub lfs: bool,
+    /// The largest blobs of the history, if they were asked for
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub largest: Vec<LargeObject>,
+}
+
+impl Sizes {
+    /// Returns the size of the git directory and the working tree together.
+    pub fn total(&self) -> u64 {
+        self.git_dir + self.work_tree
+    }
+}
+
+/// Measures the repository at `path`, along with its `largest` blobs if it's not 0.
+///
+/// Finding the largest blobs walks the whole history, it can take a while.
+pub fn measure(git: &Git, path: &Path, largest: usize) -> anyhow::Result<Sizes> {
+    let git_dir = git_dir_size(git, path)?;
+    let bare = git.stdout(path, &["rev-parse", "--is-bare-repository"]);
+    let work_tree = if bare.as_deref() == Some("true") {
+        0
+    } else {
+        work_tree_size(path)
+    };
+    let lfs = std::fs::read_to_string(path.join(".gitattributes"))
+        .is_ok_and(|attributes| attributes.contains("filter=lfs"));
+    let largest = if largest > 0 {
+        largest_objects(git, path, largest)?
+    } else {
+        Vec::new()
+    };
+
+    Ok(Sizes {
+        git_dir,
+        work_tree,
+        lfs,
+        largest,
+    })
+}
+
+/// Returns the size of the files under `path`, except the ones of the git directory and of the
+/// nested repositories.
+fn work_tree_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || (entry.file_name() != ".git" && !entry.path().join(".git").exists())
+        })
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Lists the `count` largest blobs reachable from any ref, largest first.
+fn largest_objects(git: &Git, path: &Path, count: usize) -> anyhow::Result<Vec<LargeObject>> {
+    let spawn_error =
+        |err: std::io::Error| anyhow!("unable to run {}: {}", git.program().display(), err);
+
+    let mut rev_list = git
+        .command(path)
+        .args(["rev-list", "--objects", "--all"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(spawn_error)?;
+    let objects = rev_list.stdout.take().expect("stdout is piped");
+
+    let output = git
+        .command(path)
+        .args([
+            "cat-file",
+            "--batch-check=%(objecttype) %(objectname) %(objectsize) %(rest)",
+        ])
+        .stdin(objects)
+        .output()
+        .map_err(spawn_error)?;
+    let status = rev_list.wait().map_err(spawn_error)?;
+    if !status.success() {
+        return Err(anyhow!("git rev-list --objects --all failed"));
+    }
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git cat-file --batch-check failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(parse_batch_check(
+        &String::from_utf8_lossy(&output.stdout),
+        count,
+    ))
+}
+
+/// Parses the type, hash, size and path of the objects printed by `git cat-file --batch-check`
+/// into the `count` largest blobs.
+fn parse_batch_check(output: &str, count: usize) -> Vec<LargeObject> {
+    let mut blobs: Vec<LargeObject> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, ' ');
+            if fields.next()? != "blob" {
+                return None;
+            }
+            Some(LargeObject {
+                hash: fields.next()?.to_string(),
+                size: fields.next()?.parse().ok()?,
+                path: fields.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect();
+    blobs.sort_by(|a, b| b.size.cmp(&a.size).then(a.path.cmp(&b.path)));
+    blobs.truncate(count);
+
+    blobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch_check() {
+        let output = "commit 3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a 230 \n\
+                      tree 1b2a3f4e5d6c7b8a3f2c1a9d0b7e4c5a6f8e9d0c 98 \n\
+                      blob 5d6c7b8a3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e 1024 README.md\n\
+                      blob 0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a3f2c1a9d 52428800 assets/video one.mp4\n\
+                      blob 9d0c1b2a3f4e5d6c7b8a3f2c1a9d0b7e4c5a6f8e 12 .gitignore\n";
+
+        assert_eq!(
+            vec![
+                LargeObject {
+                    hash: "0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a3f2c1a9d".to_string(),
+                    size: 52428800,
+                    path: "assets/video one.mp4".to_string(),
+                },
+                LargeObject {
+                    hash: "5d6c7b8a3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e".to_string(),
+                    size: 1024,
+                    path: "README.md".to_string(),
+                },
+            ],
+            parse_batch_check(output, 2)
+        );
+    }
+}
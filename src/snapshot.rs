@@ -0,0 +1,158 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::anyhow;
+use gitjuggling::RepoStatus;
+use serde::{Deserialize, Serialize};
+
+/// The snapshots older than this are removed when another one is saved.
+pub const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The status of every repository at some point, saved with `gitjuggling status --save`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// When the snapshot was taken, in RFC 3339
+    pub time: String,
+    pub repositories: Vec<Entry>,
+}
+
+/// The status of a repository in a [`Snapshot`], or why it couldn't be probed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<RepoStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Entry {
+    pub fn new(path: PathBuf, status: Result<RepoStatus, String>) -> Self {
+        let (status, error) = match status {
+            Ok(status) => (Some(status), None),
+            Err(err) => (None, Some(err)),
+        };
+        Self {
+            path,
+            status,
+            error,
+        }
+    }
+
+    pub fn status(&self) -> Result<RepoStatus, String> {
+        match (&self.status, &self.error) {
+            (Some(status), _) => Ok(status.clone()),
+            (None, error) => Err(error.clone().unwrap_or_default()),
+        }
+    }
+}
+
+/// Returns the directory of the snapshots, in $XDG_DATA_HOME or ~/.local/share.
+pub fn snapshots_dir() -> Option<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+
+    Some(data_home.join("gitjuggling").join("snapshots"))
+}
+
+/// Returns the path of the snapshot `name` in `dir`, the name must be usable as a file name.
+fn snapshot_path(dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(anyhow!(
+            "invalid snapshot name {:?}, it must be a file name not starting with a dot",
+            name
+        ));
+    }
+
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+/// Loads the snapshot `name` in `dir`.
+pub fn load(dir: &Path, name: &str) -> anyhow::Result<Snapshot> {
+    let path = snapshot_path(dir, name)?;
+    let contents = std::fs::read_to_string(&path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => anyhow!("no snapshot {}, save it with --save", name),
+        _ => anyhow!("unable to read {}: {}", path.display(), err),
+    })?;
+
+    serde_json::from_str(&contents)
+        .map_err(|err| anyhow!("unable to parse {}: {}", path.display(), err))
+}
+
+/// Saves `snapshot` as `name` in `dir`, replacing the previous one. The snapshots older than
+/// [`MAX_AGE`] are removed.
+pub fn save(dir: &Path, name: &str, snapshot: &Snapshot) -> anyhow::Result<()> {
+    let path = snapshot_path(dir, name)?;
+    std::fs::create_dir_all(dir)
+        .map_err(|err| anyhow!("unable to create {}: {}", dir.display(), err))?;
+    std::fs::write(&path, serde_json::to_string(snapshot).unwrap())
+        .map_err(|err| anyhow!("unable to write {}: {}", path.display(), err))?;
+
+    prune(dir, SystemTime::now() - MAX_AGE);
+    Ok(())
+}
+
+/// Removes the snapshots in `dir` last saved before `before`, the errors are ignored.
+fn prune(dir: &Path, before: SystemTime) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let old = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified < before);
+        if old
+            && path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("snapshots");
+        let snapshot = Snapshot {
+            time: "2024-06-01T08:00:00Z".to_string(),
+            repositories: vec![
+                Entry::new(
+                    PathBuf::from("/src/foo"),
+                    Ok(RepoStatus {
+                        branch: "main".to_string(),
+                        modified: 1,
+                        ..RepoStatus::default()
+                    }),
+                ),
+                Entry::new(PathBuf::from("/src/bar"), Err("bad object".to_string())),
+            ],
+        };
+
+        assert!(load(&dir, "morning").is_err());
+        save(&dir, "morning", &snapshot).unwrap();
+        let loaded = load(&dir, "morning").unwrap();
+        assert_eq!(snapshot, loaded);
+        assert_eq!(1, loaded.repositories[0].status().unwrap().modified);
+        assert_eq!(
+            Err("bad object".to_string()),
+            loaded.repositories[1].status()
+        );
+
+        assert!(save(&dir, "../morning", &snapshot).is_err());
+        assert!(save(&dir, ".hidden", &snapshot).is_err());
+
+        prune(&dir, SystemTime::now() + Duration::from_secs(60));
+        assert!(load(&dir, "morning").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use gitjuggling::DiscoverProgress;
+
+const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// Nothing is drawn before this, a discovery that fast doesn't need a spinner.
+const DELAY: Duration = Duration::from_millis(200);
+/// The spinner is redrawn at most this often, whatever the number of directories visited.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// A spinner with the number of directories visited and repositories found, drawn on a single
+/// line while the repositories are discovered and erased once they are.
+pub struct Spinner<W: Write> {
+    writer: W,
+    start: Instant,
+    last: Option<Instant>,
+    frame: usize,
+}
+
+impl<W: Write> Spinner<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            start: Instant::now(),
+            last: None,
+            frame: 0,
+        }
+    }
+
+    pub fn update(&mut self, progress: DiscoverProgress) {
+        self.update_at(progress, Instant::now());
+    }
+
+    fn update_at(&mut self, progress: DiscoverProgress, now: Instant) {
+        if now.duration_since(self.start) < DELAY {
+            return;
+        }
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < INTERVAL)
+        {
+            return;
+        }
+        self.last = Some(now);
+
+        let _ = write!(
+            self.writer,
+            "\r\x1b[2K{} discovering: {} {}, {} {}",
+            FRAMES[self.frame % FRAMES.len()],
+            progress.directories,
+            if progress.directories == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+            progress.repositories,
+            if progress.repositories == 1 {
+                "repository"
+            } else {
+                "repositories"
+            },
+        );
+        let _ = self.writer.flush();
+        self.frame += 1;
+    }
+
+    /// Erases the spinner if it was drawn, the output can start.
+    pub fn finish(mut self) {
+        if self.last.is_some() {
+            let _ = write!(self.writer, "\r\x1b[2K");
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spinner() {
+        let mut output = Vec::new();
+        let mut spinner = Spinner::new(&mut output);
+        let start = spinner.start;
+        let progress = |directories| DiscoverProgress {
+            directories,
+            repositories: 1,
+        };
+
+        spinner.update_at(progress(10), start + Duration::from_millis(100));
+        spinner.update_at(progress(20), start + Duration::from_millis(300));
+        // Too soon after the last one
+        spinner.update_at(progress(30), start + Duration::from_millis(400));
+        spinner.update_at(progress(40), start + Duration::from_millis(600));
+        spinner.finish();
+
+        assert_eq!(
+            "\r\x1b[2K| discovering: 20 directories, 1 repository\
+             \r\x1b[2K/ discovering: 40 directories, 1 repository\
+             \r\x1b[2K",
+            String::from_utf8(output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_spinner_not_drawn() {
+        let mut output = Vec::new();
+        let spinner = Spinner::new(&mut output);
+        spinner.finish();
+
+        assert!(output.is_empty());
+    }
+}
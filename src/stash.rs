@@ -0,0 +1,272 @@
+//! Stash the changes of the dirty repositories and restore exactly these stashes later.
+
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::git::Git;
+use crate::status::RepoStatus;
+
+/// The message of the stashes created by [`stash`].
+pub const MESSAGE: &str = "gitjuggling stash-all";
+
+/// The stashes created by `stash-all`, kept in a file until `unstash-all` restores them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StashState {
+    /// The stashes, sorted by path
+    #[serde(default, rename = "stash")]
+    pub stashes: Vec<Stash>,
+}
+
+/// A stash recorded in a [`StashState`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stash {
+    /// The absolute path of the repository
+    pub path: PathBuf,
+    /// The commit ID of the stash
+    pub id: String,
+}
+
+impl StashState {
+    /// Loads the state file at `path`, a missing file records no stashes.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(input) => toml::from_str(&input)
+                .map_err(|err| anyhow!("unable to parse {}: {}", path.display(), err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(anyhow!("unable to read {}: {}", path.display(), err)),
+        }
+    }
+
+    /// Writes the state file at `path`, or removes it if there are no stashes left.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if self.stashes.is_empty() {
+            return match std::fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(anyhow!("unable to remove {}: {}", path.display(), err))
+                }
+                _ => Ok(()),
+            };
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|err| anyhow!("unable to create {}: {}", dir.display(), err))?;
+        }
+        std::fs::write(path, toml::to_string(self).unwrap())
+            .map_err(|err| anyhow!("unable to write {}: {}", path.display(), err))
+    }
+}
+
+/// Returns the path of the default state file, in $XDG_STATE_HOME or ~/.local/state.
+pub fn state_path() -> Option<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+
+    Some(state_home.join("gitjuggling").join("stashes.toml"))
+}
+
+/// What stashing a repository did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StashOutcome {
+    /// The changes and the untracked files were stashed
+    Stashed {
+        /// The commit ID of the stash
+        id: String,
+    },
+    /// There was nothing to stash
+    Clean,
+    /// A stash of the repository is already recorded, it wasn't stashed again
+    AlreadyStashed {
+        /// The commit ID of the recorded stash
+        id: String,
+    },
+    /// A git command failed
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl StashOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            StashOutcome::Stashed { .. } => "stashed",
+            StashOutcome::Clean => "clean",
+            StashOutcome::AlreadyStashed { .. } => "already stashed",
+            StashOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for StashOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StashOutcome::Stashed { id } => write!(f, "stashed as {}", short_id(id)),
+            StashOutcome::AlreadyStashed { id } => write!(
+                f,
+                "already stashed as {}, run unstash-all first",
+                short_id(id)
+            ),
+            StashOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+/// What restoring a recorded stash did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnstashOutcome {
+    /// The stash was popped
+    Restored,
+    /// The stash at the top isn't the recorded one, nothing was popped
+    Mismatch {
+        /// The commit ID of the stash at the top, if there's one
+        top: Option<String>,
+    },
+    /// A git command failed, like the pop conflicting with the working tree
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl UnstashOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            UnstashOutcome::Restored => "restored",
+            UnstashOutcome::Mismatch { .. } => "mismatch",
+            UnstashOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for UnstashOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnstashOutcome::Mismatch { top: Some(top) } => write!(
+                f,
+                "the stash at the top is {}, not the recorded one",
+                short_id(top)
+            ),
+            UnstashOutcome::Mismatch { top: None } => {
+                f.write_str("the recorded stash is gone, there are no stashes")
+            }
+            UnstashOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..7).unwrap_or(id)
+}
+
+fn top_stash(git: &Git, path: &Path) -> Option<String> {
+    git.stdout(path, &["rev-parse", "--quiet", "--verify", "refs/stash"])
+}
+
+/// Stashes the changes and the untracked files of the repository at `path`, if there are any.
+pub fn stash(git: &Git, path: &Path) -> StashOutcome {
+    match RepoStatus::probe(git, path) {
+        Ok(status) if status.is_dirty() => {}
+        Ok(_) => return StashOutcome::Clean,
+        Err(err) => {
+            return StashOutcome::Failed {
+                error: err.to_string(),
+            }
+        }
+    }
+
+    let before = top_stash(git, path);
+    let output = git
+        .command(path)
+        .args([
+            "stash",
+            "push",
+            "--include-untracked",
+            "--quiet",
+            "--message",
+            MESSAGE,
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            return StashOutcome::Failed {
+                error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            }
+        }
+        Err(err) => {
+            return StashOutcome::Failed {
+                error: err.to_string(),
+            }
+        }
+    }
+
+    // git stash succeeds without creating a stash if only the submodules changed
+    match top_stash(git, path) {
+        Some(id) if Some(&id) != before.as_ref() => StashOutcome::Stashed { id },
+        _ => StashOutcome::Clean,
+    }
+}
+
+/// Pops the stash `id` of the repository at `path`, only if it's at the top of its stashes.
+pub fn unstash(git: &Git, path: &Path, id: &str) -> UnstashOutcome {
+    if !path.is_dir() {
+        return UnstashOutcome::Failed {
+            error: format!("{} doesn't exist anymore", path.display()),
+        };
+    }
+
+    match top_stash(git, path) {
+        Some(top) if top == id => {}
+        top => return UnstashOutcome::Mismatch { top },
+    }
+
+    let output = git
+        .command(path)
+        .args(["stash", "pop", "--index", "--quiet"])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => UnstashOutcome::Restored,
+        Ok(output) => UnstashOutcome::Failed {
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        },
+        Err(err) => UnstashOutcome::Failed {
+            error: err.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state/stashes.toml");
+
+        assert_eq!(StashState::default(), StashState::load(&path).unwrap());
+
+        let state = StashState {
+            stashes: vec![Stash {
+                path: PathBuf::from("/src/foo"),
+                id: "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a".to_string(),
+            }],
+        };
+        state.save(&path).unwrap();
+        assert_eq!(state, StashState::load(&path).unwrap());
+
+        StashState::default().save(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
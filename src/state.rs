@@ -0,0 +1,510 @@
+//! Classify the state of a repository before running a command in it.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::git::Git;
+
+/// The state a repository is in, a command like `git pull` only makes sense in some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RepoState {
+    /// HEAD is on a branch with commits
+    OnBranch,
+    /// HEAD points to a commit rather than a branch
+    Detached,
+    /// HEAD is on a branch without commits yet, like in an empty repository
+    Unborn,
+    /// A rebase is in progress
+    Rebasing,
+    /// A mailbox is being applied with `git am`
+    Applying,
+    /// A merge is in progress
+    Merging,
+    /// A cherry-pick is in progress
+    CherryPicking,
+    /// A revert is in progress
+    Reverting,
+    /// A bisection is in progress
+    Bisecting,
+}
+
+impl RepoState {
+    /// Every state, in the order they're checked.
+    pub const ALL: [RepoState; 9] = [
+        RepoState::Rebasing,
+        RepoState::Applying,
+        RepoState::Merging,
+        RepoState::CherryPicking,
+        RepoState::Reverting,
+        RepoState::Bisecting,
+        RepoState::Unborn,
+        RepoState::Detached,
+        RepoState::OnBranch,
+    ];
+
+    /// Returns the name of the state, as accepted by [`RepoState::from_str`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepoState::OnBranch => "on-branch",
+            RepoState::Detached => "detached",
+            RepoState::Unborn => "unborn",
+            RepoState::Rebasing => "rebasing",
+            RepoState::Applying => "applying",
+            RepoState::Merging => "merging",
+            RepoState::CherryPicking => "cherry-picking",
+            RepoState::Reverting => "reverting",
+            RepoState::Bisecting => "bisecting",
+        }
+    }
+
+    /// Returns true if an operation like a rebase or a merge is in progress.
+    pub fn is_in_progress(&self) -> bool {
+        !matches!(
+            self,
+            RepoState::OnBranch | RepoState::Detached | RepoState::Unborn
+        )
+    }
+}
+
+impl fmt::Display for RepoState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RepoState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RepoState::ALL
+            .into_iter()
+            .find(|state| state.name() == s)
+            .ok_or_else(|| anyhow!("unknown state {}", s))
+    }
+}
+
+/// The git subcommands that don't change the repository, running them during an operation in
+/// progress can't make it worse.
+const READ_ONLY_COMMANDS: &[&str] = &[
+    "blame",
+    "cat-file",
+    "check-ignore",
+    "cherry",
+    "count-objects",
+    "describe",
+    "diff",
+    "for-each-ref",
+    "fsck",
+    "grep",
+    "help",
+    "log",
+    "ls-files",
+    "ls-remote",
+    "ls-tree",
+    "name-rev",
+    "range-diff",
+    "rev-list",
+    "rev-parse",
+    "shortlog",
+    "show",
+    "show-ref",
+    "status",
+    "var",
+    "version",
+    "whatchanged",
+];
+
+/// Returns true if the git subcommand `subcommand` only reads the repository, like status or log.
+///
+/// Aliases and unknown commands might write, they aren't read-only.
+pub fn is_read_only(subcommand: &str) -> bool {
+    READ_ONLY_COMMANDS.contains(&subcommand)
+}
+
+/// Returns the git directory of the repository at `path` without running git: its `.git`
+/// directory, the one a `.git` file points to for a worktree or a submodule, or `path` itself
+/// for a bare repository.
+pub fn git_dir(path: &Path) -> Option<PathBuf> {
+    let dot_git = path.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    if let Ok(contents) = std::fs::read_to_string(&dot_git) {
+        let dir = contents.strip_prefix("gitdir:")?.trim();
+        return Some(path.join(dir));
+    }
+
+    path.join("HEAD").is_file().then(|| path.to_path_buf())
+}
+
+/// Returns the repository `path` is in without running git: `path` itself if it's a repository,
+/// otherwise the closest parent directory that is.
+pub fn enclosing_repository(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| git_dir(dir).is_some())
+        .map(Path::to_path_buf)
+}
+
+/// Returns the operation in progress in the git directory `git_dir`, if any.
+///
+/// Only the files git leaves behind are looked at, it's cheap enough to do before every command.
+pub fn in_progress(git_dir: &Path) -> Option<RepoState> {
+    if git_dir.join("rebase-merge").is_dir() {
+        return Some(RepoState::Rebasing);
+    }
+    if git_dir.join("rebase-apply").is_dir() {
+        // git am and the apply backend of git rebase share the directory
+        return Some(if git_dir.join("rebase-apply/applying").exists() {
+            RepoState::Applying
+        } else {
+            RepoState::Rebasing
+        });
+    }
+
+    [
+        ("MERGE_HEAD", RepoState::Merging),
+        ("CHERRY_PICK_HEAD", RepoState::CherryPicking),
+        ("REVERT_HEAD", RepoState::Reverting),
+        ("BISECT_LOG", RepoState::Bisecting),
+    ]
+    .into_iter()
+    .find(|(file, _)| git_dir.join(file).exists())
+    .map(|(_, state)| state)
+}
+
+/// Returns why the git directory `git_dir` can't be written to, like a read-only filesystem:
+/// a command writing to the repository would fail there. None if it can be written to.
+///
+/// Like [`in_progress`] it's cheap, nothing is written. On Unix access(2) is asked, elsewhere the
+/// permissions of the directory are looked at. A directory that can't be probed is writable.
+pub fn not_writable(git_dir: &Path) -> Option<&'static str> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(git_dir.as_os_str().as_bytes()).ok()?;
+        // SAFETY: the path is a valid C string, access only reads it
+        if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+            return None;
+        }
+        not_writable_reason(std::io::Error::last_os_error().raw_os_error()?)
+    }
+    #[cfg(not(unix))]
+    {
+        let metadata = std::fs::metadata(git_dir).ok()?;
+        metadata
+            .permissions()
+            .readonly()
+            .then_some("not writable (read-only directory)")
+    }
+}
+
+/// Returns why a directory access(2) failed on with `errno` can't be written to.
+#[cfg(unix)]
+fn not_writable_reason(errno: i32) -> Option<&'static str> {
+    match errno {
+        libc::EROFS => Some("read-only filesystem"),
+        libc::EACCES => Some("not writable (permission denied)"),
+        _ => None,
+    }
+}
+
+/// Returns true if HEAD in the git directory `git_dir` points to a branch that doesn't exist yet,
+/// like in a repository just initialized.
+///
+/// Like [`in_progress`] only files are looked at: the branch is neither a loose ref nor in
+/// `packed-refs`. A worktree finds its refs in the common git directory.
+pub fn is_unborn(git_dir: &Path) -> bool {
+    let Ok(head) = std::fs::read_to_string(git_dir.join("HEAD")) else {
+        return false;
+    };
+    let Some(branch) = head.trim().strip_prefix("ref: ") else {
+        return false;
+    };
+    // With the reftable backend HEAD points to this placeholder and the refs aren't files
+    if branch == "refs/heads/.invalid" {
+        return false;
+    }
+
+    let common_dir = common_dir(git_dir);
+    if git_dir.join(branch).is_file() || common_dir.join(branch).is_file() {
+        return false;
+    }
+    let packed = std::fs::read_to_string(common_dir.join("packed-refs")).unwrap_or_default();
+
+    !packed
+        .lines()
+        .any(|line| line.split_once(' ').is_some_and(|(_, name)| name == branch))
+}
+
+/// Returns why HEAD in the git directory `git_dir` can't be resolved, if it can't: it's neither a
+/// commit nor a ref, or it points to a branch that doesn't exist while others do, like after the
+/// branch was deleted from under it. An orphan branch just created looks the same.
+///
+/// Like [`is_unborn`] only files are looked at, a repository without any branch is unborn rather
+/// than broken.
+pub fn broken_head(git_dir: &Path) -> Option<String> {
+    let head = match std::fs::read_to_string(git_dir.join("HEAD")) {
+        Ok(head) => head,
+        Err(err) => return Some(format!("unable to read HEAD: {}", err)),
+    };
+    let Some(branch) = head.trim().strip_prefix("ref: ") else {
+        return (!is_object_id(head.trim()))
+            .then(|| "HEAD is neither a commit nor a ref".to_string());
+    };
+    if branch == "refs/heads/.invalid" {
+        return None;
+    }
+
+    let common_dir = common_dir(git_dir);
+    let loose = [git_dir.join(branch), common_dir.join(branch)]
+        .into_iter()
+        .find(|path| path.is_file());
+    if let Some(loose) = loose {
+        let target = std::fs::read_to_string(&loose).unwrap_or_default();
+        let target = target.trim();
+        return (!is_object_id(target) && !target.starts_with("ref: ")).then(|| {
+            format!(
+                "HEAD points to {}, which is neither a commit nor a ref",
+                branch
+            )
+        });
+    }
+
+    let packed = std::fs::read_to_string(common_dir.join("packed-refs")).unwrap_or_default();
+    let mut packed_branches = packed
+        .lines()
+        .filter_map(|line| line.split_once(' ').map(|(_, name)| name))
+        .filter(|name| name.starts_with("refs/heads/"));
+    if packed_branches.clone().any(|name| name == branch) {
+        return None;
+    }
+
+    (packed_branches.next().is_some() || has_file(&common_dir.join("refs/heads")))
+        .then(|| format!("HEAD points to {}, which doesn't exist", branch))
+}
+
+/// Returns the common git directory of the git directory `git_dir`, the one of the main worktree
+/// for a linked worktree.
+fn common_dir(git_dir: &Path) -> PathBuf {
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(dir) => git_dir.join(dir.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Returns true if `value` is a SHA-1 or SHA-256 object ID.
+fn is_object_id(value: &str) -> bool {
+    matches!(value.len(), 40 | 64) && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Returns true if there's a file in `dir` or one of its subdirectories.
+fn has_file(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        path.is_file() || (path.is_dir() && has_file(&path))
+    })
+}
+
+/// Classifies the repository at `path`. An operation in progress wins over where HEAD is: HEAD
+/// is detached during most rebases.
+pub fn probe(git: &Git, path: &Path) -> anyhow::Result<RepoState> {
+    let git_dir = match git_dir(path) {
+        Some(git_dir) => git_dir,
+        None => git
+            .stdout(path, &["rev-parse", "--absolute-git-dir"])
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("{} isn't a git repository", path.display()))?,
+    };
+    if let Some(state) = in_progress(&git_dir) {
+        return Ok(state);
+    }
+
+    if git
+        .stdout(path, &["symbolic-ref", "--quiet", "HEAD"])
+        .is_none()
+    {
+        return Ok(RepoState::Detached);
+    }
+    if git
+        .stdout(path, &["rev-parse", "--quiet", "--verify", "HEAD"])
+        .is_none()
+    {
+        return Ok(RepoState::Unborn);
+    }
+
+    Ok(RepoState::OnBranch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        for state in RepoState::ALL {
+            assert_eq!(state, state.name().parse().unwrap());
+        }
+        assert!("rebase".parse::<RepoState>().is_err());
+    }
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("status"));
+        assert!(is_read_only("log"));
+        assert!(!is_read_only("pull"));
+        assert!(!is_read_only("st"));
+    }
+
+    #[test]
+    fn test_enclosing_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src/bin")).unwrap();
+
+        assert_eq!(Some(repo.clone()), enclosing_repository(&repo));
+        assert_eq!(
+            Some(repo.clone()),
+            enclosing_repository(&repo.join("src/bin"))
+        );
+        assert_eq!(None, enclosing_repository(dir.path()));
+    }
+
+    #[test]
+    fn test_not_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(None, not_writable(dir.path()));
+        assert_eq!(None, not_writable(&dir.path().join("missing")));
+
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                Some("read-only filesystem"),
+                not_writable_reason(libc::EROFS)
+            );
+            assert_eq!(
+                Some("not writable (permission denied)"),
+                not_writable_reason(libc::EACCES)
+            );
+            assert_eq!(None, not_writable_reason(libc::ENOENT));
+        }
+
+        // root can write anyway
+        #[cfg(unix)]
+        if unsafe { libc::geteuid() } != 0 {
+            use std::os::unix::fs::PermissionsExt;
+
+            let read_only = dir.path().join("read-only");
+            std::fs::create_dir(&read_only).unwrap();
+            std::fs::set_permissions(&read_only, std::fs::Permissions::from_mode(0o555)).unwrap();
+            assert_eq!(
+                Some("not writable (permission denied)"),
+                not_writable(&read_only)
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(None, in_progress(dir.path()));
+
+        std::fs::write(dir.path().join("BISECT_LOG"), "").unwrap();
+        assert_eq!(Some(RepoState::Bisecting), in_progress(dir.path()));
+
+        std::fs::create_dir(dir.path().join("rebase-apply")).unwrap();
+        assert_eq!(Some(RepoState::Rebasing), in_progress(dir.path()));
+        std::fs::write(dir.path().join("rebase-apply/applying"), "").unwrap();
+        assert_eq!(Some(RepoState::Applying), in_progress(dir.path()));
+    }
+
+    #[test]
+    fn test_is_unborn() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_unborn(dir.path()));
+
+        std::fs::write(dir.path().join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        assert!(is_unborn(dir.path()));
+
+        std::fs::write(
+            dir.path().join("packed-refs"),
+            "# pack-refs with: peeled fully-peeled sorted\n\
+             3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a refs/heads/main\n",
+        )
+        .unwrap();
+        assert!(!is_unborn(dir.path()));
+
+        std::fs::write(dir.path().join("HEAD"), "ref: refs/heads/dev\n").unwrap();
+        assert!(is_unborn(dir.path()));
+        std::fs::create_dir_all(dir.path().join("refs/heads")).unwrap();
+        std::fs::write(
+            dir.path().join("refs/heads/dev"),
+            "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a\n",
+        )
+        .unwrap();
+        assert!(!is_unborn(dir.path()));
+
+        std::fs::write(
+            dir.path().join("HEAD"),
+            "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a\n",
+        )
+        .unwrap();
+        assert!(!is_unborn(dir.path()));
+    }
+
+    #[test]
+    fn test_broken_head() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(broken_head(dir.path())
+            .unwrap()
+            .starts_with("unable to read HEAD"));
+
+        // A repository just initialized isn't broken
+        std::fs::write(dir.path().join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        assert_eq!(None, broken_head(dir.path()));
+
+        std::fs::create_dir_all(dir.path().join("refs/heads/feature")).unwrap();
+        std::fs::write(
+            dir.path().join("refs/heads/feature/dev"),
+            "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Some("HEAD points to refs/heads/main, which doesn't exist".to_string()),
+            broken_head(dir.path())
+        );
+
+        std::fs::write(
+            dir.path().join("packed-refs"),
+            "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a refs/heads/main\n",
+        )
+        .unwrap();
+        assert_eq!(None, broken_head(dir.path()));
+
+        std::fs::write(dir.path().join("HEAD"), "ref: refs/heads/feature/dev\n").unwrap();
+        assert_eq!(None, broken_head(dir.path()));
+        std::fs::write(dir.path().join("refs/heads/feature/dev"), "garbage\n").unwrap();
+        assert_eq!(
+            Some(
+                "HEAD points to refs/heads/feature/dev, which is neither a commit nor a ref"
+                    .to_string()
+            ),
+            broken_head(dir.path())
+        );
+
+        std::fs::write(dir.path().join("HEAD"), "3f2c1a9d\n").unwrap();
+        assert_eq!(
+            Some("HEAD is neither a commit nor a ref".to_string()),
+            broken_head(dir.path())
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt::Write as FmtWrite;
+use std::time::Duration;
+
+use gitjuggling::maintenance::format_size;
+use gitjuggling::transfer::Transfer;
+
+use crate::output::format_duration;
+
+const HISTOGRAM_BUCKETS: usize = 10;
+const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Aggregated statistics of a run.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub wall_time: Duration,
+    pub cumulative: Duration,
+    pub average: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    pub with_output: usize,
+    /// Number of repositories per duration bucket, the buckets evenly divide `min..=max`
+    pub histogram: Vec<usize>,
+    /// What the network commands transferred, if any ran
+    pub transferred: Option<Transferred>,
+}
+
+/// The sum of what the network commands of a run transferred.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Transferred {
+    /// The sum of the transfers git wrote
+    pub total: Transfer,
+    /// How many repositories git wrote a transfer for
+    pub known: usize,
+    /// How many repositories ran a network command without git writing its transfer
+    pub unknown: usize,
+}
+
+impl Transferred {
+    /// Sums the `transfers` of the repositories that ran a network command, None for the ones
+    /// git wrote nothing for.
+    pub fn sum(transfers: &[Option<&Transfer>]) -> Self {
+        let mut total = Transfer::default();
+        for transfer in transfers.iter().flatten() {
+            total.add(transfer);
+        }
+        let known = transfers.iter().flatten().count();
+
+        Self {
+            total,
+            known,
+            unknown: transfers.len() - known,
+        }
+    }
+
+    fn render(&self) -> String {
+        if self.known == 0 {
+            return format!("unknown in {} repositories", self.unknown);
+        }
+
+        let mut output = match self.total.bytes {
+            Some(bytes) => format!("{}, {} objects", format_size(bytes), self.total.objects),
+            None => format!("{} objects", self.total.objects),
+        };
+        if self.unknown > 0 {
+            write!(&mut output, ", unknown in {} repositories", self.unknown).unwrap();
+        }
+
+        output
+    }
+}
+
+impl Stats {
+    pub fn compute(wall_time: Duration, durations: &[Duration], with_output: usize) -> Self {
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+
+        let cumulative: Duration = sorted.iter().sum();
+        let average = if sorted.is_empty() {
+            Duration::ZERO
+        } else {
+            cumulative / sorted.len() as u32
+        };
+
+        let min = sorted.first().copied().unwrap_or_default();
+        let max = sorted.last().copied().unwrap_or_default();
+
+        let mut histogram = vec![0; HISTOGRAM_BUCKETS];
+        if !sorted.is_empty() {
+            let range = (max - min).as_secs_f64();
+            for duration in &sorted {
+                let bucket = if range == 0.0 {
+                    0
+                } else {
+                    let position = (*duration - min).as_secs_f64() / range;
+                    ((position * HISTOGRAM_BUCKETS as f64) as usize).min(HISTOGRAM_BUCKETS - 1)
+                };
+                histogram[bucket] += 1;
+            }
+        }
+
+        Self {
+            wall_time,
+            cumulative,
+            average,
+            median: percentile(&sorted, 50),
+            p95: percentile(&sorted, 95),
+            min,
+            max,
+            with_output,
+            histogram,
+            transferred: None,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        let lines = [
+            ("Wall time:  ", format_duration(self.wall_time)),
+            ("Cumulative: ", format_duration(self.cumulative)),
+            ("Average:    ", format_duration(self.average)),
+            ("Median:     ", format_duration(self.median)),
+            ("p95:        ", format_duration(self.p95)),
+            ("With output:", self.with_output.to_string()),
+            (
+                "Histogram:  ",
+                format!(
+                    "{} ({} … {})",
+                    self.sparkline(),
+                    format_duration(self.min),
+                    format_duration(self.max)
+                ),
+            ),
+        ];
+        for (label, value) in lines {
+            writeln!(&mut output, "{} {}", label, value).unwrap();
+        }
+        if let Some(transferred) = &self.transferred {
+            writeln!(&mut output, "Transferred: {}", transferred.render()).unwrap();
+        }
+
+        output
+    }
+
+    fn sparkline(&self) -> String {
+        let highest = self.histogram.iter().copied().max().unwrap_or(0);
+
+        self.histogram
+            .iter()
+            .map(|&count| {
+                if count == 0 || highest == 0 {
+                    ' '
+                } else {
+                    BLOCKS[count * (BLOCKS.len() - 1) / highest]
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the nearest-rank percentile of `sorted`.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute() {
+        let durations: Vec<Duration> = (1..=20).map(Duration::from_millis).collect();
+
+        let stats = Stats::compute(Duration::from_millis(30), &durations, 3);
+
+        assert_eq!(Duration::from_millis(210), stats.cumulative);
+        assert_eq!(Duration::from_micros(10500), stats.average);
+        assert_eq!(Duration::from_millis(10), stats.median);
+        assert_eq!(Duration::from_millis(19), stats.p95);
+        assert_eq!(vec![2, 2, 2, 2, 2, 2, 2, 2, 2, 2], stats.histogram);
+        assert_eq!("██████████", stats.sparkline());
+    }
+
+    #[test]
+    fn test_transferred() {
+        let fetched = Transfer {
+            objects: 12,
+            bytes: Some(1536),
+            deltas: Some(2),
+        };
+        let pushed = Transfer {
+            objects: 3,
+            bytes: Some(280),
+            deltas: None,
+        };
+
+        let transferred = Transferred::sum(&[Some(&fetched), None, Some(&pushed), None]);
+        assert_eq!(15, transferred.total.objects);
+        assert_eq!(
+            "1.8 KiB, 15 objects, unknown in 2 repositories",
+            transferred.render()
+        );
+        assert_eq!(
+            "unknown in 2 repositories",
+            Transferred::sum(&[None, None]).render()
+        );
+    }
+}
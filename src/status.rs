@@ -0,0 +1,157 @@
+//! Summarize the working tree and branch of a repository.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::git::Git;
+
+/// The state of a repository as reported by `git status --porcelain=v2 --branch`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoStatus {
+    /// The current branch, or a detached HEAD marker with the abbreviated commit
+    pub branch: String,
+    /// The commit ID of HEAD, if there's a commit
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// The upstream of the current branch, if it has one
+    pub upstream: Option<String>,
+    /// How many commits the current branch is ahead of its upstream
+    pub ahead: Option<usize>,
+    /// How many commits the current branch is behind its upstream
+    pub behind: Option<usize>,
+    /// The number of staged, modified or conflicted files
+    pub modified: usize,
+    /// The number of untracked files
+    pub untracked: usize,
+    /// Whether the current branch has no commits yet, like in a repository just initialized
+    pub unborn: bool,
+}
+
+impl RepoStatus {
+    /// Runs `git status` in the repository at `path` and parses its output.
+    pub fn probe(git: &Git, path: &Path) -> anyhow::Result<Self> {
+        let output = git
+            .command(path)
+            .args(["status", "--porcelain=v2", "--branch"])
+            .output()
+            .map_err(|err| anyhow!("unable to run {}: {}", git.program().display(), err))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git status failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parses the output of `git status --porcelain=v2 --branch`.
+    pub fn parse(output: &str) -> Self {
+        let mut status = Self::default();
+        let mut oid = "";
+        let mut head = "";
+
+        for line in output.lines() {
+            if let Some(header) = line.strip_prefix("# ") {
+                let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+                match key {
+                    "branch.oid" => oid = value,
+                    "branch.head" => head = value,
+                    "branch.upstream" => status.upstream = Some(value.to_string()),
+                    "branch.ab" => {
+                        let mut counts = value
+                            .split_whitespace()
+                            .map(|count| count.trim_start_matches(['+', '-']).parse().ok());
+                        status.ahead = counts.next().flatten();
+                        status.behind = counts.next().flatten();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match line.split(' ').next() {
+                Some("1") | Some("2") | Some("u") => status.modified += 1,
+                Some("?") => status.untracked += 1,
+                _ => {}
+            }
+        }
+
+        status.unborn = oid == "(initial)";
+        if !status.unborn && !oid.is_empty() {
+            status.commit = Some(oid.to_string());
+        }
+        status.branch = if head == "(detached)" {
+            format!("detached {}", oid.get(..7).unwrap_or(oid))
+        } else {
+            head.to_string()
+        };
+
+        status
+    }
+
+    /// Returns true if the working tree has modified, staged or untracked files.
+    pub fn is_dirty(&self) -> bool {
+        self.modified > 0 || self.untracked > 0
+    }
+
+    /// Returns true if HEAD doesn't point to a branch.
+    pub fn is_detached(&self) -> bool {
+        self.branch.starts_with("detached ")
+    }
+
+    /// Returns true if the current branch and its upstream both have commits the other doesn't.
+    pub fn is_diverged(&self) -> bool {
+        self.ahead.unwrap_or(0) > 0 && self.behind.unwrap_or(0) > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "# branch.oid 3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a
+# branch.head main
+# branch.upstream origin/main
+# branch.ab +2 -1
+1 .M N... 100644 100644 100644 3f2c1a9 3f2c1a9 src/main.rs
+2 R. N... 100644 100644 100644 3f2c1a9 3f2c1a9 R100 src/new.rs\tsrc/old.rs
+u UU N... 100644 100644 100644 100644 3f2c1a9 3f2c1a9 3f2c1a9 Cargo.lock
+? notes.txt
+! target/
+";
+        let status = RepoStatus::parse(output);
+        assert_eq!(
+            RepoStatus {
+                branch: "main".to_string(),
+                commit: Some("3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a".to_string()),
+                upstream: Some("origin/main".to_string()),
+                ahead: Some(2),
+                behind: Some(1),
+                modified: 3,
+                untracked: 1,
+                unborn: false,
+            },
+            status
+        );
+        assert!(status.is_dirty());
+        assert!(status.is_diverged());
+
+        let status = RepoStatus::parse(
+            "# branch.oid 3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a\n# branch.head (detached)\n",
+        );
+        assert_eq!("detached 3f2c1a9", status.branch);
+        assert!(status.is_detached());
+        assert_eq!(None, status.ahead);
+        assert!(!status.is_dirty());
+
+        // An unborn branch has no commit yet
+        let status = RepoStatus::parse("# branch.oid (initial)\n# branch.head master\n");
+        assert_eq!("master", status.branch);
+        assert!(status.unborn);
+    }
+}
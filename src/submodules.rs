@@ -0,0 +1,236 @@
+//! Update the submodules of a repository, with the outcome of each one.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::git::Git;
+use crate::gitmodules::GitModules;
+
+/// What updating a submodule did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmoduleOutcome {
+    /// The submodule was cloned or its commit changed, and it was checked out
+    CheckedOut {
+        /// The commit checked out
+        commit: String,
+    },
+    /// The submodule was already at the commit of the superproject
+    UpToDate,
+    /// Cloning, fetching or checking out the submodule failed
+    Failed {
+        /// The error of git about this submodule
+        error: String,
+    },
+}
+
+impl SubmoduleOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SubmoduleOutcome::CheckedOut { .. } => "checked out",
+            SubmoduleOutcome::UpToDate => "up to date",
+            SubmoduleOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for SubmoduleOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmoduleOutcome::CheckedOut { commit } => {
+                write!(f, "checked out {}", commit.get(..7).unwrap_or(commit))
+            }
+            SubmoduleOutcome::UpToDate => f.write_str("up to date"),
+            SubmoduleOutcome::Failed { error } => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// Syncs the URLs of the submodules of the repository at `path` with its .gitmodules, then
+/// initializes and updates them recursively.
+///
+/// Returns the outcome of each submodule by path, the ones of `gitmodules` first in the order
+/// they're declared, then the nested ones git reported.
+pub fn update(git: &Git, path: &Path, gitmodules: &GitModules) -> Vec<(String, SubmoduleOutcome)> {
+    let declared: Vec<String> = gitmodules
+        .submodules()
+        .iter()
+        .map(|submodule| submodule.path().to_string_lossy().to_string())
+        .collect();
+    let fail_all = |error: String| {
+        declared
+            .iter()
+            .map(|path| {
+                let error = error.clone();
+                (path.clone(), SubmoduleOutcome::Failed { error })
+            })
+            .collect()
+    };
+
+    let output = match git
+        .command(path)
+        .args(["submodule", "sync", "--quiet", "--recursive"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            return fail_all(format!(
+                "unable to run {}: {}",
+                git.program().display(),
+                err
+            ))
+        }
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error = stderr.lines().last().unwrap_or("").trim();
+        return fail_all(format!("git submodule sync failed: {}", error));
+    }
+
+    let output = match git
+        .command(path)
+        .args(["submodule", "update", "--init", "--recursive"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            return fail_all(format!(
+                "unable to run {}: {}",
+                git.program().display(),
+                err
+            ))
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let mut outcomes = parse_update(&format!("{}\n{}", stdout, stderr), path);
+    for path in &declared {
+        if !outcomes.iter().any(|(known, _)| known == path) {
+            outcomes.push((path.clone(), SubmoduleOutcome::UpToDate));
+        }
+    }
+    // The stable sort keeps the nested submodules in the order git reported them
+    outcomes.sort_by_key(|(path, _)| {
+        declared
+            .iter()
+            .position(|declared| declared == path)
+            .unwrap_or(declared.len())
+    });
+
+    // git stops at the first submodule failing, the ones it didn't report on are only up to
+    // date if their commit is the one of the superproject
+    if !output.status.success() {
+        let status = git
+            .stdout(path, &["submodule", "status", "--recursive"])
+            .unwrap_or_default();
+        let error = format!(
+            "not updated, git submodule update stopped: {}",
+            stderr.lines().last().unwrap_or("").trim()
+        );
+        for (submodule, outcome) in &mut outcomes {
+            if *outcome == SubmoduleOutcome::UpToDate && !is_in_sync(&status, submodule) {
+                *outcome = SubmoduleOutcome::Failed {
+                    error: error.clone(),
+                };
+            }
+        }
+    }
+
+    outcomes
+}
+
+/// Returns true if the output of `git submodule status` says that the submodule at `path` is
+/// checked out at the commit of the superproject.
+fn is_in_sync(status: &str, path: &str) -> bool {
+    status.lines().any(|line| {
+        line.strip_prefix(' ')
+            .and_then(|line| line.split_whitespace().nth(1))
+            == Some(path)
+    })
+}
+
+/// Parses the combined output of `git submodule update` run in the repository at `root` into the
+/// outcome of the submodules it mentions, in the order it mentions them.
+fn parse_update(output: &str, root: &Path) -> Vec<(String, SubmoduleOutcome)> {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    let [checked_out, failed] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"^Submodule path '(.+)': checked out '([0-9a-f]+)'$").unwrap(),
+            Regex::new(
+                r"(?:clone of '.*' into submodule path '(.+)' failed|Unable to checkout '.*' in submodule path '(.+)'|Unable to fetch in submodule path '(.+)'|Fetched in submodule path '(.+)', but it did not contain|Failed to recurse into submodule path '(.+)')",
+            )
+            .unwrap(),
+        ]
+    });
+
+    let mut outcomes: Vec<(String, SubmoduleOutcome)> = Vec::new();
+    for line in output.lines().map(str::trim) {
+        let (path, outcome) = if let Some(captures) = checked_out.captures(line) {
+            (
+                captures[1].to_string(),
+                SubmoduleOutcome::CheckedOut {
+                    commit: captures[2].to_string(),
+                },
+            )
+        } else if let Some(captures) = failed.captures(line) {
+            let path = captures.iter().skip(1).flatten().next().unwrap().as_str();
+            // Some errors have the absolute path of the submodule
+            let path = Path::new(path)
+                .strip_prefix(root)
+                .unwrap_or(Path::new(path))
+                .to_string_lossy()
+                .to_string();
+            (
+                path,
+                SubmoduleOutcome::Failed {
+                    error: line.to_string(),
+                },
+            )
+        } else {
+            continue;
+        };
+
+        match outcomes.iter_mut().find(|(known, _)| *known == path) {
+            // The first failure is the most telling, the next ones are about recursing
+            Some((_, known @ SubmoduleOutcome::CheckedOut { .. })) => *known = outcome,
+            Some(_) => {}
+            None => outcomes.push((path, outcome)),
+        }
+    }
+
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_update() {
+        let output = "Submodule 'lib' (https://example.com/lib.git) registered for path 'lib'\n\
+                      Submodule 'vendor/gone' (https://example.com/gone.git) registered for path 'vendor/gone'\n\
+                      Cloning into '/src/foo/lib'...\n\
+                      Submodule path 'lib': checked out '3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a'\n\
+                      Submodule path 'lib/nested': checked out 'aeddf8c0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a'\n\
+                      fatal: repository 'https://example.com/gone.git/' not found\n\
+                      fatal: clone of 'https://example.com/gone.git' into submodule path '/src/foo/vendor/gone' failed\n\
+                      Failed to clone 'vendor/gone'. Retry scheduled\n";
+
+        let outcomes = parse_update(output, Path::new("/src/foo"));
+        assert_eq!(3, outcomes.len());
+        assert_eq!("lib", outcomes[0].0);
+        assert_eq!(
+            SubmoduleOutcome::CheckedOut {
+                commit: "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a".to_string()
+            },
+            outcomes[0].1
+        );
+        assert_eq!("lib/nested", outcomes[1].0);
+        assert_eq!("vendor/gone", outcomes[2].0);
+        assert!(matches!(outcomes[2].1, SubmoduleOutcome::Failed { .. }));
+    }
+}
@@ -0,0 +1,161 @@
+//! Switch a repository to a branch only if it has it.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::git::Git;
+use crate::status::RepoStatus;
+
+/// How a repository is switched.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwitchOptions {
+    /// Create a local branch tracking the remote-tracking branch if there's no local branch
+    pub create_tracking: bool,
+    /// Switch even if tracked files have changes, git still refuses if they conflict
+    pub force: bool,
+}
+
+/// What switching a repository did, or why it was left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SwitchOutcome {
+    /// The existing local branch was checked out
+    Switched,
+    /// The branch was already checked out
+    AlreadyOn,
+    /// A local branch tracking this remote-tracking branch was created and checked out
+    Created {
+        /// The remote-tracking branch, like `origin/main`
+        remote_ref: String,
+    },
+    /// Neither a local nor a remote-tracking branch exists
+    NoSuchBranch,
+    /// Only a remote-tracking branch exists and creating a local branch wasn't asked
+    OnlyRemote {
+        /// The remote-tracking branch, like `origin/main`
+        remote_ref: String,
+    },
+    /// Tracked files have changes
+    Dirty,
+    /// A git command failed, like a checkout conflicting with the changes
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl SwitchOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SwitchOutcome::Switched => "switched",
+            SwitchOutcome::AlreadyOn => "already on the branch",
+            SwitchOutcome::Created { .. } => "created",
+            SwitchOutcome::NoSuchBranch => "no such branch",
+            SwitchOutcome::OnlyRemote { .. } => "only on a remote",
+            SwitchOutcome::Dirty => "dirty",
+            SwitchOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for SwitchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwitchOutcome::Created { remote_ref } => write!(f, "created, tracking {}", remote_ref),
+            SwitchOutcome::OnlyRemote { remote_ref } => write!(
+                f,
+                "only {} exists, pass --create-tracking to create the branch",
+                remote_ref
+            ),
+            SwitchOutcome::Dirty => f.write_str("dirty, pass --force to switch anyway"),
+            SwitchOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+/// Switches the repository at `path` to `branch` if it has a local branch with that name, or a
+/// remote-tracking one with `create_tracking`.
+pub fn switch(git: &Git, path: &Path, branch: &str, options: SwitchOptions) -> SwitchOutcome {
+    let status = match RepoStatus::probe(git, path) {
+        Ok(status) => status,
+        Err(err) => {
+            return SwitchOutcome::Failed {
+                error: err.to_string(),
+            }
+        }
+    };
+    if status.branch == branch {
+        return SwitchOutcome::AlreadyOn;
+    }
+
+    let local = git
+        .stdout(
+            path,
+            &[
+                "rev-parse",
+                "--quiet",
+                "--verify",
+                &format!("refs/heads/{}", branch),
+            ],
+        )
+        .is_some();
+    let remote_ref = if local {
+        None
+    } else {
+        match remote_ref(git, path, branch) {
+            Some(remote_ref) => Some(remote_ref),
+            None => return SwitchOutcome::NoSuchBranch,
+        }
+    };
+
+    if let Some(remote_ref) = &remote_ref {
+        if !options.create_tracking {
+            return SwitchOutcome::OnlyRemote {
+                remote_ref: remote_ref.clone(),
+            };
+        }
+    }
+    if status.modified > 0 && !options.force {
+        return SwitchOutcome::Dirty;
+    }
+
+    let mut command = git.command(path);
+    command.args(["switch", "--quiet"]);
+    match &remote_ref {
+        Some(remote_ref) => command.args(["--create", branch, "--track", remote_ref]),
+        None => command.arg(branch),
+    };
+
+    match command.output() {
+        Ok(output) if output.status.success() => match remote_ref {
+            Some(remote_ref) => SwitchOutcome::Created { remote_ref },
+            None => SwitchOutcome::Switched,
+        },
+        Ok(output) => SwitchOutcome::Failed {
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        },
+        Err(err) => SwitchOutcome::Failed {
+            error: err.to_string(),
+        },
+    }
+}
+
+/// Returns the remote-tracking branch named `branch`, the one of origin if several remotes have
+/// it.
+fn remote_ref(git: &Git, path: &Path, branch: &str) -> Option<String> {
+    let refs = git.stdout(
+        path,
+        &[
+            "for-each-ref",
+            "--format=%(refname:short)",
+            &format!("refs/remotes/*/{}", branch),
+        ],
+    )?;
+
+    let refs: Vec<&str> = refs.lines().collect();
+    refs.iter()
+        .find(|remote_ref| remote_ref.starts_with("origin/"))
+        .or(refs.first())
+        .map(|remote_ref| remote_ref.to_string())
+}
@@ -0,0 +1,198 @@
+//! Catch a repository up with its upstream without ever creating a merge commit.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::git::Git;
+use crate::status::RepoStatus;
+
+/// The git arguments fetching all the remotes of a repository before it's synced.
+pub const FETCH_ARGS: &[&str] = &["fetch", "--all", "--prune", "--quiet"];
+
+/// What syncing a repository did, or why it was left untouched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// The current branch was fast-forwarded by this many commits
+    FastForwarded {
+        /// The number of commits pulled
+        commits: usize,
+    },
+    /// The current branch already contains its upstream
+    UpToDate,
+    /// The current branch has commits its upstream doesn't, there's nothing to fast-forward
+    Ahead {
+        /// The number of commits not pushed
+        commits: usize,
+    },
+    /// The working tree has changes
+    Dirty,
+    /// The current branch and its upstream both have commits the other doesn't
+    Diverged {
+        /// The number of commits not pushed
+        ahead: usize,
+        /// The number of commits not pulled
+        behind: usize,
+    },
+    /// HEAD doesn't point to a branch
+    Detached,
+    /// The current branch has no commits yet
+    NoCommits,
+    /// The current branch has no upstream, or its upstream is gone
+    NoUpstream,
+    /// A git command failed
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl SyncOutcome {
+    /// Returns true if the repository was left untouched for a reason the user should look at.
+    pub fn needs_attention(&self) -> bool {
+        !matches!(
+            self,
+            SyncOutcome::FastForwarded { .. } | SyncOutcome::UpToDate | SyncOutcome::Failed { .. }
+        )
+    }
+
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SyncOutcome::FastForwarded { .. } => "fast-forwarded",
+            SyncOutcome::UpToDate => "up to date",
+            SyncOutcome::Ahead { .. } => "ahead",
+            SyncOutcome::Dirty => "dirty",
+            SyncOutcome::Diverged { .. } => "diverged",
+            SyncOutcome::Detached => "detached",
+            SyncOutcome::NoCommits => "no commits",
+            SyncOutcome::NoUpstream => "no upstream",
+            SyncOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for SyncOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncOutcome::FastForwarded { commits } => {
+                write!(f, "fast-forwarded by {}", plural(*commits, "commit"))
+            }
+            SyncOutcome::Ahead { commits } => write!(f, "ahead by {}", plural(*commits, "commit")),
+            SyncOutcome::Diverged { ahead, behind } => {
+                write!(f, "diverged, ahead {} and behind {}", ahead, behind)
+            }
+            SyncOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Fast-forwards the current branch of the repository at `path` to its upstream, if the working
+/// tree is clean and the branch doesn't have commits of its own.
+///
+/// The remotes must have been fetched already, see [`FETCH_ARGS`].
+pub fn fast_forward(git: &Git, path: &Path) -> SyncOutcome {
+    let status = match RepoStatus::probe(git, path) {
+        Ok(status) => status,
+        Err(err) => {
+            return SyncOutcome::Failed {
+                error: err.to_string(),
+            }
+        }
+    };
+
+    decide(&status).unwrap_or_else(|| {
+        let output = git
+            .command(path)
+            .args(["merge", "--ff-only", "--quiet", "@{upstream}"])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => SyncOutcome::FastForwarded {
+                commits: status.behind.unwrap_or(0),
+            },
+            Ok(output) => SyncOutcome::Failed {
+                error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            },
+            Err(err) => SyncOutcome::Failed {
+                error: err.to_string(),
+            },
+        }
+    })
+}
+
+/// Decides the outcome of a repository from its status, `None` if it can be fast-forwarded.
+fn decide(status: &RepoStatus) -> Option<SyncOutcome> {
+    if status.is_detached() {
+        return Some(SyncOutcome::Detached);
+    }
+    if status.unborn {
+        return Some(SyncOutcome::NoCommits);
+    }
+    if status.is_dirty() {
+        return Some(SyncOutcome::Dirty);
+    }
+
+    match (status.ahead, status.behind) {
+        (Some(0), Some(0)) => Some(SyncOutcome::UpToDate),
+        (Some(ahead), Some(0)) => Some(SyncOutcome::Ahead { commits: ahead }),
+        (Some(0), Some(_)) => None,
+        (Some(ahead), Some(behind)) => Some(SyncOutcome::Diverged { ahead, behind }),
+        _ => Some(SyncOutcome::NoUpstream),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decide() {
+        let status = |ahead, behind| RepoStatus {
+            branch: "main".to_string(),
+            upstream: Some("origin/main".to_string()),
+            ahead,
+            behind,
+            ..RepoStatus::default()
+        };
+
+        assert_eq!(None, decide(&status(Some(0), Some(2))));
+        assert_eq!(
+            Some(SyncOutcome::UpToDate),
+            decide(&status(Some(0), Some(0)))
+        );
+        assert_eq!(
+            Some(SyncOutcome::Diverged {
+                ahead: 1,
+                behind: 2
+            }),
+            decide(&status(Some(1), Some(2)))
+        );
+        assert_eq!(Some(SyncOutcome::NoUpstream), decide(&status(None, None)));
+
+        let dirty = RepoStatus {
+            untracked: 1,
+            ..status(Some(0), Some(2))
+        };
+        assert_eq!(Some(SyncOutcome::Dirty), decide(&dirty));
+
+        let detached = RepoStatus {
+            branch: "detached 3f2c1a9".to_string(),
+            ..RepoStatus::default()
+        };
+        assert_eq!(Some(SyncOutcome::Detached), decide(&detached));
+
+        let unborn = RepoStatus {
+            unborn: true,
+            ..status(None, None)
+        };
+        assert_eq!(Some(SyncOutcome::NoCommits), decide(&unborn));
+    }
+}
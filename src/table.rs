@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use std::fmt::Write as FmtWrite;
+use std::str::FromStr;
+use std::time::Duration;
+
+use colored::Colorize;
+
+use crate::output::format_duration;
+
+/// Used if the terminal width can't be determined.
+const DEFAULT_WIDTH: usize = 80;
+/// The name column is never truncated below this width.
+const MIN_NAME_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RowStatus {
+    Fail,
+    Ok,
+}
+
+impl RowStatus {
+    fn label(&self) -> colored::ColoredString {
+        match self {
+            RowStatus::Ok => "OK".bright_green(),
+            RowStatus::Fail => "FAIL".bright_red(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableSort {
+    Name,
+    Status,
+    Duration,
+}
+
+impl FromStr for TableSort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(TableSort::Name),
+            "status" => Ok(TableSort::Status),
+            "duration" => Ok(TableSort::Duration),
+            _ => Err(anyhow::anyhow!("unknown table sort {}", s)),
+        }
+    }
+}
+
+pub struct Row {
+    pub name: String,
+    pub status: RowStatus,
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+    pub first_line: String,
+}
+
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(width, _)| width.0 as usize)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Renders `rows` as a table with aligned columns fitting in `width`.
+pub fn render(mut rows: Vec<Row>, sort: TableSort, width: usize) -> String {
+    match sort {
+        TableSort::Name => rows.sort_by(|a, b| a.name.cmp(&b.name)),
+        TableSort::Status => rows.sort_by(|a, b| a.status.cmp(&b.status).then(a.name.cmp(&b.name))),
+        TableSort::Duration => rows.sort_by_key(|row| Reverse(row.duration)),
+    }
+
+    let cells: Vec<(String, String)> = rows
+        .iter()
+        .map(|row| {
+            let exit_code = row
+                .exit_code
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "-".to_string());
+
+            (exit_code, format_duration(row.duration))
+        })
+        .collect();
+
+    let exit_code_width = cells.iter().map(|c| c.0.len()).max().unwrap_or(0);
+    let duration_width = cells.iter().map(|c| c.1.len()).max().unwrap_or(0);
+
+    // 4 for the status and one space between each column
+    let fixed_width = 4 + exit_code_width + duration_width + 4;
+
+    let longest_name = rows
+        .iter()
+        .map(|row| row.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let name_width = longest_name.min(
+        width
+            .saturating_sub(fixed_width)
+            .saturating_sub(width / 3)
+            .max(MIN_NAME_WIDTH),
+    );
+    let line_width = width.saturating_sub(fixed_width + name_width);
+
+    let mut result = String::new();
+
+    for (row, (exit_code, duration)) in rows.iter().zip(cells) {
+        let name = truncate_middle(&row.name, name_width);
+        let status = row.status.label();
+        let line = truncate_end(&row.first_line, line_width);
+
+        writeln!(
+            &mut result,
+            "{:name_width$} {:4} {:>exit_code_width$} {:>duration_width$} {}",
+            name,
+            status,
+            exit_code,
+            duration,
+            line,
+            name_width = name_width,
+            exit_code_width = exit_code_width,
+            duration_width = duration_width,
+        )
+        .unwrap();
+    }
+
+    result
+}
+
+/// Truncates `s` to `width` characters by replacing its middle with an ellipsis, so that the
+/// distinguishing suffix survives.
+fn truncate_middle(s: &str, width: usize) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= width {
+        return s.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let head = (width - 1) / 2;
+    let tail = width - 1 - head;
+
+    let mut result: String = chars[..head].iter().collect();
+    result.push('…');
+    result.extend(&chars[chars.len() - tail..]);
+
+    result
+}
+
+fn truncate_end(s: &str, width: usize) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= width {
+        return s.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    let mut result: String = chars[..width - 1].iter().collect();
+    result.push('…');
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!("foo/bar", truncate_middle("foo/bar", 7));
+        assert_eq!("src…/api", truncate_middle("src/work/api", 8));
+        assert_eq!("", truncate_middle("src/work/api", 0));
+    }
+
+    #[test]
+    fn test_render() {
+        colored::control::set_override(false);
+
+        let rows = vec![
+            Row {
+                name: "foo".to_string(),
+                status: RowStatus::Ok,
+                exit_code: Some(0),
+                duration: Duration::from_millis(20),
+                first_line: "Already up to date.".to_string(),
+            },
+            Row {
+                name: "barbaz".to_string(),
+                status: RowStatus::Fail,
+                exit_code: Some(128),
+                duration: Duration::from_millis(1500),
+                first_line: "fatal: not a git repository".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            "barbaz FAIL 128 1.50s fatal: not a git repository\n\
+             foo    OK     0  20ms Already up to date.\n",
+            render(rows, TableSort::Status, 80)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Create the same tag in a repository, knowing whether it was already there.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::git::Git;
+
+/// How a tag is created.
+#[derive(Debug, Clone, Default)]
+pub struct TagOptions {
+    /// Create an annotated tag with this message
+    pub message: Option<String>,
+    /// Create a signed tag, with the message or the name of the tag as its message
+    pub sign: bool,
+    /// Move the tag to HEAD if it points to another commit
+    pub force: bool,
+}
+
+/// What tagging a repository did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagOutcome {
+    /// The tag was created on HEAD
+    Created,
+    /// The tag already points to HEAD
+    AlreadyPresent,
+    /// The tag was moved from another commit to HEAD
+    Moved {
+        /// The commit the tag pointed to
+        from: String,
+    },
+    /// The tag points to another commit and moving it wasn't asked
+    Conflict {
+        /// The commit the tag points to
+        target: String,
+    },
+    /// A git command failed
+    Failed {
+        /// What failed
+        error: String,
+    },
+}
+
+impl TagOutcome {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TagOutcome::Created => "created",
+            TagOutcome::AlreadyPresent => "already present",
+            TagOutcome::Moved { .. } => "moved",
+            TagOutcome::Conflict { .. } => "conflict",
+            TagOutcome::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl fmt::Display for TagOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagOutcome::Moved { from } => write!(f, "moved from {}", short_id(from)),
+            TagOutcome::Conflict { target } => write!(
+                f,
+                "conflict, the tag points to {}, pass --force to move it",
+                short_id(target)
+            ),
+            TagOutcome::Failed { error } => write!(f, "failed: {}", error),
+            outcome => f.write_str(outcome.label()),
+        }
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..7).unwrap_or(id)
+}
+
+/// Creates the tag `name` on the HEAD of the repository at `path`.
+pub fn tag(git: &Git, path: &Path, name: &str, options: &TagOptions) -> TagOutcome {
+    let Some(head) = git.stdout(path, &["rev-parse", "--quiet", "--verify", "HEAD"]) else {
+        return TagOutcome::Failed {
+            error: "there are no commits to tag".to_string(),
+        };
+    };
+
+    let existing = git.stdout(
+        path,
+        &[
+            "rev-parse",
+            "--quiet",
+            "--verify",
+            &format!("refs/tags/{}^{{commit}}", name),
+        ],
+    );
+    match &existing {
+        Some(target) if *target == head => return TagOutcome::AlreadyPresent,
+        Some(target) if !options.force => {
+            return TagOutcome::Conflict {
+                target: target.clone(),
+            }
+        }
+        _ => {}
+    }
+
+    let mut command = git.command(path);
+    command.arg("tag");
+    if options.sign {
+        command.arg("--sign");
+    } else if options.message.is_some() {
+        command.arg("--annotate");
+    }
+    if options.sign || options.message.is_some() {
+        command
+            .arg("--message")
+            .arg(options.message.as_deref().unwrap_or(name));
+    }
+    if existing.is_some() {
+        command.arg("--force");
+    }
+    command.arg(name);
+
+    match command.output() {
+        Ok(output) if output.status.success() => match existing {
+            Some(from) => TagOutcome::Moved { from },
+            None => TagOutcome::Created,
+        },
+        Ok(output) => TagOutcome::Failed {
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        },
+        Err(err) => TagOutcome::Failed {
+            error: err.to_string(),
+        },
+    }
+}
@@ -0,0 +1,107 @@
+//! Show the progress of a run in the terminal itself: in the title of the tab and, on the
+//! terminals supporting OSC 9;4 like Windows Terminal or ConEmu, as a progress indicator in the
+//! taskbar or the tab.
+//!
+//! The title is pushed on the title stack of the terminal when the run starts and popped when it
+//! ends, restoring the one it had. The terminals not knowing a sequence ignore it.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+struct State<W: Write> {
+    writer: W,
+    done: usize,
+    failed: usize,
+}
+
+/// The sequences updating the terminal as the repositories complete, written all at once so they
+/// never end up in the middle of another line.
+pub struct TerminalProgress<W: Write> {
+    total: usize,
+    state: Mutex<State<W>>,
+}
+
+impl TerminalProgress<io::Stderr> {
+    /// Starts showing the progress on stderr, if both stdout and stderr are a terminal.
+    pub fn start(total: usize) -> Option<Self> {
+        (io::stdout().is_terminal() && io::stderr().is_terminal())
+            .then(|| Self::new(io::stderr(), total))
+    }
+}
+
+impl<W: Write> TerminalProgress<W> {
+    pub fn new(writer: W, total: usize) -> Self {
+        let mut state = State {
+            writer,
+            done: 0,
+            failed: 0,
+        };
+        // Saves the title before the first one is set
+        state.write("\x1b[22;0t", total);
+
+        Self {
+            total,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Counts a repository done, failed unless `success`.
+    pub fn update(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.done += 1;
+        if !success {
+            state.failed += 1;
+        }
+        state.write("", self.total);
+    }
+
+    /// Removes the progress indicator and restores the title.
+    pub fn finish(self) {
+        let mut state = self.state.into_inner().unwrap();
+        let _ = state.writer.write_all(b"\x1b]9;4;0;0\x07\x1b[23;0t");
+        let _ = state.writer.flush();
+    }
+}
+
+impl<W: Write> State<W> {
+    fn write(&mut self, prefix: &str, total: usize) {
+        let mut title = format!("gitjuggling: {}/{}", self.done, total);
+        if self.failed > 0 {
+            title.push_str(&format!(" ({} failed)", self.failed));
+        }
+        let percent = (self.done * 100).checked_div(total).unwrap_or(100);
+        // The indicator turns red once a command failed
+        let indicator = if self.failed > 0 { 2 } else { 1 };
+
+        let sequences = format!(
+            "{}\x1b]0;{}\x07\x1b]9;4;{};{}\x07",
+            prefix, title, indicator, percent
+        );
+        let _ = self.writer.write_all(sequences.as_bytes());
+        let _ = self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress() {
+        let progress = TerminalProgress::new(Vec::new(), 4);
+        progress.update(true);
+        progress.update(false);
+        assert_eq!(
+            "\x1b[22;0t\x1b]0;gitjuggling: 0/4\x07\x1b]9;4;1;0\x07\
+             \x1b]0;gitjuggling: 1/4\x07\x1b]9;4;1;25\x07\
+             \x1b]0;gitjuggling: 2/4 (1 failed)\x07\x1b]9;4;2;50\x07",
+            String::from_utf8(progress.state.lock().unwrap().writer.clone()).unwrap()
+        );
+
+        let mut writer = Vec::new();
+        TerminalProgress::new(&mut writer, 0).finish();
+        assert!(String::from_utf8(writer)
+            .unwrap()
+            .ends_with("\x1b]0;gitjuggling: 0/0\x07\x1b]9;4;1;100\x07\x1b]9;4;0;0\x07\x1b[23;0t"));
+    }
+}
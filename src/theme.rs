@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use colored::Color;
+
+/// The roles of the colors a config file can override in its `[colors]` table.
+pub const ROLES: &[&str] = &["path", "command", "stdout", "stderr", "summary"];
+
+/// The 16 ANSI colors with the RGB values of xterm, to replace the TrueColor ones with.
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::White, (229, 229, 229)),
+    (Color::BrightBlack, (127, 127, 127)),
+    (Color::BrightRed, (255, 0, 0)),
+    (Color::BrightGreen, (0, 255, 0)),
+    (Color::BrightYellow, (255, 255, 0)),
+    (Color::BrightBlue, (92, 92, 255)),
+    (Color::BrightMagenta, (255, 0, 255)),
+    (Color::BrightCyan, (0, 255, 255)),
+    (Color::BrightWhite, (255, 255, 255)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeName {
+    Light,
+    Dark,
+    Plain,
+}
+
+impl FromStr for ThemeName {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "light" => Ok(ThemeName::Light),
+            "dark" => Ok(ThemeName::Dark),
+            "plain" => Ok(ThemeName::Plain),
+            _ => Err(anyhow::anyhow!("unknown theme {}", s)),
+        }
+    }
+}
+
+/// The colors used to render the output.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub path: Color,
+    pub command: Color,
+    pub stdout: Color,
+    pub stderr: Color,
+    pub summary: Color,
+    /// Whether the TrueColor values are kept, they're replaced with ANSI ones otherwise
+    truecolor: bool,
+}
+
+impl Theme {
+    /// Builds the theme named `name`.
+    ///
+    /// If the terminal doesn't advertise truecolor support via COLORTERM the TrueColor values
+    /// are replaced with their 16-color ANSI counterpart.
+    pub fn new(name: ThemeName) -> Self {
+        Self::build(name, truecolor_support())
+    }
+
+    /// Builds the theme named `name` with the 16-color ANSI values only.
+    pub fn ansi(name: ThemeName) -> Self {
+        Self::build(name, false)
+    }
+
+    /// Replaces the colors of the roles in `colors`, like the `[colors]` table of the config
+    /// files. They're checked when the config is parsed.
+    ///
+    /// Like the colors of the theme, the `#rrggbb` ones are replaced with the closest ANSI color
+    /// without truecolor support.
+    pub fn with_colors(mut self, colors: &BTreeMap<String, String>) -> Self {
+        for (role, color) in colors {
+            let Ok(color) = parse_color(color) else {
+                continue;
+            };
+            let color = if self.truecolor {
+                color
+            } else {
+                to_ansi(color)
+            };
+            match role.as_str() {
+                "path" => self.path = color,
+                "command" => self.command = color,
+                "stdout" => self.stdout = color,
+                "stderr" => self.stderr = color,
+                "summary" => self.summary = color,
+                _ => {}
+            }
+        }
+
+        self
+    }
+
+    fn build(name: ThemeName, truecolor: bool) -> Self {
+        let pick = |true_color: Color, ansi: Color| if truecolor { true_color } else { ansi };
+
+        match name {
+            ThemeName::Dark | ThemeName::Plain => Self {
+                path: Color::Green,
+                command: Color::Yellow,
+                stdout: pick(
+                    Color::TrueColor {
+                        r: 176,
+                        g: 176,
+                        b: 176,
+                    },
+                    Color::White,
+                ),
+                stderr: pick(
+                    Color::TrueColor {
+                        r: 219,
+                        g: 154,
+                        b: 154,
+                    },
+                    Color::BrightRed,
+                ),
+                summary: Color::BrightCyan,
+                truecolor,
+            },
+            ThemeName::Light => Self {
+                path: Color::Green,
+                command: Color::Blue,
+                stdout: pick(
+                    Color::TrueColor {
+                        r: 88,
+                        g: 88,
+                        b: 88,
+                    },
+                    Color::BrightBlack,
+                ),
+                stderr: pick(
+                    Color::TrueColor {
+                        r: 168,
+                        g: 48,
+                        b: 48,
+                    },
+                    Color::Red,
+                ),
+                summary: Color::Blue,
+                truecolor,
+            },
+        }
+    }
+}
+
+/// Parses a color of the config files: a name like `red` or `bright blue`, or `#rrggbb`.
+pub fn parse_color(s: &str) -> anyhow::Result<Color> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let channel = |index: usize| {
+            hex.get(index..index + 2)
+                .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+        };
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::TrueColor { r, g, b }),
+            _ => Err(anyhow!(
+                "invalid color {}, the hexadecimal ones are like #rrggbb",
+                s
+            )),
+        };
+    }
+
+    s.parse().map_err(|_| {
+        anyhow!(
+            "unknown color {}, the colors are black, red, green, yellow, blue, magenta, cyan and \
+             white, optionally after bright, or #rrggbb",
+            s
+        )
+    })
+}
+
+/// Returns the ANSI color closest to a TrueColor one, the other colors are returned as is.
+fn to_ansi(color: Color) -> Color {
+    let Color::TrueColor { r, g, b } = color else {
+        return color;
+    };
+    let distance = |(ansi_r, ansi_g, ansi_b): (u8, u8, u8)| {
+        [(r, ansi_r), (g, ansi_g), (b, ansi_b)]
+            .iter()
+            .map(|(a, b)| (i32::from(*a) - i32::from(*b)).pow(2))
+            .sum::<i32>()
+    };
+
+    ANSI.iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(ansi, _)| *ansi)
+        .unwrap()
+}
+
+fn truecolor_support() -> bool {
+    match env::var("COLORTERM") {
+        Ok(v) => v == "truecolor" || v == "24bit",
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dark_theme_defaults() {
+        let theme = Theme::build(ThemeName::Dark, true);
+        assert_eq!(
+            Color::TrueColor {
+                r: 176,
+                g: 176,
+                b: 176
+            },
+            theme.stdout
+        );
+        assert_eq!(
+            Color::TrueColor {
+                r: 219,
+                g: 154,
+                b: 154
+            },
+            theme.stderr
+        );
+    }
+
+    #[test]
+    fn test_with_colors() {
+        let colors = BTreeMap::from([
+            ("path".to_string(), "bright magenta".to_string()),
+            ("stderr".to_string(), "#ff8000".to_string()),
+        ]);
+        let theme = Theme::build(ThemeName::Dark, true).with_colors(&colors);
+        assert_eq!(Color::BrightMagenta, theme.path);
+        assert_eq!(
+            Color::TrueColor {
+                r: 255,
+                g: 128,
+                b: 0
+            },
+            theme.stderr
+        );
+        assert_eq!(Color::Yellow, theme.command);
+
+        // Without truecolor support the closest ANSI color is used instead
+        let theme = Theme::build(ThemeName::Dark, false).with_colors(&colors);
+        assert_eq!(Color::BrightMagenta, theme.path);
+        assert_eq!(Color::Yellow, theme.stderr);
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        assert_eq!(Color::Red, to_ansi(Color::Red));
+        assert_eq!(
+            Color::BrightWhite,
+            to_ansi(Color::TrueColor {
+                r: 250,
+                g: 250,
+                b: 250
+            })
+        );
+        assert_eq!(
+            Color::Blue,
+            to_ansi(Color::TrueColor { r: 0, g: 0, b: 200 })
+        );
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Color::Red, parse_color("red").unwrap());
+        assert_eq!(Color::BrightBlue, parse_color("Bright Blue").unwrap());
+        assert!(parse_color("orange").is_err());
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg0000").is_err());
+    }
+
+    #[test]
+    fn test_ansi_fallback() {
+        let theme = Theme::build(ThemeName::Dark, false);
+        assert_eq!(Color::White, theme.stdout);
+        assert_eq!(Color::BrightRed, theme.stderr);
+    }
+}
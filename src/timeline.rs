@@ -0,0 +1,132 @@
+//! List the commits of a repository to merge them with the ones of other repositories.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::Serialize;
+
+use crate::git::Git;
+
+/// A commit listed by `git log`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Commit {
+    /// The full hash of the commit
+    pub hash: String,
+    /// The committer date, in seconds since the Unix epoch
+    pub timestamp: i64,
+    /// The committer date in the local timezone, like `2024-05-30 14:02`
+    pub date: String,
+    /// The name of the author
+    pub author: String,
+    /// The first line of the message
+    pub subject: String,
+}
+
+impl Commit {
+    /// Returns the abbreviated hash of the commit.
+    pub fn short_hash(&self) -> &str {
+        self.hash.get(..7).unwrap_or(&self.hash)
+    }
+}
+
+/// Which commits are listed, like the options of `git log` of the same name.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Only the commits more recent than this date
+    pub since: Option<String>,
+    /// Only the commits older than this date
+    pub until: Option<String>,
+    /// Only the commits whose author matches this pattern, `me` is the `user.email` of the
+    /// repository
+    pub author: Option<String>,
+    /// The commits of all the branches instead of the current one
+    pub all: bool,
+}
+
+const FORMAT: &str = "--format=%H%x00%ct%x00%cd%x00%an%x00%s";
+
+/// Lists the commits of the repository at `path`, newest first.
+///
+/// A repository without any commit has no commits rather than an error.
+pub fn log(git: &Git, path: &Path, options: &LogOptions) -> anyhow::Result<Vec<Commit>> {
+    if git
+        .stdout(path, &["rev-parse", "--quiet", "--verify", "HEAD"])
+        .is_none()
+    {
+        return Ok(Vec::new());
+    }
+
+    let mut command = git.command(path);
+    command.args(["log", FORMAT, "--date=format-local:%Y-%m-%d %H:%M"]);
+    if let Some(since) = &options.since {
+        command.arg(format!("--since={}", since));
+    }
+    if let Some(until) = &options.until {
+        command.arg(format!("--until={}", until));
+    }
+    let author = match options.author.as_deref() {
+        Some("me") => Some(
+            git.config(path, "user.email")
+                .ok_or_else(|| anyhow!("user.email isn't set, --author me matches nobody"))?,
+        ),
+        author => author.map(str::to_string),
+    };
+    if let Some(author) = author {
+        command.arg(format!("--author={}", author));
+    }
+    if options.all {
+        command.arg("--all");
+    }
+
+    let output = command
+        .output()
+        .map_err(|err| anyhow!("unable to run {}: {}", git.program().display(), err))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse(output: &str) -> Vec<Commit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\0');
+            Some(Commit {
+                hash: fields.next()?.to_string(),
+                timestamp: fields.next()?.parse().ok()?,
+                date: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let output = "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a\x001717077720\x002024-05-30 14:02\x00Jane Doe\x00Fix the parser\n\
+                      garbage\n";
+
+        let commits = parse(output);
+        assert_eq!(
+            vec![Commit {
+                hash: "3f2c1a9d0b7e4c5a6f8e9d0c1b2a3f4e5d6c7b8a".to_string(),
+                timestamp: 1717077720,
+                date: "2024-05-30 14:02".to_string(),
+                author: "Jane Doe".to_string(),
+                subject: "Fix the parser".to_string(),
+            }],
+            commits
+        );
+        assert_eq!("3f2c1a9", commits[0].short_hash());
+    }
+}
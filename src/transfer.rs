@@ -0,0 +1,181 @@
+//! Read how much data a network command moved from the progress git writes on stderr.
+//!
+//! Git only writes its progress when stderr is a terminal or with `--progress`, and translates
+//! it: a command without a summary git wrote in English transferred an unknown amount, not
+//! nothing.
+
+use serde::{Deserialize, Serialize};
+
+/// The subcommands transferring objects with a remote.
+const NETWORK_COMMANDS: &[&str] = &["fetch", "pull", "clone", "push"];
+
+/// What a command received or sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transfer {
+    /// The number of objects received or written
+    pub objects: u64,
+    /// The size of the pack, rounded by git to 2 decimals of its unit. Git leaves it out when
+    /// the transfer was too quick to measure it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// The number of deltas resolved after receiving the objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deltas: Option<u64>,
+}
+
+impl Transfer {
+    /// Adds the objects, the bytes and the deltas of `other`, a size or a count only one of them
+    /// has is kept as is.
+    pub fn add(&mut self, other: &Transfer) {
+        let sum = |a: Option<u64>, b: Option<u64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+
+        self.objects += other.objects;
+        self.bytes = sum(self.bytes, other.bytes);
+        self.deltas = sum(self.deltas, other.deltas);
+    }
+}
+
+/// Returns true if the git subcommand `subcommand` transfers objects with a remote, like fetch or
+/// push.
+pub fn is_network(subcommand: &str) -> bool {
+    NETWORK_COMMANDS.contains(&subcommand)
+}
+
+/// Parses the last progress lines of `stderr`, the one of the objects received like
+/// `Receiving objects: 100% (12/12), 1.50 KiB | 1.50 MiB/s, done.` or unpacked, or written like
+/// `Writing objects: …` for a push, and the one of the deltas. Returns None without such a line.
+///
+/// A progress line is rewritten in place with carriage returns, only the last state of each one
+/// counts.
+pub fn parse(stderr: &str) -> Option<Transfer> {
+    let mut received = None;
+    let mut written = None;
+    let mut deltas = None;
+
+    for line in stderr.split(['\r', '\n']) {
+        let line = line.trim();
+        // Git unpacks a small pack it receives instead of keeping it
+        let receiving = line
+            .strip_prefix("Receiving objects:")
+            .or_else(|| line.strip_prefix("Unpacking objects:"));
+        if let Some(progress) = receiving {
+            received = parse_objects(progress).or(received);
+        } else if let Some(progress) = line.strip_prefix("Writing objects:") {
+            written = parse_objects(progress).or(written);
+        } else if let Some(progress) = line.strip_prefix("Resolving deltas:") {
+            deltas = parse_count(progress).or(deltas);
+        }
+    }
+    if received.is_none() && written.is_none() {
+        return None;
+    }
+
+    let mut transfer = Transfer {
+        deltas,
+        ..Transfer::default()
+    };
+    for (objects, bytes) in received.into_iter().chain(written) {
+        transfer.add(&Transfer {
+            objects,
+            bytes,
+            deltas: None,
+        });
+    }
+
+    Some(transfer)
+}
+
+/// Parses the count and the size of ` 100% (12/12), 1.50 KiB | 1.50 MiB/s, done.`.
+fn parse_objects(progress: &str) -> Option<(u64, Option<u64>)> {
+    let count = parse_count(progress)?;
+    let bytes = progress
+        .split_once("), ")
+        .and_then(|(_, rest)| parse_size(rest.split([',', '|']).next()?.trim()));
+
+    Some((count, bytes))
+}
+
+/// Parses the done count of ` 100% (12/12), done.`.
+fn parse_count(progress: &str) -> Option<u64> {
+    let (_, counts) = progress.split_once('(')?;
+    let (done, _) = counts.split_once('/')?;
+
+    done.trim().parse().ok()
+}
+
+/// Parses a size the way git writes it, like `280 bytes` or `1.50 MiB`.
+fn parse_size(size: &str) -> Option<u64> {
+    const UNITS: [(&str, u64); 6] = [
+        ("byte", 1),
+        ("bytes", 1),
+        ("KiB", 1 << 10),
+        ("MiB", 1 << 20),
+        ("GiB", 1 << 30),
+        ("TiB", 1 << 40),
+    ];
+
+    let (number, unit) = size.split_once(' ')?;
+    let (_, multiplier) = UNITS.iter().find(|(name, _)| *name == unit)?;
+    let number: f64 = number.parse().ok()?;
+
+    Some((number * *multiplier as f64).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let stderr = "remote: Enumerating objects: 12, done.\n\
+                      remote: Total 12 (delta 2), reused 0 (delta 0), pack-reused 0\n\
+                      Receiving objects:  50% (6/12)\rReceiving objects: 100% (12/12), 1.50 KiB | 1.50 MiB/s, done.\n\
+                      Resolving deltas:   0% (0/2)\rResolving deltas: 100% (2/2), done.\n\
+                      From /src/origin\n";
+        assert_eq!(
+            Some(Transfer {
+                objects: 12,
+                bytes: Some(1536),
+                deltas: Some(2),
+            }),
+            parse(stderr)
+        );
+
+        let stderr = "Writing objects: 100% (3/3), 280 bytes | 280.00 KiB/s, done.\n\
+                      Total 3 (delta 0), reused 0 (delta 0), pack-reused 0\n";
+        assert_eq!(
+            Some(Transfer {
+                objects: 3,
+                bytes: Some(280),
+                deltas: None,
+            }),
+            parse(stderr)
+        );
+
+        // Too quick to have a size
+        let transfer = parse("Unpacking objects: 100% (3/3), done.\n").unwrap();
+        assert_eq!((3, None), (transfer.objects, transfer.bytes));
+
+        // Without progress or in another language the transfer is unknown
+        assert_eq!(
+            None,
+            parse("From /src/origin\n * branch main -> FETCH_HEAD\n")
+        );
+        assert_eq!(
+            None,
+            parse("Réception d'objets: 100% (3/3), 280 octets | 280.00 Kio/s, fait.\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(Some(1), parse_size("1 byte"));
+        assert_eq!(Some(280), parse_size("280 bytes"));
+        assert_eq!(Some(1_572_864), parse_size("1.50 MiB"));
+        assert_eq!(None, parse_size("1.50 Mio"));
+        assert_eq!(None, parse_size("done."));
+    }
+}
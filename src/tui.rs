@@ -0,0 +1,694 @@
+//! The browser of --tui: once the run is done, the repositories grouped by status on the left and
+//! the output of the selected one on the right.
+//!
+//! It's drawn with the escape sequences every terminal knows, on the alternate screen: the output
+//! of the run is still there once it's closed.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use gitjuggling::{ansi, RunResult};
+
+/// Whether the terminal can be put in raw mode on this platform.
+pub const SUPPORTED: bool = cfg!(unix);
+
+const HELP: &str =
+    "↑/↓ select  PgUp/PgDn scroll  / search  n/N next match  r re-run  y copy the path  q quit";
+
+/// The groups of the left pane, in their order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Group {
+    Failed,
+    Succeeded,
+    Skipped,
+    NotAttempted,
+}
+
+impl Group {
+    fn label(self) -> &'static str {
+        match self {
+            Group::Failed => "Failed",
+            Group::Succeeded => "Succeeded",
+            Group::Skipped => "Skipped",
+            Group::NotAttempted => "Not run",
+        }
+    }
+}
+
+/// A repository of the browser.
+pub struct Entry {
+    pub group: Group,
+    /// The path as it should be displayed
+    pub display: String,
+    pub path: PathBuf,
+    /// The lines of the right pane, without colors
+    pub lines: Vec<String>,
+}
+
+impl Entry {
+    /// Describes a repository the command ran in: the commands with their output, and why it
+    /// failed.
+    pub fn ran(display: String, result: &RunResult) -> Self {
+        let mut lines = Vec::new();
+        for step in &result.steps {
+            lines.push(format!("$ git {}", step.args.join(" ")));
+            push_output(&mut lines, &step.stdout.to_str_lossy());
+            push_output(&mut lines, &step.stderr.to_str_lossy());
+        }
+        lines.push(format!("$ git {}", result.args.join(" ")));
+        push_output(&mut lines, &result.stdout.to_str_lossy());
+        push_output(&mut lines, &result.stderr.to_str_lossy());
+
+        let group = if result.success {
+            if let Some(reason) = &result.reason {
+                lines.push(format!("note: {}", reason));
+            }
+            Group::Succeeded
+        } else {
+            lines.push(format!("failed: {}", result.failure_reason()));
+            Group::Failed
+        };
+
+        Self {
+            group,
+            display,
+            path: result.path.clone(),
+            lines,
+        }
+    }
+
+    /// Describes a repository of `group` the command didn't run in, for `reason`.
+    pub fn not_run(group: Group, display: String, path: PathBuf, reason: &str) -> Self {
+        Self {
+            group,
+            display,
+            path,
+            lines: vec![reason.to_string()],
+        }
+    }
+}
+
+/// Appends the lines of `text` to `lines`, a progress line rewritten with carriage returns only
+/// keeps its last state.
+fn push_output(lines: &mut Vec<String>, text: &str) {
+    lines.extend(ansi::strip_ansi(text).lines().map(|line| {
+        let line = line
+            .rsplit('\r')
+            .find(|part| !part.is_empty())
+            .unwrap_or("");
+        line.replace('\t', "    ")
+    }));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    /// Ctrl-C, the terminal doesn't turn it into a signal in raw mode
+    Interrupt,
+}
+
+/// Parses the keys read from the terminal.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let (key, length) = match bytes[index..] {
+            [0x1b, b'[' | b'O', b'A', ..] => (Key::Up, 3),
+            [0x1b, b'[' | b'O', b'B', ..] => (Key::Down, 3),
+            [0x1b, b'[' | b'O', b'H', ..] => (Key::Home, 3),
+            [0x1b, b'[' | b'O', b'F', ..] => (Key::End, 3),
+            [0x1b, b'[', b'5', b'~', ..] => (Key::PageUp, 4),
+            [0x1b, b'[', b'6', b'~', ..] => (Key::PageDown, 4),
+            [0x1b, b'[', b'1' | b'7', b'~', ..] => (Key::Home, 4),
+            [0x1b, b'[', b'4' | b'8', b'~', ..] => (Key::End, 4),
+            // The other sequences are ignored up to their final byte
+            [0x1b, b'[', ..] => {
+                let length = bytes[index + 2..]
+                    .iter()
+                    .position(|byte| (0x40..=0x7e).contains(byte))
+                    .map_or(bytes.len() - index, |end| end + 3);
+                index += length;
+                continue;
+            }
+            [0x1b, ..] => (Key::Escape, 1),
+            [b'\r' | b'\n', ..] => (Key::Enter, 1),
+            [0x7f | 0x08, ..] => (Key::Backspace, 1),
+            [0x03, ..] => (Key::Interrupt, 1),
+            [byte, ..] => {
+                let length = match byte {
+                    0xf0..=0xff => 4,
+                    0xe0..=0xef => 3,
+                    0xc0..=0xdf => 2,
+                    _ => 1,
+                };
+                let end = (index + length).min(bytes.len());
+                match std::str::from_utf8(&bytes[index..end]) {
+                    Ok(text) => (Key::Char(text.chars().next().unwrap()), end - index),
+                    Err(_) => {
+                        index += 1;
+                        continue;
+                    }
+                }
+            }
+            [] => break,
+        };
+        keys.push(key);
+        index += length;
+    }
+
+    keys
+}
+
+/// What the browser asks for after a key.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    Quit,
+    Rerun(PathBuf),
+    Copy(PathBuf),
+}
+
+/// A row of the left pane.
+enum Row {
+    Header(Group, usize),
+    Entry(usize),
+}
+
+/// The state of the browser, drawn again after every key.
+struct Browser {
+    /// Sorted by group, then by path
+    entries: Vec<Entry>,
+    selected: usize,
+    /// The first line of the output shown
+    scroll: usize,
+    /// The text searched for
+    search: Option<String>,
+    /// The search being typed
+    input: Option<String>,
+    message: Option<String>,
+}
+
+impl Browser {
+    fn new(mut entries: Vec<Entry>) -> Self {
+        entries.sort_by(|a, b| (a.group, &a.display).cmp(&(b.group, &b.display)));
+
+        Self {
+            entries,
+            selected: 0,
+            scroll: 0,
+            search: None,
+            input: None,
+            message: None,
+        }
+    }
+
+    /// Replaces the entry of the same repository, the selection stays on it.
+    fn replace(&mut self, entry: Entry) {
+        let path = entry.path.clone();
+        self.entries.retain(|other| other.path != path);
+        self.entries.push(entry);
+        self.entries
+            .sort_by(|a, b| (a.group, &a.display).cmp(&(b.group, &b.display)));
+        self.selected = self
+            .entries
+            .iter()
+            .position(|entry| entry.path == path)
+            .unwrap_or(0);
+        self.scroll = 0;
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if index == 0 || self.entries[index - 1].group != entry.group {
+                let count = self
+                    .entries
+                    .iter()
+                    .filter(|other| other.group == entry.group)
+                    .count();
+                rows.push(Row::Header(entry.group, count));
+            }
+            rows.push(Row::Entry(index));
+        }
+
+        rows
+    }
+
+    /// Handles a key, `page` is the number of lines of output shown at once.
+    fn handle(&mut self, key: Key, page: usize) -> Action {
+        if let Some(input) = &mut self.input {
+            match key {
+                Key::Char(c) => input.push(c),
+                Key::Backspace => {
+                    input.pop();
+                }
+                Key::Enter => {
+                    let search = self.input.take().unwrap_or_default();
+                    if !search.is_empty() {
+                        self.search = Some(search);
+                        self.find(self.scroll, true);
+                    }
+                }
+                Key::Escape | Key::Interrupt => self.input = None,
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        self.message = None;
+        let Some(entry) = self.entries.get(self.selected) else {
+            return match key {
+                Key::Char('q') | Key::Escape | Key::Interrupt => Action::Quit,
+                _ => Action::None,
+            };
+        };
+        let last = entry.lines.len().saturating_sub(page);
+
+        match key {
+            Key::Char('q') | Key::Escape | Key::Interrupt => return Action::Quit,
+            Key::Up | Key::Char('k') if self.selected > 0 => {
+                self.selected -= 1;
+                self.scroll = 0;
+            }
+            Key::Down | Key::Char('j') if self.selected + 1 < self.entries.len() => {
+                self.selected += 1;
+                self.scroll = 0;
+            }
+            Key::PageDown | Key::Char(' ') => self.scroll = (self.scroll + page).min(last),
+            Key::PageUp | Key::Char('b') => self.scroll = self.scroll.saturating_sub(page),
+            Key::Home | Key::Char('g') => self.scroll = 0,
+            Key::End | Key::Char('G') => self.scroll = last,
+            Key::Char('/') => self.input = Some(String::new()),
+            Key::Char('n') => self.find(self.scroll + 1, true),
+            Key::Char('N') => self.find(self.scroll.saturating_sub(1), false),
+            Key::Char('r') => match entry.group {
+                Group::Failed | Group::Succeeded => return Action::Rerun(entry.path.clone()),
+                _ => self.message = Some("the command didn't run there".to_string()),
+            },
+            Key::Char('y') => return Action::Copy(entry.path.clone()),
+            _ => {}
+        }
+
+        Action::None
+    }
+
+    /// Scrolls to the line matching the search from the line `from`, wrapping around, searching
+    /// after it if `forward` and before it otherwise.
+    fn find(&mut self, from: usize, forward: bool) {
+        let Some(search) = &self.search else {
+            self.message = Some("nothing searched yet, press / to search".to_string());
+            return;
+        };
+        let search = search.to_ascii_lowercase();
+        let lines = &self.entries[self.selected].lines;
+        let matching: Vec<usize> = (0..lines.len())
+            .filter(|&index| lines[index].to_ascii_lowercase().contains(&search))
+            .collect();
+        if matching.is_empty() {
+            self.message = Some(format!("no match for {}", search));
+            return;
+        }
+
+        let found = if forward {
+            matching.iter().position(|&line| line >= from).unwrap_or(0)
+        } else {
+            matching
+                .iter()
+                .rposition(|&line| line <= from)
+                .unwrap_or(matching.len() - 1)
+        };
+        self.scroll = matching[found];
+        self.message = Some(format!("match {} of {}", found + 1, matching.len()));
+    }
+
+    /// Draws the whole screen, `width` columns and `height` rows.
+    fn render(&self, width: usize, height: usize) -> String {
+        let body = height.saturating_sub(1);
+        let rows = self.rows();
+        let longest = self
+            .entries
+            .iter()
+            .map(|entry| entry.display.chars().count() + 2)
+            .chain(rows.iter().map(|row| match row {
+                Row::Header(group, count) => group.label().len() + count.to_string().len() + 3,
+                Row::Entry(_) => 0,
+            }))
+            .max()
+            .unwrap_or(0);
+        let left = longest.min(width / 3);
+        let right = width.saturating_sub(left + 1);
+
+        let selected_row = rows
+            .iter()
+            .position(|row| matches!(row, Row::Entry(index) if *index == self.selected))
+            .unwrap_or(0);
+        let first_row = (selected_row + 1).saturating_sub(body);
+        let lines = self
+            .entries
+            .get(self.selected)
+            .map(|entry| entry.lines.as_slice())
+            .unwrap_or_default();
+
+        let mut screen = String::from("\x1b[H");
+        for row in 0..body {
+            let cell = match rows.get(first_row + row) {
+                Some(Row::Header(group, count)) => {
+                    let text = fit(&format!("{} ({})", group.label(), count), left);
+                    format!("\x1b[1m{}\x1b[22m", text)
+                }
+                Some(Row::Entry(index)) => {
+                    let text = fit(&format!("  {}", self.entries[*index].display), left);
+                    if *index == self.selected {
+                        format!("\x1b[7m{}\x1b[27m", text)
+                    } else {
+                        text
+                    }
+                }
+                None => fit("", left),
+            };
+            screen.push_str(&cell);
+            screen.push('│');
+
+            if let Some(line) = lines.get(self.scroll + row) {
+                let line: String = line.chars().take(right).collect();
+                screen.push_str(&highlight(&line, self.search.as_deref()));
+            }
+            screen.push_str("\x1b[K\r\n");
+        }
+
+        let status = match (&self.input, &self.message, self.entries.get(self.selected)) {
+            (Some(input), _, _) => format!("/{}", input),
+            (None, Some(message), _) => message.clone(),
+            (None, None, Some(entry)) => format!(
+                "{}: {}/{}  {}",
+                entry.display,
+                (self.scroll + body).min(entry.lines.len()),
+                entry.lines.len(),
+                HELP
+            ),
+            (None, None, None) => HELP.to_string(),
+        };
+        screen.push_str(&format!("\x1b[7m{}\x1b[27m\x1b[K", fit(&status, width)));
+
+        screen
+    }
+}
+
+/// Returns `text` cut or padded with spaces to `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut text: String = text.chars().take(width).collect();
+    let count = text.chars().count();
+    text.extend(std::iter::repeat_n(' ', width - count));
+
+    text
+}
+
+/// Shows the matches of `search` in `line` in reverse video, ignoring the ASCII case.
+fn highlight(line: &str, search: Option<&str>) -> String {
+    let Some(search) = search.filter(|search| !search.is_empty()) else {
+        return line.to_string();
+    };
+    let lowercase = line.to_ascii_lowercase();
+    let search = search.to_ascii_lowercase();
+
+    let mut output = String::new();
+    let mut start = 0;
+    while let Some(found) = lowercase[start..].find(&search) {
+        let found = start + found;
+        let end = found + search.len();
+        output.push_str(&line[start..found]);
+        output.push_str(&format!("\x1b[7m{}\x1b[27m", &line[found..end]));
+        start = end;
+    }
+    output.push_str(&line[start..]);
+
+    output
+}
+
+/// Encodes `bytes` in base64, for the clipboard sequence.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::new();
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (index, byte)| {
+            value | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (value >> (18 - 6 * index)) & 0x3f;
+                output.push(ALPHABET[sextet as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+/// The terminal in raw mode on the alternate screen, restored when dropped.
+struct Screen {
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        let screen = Self::raw_mode()?;
+
+        let mut stdout = io::stdout();
+        stdout.write_all(b"\x1b[?1049h\x1b[?25l")?;
+        stdout.flush()?;
+
+        Ok(screen)
+    }
+
+    #[cfg(unix)]
+    fn raw_mode() -> io::Result<Self> {
+        let mut original = std::mem::MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr initializes the termios it's given when it succeeds
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, original.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            original.assume_init()
+        };
+        let mut raw = original;
+        // SAFETY: raw is a valid termios
+        unsafe {
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(Self { original })
+    }
+
+    #[cfg(not(unix))]
+    fn raw_mode() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw mode isn't supported on this platform",
+        ))
+    }
+
+    fn draw(&self, screen: &str) -> io::Result<()> {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(screen.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        #[cfg(unix)]
+        // SAFETY: original is the termios tcgetattr returned
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Browses the `entries` until the browser is closed. `rerun` runs the command again in a
+/// repository, None if it can't anymore.
+///
+/// Returns the result of the repositories run again, the last one of each.
+pub fn browse<F>(entries: Vec<Entry>, mut rerun: F) -> io::Result<Vec<RunResult>>
+where
+    F: FnMut(&Path) -> Option<RunResult>,
+{
+    let screen = Screen::enter()?;
+    let mut browser = Browser::new(entries);
+    let mut reruns: HashMap<PathBuf, RunResult> = HashMap::new();
+    let mut stdin = io::stdin().lock();
+
+    loop {
+        let (width, height) = terminal_size::terminal_size()
+            .map(|(width, height)| (width.0 as usize, height.0 as usize))
+            .unwrap_or((80, 24));
+        screen.draw(&browser.render(width, height))?;
+
+        let mut buffer = [0; 64];
+        let read = stdin.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for key in parse_keys(&buffer[..read]) {
+            match browser.handle(key, height.saturating_sub(1)) {
+                Action::None => {}
+                Action::Quit => return Ok(reruns.into_values().collect()),
+                Action::Rerun(path) => {
+                    let display = browser.entries[browser.selected].display.clone();
+                    browser.message = Some(format!("running again in {}…", display));
+                    screen.draw(&browser.render(width, height))?;
+
+                    match rerun(&path) {
+                        Some(result) => {
+                            browser.replace(Entry::ran(display, &result));
+                            browser.message = Some(
+                                if result.success {
+                                    "succeeded"
+                                } else {
+                                    "failed"
+                                }
+                                .to_string(),
+                            );
+                            reruns.insert(path, result);
+                        }
+                        None => {
+                            browser.message = Some("the run was stopped, not running".to_string())
+                        }
+                    }
+                }
+                Action::Copy(path) => {
+                    // OSC 52 sets the clipboard, the terminals not supporting it ignore it
+                    let path = path.to_string_lossy();
+                    screen.draw(&format!("\x1b]52;c;{}\x07", base64(path.as_bytes())))?;
+                    browser.message = Some(format!("copied {}", path));
+                }
+            }
+        }
+    }
+
+    Ok(reruns.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(group: Group, display: &str, lines: &[&str]) -> Entry {
+        Entry {
+            group,
+            display: display.to_string(),
+            path: PathBuf::from("/src").join(display),
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            vec![
+                Key::Up,
+                Key::Char('j'),
+                Key::PageDown,
+                Key::Char('é'),
+                Key::Enter,
+                Key::Escape
+            ],
+            parse_keys("\x1b[Aj\x1b[6~é\r\x1b".as_bytes())
+        );
+        // An unknown sequence is skipped whole
+        assert_eq!(vec![Key::Char('q')], parse_keys(b"\x1b[1;5Cq"));
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut browser = Browser::new(vec![
+            entry(Group::Succeeded, "clean", &["ok"]),
+            entry(Group::Failed, "dirty", &["a", "error: b", "c", "error: d"]),
+            entry(Group::Skipped, "old", &["read-only"]),
+        ]);
+        // The failures come first
+        assert_eq!("dirty", browser.entries[browser.selected].display);
+
+        for key in [Key::Char('/'), Key::Char('E'), Key::Char('r'), Key::Enter] {
+            assert_eq!(Action::None, browser.handle(key, 2));
+        }
+        assert_eq!(1, browser.scroll);
+        assert_eq!(Some("match 1 of 2"), browser.message.as_deref());
+        browser.handle(Key::Char('n'), 2);
+        assert_eq!(3, browser.scroll);
+        browser.handle(Key::Char('n'), 2);
+        assert_eq!(1, browser.scroll);
+
+        assert_eq!(
+            Action::Rerun(PathBuf::from("/src/dirty")),
+            browser.handle(Key::Char('r'), 2)
+        );
+        browser.handle(Key::Down, 2);
+        browser.handle(Key::Down, 2);
+        assert_eq!(Action::None, browser.handle(Key::Char('r'), 2));
+        assert_eq!(
+            Action::Copy(PathBuf::from("/src/old")),
+            browser.handle(Key::Char('y'), 2)
+        );
+        assert_eq!(Action::Quit, browser.handle(Key::Char('q'), 2));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut browser = Browser::new(vec![
+            entry(Group::Succeeded, "clean", &["ok"]),
+            entry(Group::Failed, "dirty", &["error: nope"]),
+        ]);
+        browser.search = Some("nope".to_string());
+
+        let screen = browser.render(40, 5);
+        let rows: Vec<&str> = screen.split("\r\n").collect();
+        assert_eq!(5, rows.len());
+        assert_eq!(
+            "\x1b[H\x1b[1mFailed (1)   \x1b[22m│error: \x1b[7mnope\x1b[27m\x1b[K",
+            rows[0]
+        );
+        assert_eq!("\x1b[7m  dirty      \x1b[27m│\x1b[K", rows[1]);
+        assert_eq!("\x1b[1mSucceeded (1)\x1b[22m│\x1b[K", rows[2]);
+        assert!(rows[4].starts_with("\x1b[7mdirty: 1/1"), "{:?}", rows[4]);
+    }
+
+    #[test]
+    fn test_push_output() {
+        let mut lines = Vec::new();
+        push_output(
+            &mut lines,
+            "\x1b[31mred\x1b[m\nReceiving:  50%\rReceiving: 100%\r\n\tdone\n",
+        );
+        assert_eq!(vec!["red", "Receiving: 100%", "    done"], lines);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!("", base64(b""));
+        assert_eq!("Zg==", base64(b"f"));
+        assert_eq!("Zm8=", base64(b"fo"));
+        assert_eq!("Zm9v", base64(b"foo"));
+        assert_eq!("L3NyYy9mb28=", base64(b"/src/foo"));
+    }
+}
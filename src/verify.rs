@@ -0,0 +1,217 @@
+//! Verify the signature of the HEAD commit of a repository.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::anyhow;
+use serde::Serialize;
+
+use crate::git::Git;
+
+/// How the signature of a commit checked out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum Signature {
+    /// Signed with a good signature by a trusted key
+    Trusted {
+        /// The abbreviated commit
+        commit: String,
+        /// Who signed the commit
+        signer: String,
+        /// The fingerprint of the key
+        key: String,
+    },
+    /// Signed with a good signature, by a key that isn't trusted or can't be found
+    UnknownKey {
+        /// The abbreviated commit
+        commit: String,
+        /// Who signed the commit, if known
+        signer: Option<String>,
+        /// The fingerprint or the ID of the key
+        key: String,
+    },
+    /// Not signed
+    Unsigned {
+        /// The abbreviated commit
+        commit: String,
+    },
+    /// The signature is bad, expired or revoked, or it couldn't be checked
+    Error {
+        /// What's wrong
+        error: String,
+    },
+    /// The repository is exempted from the verification by the config
+    Exempted,
+}
+
+impl Signature {
+    /// Returns the name of the outcome, without its details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Signature::Trusted { .. } => "trusted",
+            Signature::UnknownKey { .. } => "unknown key",
+            Signature::Unsigned { .. } => "unsigned",
+            Signature::Error { .. } => "error",
+            Signature::Exempted => "exempted",
+        }
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Signature::Trusted {
+                commit,
+                signer,
+                key,
+            } => write!(f, "{} trusted, signed by {} with {}", commit, signer, key),
+            Signature::UnknownKey {
+                commit,
+                signer: Some(signer),
+                key,
+            } => write!(
+                f,
+                "{} unknown key, signed by {} with {}",
+                commit, signer, key
+            ),
+            Signature::UnknownKey {
+                commit,
+                signer: None,
+                key,
+            } => write!(f, "{} unknown key, signed with {}", commit, key),
+            Signature::Unsigned { commit } => write!(f, "{} unsigned", commit),
+            Signature::Error { error } => write!(f, "error: {}", error),
+            Signature::Exempted => f.write_str("exempted"),
+        }
+    }
+}
+
+/// Verifies the signature of the HEAD commit of the repository at `path`, with the GPG, SSH or
+/// X.509 settings of the repository.
+pub fn verify(git: &Git, path: &Path) -> Signature {
+    match probe(git, path) {
+        Ok(signature) => signature,
+        Err(err) => Signature::Error {
+            error: err.to_string(),
+        },
+    }
+}
+
+fn probe(git: &Git, path: &Path) -> anyhow::Result<Signature> {
+    let output = git
+        .command(path)
+        .args([
+            "log",
+            "-1",
+            "--format=%h%x00%G?%x00%GS%x00%GK",
+            "HEAD",
+            "--",
+        ])
+        .output()
+        .map_err(|err| anyhow!("unable to run {}: {}", git.program().display(), err))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(anyhow!("git log failed: {}", stderr.trim()));
+    }
+
+    let signature = parse(String::from_utf8_lossy(&output.stdout).trim_end())?;
+
+    // git says a commit isn't signed when it can't find the program to check the signature,
+    // it's only unsigned if there's no signature in the commit object
+    if matches!(signature, Signature::Unsigned { .. }) {
+        let object = git
+            .stdout(path, &["cat-file", "commit", "HEAD"])
+            .ok_or_else(|| anyhow!("git cat-file failed"))?;
+        let headers = object.split("\n\n").next().unwrap_or("");
+        if headers
+            .lines()
+            .any(|line| line.starts_with("gpgsig ") || line.starts_with("gpgsig-sha256 "))
+        {
+            let error = stderr
+                .lines()
+                .next()
+                .map(|line| line.trim().trim_start_matches("error: "))
+                .filter(|line| !line.is_empty())
+                .unwrap_or("the signature couldn't be checked");
+            return Err(anyhow!("{}", error));
+        }
+    }
+
+    Ok(signature)
+}
+
+/// Parses the abbreviated commit, `%G?`, `%GS` and `%GK` separated by NUL bytes.
+fn parse(output: &str) -> anyhow::Result<Signature> {
+    let mut fields = output.split('\0');
+    let (Some(commit), Some(status), signer, key) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err(anyhow!("unable to parse the output of git log: {}", output));
+    };
+    let commit = commit.to_string();
+    let signer = signer
+        .filter(|signer| !signer.is_empty())
+        .map(str::to_string);
+    let key = key.unwrap_or("").to_string();
+
+    let error = |error: &str| Signature::Error {
+        error: format!("{} {}", commit, error),
+    };
+
+    Ok(match status {
+        "G" => Signature::Trusted {
+            commit,
+            signer: signer.unwrap_or_default(),
+            key,
+        },
+        "U" | "E" => Signature::UnknownKey {
+            commit,
+            signer,
+            key,
+        },
+        "N" => Signature::Unsigned { commit },
+        "B" => error("bad signature"),
+        "X" => error("signature expired"),
+        "Y" => error("signed by an expired key"),
+        "R" => error("signed by a revoked key"),
+        status => error(&format!("unknown signature status {}", status)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Signature::Trusted {
+                commit: "3eace03".to_string(),
+                signer: "jane@example.com".to_string(),
+                key: "SHA256:FKGo0dodnWJRSX8H".to_string(),
+            },
+            parse("3eace03\0G\0jane@example.com\0SHA256:FKGo0dodnWJRSX8H").unwrap()
+        );
+        assert_eq!(
+            Signature::UnknownKey {
+                commit: "3eace03".to_string(),
+                signer: None,
+                key: "4AEE18F83AFDEB23".to_string(),
+            },
+            parse("3eace03\0E\0\x004AEE18F83AFDEB23").unwrap()
+        );
+        assert_eq!(
+            Signature::Unsigned {
+                commit: "3eace03".to_string()
+            },
+            parse("3eace03\0N\0\0").unwrap()
+        );
+        assert_eq!(
+            Signature::Error {
+                error: "3eace03 signed by a revoked key".to_string()
+            },
+            parse("3eace03\0R\0jane\0ABCD").unwrap()
+        );
+        assert!(parse("").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+/// The version of the binary along with the build metadata baked in by build.rs.
+#[derive(Debug, Serialize)]
+pub struct Version {
+    pub version: &'static str,
+    /// The abbreviated git commit, if the binary was built from a git checkout
+    pub commit: Option<&'static str>,
+    pub build_date: &'static str,
+}
+
+impl Version {
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: Some(env!("GITJUGGLING_COMMIT")).filter(|commit| !commit.is_empty()),
+            build_date: env!("GITJUGGLING_BUILD_DATE"),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        match self.commit {
+            Some(commit) => format!(
+                "gitjuggling {} ({} {})\n",
+                self.version, commit, self.build_date
+            ),
+            None => format!("gitjuggling {} ({})\n", self.version, self.build_date),
+        }
+    }
+
+    pub fn render_json(&self) -> String {
+        let mut json = serde_json::to_string(self).unwrap();
+        json.push('\n');
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut version = Version {
+            version: "1.4.0",
+            commit: Some("c692ad7"),
+            build_date: "2026-10-14",
+        };
+        assert_eq!("gitjuggling 1.4.0 (c692ad7 2026-10-14)\n", version.render());
+        assert_eq!(
+            "{\"version\":\"1.4.0\",\"commit\":\"c692ad7\",\"build_date\":\"2026-10-14\"}\n",
+            version.render_json()
+        );
+
+        version.commit = None;
+        assert_eq!("gitjuggling 1.4.0 (2026-10-14)\n", version.render());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+use gitjuggling::{RunResult, Runner};
+use notify::event::{AccessKind, AccessMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// A repository is re-run once no file under it changed for this long.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Entries of the .git directory ignored by default, they change without the repository
+/// state changing.
+pub const DEFAULT_GIT_EXCLUDES: &[&str] = &["FETCH_HEAD", "ORIG_HEAD", "objects", "logs"];
+
+/// Decides which file changes trigger a re-run.
+pub struct Filter {
+    /// Entries of the .git directory whose changes are ignored
+    pub git_excludes: Vec<String>,
+}
+
+impl Filter {
+    /// Returns true if a change of `path` in the repository at `repository` should re-run it.
+    ///
+    /// `just_ran` is true if the command of the repository was running when the change
+    /// happened: commands like git status refresh the index, that's not a change worth a re-run.
+    fn is_relevant(&self, repository: &Path, path: &Path, just_ran: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(repository) else {
+            return false;
+        };
+
+        let mut components = relative.components();
+        if components.next() != Some(Component::Normal(OsStr::new(".git"))) {
+            return true;
+        }
+        if just_ran {
+            return false;
+        }
+
+        match components.next() {
+            Some(Component::Normal(name)) => {
+                let name = name.to_string_lossy();
+                !name.ends_with(".lock")
+                    && !self.git_excludes.iter().any(|exclude| *exclude == name)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Returns the index of the repository containing `path`, the innermost if they're nested.
+fn repository_of(paths: &[PathBuf], path: &Path) -> Option<usize> {
+    paths
+        .iter()
+        .enumerate()
+        .filter(|(_, repository)| path.starts_with(repository))
+        .max_by_key(|(_, repository)| repository.components().count())
+        .map(|(index, _)| index)
+}
+
+/// Runs the command in all `paths` then again in each repository whose files changed, until
+/// `stop` is set.
+///
+/// `render` formats the results of all repositories, it's redrawn in-place on a terminal.
+/// Returns the last result of each repository.
+pub fn run<T, F, R>(
+    runner: &Runner,
+    paths: &[PathBuf],
+    filter: &Filter,
+    stop: &AtomicBool,
+    make_item: F,
+    render: R,
+) -> anyhow::Result<Vec<T>>
+where
+    F: Fn(usize, RunResult) -> T,
+    R: Fn(&[&T]) -> String,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for path in paths {
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+
+    let is_terminal = io::stdout().is_terminal();
+    let draw = |state: &[Option<T>]| {
+        let items: Vec<&T> = state.iter().flatten().collect();
+
+        let mut stdout = io::stdout().lock();
+        if is_terminal {
+            // Move to the top left and clear the screen
+            let _ = stdout.write_all(b"\x1b[H\x1b[2J");
+        }
+        let _ = stdout.write_all(render(&items).as_bytes());
+        let _ = writeln!(
+            stdout,
+            "watching {} repositories, updated at {}, press Ctrl-C to exit",
+            paths.len(),
+            humantime::format_rfc3339_seconds(SystemTime::now())
+        );
+        let _ = stdout.flush();
+    };
+
+    let all: Vec<usize> = (0..paths.len()).collect();
+    let mut state: Vec<Option<T>> = (0..paths.len()).map(|_| None).collect();
+    let mut ran_at: Vec<Option<Instant>> = vec![None; paths.len()];
+    let mut pending: HashMap<usize, Instant> = HashMap::new();
+
+    let mut to_run = all;
+    loop {
+        if !to_run.is_empty() {
+            let batch: Vec<PathBuf> = to_run.iter().map(|&index| paths[index].clone()).collect();
+            let results = runner.run_with(&batch, |batch_index, result| {
+                result.map(|result| (to_run[batch_index], result))
+            });
+
+            let now = Instant::now();
+            for (index, result) in results {
+                state[index] = Some(make_item(index, result));
+                ran_at[index] = Some(now);
+            }
+
+            draw(&state);
+        }
+
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+
+        match rx.recv_timeout(DEBOUNCE / 3) {
+            Ok(Ok(event)) => {
+                let is_write = match event.kind {
+                    EventKind::Access(kind) => kind == AccessKind::Close(AccessMode::Write),
+                    _ => true,
+                };
+
+                for path in event.paths.iter().filter(|_| is_write) {
+                    let Some(index) = repository_of(paths, path) else {
+                        continue;
+                    };
+                    let just_ran = ran_at[index].is_some_and(|at| at.elapsed() < DEBOUNCE);
+
+                    if filter.is_relevant(&paths[index], path, just_ran) {
+                        pending.insert(index, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(err)) => return Err(err.into()),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        to_run = pending
+            .iter()
+            .filter(|(_, changed_at)| changed_at.elapsed() >= DEBOUNCE)
+            .map(|(&index, _)| index)
+            .collect();
+        to_run.sort();
+        for index in &to_run {
+            pending.remove(index);
+        }
+    }
+
+    Ok(state.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_relevant() {
+        let filter = Filter {
+            git_excludes: DEFAULT_GIT_EXCLUDES
+                .iter()
+                .map(|exclude| exclude.to_string())
+                .collect(),
+        };
+        let repository = Path::new("/src/foo");
+
+        assert!(filter.is_relevant(repository, Path::new("/src/foo/main.rs"), false));
+        assert!(filter.is_relevant(repository, Path::new("/src/foo/main.rs"), true));
+        assert!(filter.is_relevant(repository, Path::new("/src/foo/.git/index"), false));
+        assert!(!filter.is_relevant(repository, Path::new("/src/foo/.git/index"), true));
+        assert!(!filter.is_relevant(repository, Path::new("/src/foo/.git/index.lock"), false));
+        assert!(!filter.is_relevant(repository, Path::new("/src/foo/.git/FETCH_HEAD"), false));
+        assert!(!filter.is_relevant(
+            repository,
+            Path::new("/src/foo/.git/objects/ab/cdef"),
+            false
+        ));
+    }
+
+    #[test]
+    fn test_repository_of() {
+        let paths = vec![PathBuf::from("/src/foo"), PathBuf::from("/src/foo/bar")];
+
+        assert_eq!(
+            Some(0),
+            repository_of(&paths, Path::new("/src/foo/main.rs"))
+        );
+        assert_eq!(
+            Some(1),
+            repository_of(&paths, Path::new("/src/foo/bar/main.rs"))
+        );
+        assert_eq!(None, repository_of(&paths, Path::new("/src/baz/main.rs")));
+    }
+}
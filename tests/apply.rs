@@ -0,0 +1,94 @@
+mod common;
+
+use std::path::Path;
+
+use common::{git, Fixture};
+
+fn init(path: &Path, readme: &str) {
+    std::fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q", "-b", "main"]);
+    std::fs::write(path.join("README"), readme).unwrap();
+    std::fs::write(path.join("notes"), "").unwrap();
+    git(path, &["add", "."]);
+    git(path, &["commit", "-q", "-m", "init"]);
+}
+
+#[test]
+fn test_apply_all() {
+    let fixture = Fixture::empty();
+    let base = fixture.path("../base");
+    let work = fixture.root.clone();
+
+    init(&base, "hello\n");
+    std::fs::write(base.join("README"), "hello world\n").unwrap();
+    git(&base, &["commit", "-q", "-a", "-m", "Greet the world"]);
+    let patch = fixture.path("../readme.patch");
+    std::fs::write(&patch, git(&base, &["diff", "HEAD~1"]) + "\n").unwrap();
+    let mbox = fixture.path("../readme.mbox");
+    std::fs::write(
+        &mbox,
+        git(&base, &["format-patch", "-1", "--stdout"]) + "\n",
+    )
+    .unwrap();
+
+    init(&work.join("clean"), "hello\n");
+    init(&work.join("applied"), "hello world\n");
+    init(&work.join("conflict"), "bonjour\n");
+    init(&work.join("dirty"), "hello\n");
+    std::fs::write(work.join("dirty/notes"), "todo").unwrap();
+
+    let output = fixture
+        .subcommand("apply-all", &[])
+        .env("GIT_AUTHOR_NAME", "test")
+        .env("GIT_AUTHOR_EMAIL", "test@example.com")
+        .env("GIT_COMMITTER_NAME", "test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .arg("--patch")
+        .arg(&patch)
+        .args(["--branch", "fix/readme", "-m", "Update the README"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        "conflict conflict, left untouched: error: patch failed: README:1\n\
+         dirty    dirty\n\
+         clean    applied and committed\n\
+         applied  already applied\n\
+         \n\
+         1 applied, 1 already applied, 2 need attention (1 conflict, 1 dirty), 0 failed\n",
+        stdout
+    );
+
+    let clean = work.join("clean");
+    assert_eq!("fix/readme", git(&clean, &["branch", "--show-current"]));
+    assert_eq!(
+        "Update the README",
+        git(&clean, &["log", "-1", "--format=%s"])
+    );
+    assert_eq!("", git(&clean, &["status", "--porcelain"]));
+    let conflict = work.join("conflict");
+    assert_eq!("main", git(&conflict, &["branch", "--show-current"]));
+    assert_eq!("", git(&conflict, &["status", "--porcelain"]));
+
+    // The commit of a mailbox keeps its message
+    for name in ["clean", "applied", "conflict", "dirty"] {
+        std::fs::remove_dir_all(work.join(name)).unwrap();
+    }
+    init(&work.join("clean"), "hello\n");
+    let output = fixture
+        .subcommand("apply-all", &[])
+        .env("GIT_COMMITTER_NAME", "test")
+        .env("GIT_COMMITTER_EMAIL", "test@example.com")
+        .arg("--patch")
+        .arg(&mbox)
+        .arg("--am")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        "Greet the world",
+        git(&work.join("clean"), &["log", "-1", "--format=%s"])
+    );
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_audit() {
+    let fixture = Fixture::empty();
+    let audit = |args: &[&str]| fixture.subcommand("audit", args).output().unwrap();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    std::fs::write(upstream.join("README"), "hello\n").unwrap();
+    git(&upstream, &["add", "README"]);
+    git(&upstream, &["commit", "-q", "-m", "init"]);
+    git(work, &["clone", "-q", "../upstream", "clean"]);
+    git(work, &["clone", "-q", "../upstream", "local"]);
+
+    let local = work.join("local");
+    git(&local, &["commit", "-q", "--allow-empty", "-m", "unpushed"]);
+    git(&local, &["branch", "wip"]);
+    std::fs::write(local.join("README"), "hello\nworld\n").unwrap();
+    std::fs::write(local.join("notes.txt"), "todo").unwrap();
+
+    let output = audit(&[]);
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    assert_eq!(
+        "local\n  \
+         uncommitted changes: 1 file changed, 1 insertion(+)\n  \
+         untracked files: 1\n  \
+         no upstream: wip\n  \
+         ahead: main by 1\n\
+         \n\
+         1 need attention, 1 clean\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let output = audit(&["--all", "--format", "json"]);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("clean", entries[0]["name"]);
+    assert_eq!(true, entries[0]["clean"]);
+    assert_eq!(1, entries[1]["ahead"][0]["commits"]);
+
+    let output = audit(&["--exclude", "local"]);
+    assert_eq!(Some(0), output.status.code(), "{:?}", output);
+}
@@ -0,0 +1,67 @@
+//! The libgit2 backend must give the same answers as the git CLI.
+
+#![cfg(feature = "libgit2")]
+
+mod common;
+
+use std::path::Path;
+
+use gitjuggling::{Backend, Git};
+
+use common::git;
+
+fn assert_equivalent(path: &Path) {
+    let git = Git::default();
+    let cli = Backend::Cli;
+    let libgit2 = Backend::Libgit2;
+
+    assert_eq!(
+        cli.current_branch(&git, path),
+        libgit2.current_branch(&git, path)
+    );
+    assert_eq!(cli.is_dirty(&git, path), libgit2.is_dirty(&git, path));
+    assert_eq!(
+        cli.ahead_behind(&git, path),
+        libgit2.ahead_behind(&git, path)
+    );
+}
+
+#[test]
+fn test_backends_equivalence() {
+    let dir = tempfile::tempdir().unwrap();
+    let upstream = dir.path().join("upstream");
+    let clone = dir.path().join("clone");
+
+    // Unborn branch
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    assert_equivalent(&upstream);
+
+    // Untracked then committed file
+    std::fs::write(upstream.join("foo"), "foo").unwrap();
+    assert_equivalent(&upstream);
+    git(&upstream, &["add", "foo"]);
+    git(&upstream, &["commit", "-q", "-m", "foo"]);
+    assert_equivalent(&upstream);
+
+    // Ahead and behind its upstream
+    git(dir.path(), &["clone", "-q", "upstream", "clone"]);
+    assert_equivalent(&clone);
+    git(
+        &upstream,
+        &["commit", "-q", "--allow-empty", "-m", "upstream"],
+    );
+    git(&clone, &["commit", "-q", "--allow-empty", "-m", "clone"]);
+    git(&clone, &["fetch", "-q"]);
+    assert_eq!(
+        Some((1, 1)),
+        Backend::Libgit2.ahead_behind(&Git::default(), &clone)
+    );
+    assert_equivalent(&clone);
+
+    // Modified file and detached HEAD
+    std::fs::write(clone.join("foo"), "bar").unwrap();
+    assert_equivalent(&clone);
+    git(&clone, &["checkout", "-q", "--detach"]);
+    assert_equivalent(&clone);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::process::Output;
+
+use common::{git, Fixture};
+
+fn branches(fixture: &Fixture, args: &[&str]) -> Output {
+    let output = fixture.subcommand("branches", args).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    output
+}
+
+#[test]
+fn test_branches() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(work, &["clone", "-q", "../upstream", "foo"]);
+
+    let foo = work.join("foo");
+    git(&foo, &["branch", "done"]);
+    git(&foo, &["checkout", "-q", "-b", "wip"]);
+    git(&foo, &["commit", "-q", "--allow-empty", "-m", "wip"]);
+
+    let output = branches(&fixture, &["--format", "json"]);
+    let repositories: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("origin/main", repositories[0]["default"]);
+    let names: Vec<(&str, bool)> = repositories[0]["branches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|branch| {
+            (
+                branch["name"].as_str().unwrap(),
+                branch["merged"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(vec![("done", true), ("main", false), ("wip", false)], names);
+
+    // Nothing is older than a day
+    let output = branches(&fixture, &["--stale", "1day"]);
+    assert!(output.stdout.is_empty(), "{:?}", output);
+
+    let output = branches(&fixture, &["--delete-merged", "--dry-run"]);
+    assert_eq!(
+        "foo: would delete done\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(git(&foo, &["branch", "--list", "done"]).contains("done"));
+
+    branches(&fixture, &["--delete-merged"]);
+    assert_eq!("", git(&foo, &["branch", "--list", "done"]));
+    assert!(git(&foo, &["branch", "--list", "main"]).contains("main"));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::Fixture;
+
+/// Runs gitjuggling with a git printing its arguments, returns the lines it printed.
+fn git_args(fixture: &Fixture, args: &[&str]) -> Vec<String> {
+    let output = fixture
+        .command(&["--no-branch"])
+        .args(args)
+        .env("GITJUGGLING_GIT", fixture.path("../git-wrapper"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("git "))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_git_args() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = Fixture::empty();
+    std::fs::create_dir_all(fixture.path("foo/.git")).unwrap();
+    let wrapper = fixture.path("../git-wrapper");
+    std::fs::write(
+        &wrapper,
+        "#!/bin/sh\n[ \"$1\" = config ] && exit 1\necho \"git $*\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let run = |args: &[&str]| git_args(&fixture, args);
+
+    // The flags after the git subcommand go to git
+    assert_eq!(vec!["log --oneline -5"], run(&["log", "--oneline", "-5"]));
+    assert_eq!(
+        vec!["push --force-with-lease"],
+        run(&["push", "--force-with-lease"])
+    );
+
+    // So do the ones before it that gitjuggling doesn't know
+    assert_eq!(
+        vec!["-c core.quotepath=false ls-files"],
+        run(&["-c", "core.quotepath=false", "ls-files"])
+    );
+
+    // gitjuggling options before the git arguments are still its own
+    assert_eq!(
+        vec!["log --oneline"],
+        run(&["--depth", "2", "log", "--oneline"])
+    );
+    assert_eq!(vec!["log -v"], run(&["log", "-v"]));
+    let output = fixture
+        .command(&["-v", "--no-branch", "log"])
+        .env("GITJUGGLING_GIT", &wrapper)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("foo executing log\n  cwd: "), "{}", stdout);
+
+    // Everything after -- goes to git, even what gitjuggling knows
+    assert_eq!(
+        vec!["-c foo=bar status"],
+        run(&["--", "-c", "foo=bar", "status"])
+    );
+    assert_eq!(vec!["-v --depth 2"], run(&["--", "-v", "--depth", "2"]));
+}
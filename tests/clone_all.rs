@@ -0,0 +1,91 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_clone_all() {
+    let fixture = Fixture::empty();
+    let remotes = fixture.path("../remotes");
+    let work = &fixture.root;
+
+    for name in ["foo", "bar"] {
+        let remote = remotes.join("team").join(name);
+        std::fs::create_dir_all(&remote).unwrap();
+        git(&remote, &["init", "-q", "-b", "main"]);
+        git(&remote, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    }
+    // bar is already cloned, and something else is where baz would go
+    std::fs::create_dir_all(work.join("team")).unwrap();
+    git(
+        &work.join("team"),
+        &["clone", "-q", remotes.join("team/bar").to_str().unwrap()],
+    );
+    std::fs::create_dir_all(work.join("team/baz")).unwrap();
+    std::fs::write(work.join("team/baz/notes.txt"), "todo").unwrap();
+
+    // The directories of file:// URLs would be their whole path
+    let url = |name: &str| format!("https://git.example.com/team/{}", name);
+    let list = fixture.path("../urls.txt");
+    std::fs::write(
+        &list,
+        format!(
+            "# the team\n{}\n{}\n\n{}\n{}\n",
+            url("foo"),
+            url("bar"),
+            url("baz"),
+            url("missing.git")
+        ),
+    )
+    .unwrap();
+
+    let output = fixture
+        .command_in_root(&["clone-all", "--into"])
+        .arg(work)
+        .arg(&list)
+        .env("GIT_CONFIG_COUNT", "1")
+        .env(
+            "GIT_CONFIG_KEY_0",
+            format!("url.file://{}/.insteadOf", remotes.display()),
+        )
+        .env("GIT_CONFIG_VALUE_0", "https://git.example.com/")
+        .output()
+        .unwrap();
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(
+        lines[0].starts_with(&format!(
+            "team/baz     failed, {}: {} exists and isn't a git repository",
+            url("baz"),
+            work.join("team/baz").display()
+        )),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[1].starts_with(&format!(
+            "team/missing failed, {}: fatal: '{}' does not appear to be a git repository",
+            url("missing.git"),
+            remotes.join("team/missing.git").display()
+        )),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        &[
+            "team/foo     cloned",
+            "team/bar     already present",
+            "",
+            "1 cloned, 1 already present, 2 failed",
+            "",
+            "Failed URLs:",
+            &url("baz"),
+            &url("missing.git"),
+        ],
+        &lines[2..]
+    );
+
+    assert!(work.join("team/foo/.git").is_dir());
+    assert!(!work.join("team/missing").exists());
+}
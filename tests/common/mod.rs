@@ -0,0 +1,173 @@
+//! The helpers shared by the integration tests: git without the user configuration and a tree
+//! of real repositories to run gitjuggling on.
+#![allow(dead_code)]
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Runs git in `path` with a fixed identity and without the global and system configurations,
+/// returns its trimmed stdout.
+pub fn git(path: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args([
+            "-c",
+            "protocol.file.allow=always",
+            "-c",
+            "init.defaultBranch=main",
+        ])
+        .args(args)
+        .current_dir(path)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {:?}",
+        args,
+        output
+    );
+
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Creates an empty repository at `path`.
+pub fn init(path: &Path) {
+    std::fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q"]);
+}
+
+/// Creates a repository with a single commit at `path`.
+pub fn repository(path: &Path) {
+    std::fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q"]);
+    std::fs::write(path.join("README"), "hello\n").unwrap();
+    git(path, &["add", "README"]);
+    git(path, &["commit", "-q", "-m", "init"]);
+}
+
+/// A temporary directory with one repository of every kind gitjuggling has to deal with:
+///
+/// - `clean`, a repository with a commit
+/// - `dirty`, a repository with a modified file
+/// - `bare.git`, a bare repository
+/// - `clean-worktree`, a worktree of `clean`
+/// - `super`, a repository with `clean` as a submodule in `super/lib`
+pub struct Fixture {
+    dir: tempfile::TempDir,
+    /// The canonical path of the tree, gitjuggling prints canonical paths
+    pub root: PathBuf,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        let fixture = Self::empty();
+        let root = &fixture.root;
+
+        repository(&root.join("clean"));
+
+        repository(&root.join("dirty"));
+        std::fs::write(root.join("dirty/README"), "changed\n").unwrap();
+
+        std::fs::create_dir(root.join("bare.git")).unwrap();
+        git(&root.join("bare.git"), &["init", "-q", "--bare"]);
+
+        git(
+            &root.join("clean"),
+            &["worktree", "add", "-q", "../clean-worktree"],
+        );
+
+        repository(&root.join("super"));
+        let url = root.join("clean");
+        git(
+            &root.join("super"),
+            &["submodule", "add", "-q", url.to_str().unwrap(), "lib"],
+        );
+        git(&root.join("super"), &["commit", "-q", "-m", "add lib"]);
+
+        fixture
+    }
+
+    /// Returns a tree without any repository, for the tests that make their own. The parent
+    /// directory of the root is free for what has to be out of the tree, like an upstream.
+    pub fn empty() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        Self { dir, root }
+    }
+
+    /// Returns the absolute path of `name` in the tree.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+
+    /// Returns the gitjuggling command on the tree, with `args` after `--root`: other directories
+    /// for the configuration and the state, no colors, the usual output even in CI and a git
+    /// without the global and system configurations.
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = self.command_in_root(&["--root"]);
+        command.arg(&self.root).args(args);
+        command
+    }
+
+    /// Returns the gitjuggling command started in the tree instead of given it with `--root`, for
+    /// the arguments that have to come first like a subcommand.
+    pub fn command_in_root(&self, args: &[&str]) -> Command {
+        let mut command = self.isolated(env!("CARGO_BIN_EXE_gitjuggling"));
+        command.args(args);
+        command
+    }
+
+    /// Returns `program` started in the tree with the environment of [`Fixture::command`], for
+    /// gitjuggling started another way like through git.
+    pub fn isolated(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        command
+            .current_dir(&self.root)
+            .env("XDG_CONFIG_HOME", self.dir.path())
+            .env("XDG_DATA_HOME", self.dir.path())
+            .env("XDG_STATE_HOME", self.dir.path())
+            .env("NO_COLOR", "1")
+            .env("GITJUGGLING_NO_CI", "1")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1");
+        command
+    }
+
+    /// Returns the gitjuggling `subcommand` on the tree, with `args` after `--root`.
+    pub fn subcommand(&self, subcommand: &str, args: &[&str]) -> Command {
+        let mut command = self.command_in_root(&[subcommand, "--root"]);
+        command.arg(&self.root).args(args);
+        command
+    }
+
+    /// Runs gitjuggling on the tree with `args`.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    /// Returns the repositories gitjuggling finds in the tree with `args`, relative to the root.
+    pub fn list(&self, args: &[&str]) -> Vec<String> {
+        let mut list_args = vec!["--list"];
+        list_args.extend_from_slice(args);
+        let output = self.run(&list_args);
+        assert!(output.status.success(), "{:?}", output);
+
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                Path::new(line)
+                    .strip_prefix(&self.root)
+                    .unwrap()
+                    .display()
+                    .to_string()
+            })
+            .collect()
+    }
+}
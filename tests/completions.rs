@@ -0,0 +1,23 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn test_completions() {
+    let fixture = Fixture::empty();
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let output = fixture
+            .command_in_root(&["completions", shell])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "{} completions failed", shell);
+
+        let script = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            script.contains("porcelain"),
+            "{} completions incomplete",
+            shell
+        );
+    }
+}
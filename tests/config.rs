@@ -0,0 +1,250 @@
+mod common;
+
+use std::process::Output;
+
+use common::{git, init, Fixture};
+
+/// Runs `git status` with `args` and `envs` on the tree, in the porcelain format.
+fn status(fixture: &Fixture, args: &[&str], envs: &[(&str, &str)]) -> Output {
+    let output = fixture
+        .command(&["--porcelain"])
+        .args(args)
+        .arg("status")
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    output
+}
+
+/// Writes the user configuration file of the tree.
+fn write_config(fixture: &Fixture, contents: &str) {
+    std::fs::create_dir_all(fixture.path("../gitjuggling")).unwrap();
+    std::fs::write(fixture.path("../gitjuggling/config.toml"), contents).unwrap();
+}
+
+/// Returns the number of repositories the command ran in.
+fn count(output: &Output) -> usize {
+    String::from_utf8_lossy(&output.stdout).lines().count()
+}
+
+#[test]
+fn test_config_precedence() {
+    let fixture = Fixture::empty();
+    let root = &fixture.root;
+    init(&root.join("foo"));
+    init(&root.join("bar"));
+
+    write_config(
+        &fixture,
+        "depth = 1\nexcludes = [\"bar\"]\ncolour = \"never\"\n",
+    );
+
+    // The repositories are 2 levels deep, not found with the depth of the config file
+    let output = status(&fixture, &[], &[]);
+    assert_eq!(0, count(&output));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown key colour"), "{}", stderr);
+
+    // The environment overrides the config file
+    let output = status(&fixture, &[], &[("GITJUGGLING_DEPTH", "2")]);
+    assert_eq!(1, count(&output));
+
+    // The command line overrides both
+    let output = status(&fixture, &["--depth", "1"], &[("GITJUGGLING_DEPTH", "2")]);
+    assert_eq!(0, count(&output));
+    let output = status(&fixture, &["--depth", "2", "--exclude", "foo"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("bar\t\n"), "{}", stdout);
+
+    let output = status(&fixture, &["--no-config"], &[]);
+    assert_eq!(2, count(&output));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_invalid_config() {
+    let fixture = Fixture::empty();
+    write_config(&fixture, "theme = \"blue\"\n");
+
+    let output = fixture.run(&["status"]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn test_project_config() {
+    let fixture = Fixture::empty();
+    let root = &fixture.root;
+    init(&root.join("work/foo"));
+    init(&root.join("work/bar"));
+    init(&root.join("home/baz"));
+
+    write_config(&fixture, "depth = 2\n");
+    std::fs::write(
+        root.join(".gitjuggling.toml"),
+        "depth = 3\nexcludes = [\"work/bar\"]\n[groups]\nwork = [\"work/*\"]\n",
+    )
+    .unwrap();
+
+    // The project config file overrides the user one
+    let output = status(&fixture, &[], &[]);
+    assert_eq!(2, count(&output));
+
+    let output = status(&fixture, &["--group", "work"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("work/foo\t\n"), "{}", stdout);
+
+    let output = fixture
+        .command_in_root(&["config", "show", "--root"])
+        .arg(root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("depth = 3 # project config"), "{}", stdout);
+    assert!(stdout.contains("theme = \"dark\" # default"), "{}", stdout);
+    assert!(stdout.contains("groups.work = [\"work/*\"]"), "{}", stdout);
+}
+
+#[test]
+fn test_aliases() {
+    let fixture = Fixture::empty();
+    let root = &fixture.root;
+    init(&root.join("foo"));
+
+    write_config(
+        &fixture,
+        "[aliases]\nst = [\"--porcelain\", \"status\"]\nnested = [\"@st\"]\n",
+    );
+
+    let run = |args: &[&str]| fixture.run(args);
+
+    let output = run(&["@st"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(1, count(&output));
+
+    let output = run(&["--alias", "st"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(1, count(&output));
+
+    // Extra arguments are appended to the expansion
+    let output = run(&["--dry-run", "@st", "--short"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("@st expands to --porcelain status\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.ends_with("foo: git status --short\n"), "{}", stdout);
+
+    assert_eq!(Some(2), run(&["@nested"]).status.code());
+    assert_eq!(Some(2), run(&["@unknown"]).status.code());
+}
+
+#[test]
+fn test_profiles() {
+    let fixture = Fixture::empty();
+    let root = &fixture.root;
+    init(&root.join("work/foo"));
+    init(&root.join("home/bar"));
+
+    write_config(
+        &fixture,
+        "[profile.work]\nexcludes = [\"home/*\"]\n[profile.home]\nexcludes = [\"work/*\"]\n",
+    );
+
+    let output = status(&fixture, &["--profile", "work"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("work/foo\t\n"), "{}", stdout);
+
+    let output = status(&fixture, &[], &[("GITJUGGLING_PROFILE", "home")]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.ends_with("home/bar\t\n"), "{}", stdout);
+
+    // The command line still overrides the profile
+    let output = status(
+        &fixture,
+        &["--profile", "work", "--exclude", "nothing"],
+        &[],
+    );
+    assert_eq!(2, count(&output));
+
+    let output = fixture.run(&["--profile", "office", "status"]);
+    assert_eq!(Some(2), output.status.code());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the profiles are: home, work"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_config_set_get() {
+    let fixture = Fixture::empty();
+    let config = |args: &[&str]| {
+        fixture
+            .command_in_root(&["config"])
+            .args(args)
+            .env_remove("GITJUGGLING_JOBS")
+            .output()
+            .unwrap()
+    };
+
+    let output = config(&["get", "jobs"]);
+    assert_eq!("0 # default\n", String::from_utf8_lossy(&output.stdout));
+
+    assert!(config(&["set", "jobs", "4"]).status.success());
+    assert!(config(&["set", "excludes", r#"["archive/*"]"#])
+        .status
+        .success());
+    let output = config(&["get", "jobs"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("4 # user config "), "{}", stdout);
+
+    // Invalid values and unknown keys are rejected
+    assert_eq!(Some(2), config(&["set", "jobs", "many"]).status.code());
+    assert_eq!(Some(2), config(&["set", "colour", "never"]).status.code());
+    assert_eq!(Some(2), config(&["get", "colour"]).status.code());
+    assert_eq!(
+        "jobs = 4\nexcludes = [\"archive/*\"]\n",
+        std::fs::read_to_string(fixture.path("../gitjuggling/config.toml")).unwrap()
+    );
+
+    // Not set and without a default
+    assert_eq!(Some(1), config(&["get", "ssh_command"]).status.code());
+}
+
+#[test]
+fn test_repository_args() {
+    let fixture = Fixture::empty();
+    let root = &fixture.root;
+    init(&root.join("legacy/foo"));
+    init(&root.join("bar"));
+    init(&root.join("baz"));
+
+    std::fs::write(
+        root.join(".gitjuggling.toml"),
+        r#"
+[repos."legacy/*"]
+append = ["--rebase=false"]
+[repos."*/foo"]
+replace = ["pull", "--ff-only"]
+"#,
+    )
+    .unwrap();
+    git(
+        &root.join("baz"),
+        &["config", "gitjuggling.extra-args", "--depth 1"],
+    );
+
+    let output = fixture.run(&["--output-order", "sorted", "--dry-run", "pull"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    // The overrides apply in the order they're declared
+    assert_eq!(
+        "bar: git pull\nbaz: git pull --depth 1\nlegacy/foo: git pull --ff-only\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::{git, Fixture};
+
+fn setup() -> Fixture {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    for name in ["behind", "diverged"] {
+        git(work, &["clone", "-q", "../upstream", name]);
+    }
+
+    git(
+        &work.join("diverged"),
+        &["commit", "-q", "--allow-empty", "-m", "local"],
+    );
+    git(
+        &upstream,
+        &["commit", "-q", "--allow-empty", "-m", "remote"],
+    );
+    for name in ["behind", "diverged"] {
+        git(&work.join(name), &["fetch", "-q"]);
+    }
+
+    fixture
+}
+
+#[test]
+fn test_diverged() {
+    let fixture = setup();
+
+    let output = fixture.subcommand("diverged", &[]).output().unwrap();
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    assert_eq!(
+        "diverged origin/main ahead 1, behind 1\n\n1 diverged\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_skip_diverged() {
+    let fixture = setup();
+
+    let output = fixture.run(&[
+        "--skip-diverged",
+        "--show-skipped",
+        "pull",
+        "--quiet",
+        "--ff-only",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  1\n"), "{}", stdout);
+    assert!(stdout.contains("Failed:     0\n"), "{}", stdout);
+    assert!(stdout.contains("Skipped:    1\n"), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "=== Skipped repositories ===\n\ndiverged diverged, ahead 1 and behind 1 of origin/main\n"
+        ),
+        "{}",
+        stdout
+    );
+
+    // The diverged repository wasn't pulled
+    let head = git(&fixture.path("diverged"), &["rev-list", "--count", "HEAD"]);
+    assert_eq!("2", head);
+    let head = git(&fixture.path("behind"), &["rev-list", "--count", "HEAD"]);
+    assert_eq!("2", head);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use std::process::Command;
+
+use common::{init, Fixture};
+
+/// Returns the exit code of `command`.
+fn code(mut command: Command) -> Option<i32> {
+    command.output().unwrap().status.code()
+}
+
+#[test]
+fn test_exit_codes() {
+    let fixture = Fixture::empty();
+    init(&fixture.path("foo"));
+    init(&fixture.path("bar"));
+    let nonexistent = |args: &[&str]| {
+        let mut command = fixture.command_in_root(&["--root"]);
+        command.arg(fixture.path("nonexistent")).args(args);
+        command
+    };
+
+    // status succeeds everywhere
+    assert_eq!(Some(0), code(fixture.command(&["status"])));
+
+    // log fails in repositories without any commit
+    assert_eq!(Some(1), code(fixture.command(&["log"])));
+    assert_eq!(Some(0), code(fixture.command(&["--exit-zero", "log"])));
+    assert_eq!(
+        Some(1),
+        code(fixture.command(&["--fail-threshold", "1", "log"]))
+    );
+    assert_eq!(
+        Some(0),
+        code(fixture.command(&["--fail-threshold", "2", "log"]))
+    );
+
+    // usage error
+    assert_eq!(Some(2), code(fixture.command(&["--theme", "foo", "log"])));
+
+    // discovery error
+    assert_eq!(Some(3), code(nonexistent(&["status"])));
+
+    // git can't be run, --exit-zero hides it too
+    let git = fixture.path("../nonexistent-git");
+    let git = git.to_str().unwrap();
+    assert_eq!(Some(4), code(fixture.command(&["--git", git, "status"])));
+    assert_eq!(
+        Some(0),
+        code(fixture.command(&["--exit-zero", "--git", git, "status"]))
+    );
+
+    // A usage error isn't hidden, a discovery error is
+    assert_eq!(
+        Some(2),
+        code(fixture.command(&["--exit-zero", "--theme", "foo", "log"]))
+    );
+    assert_eq!(Some(0), code(nonexistent(&["--exit-zero", "status"])));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_discovery_error() {
+    let fixture = Fixture::empty();
+    let root = &fixture.root;
+    // A symlink loop can't be resolved
+    std::os::unix::fs::symlink(root.join("a"), root.join("b")).unwrap();
+    std::os::unix::fs::symlink(root.join("b"), root.join("a")).unwrap();
+
+    let output = fixture.run(&["status"]);
+    assert_eq!(Some(3), output.status.code());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let prefix = format!(
+        "unable to discover the repositories under {}: unable to resolve {}/",
+        root.display(),
+        root.display()
+    );
+    assert!(stderr.starts_with(&prefix), "{}", stderr);
+    assert_eq!(1, stderr.lines().count(), "{}", stderr);
+}
+
+#[test]
+fn test_fail_fast() {
+    let fixture = Fixture::empty();
+    init(&fixture.path("bar"));
+    init(&fixture.path("foo"));
+
+    // One repository at a time, log fails in the first one and the second one is never started
+    let output = fixture.run(&[
+        "-j",
+        "1",
+        "--output-order",
+        "sorted",
+        "--fail-fast",
+        "--porcelain",
+        "log",
+    ]);
+    assert_eq!(Some(5), output.status.code(), "{:?}", output);
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let statuses: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split('\t').next().unwrap())
+        .collect();
+    assert_eq!(vec!["fail", "not-attempted"], statuses, "{:?}", stdout);
+    assert!(stdout.contains("--fail-fast"), "{:?}", stdout);
+
+    // A run cut short is hidden too
+    assert_eq!(
+        Some(0),
+        code(fixture.command(&["-j", "1", "--fail-fast", "--exit-zero", "log"]))
+    );
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::{init, Fixture};
+
+#[cfg(unix)]
+#[test]
+fn test_git_program() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = Fixture::empty();
+    std::fs::create_dir_all(fixture.path("foo/.git")).unwrap();
+
+    // A wrapper printing its arguments and GIT_SSH_COMMAND, without any git config
+    let wrapper = fixture.path("../git-wrapper");
+    std::fs::write(
+        &wrapper,
+        "#!/bin/sh\n[ \"$1\" = config ] && exit 1\necho \"wrapper $* ssh=$GIT_SSH_COMMAND\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = fixture
+        .command(&["--ssh-command", "ssh -i key", "--no-branch", "fetch"])
+        .env("GITJUGGLING_GIT", &wrapper)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("wrapper fetch ssh=ssh -i key"),
+        "{}",
+        stdout
+    );
+
+    // A git that can't run has its own exit code
+    let output = fixture.run(&["--git", "/nonexistent/git", "fetch"]);
+    assert_eq!(Some(4), output.status.code());
+
+    let output = fixture.run(&["--git", "nonexistent-git", "fetch"]);
+    assert_eq!(Some(4), output.status.code());
+    assert_eq!(
+        "nonexistent-git executable not found in PATH; nothing was executed\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_verbose_banner() {
+    let fixture = Fixture::empty();
+    let foo = fixture.path("foo");
+    init(&foo);
+
+    let output = fixture
+        .command(&["-v", "--ssh-command", "ssh -i key", "--no-branch", "status"])
+        .env("GIT_DIR", "/nonexistent")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!(
+            "foo executing status\n  cwd: {}\n  git: git\n  env: GIT_DIR unset\n  env: GIT_SSH_COMMAND=ssh -i key\n",
+            foo.display()
+        )),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+use common::{init, Fixture};
+
+/// Runs git in `dir` with the binary installed as git-juggle in the PATH.
+fn git(fixture: &Fixture, dir: &Path, args: &[&str]) -> Output {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut paths = vec![fixture.path("../bin")];
+    paths.extend(std::env::split_paths(&path));
+
+    fixture
+        .isolated("git")
+        .args(args)
+        .current_dir(dir)
+        .env("PATH", std::env::join_paths(paths).unwrap())
+        .output()
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_git_subcommand() {
+    let fixture = Fixture::empty();
+    std::fs::create_dir(fixture.path("../bin")).unwrap();
+    std::os::unix::fs::symlink(
+        env!("CARGO_BIN_EXE_gitjuggling"),
+        fixture.path("../bin/git-juggle"),
+    )
+    .unwrap();
+    init(&fixture.path("outer"));
+    init(&fixture.path("outer/sub/inner"));
+
+    let output = git(
+        &fixture,
+        &fixture.path("outer"),
+        &["juggle", "--", "status"],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  2"), "{}", stdout);
+
+    let output = git(&fixture, &fixture.root, &["juggle", "-h"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Usage: git juggle"), "{}", stdout);
+
+    // Aliases run from the toplevel of the repository, discovery must start from where the
+    // alias was invoked
+    let output = git(
+        &fixture,
+        &fixture.path("outer/sub"),
+        &["-c", "alias.jj=!git-juggle", "jj", "--", "status"],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  1"), "{}", stdout);
+    assert!(stdout.contains("inner"), "{}", stdout);
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use std::process::Output;
+
+use common::{git, Fixture};
+
+/// Runs gitjuggling on the tree with `args`, one repository after the other without colors.
+fn sorted(fixture: &Fixture, args: &[&str]) -> Output {
+    fixture
+        .command(&["--theme", "plain", "--output-order", "sorted"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_grep_mode() {
+    let fixture = Fixture::empty();
+    for name in ["foo", "bar", "baz"] {
+        let path = fixture.path(name);
+        std::fs::create_dir_all(path.join("src")).unwrap();
+        git(&path, &["init", "-q"]);
+    }
+    std::fs::write(fixture.path("foo/src/main.rs"), "// TODO\nfn main() {}\n").unwrap();
+    std::fs::write(fixture.path("bar/README"), "TODO\nTODO too\n").unwrap();
+    git(&fixture.path("foo"), &["add", "."]);
+    git(&fixture.path("bar"), &["add", "."]);
+
+    let output = sorted(&fixture, &["grep", "-n", "TODO"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("bar/README:1:TODO\nbar/README:2:TODO too\nfoo/src/main.rs:1:// TODO\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("baz"), "{}", stdout);
+    assert!(
+        stdout.ends_with("Matches:    3\n  bar 2\n  foo 1\n"),
+        "{}",
+        stdout
+    );
+
+    // Nothing matching anywhere isn't a failure
+    let output = sorted(&fixture, &["grep", "NOTHING"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Matches:    0\n"));
+}
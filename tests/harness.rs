@@ -0,0 +1,1039 @@
+mod common;
+
+use common::Fixture;
+
+#[test]
+fn test_list() {
+    let fixture = Fixture::new();
+
+    // Neither the bare repository nor the submodule are run
+    assert_eq!(
+        vec!["clean", "clean-worktree", "dirty", "super"],
+        fixture.list(&[])
+    );
+    assert_eq!(
+        vec!["clean", "super"],
+        fixture.list(&["--exclude", "dirty", "--exclude", "*-worktree"])
+    );
+
+    assert_eq!(
+        vec!["clean", "clean-worktree", "dirty", "super", "super/lib"],
+        fixture.list(&["--include-submodules"])
+    );
+
+    assert_eq!(
+        vec!["clean", "clean-worktree"],
+        fixture.list(&["--name", "CLEAN*"])
+    );
+    assert_eq!(
+        vec!["dirty", "super"],
+        fixture.list(&["--name", "s*", "--name", "*ty", "--exclude", "super/*"])
+    );
+    assert!(fixture
+        .list(&["--name", "CLEAN*", "--case-sensitive"])
+        .is_empty());
+
+    let output = fixture.run(&["--list", "--dry-run"]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn test_porcelain() {
+    let fixture = Fixture::new();
+    let porcelain = |args: &[&str]| {
+        let output = fixture.run(args);
+        let mut lines: Vec<[String; 4]> = String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                assert_eq!(5, fields.len(), "{}", line);
+                assert!(fields[2].parse::<u64>().is_ok(), "{}", line);
+                [fields[0], fields[1], fields[3], fields[4]].map(str::to_string)
+            })
+            .collect();
+        lines.sort_by(|a, b| a[2].cmp(&b[2]));
+        (output.status.code(), lines)
+    };
+    let line = |status: &str, code: &str, name: &str, reason: &str| {
+        [
+            status.to_string(),
+            code.to_string(),
+            fixture.path(name).display().to_string(),
+            reason.to_string(),
+        ]
+    };
+
+    assert_eq!(
+        (
+            Some(0),
+            vec![
+                line("ok", "0", "clean", ""),
+                line("ok", "0", "clean-worktree", ""),
+                line("ok", "0", "dirty", ""),
+                line("ok", "0", "super", ""),
+            ]
+        ),
+        porcelain(&["--porcelain", "status"])
+    );
+    assert_eq!(
+        (
+            Some(1),
+            vec![
+                line("ok", "0", "clean", ""),
+                line("ok", "0", "clean-worktree", ""),
+                line("fail", "1", "dirty", ""),
+                line("ok", "0", "super", ""),
+            ]
+        ),
+        porcelain(&["--porcelain", "diff", "--quiet"])
+    );
+
+    // A skipped repository doesn't change the exit code
+    std::fs::write(fixture.path("dirty/.gitjuggling-skip"), "mirror\n").unwrap();
+    assert_eq!(
+        (
+            Some(0),
+            vec![
+                line("ok", "0", "clean", ""),
+                line("ok", "0", "clean-worktree", ""),
+                line("skip", "-", "dirty", ".gitjuggling-skip: mirror"),
+                line("ok", "0", "super", ""),
+            ]
+        ),
+        porcelain(&["--porcelain", "diff", "--quiet"])
+    );
+}
+
+#[test]
+fn test_no_color() {
+    let fixture = Fixture::new();
+    let stdout = |no_color: &str| {
+        let output = fixture
+            .command(&["status"])
+            .env("CLICOLOR_FORCE", "1")
+            .env("NO_COLOR", no_color)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert!(stdout("").contains('\x1b'));
+    assert!(!stdout("1").contains('\x1b'));
+}
+
+#[test]
+fn test_skip_markers() {
+    let fixture = Fixture::new();
+    std::fs::write(
+        fixture.path("dirty/.gitjuggling-skip"),
+        "read-only mirror\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(fixture.path("archive")).unwrap();
+    common::repository(&fixture.path("archive/old"));
+    std::fs::write(fixture.path("archive/.gitjuggling-skip-all"), "").unwrap();
+
+    assert_eq!(vec!["clean", "clean-worktree", "super"], fixture.list(&[]));
+
+    let output = fixture.run(&["--verbose", "--show-skipped", "status"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skipped:    2\n"), "{}", stdout);
+    assert!(
+        stdout.contains(
+            "=== Skipped repositories ===\n\n\
+             archive .gitjuggling-skip-all\n\
+             dirty .gitjuggling-skip: read-only mirror\n"
+        ),
+        "{}",
+        stdout
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("dirty: skipped, .gitjuggling-skip: read-only mirror\n"),
+        "{}",
+        stderr
+    );
+
+    // Without --show-skipped they're only counted
+    let output = fixture.run(&["status"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skipped:    2\n"), "{}", stdout);
+    assert!(!stdout.contains("read-only mirror"), "{}", stdout);
+}
+
+#[test]
+fn test_verbose() {
+    let fixture = Fixture::new();
+
+    let output = fixture.run(&["-vv", "--exclude", "dirty", "--porcelain", "status"]);
+    assert!(output.status.success(), "{:?}", output);
+    // The diagnostics don't get mixed with the machine output
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(3, stdout.lines().count(), "{}", stdout);
+    assert!(stdout.lines().all(|line| line.starts_with("ok\t0\t")));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    for expected in [
+        "config: depth = 3 # default\n".to_string(),
+        "config: excludes = [\"dirty\"] # command line\n".to_string(),
+        "dirty: left out, excluded by dirty\n".to_string(),
+        "super/lib: left out, submodule\n".to_string(),
+        "clean: found\n".to_string(),
+        format!(
+            "clean: ran git status in {}\n",
+            fixture.path("clean").display()
+        ),
+        "clean: succeeded after ".to_string(),
+    ] {
+        assert!(stderr.contains(&expected), "{}", stderr);
+    }
+
+    let output = fixture.run(&["-v", "--exclude", "dirty", "status"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("dirty: left out"), "{}", stderr);
+    assert!(!stderr.contains("submodule"), "{}", stderr);
+    assert!(!stderr.contains("found"), "{}", stderr);
+}
+
+#[test]
+fn test_short_names() {
+    let fixture = Fixture::new();
+    common::repository(&fixture.path("archive/clean"));
+
+    assert_eq!(
+        vec!["archive/clean"],
+        fixture.list(&["--only", "archive/clean"])
+    );
+    assert_eq!(
+        vec!["archive/clean", "clean-worktree"],
+        fixture.list(&[
+            "--exclude-name",
+            "dirty,super",
+            "--exclude-name",
+            "root/clean"
+        ])
+    );
+
+    let output = fixture.run(&["--list", "--only", "clean"]);
+    assert_eq!(Some(2), output.status.code());
+    assert_eq!(
+        "ambiguous name clean, it could be archive/clean, root/clean\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let output = fixture.run(&["--list", "--only", "nothing"]);
+    assert_eq!(Some(2), output.status.code());
+
+    let output = fixture.run(&["--short-names", "--output-order", "sorted", "status", "-s"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for banner in [
+        "archive/clean (main) executing",
+        "root/clean (main) executing",
+        "clean-worktree (clean-worktree) executing",
+        "dirty (main) executing",
+    ] {
+        assert!(stdout.contains(banner), "{}", stdout);
+    }
+
+    // The machine formats keep the full paths
+    let output = fixture.run(&["--short-names", "--porcelain", "--only", "dirty", "status"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains(&format!("\t{}\t", fixture.path("dirty").display())),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_per_root_jobs() {
+    let fixture = Fixture::new();
+    let output = fixture.run(&["-v", "--per-root-jobs", "1", "status", "-s"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let line = format!(
+        "clean: jobs, 1 at a time under {}\n",
+        fixture.root.display()
+    );
+    assert!(stderr.contains(&line), "{}", stderr);
+}
+
+#[test]
+fn test_ci() {
+    let fixture = Fixture::new();
+    let ci = |args: &[&str]| {
+        let output = fixture
+            .command(args)
+            .env_remove("GITJUGGLING_NO_CI")
+            .env("GITHUB_ACTIONS", "true")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Folded, with the repository and the time on every line
+    let stdout = ci(&["--only", "dirty", "--no-branch", "status", "-s"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines.len() > 4, "{}", stdout);
+    assert_eq!("::group::dirty", lines[0]);
+    assert_eq!("dirty executing status -s", lines[1]);
+    assert!(lines[2].ends_with(" dirty | M README"), "{}", stdout);
+    assert!(lines[2].starts_with("20"), "{}", stdout);
+    assert_eq!("::endgroup::", lines[3]);
+
+    let stdout = ci(&[
+        "--only",
+        "dirty",
+        "--no-branch",
+        "--no-groups",
+        "--no-prefix",
+        "--no-timestamps",
+        "status",
+        "-s",
+    ]);
+    assert!(
+        stdout.starts_with("dirty executing status -s\nM README\n\n"),
+        "{}",
+        stdout
+    );
+
+    let stdout = ci(&["--no-ci", "--only", "dirty", "--no-branch", "status", "-s"]);
+    assert!(
+        stdout.starts_with("dirty executing status -s\nM README\n\n"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_history() {
+    let fixture = Fixture::new();
+    // The history is next to the configuration, in the parent directory of the tree
+    let config_dir = fixture.path("../gitjuggling");
+    let history = |args: &[&str]| {
+        fixture
+            .command_in_root(&["history"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    assert!(fixture
+        .run(&["--only", "clean", "status", "-s"])
+        .status
+        .success());
+    assert!(!fixture
+        .run(&["--only", "dirty", "checkout", "nonexistent"])
+        .status
+        .success());
+
+    let output = history(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(2, lines.len(), "{}", stdout);
+    assert!(lines[0].starts_with("   1  20"), "{}", stdout);
+    assert!(lines[0].contains("  1 ok, 0 failed"), "{}", stdout);
+    assert!(lines[0].ends_with("  git status -s"), "{}", stdout);
+    assert!(lines[1].contains("  0 ok, 1 failed"), "{}", stdout);
+
+    let output = history(&["show", "2"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("run 2 of git checkout nonexistent at 20"));
+    assert!(stdout.contains("dirty exited with code 1\nerror: pathspec 'nonexistent'"));
+    assert!(
+        stdout.contains("Succeeded:  0\nFailed:     1\n"),
+        "{}",
+        stdout
+    );
+
+    // The colors follow the same options as a run
+    let colored = |args: &[&str]| {
+        let output = history(&[&["show", "2"], args].concat());
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap().contains('\u{1b}')
+    };
+    assert!(colored(&["--color", "always"]));
+    assert!(!colored(&["--color", "always", "--theme", "plain"]));
+
+    let output = history(&["show", "3"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("no run 3 in "));
+
+    // Nothing is recorded once it's disabled
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(config_dir.join("config.toml"), "history = false\n").unwrap();
+    assert!(fixture.run(&["--only", "clean", "status"]).status.success());
+    let stdout = String::from_utf8(history(&[]).stdout).unwrap();
+    assert_eq!(2, stdout.lines().count(), "{}", stdout);
+}
+
+#[test]
+fn test_since_last_run() {
+    let fixture = Fixture::new();
+    let run = |args: &[&str]| {
+        let mut all = vec!["--only", "clean", "--only", "dirty", "--since-last-run"];
+        all.extend(args);
+        let output = fixture.run(&all);
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = run(&["status", "-s"]);
+    assert!(stdout.contains("Succeeded:  2\n"), "{}", stdout);
+    let stdout = run(&["status", "-s"]);
+    assert!(stdout.contains("Succeeded:  0\n"), "{}", stdout);
+    assert!(stdout.contains("Skipped:    2\n"), "{}", stdout);
+
+    // Another command doesn't share the last runs
+    let stdout = run(&["status"]);
+    assert!(stdout.contains("Succeeded:  2\n"), "{}", stdout);
+
+    std::fs::write(fixture.path("dirty/README"), "changed again\n").unwrap();
+    let stdout = run(&["--show-skipped", "status", "-s"]);
+    assert!(stdout.contains("Succeeded:  1\n"), "{}", stdout);
+    assert!(stdout.contains("clean unchanged since the last successful run"));
+
+    let stdout = run(&["--force", "status", "-s"]);
+    assert!(stdout.contains("Succeeded:  2\n"), "{}", stdout);
+}
+
+#[test]
+fn test_self() {
+    let fixture = Fixture::new();
+    std::fs::create_dir_all(fixture.path("super/docs")).unwrap();
+    let list = |root: &str, args: &[&str]| {
+        let output = fixture
+            .command_in_root(&["--root"])
+            .arg(fixture.path(root))
+            .arg("--list")
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    let (stdout, stderr) = list("clean", &[]);
+    assert_eq!(format!("{}\n", fixture.path("clean").display()), stdout);
+    assert!(
+        stderr.starts_with(&format!(
+            "note: {} is a repository, only it was found. Pass --root {} ",
+            fixture.path("clean").display(),
+            fixture.path("clean").parent().unwrap().display()
+        )),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        ("".to_string(), "".to_string()),
+        list("clean", &["--exclude-self"])
+    );
+
+    let (stdout, stderr) = list("super/docs", &[]);
+    assert_eq!("", stdout);
+    assert!(stderr.contains(" and nothing was found below it. Pass --include-self"));
+    let (stdout, stderr) = list("super/docs", &["--include-self"]);
+    assert_eq!(format!("{}\n", fixture.path("super").display()), stdout);
+    assert_eq!("", stderr);
+
+    // Nothing to say when the repositories are nested in it
+    let (stdout, stderr) = list("super", &["--include-submodules"]);
+    assert_eq!(2, stdout.lines().count(), "{}", stdout);
+    assert_eq!("", stderr);
+}
+
+#[test]
+fn test_color() {
+    let fixture = Fixture::new();
+    let config = fixture.path("../gitconfig");
+    std::fs::write(&config, "[color]\n\tui = always\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = fixture.command(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let status = ["--only", "dirty", "--no-branch", "status", "-s"];
+
+    // The colors of git are passed through, the banner shows the command as it was spawned
+    let mut args = vec!["--color", "always"];
+    args.extend(status);
+    let stdout = run(&args);
+    assert!(
+        stdout.contains("executing \x1b[") && stdout.contains("-c color.ui=always status -s"),
+        "{:?}",
+        stdout
+    );
+    assert!(stdout.contains("\x1b[31mM\x1b[m README"), "{:?}", stdout);
+
+    let stdout = run(&status);
+    assert!(!stdout.contains('\x1b'), "{:?}", stdout);
+
+    // Even with git configured to color, nothing is captured with never
+    let mut args = vec!["--color", "never"];
+    args.extend(status);
+    let output = fixture
+        .command(&args)
+        .env("GIT_CONFIG_GLOBAL", &config)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("dirty executing -c color.ui=false status -s\nM README\n"),
+        "{:?}",
+        stdout
+    );
+
+    // So does the log file, along with the git program
+    let log = fixture.path("../color.log");
+    let mut args = vec!["--color", "always", "--log-file", log.to_str().unwrap()];
+    args.extend(status);
+    assert!(fixture.run(&args).status.success());
+    let contents = std::fs::read_to_string(&log).unwrap();
+    assert!(
+        contents.contains(" executing git -c color.ui=always status -s\n"),
+        "{:?}",
+        contents
+    );
+}
+
+#[test]
+fn test_tee() {
+    let fixture = Fixture::new();
+    let tee = fixture.path("../tee.txt");
+
+    // The repositories hidden in the terminal are still written to the file, without colors
+    let output = fixture
+        .command(&["--color", "always", "--no-branch", "--hide-empty", "--tee"])
+        .arg(&tee)
+        .args(["status", "-s"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains('\x1b'), "{:?}", stdout);
+    assert!(!stdout.contains("clean executing"), "{:?}", stdout);
+
+    let contents = std::fs::read_to_string(&tee).unwrap();
+    assert!(!contents.contains('\x1b'), "{:?}", contents);
+    assert!(
+        contents.contains("clean executing -c color.ui=always status -s"),
+        "{:?}",
+        contents
+    );
+    assert!(
+        contents.contains("dirty executing -c color.ui=always status -s\nM README\n"),
+        "{:?}",
+        contents
+    );
+    assert!(contents.contains("Quiet:      3"), "{:?}", contents);
+
+    // A read-only command can write inside a repository, not one changing it
+    let inside = fixture.path("clean/tee.txt");
+    let output = fixture
+        .command(&["--only", "clean", "--tee"])
+        .arg(&inside)
+        .arg("status")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    std::fs::remove_file(&inside).unwrap();
+
+    let output = fixture
+        .command(&["--only", "clean", "--tee"])
+        .arg(&inside)
+        .args(["clean", "-fdx"])
+        .output()
+        .unwrap();
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("refusing to write"));
+    assert!(!inside.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_priority() {
+    let fixture = Fixture::new();
+
+    let output = fixture
+        .command(&[
+            "--only", "clean", "-v", "--nice", "19", "--ionice", "idle", "status",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("clean: priority nice 19"), "{:?}", stderr);
+    assert!(!stderr.contains("warning"), "{:?}", stderr);
+
+    let output = fixture
+        .command(&["--ionice", "idle:3", "status"])
+        .output()
+        .unwrap();
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+}
+
+#[test]
+fn test_args_file() {
+    let fixture = Fixture::new();
+    let args_file = fixture.path("../args.txt");
+    std::fs::write(
+        &args_file,
+        "# the new names\nclean --format=%s 'HEAD'\ndirt* -1\n",
+    )
+    .unwrap();
+    let dry_run = |args: &[&str]| {
+        let mut all = vec!["--dry-run", "--args-file", args_file.to_str().unwrap()];
+        all.extend(args);
+        all.extend(["log", "--oneline"]);
+        let output = fixture.command(&all).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let stdout = dry_run(&[]);
+    assert!(
+        stdout.contains("clean: git log --oneline --format=%s HEAD\n"),
+        "{:?}",
+        stdout
+    );
+    assert!(
+        stdout.contains("dirty: git log --oneline -1\n"),
+        "{:?}",
+        stdout
+    );
+    assert!(
+        stdout.contains("super: git log --oneline\n"),
+        "{:?}",
+        stdout
+    );
+
+    let stdout = dry_run(&["--args-file-only"]);
+    assert_eq!(2, stdout.lines().count(), "{:?}", stdout);
+
+    // A repository mapped twice is an error naming both lines
+    std::fs::write(&args_file, "clean -1\nclean* -2\n").unwrap();
+    let output = fixture
+        .command(&["--args-file", args_file.to_str().unwrap(), "log"])
+        .output()
+        .unwrap();
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("clean is mapped by line 1 (clean) and line 2 (clean*)"),
+        "{:?}",
+        stderr
+    );
+}
+
+#[test]
+fn test_counts() {
+    let fixture = Fixture::new();
+    // dirty starts from the first commit of clean, then each one gets its own. Their own first
+    // commits are only the same when they were made in the same second
+    let dirty = fixture.path("dirty");
+    let clean = fixture.path("clean");
+    common::git(&clean, &["commit", "-q", "--allow-empty", "-m", "behind"]);
+    common::git(
+        &dirty,
+        &["remote", "add", "origin", clean.to_str().unwrap()],
+    );
+    common::git(&dirty, &["fetch", "-q", "origin"]);
+    common::git(&dirty, &["reset", "-q", "--keep", "origin/main~1"]);
+    common::git(
+        &dirty,
+        &["branch", "-q", "--set-upstream-to", "origin/main"],
+    );
+    common::git(&dirty, &["commit", "-q", "-a", "-m", "ahead"]);
+
+    let output = fixture
+        .command(&[
+            "--only",
+            "dirty",
+            "--only",
+            "clean",
+            "--output-order",
+            "sorted",
+            "--counts",
+            "status",
+            "-s",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("clean (main) executing status -s\n"),
+        "{:?}",
+        stdout
+    );
+    assert!(
+        stdout.contains("dirty (main ↑1 ↓1) executing status -s\n"),
+        "{:?}",
+        stdout
+    );
+}
+
+#[test]
+fn test_safety() {
+    let fixture = Fixture::new();
+    let readme = fixture.path("dirty/README");
+    let modified = std::fs::read_to_string(&readme).unwrap();
+
+    // There's no terminal to ask for a confirmation
+    let output = fixture
+        .command(&["reset", "--hard"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("high risk, reset --hard discards the uncommitted changes, in "),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("pass --yes"), "{}", stderr);
+    assert_eq!(modified, std::fs::read_to_string(&readme).unwrap());
+
+    // --force is only about --since-last-run
+    let output = fixture
+        .command(&["--force", "reset", "--hard"])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+
+    // A dry run doesn't run anything, a single repository is left as is
+    for args in [
+        &["--dry-run", "reset", "--hard"][..],
+        &["--only", "clean", "reset", "--hard"],
+    ] {
+        let output = fixture
+            .command(args)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+    }
+
+    let output = fixture
+        .command(&[
+            "--no-safety",
+            "--only",
+            "dirty",
+            "--only",
+            "clean",
+            "reset",
+            "-q",
+            "--hard",
+        ])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_ne!(modified, std::fs::read_to_string(&readme).unwrap());
+
+    let output = fixture
+        .command(&[
+            "--yes", "--only", "dirty", "--only", "clean", "clean", "-fd",
+        ])
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_skip_read_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixture = Fixture::new();
+    let git_dir = fixture.path("dirty/.git");
+    std::fs::set_permissions(&git_dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+    // root writes whatever the permissions
+    if std::fs::write(git_dir.join("probe"), "").is_ok() {
+        return;
+    }
+
+    let output = fixture
+        .command(&[
+            "--verbose",
+            "--only",
+            "dirty",
+            "--only",
+            "clean",
+            "tag",
+            "v1",
+        ])
+        .output()
+        .unwrap();
+    std::fs::set_permissions(&git_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr
+            .contains("dirty: skipped, not writable (permission denied), pass --include-readonly"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_list_json() {
+    let fixture = Fixture::new();
+    common::git(
+        &fixture.path("clean"),
+        &["remote", "add", "origin", "https://example.com/clean.git"],
+    );
+
+    let output = fixture.run(&[
+        "--list",
+        "--format",
+        "json",
+        "--enrich",
+        "--include-submodules",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let entry = |relative: &str| {
+        entries
+            .iter()
+            .find(|entry| entry["relative"] == relative)
+            .unwrap_or_else(|| panic!("no {} in {:?}", relative, entries))
+    };
+
+    let clean = entry("clean");
+    assert_eq!(1, clean["version"]);
+    assert_eq!(fixture.path("clean").to_str().unwrap(), clean["path"]);
+    assert_eq!("main", clean["branch"]);
+    assert_eq!("https://example.com/clean.git", clean["origin"]);
+    // The bare repository isn't discovered, the field is there for the schema
+    assert!(entries.iter().all(|entry| entry["bare"] == false));
+    assert!(
+        entries.iter().all(|entry| entry["relative"] != "bare.git"),
+        "{:?}",
+        entries
+    );
+    assert!(entry("dirty").get("origin").is_none());
+    assert_eq!(true, entry("clean-worktree")["worktree"]);
+    assert_eq!(true, entry("super/lib")["submodule"]);
+    assert_eq!(false, entry("super")["submodule"]);
+
+    // Only --version and --list have a format
+    let output = fixture.run(&["--format", "json", "status"]);
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+}
+
+#[test]
+fn test_broken_head() {
+    let fixture = Fixture::new();
+    let dirty = fixture.path("dirty");
+    // The branch HEAD is on was deleted from under it
+    std::fs::write(dirty.join(".git/HEAD"), "ref: refs/heads/gone\n").unwrap();
+
+    let output = fixture
+        .command(&["--only", "dirty", "--only", "clean", "ls-files"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "warning: dirty: broken, HEAD points to refs/heads/gone, which doesn't exist"
+        ),
+        "{}",
+        stderr
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("dirty (gone) executing"), "{}", stdout);
+    assert!(stdout.contains("Succeeded:  2"), "{}", stdout);
+    assert!(stdout.contains("Broken:     1"), "{}", stdout);
+
+    let output = fixture
+        .command(&[
+            "--only",
+            "dirty",
+            "--only",
+            "clean",
+            "--skip-broken",
+            "ls-files",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("dirty (gone) executing"), "{}", stdout);
+    assert!(stdout.contains("Skipped:    1"), "{}", stdout);
+    assert!(stdout.contains("Broken:     1"), "{}", stdout);
+}
+
+#[test]
+fn test_script() {
+    let fixture = Fixture::new();
+    let script = fixture.path("../script.txt");
+    std::fs::write(
+        &script,
+        "# check everything\nrev-parse --is-inside-work-tree\n\ngit nope\nlog\n",
+    )
+    .unwrap();
+    let script = script.to_str().unwrap();
+
+    let output = fixture
+        .command(&["--only", "clean", "--dry-run", "--script", script])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        "clean: git rev-parse --is-inside-work-tree (step 1 of 3)\n\
+         clean: git nope (step 2 of 3)\n\
+         clean: git log (step 3 of 3)\n",
+        stdout
+    );
+
+    // The steps stop at the first one failing
+    let output = fixture
+        .command(&["--only", "clean", "--script", script])
+        .output()
+        .unwrap();
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("executing rev-parse --is-inside-work-tree (step 1 of 3)\ntrue\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("executing nope (step 2 of 3)"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("step 2 of 3, nope: exited with code 1"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("executing log"), "{}", stdout);
+
+    let output = fixture
+        .command(&["--script", script, "status"])
+        .output()
+        .unwrap();
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+}
+
+#[test]
+fn test_stats_transferred() {
+    let fixture = Fixture::new();
+    let origin = fixture.path("clean");
+    let url = format!("file://{}", origin.display());
+    common::git(&origin, &["commit", "-q", "--allow-empty", "-m", "new"]);
+
+    // Kept as a pack, git writes how much it received with the --progress added
+    let output = fixture
+        .command(&[
+            "--only",
+            "dirty",
+            "--stats",
+            "--",
+            "-c",
+            "fetch.unpackLimit=1",
+            "fetch",
+            &url,
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout
+        .lines()
+        .find(|line| line.starts_with("Transferred:"))
+        .unwrap_or_else(|| panic!("{}", stdout));
+    assert!(line.ends_with(" objects"), "{}", line);
+
+    // With --no-progress git writes nothing
+    let output = fixture
+        .command(&["--only", "dirty", "--stats", "fetch", "--no-progress", &url])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Transferred: unknown in 1 repositories"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_tui_without_terminal() {
+    let fixture = Fixture::new();
+
+    let output = fixture
+        .command(&["--only", "clean", "--tui", "status"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("warning: --tui needs a terminal, it's ignored"),
+        "{}",
+        stderr
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  1"), "{}", stdout);
+}
+
+#[test]
+fn test_git_named_subcommands() {
+    let fixture = Fixture::new();
+    let run = |args: &[&str]| {
+        let output = fixture.command_in_root(args).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        (output.status.code(), stdout)
+    };
+
+    // The arguments the subcommands don't know go to git
+    let (code, stdout) = run(&["status", "--short"]);
+    assert_eq!(Some(0), code, "{}", stdout);
+    assert!(
+        stdout.contains("dirty (main) executing status --short"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("M README"), "{}", stdout);
+
+    let (code, stdout) = run(&["config", "--get", "core.bare"]);
+    assert_eq!(Some(0), code, "{}", stdout);
+    assert!(
+        stdout.contains("executing config --get core.bare"),
+        "{}",
+        stdout
+    );
+
+    let (code, stdout) = run(&["maintenance", "run", "--task", "pack-refs"]);
+    assert_eq!(Some(0), code, "{}", stdout);
+    assert!(stdout.contains("executing maintenance run"), "{}", stdout);
+
+    // Their own options still run the subcommands
+    let (code, stdout) = run(&["status", "--format", "json"]);
+    assert_eq!(Some(0), code, "{}", stdout);
+    assert!(stdout.starts_with('['), "{}", stdout);
+
+    // And a mistake somewhere else isn't passed to git
+    let (code, _) = run(&["doctor", "--short"]);
+    assert_eq!(Some(2), code);
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use std::path::Path;
+
+use gitjuggling::classify::Classifier;
+use gitjuggling::git::Git;
+use gitjuggling::limits::PathLimits;
+use gitjuggling::order::Dependencies;
+use gitjuggling::{discover_repositories, DiscoverOptions, Runner};
+
+use common::init;
+
+#[test]
+fn test_discover_and_run() {
+    let dir = tempfile::tempdir().unwrap();
+    init(&dir.path().join("foo"));
+    init(&dir.path().join("bar"));
+
+    let options = DiscoverOptions {
+        roots: vec![dir.path().to_path_buf()],
+        ..DiscoverOptions::default()
+    };
+    let mut paths = discover_repositories(&options).unwrap();
+    paths.sort();
+
+    let root = dir.path().canonicalize().unwrap();
+    assert_eq!(vec![root.join("bar"), root.join("foo")], paths);
+
+    let results = Runner::new(&["status", "--short"]).run(&paths);
+    assert_eq!(2, results.len());
+    for (result, path) in results.iter().zip(&paths) {
+        assert_eq!(path, &result.path);
+        assert!(result.success);
+        assert!(result.is_quiet());
+        assert_eq!(Some("main"), result.branch.as_deref());
+    }
+
+    // log fails in repositories without any commit, unless the exit code is accepted
+    let results = Runner::new(&["log"]).show_branch(false).run(&paths);
+    assert!(results.iter().all(|result| !result.success));
+    assert!(results.iter().all(|result| result.branch.is_none()));
+    assert_eq!("exited with code 128", results[0].failure_reason());
+
+    let results = Runner::new(&["log"])
+        .classifier(Classifier::new(None, vec![128], false))
+        .run(&paths);
+    assert!(results.iter().all(|result| result.success));
+}
+
+#[test]
+fn test_git_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let paths: Vec<_> = (0..32)
+        .map(|index| dir.path().join(format!("repo{}", index)))
+        .collect();
+    for path in &paths {
+        init(path);
+    }
+
+    // Every repository would fail the same way, the first failure stops the run
+    let runner = Runner::new(&["status"]).git(Git::new("nonexistent-git"));
+    let results = runner.run(&paths);
+    assert_eq!(
+        Some("nonexistent-git executable not found in PATH"),
+        runner.git_error()
+    );
+    assert!(results.len() < paths.len());
+    assert!(results.iter().all(|result| result.error.is_some()));
+
+    // A directory that doesn't exist only fails its own repository
+    let mut paths = paths;
+    paths.insert(0, dir.path().join("nonexistent"));
+    let runner = Runner::new(&["status"]);
+    let results = runner.run(&paths);
+    assert_eq!(None, runner.git_error());
+    assert_eq!(33, results.len());
+    assert!(results[0].error.is_some());
+    assert!(results[1..].iter().all(|result| result.success));
+}
+
+#[test]
+fn test_dependencies() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let paths = vec![root.join("lib"), root.join("app"), root.join("other")];
+    for path in &paths {
+        init(path);
+    }
+    let mut dependencies = Dependencies::default();
+    dependencies.add(&paths[0], &paths[1]);
+
+    // Every repository appends its path to the log once it's done
+    let log = root.join("log");
+    let alias = format!("alias.done=!sleep 0.2; pwd >> '{}'", log.display());
+    let order = |dependencies: Dependencies| {
+        let _ = std::fs::remove_file(&log);
+        let results = Runner::new(&["-c", alias.as_str(), "done"])
+            .dependencies(dependencies)
+            .run(&paths);
+        assert!(results.iter().all(|result| result.success), "{:?}", results);
+        // The results are in the order of the paths whatever the order they ran in
+        assert_eq!(paths[0], results[0].path);
+
+        let log = std::fs::read_to_string(&log).unwrap();
+        log.lines()
+            .map(|line| {
+                Path::new(line)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .filter(|name| name != "other")
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(vec!["app", "lib"], order(dependencies.clone()));
+    assert_eq!(vec!["lib", "app"], order(dependencies.reversed()));
+}
+
+#[test]
+fn test_path_limits() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let paths: Vec<_> = (0..4)
+        .map(|index| root.join("nas").join(format!("repo{}", index)))
+        .collect();
+    for path in &paths {
+        init(path);
+    }
+
+    // The commands under the limit never overlap
+    let log = root.join("log");
+    let alias = format!(
+        "alias.slow=!echo start >> '{log}'; sleep 0.1; echo end >> '{log}'",
+        log = log.display()
+    );
+    let mut limits = PathLimits::default();
+    limits.add(root.join("nas"), 1);
+    let results = Runner::new(&["-c", alias.as_str(), "slow"])
+        .path_limits(limits)
+        .run(&paths);
+    assert_eq!(4, results.len());
+    assert!(results.iter().all(|result| result.success), "{:?}", results);
+
+    assert_eq!(
+        "start\nend\n".repeat(4),
+        std::fs::read_to_string(&log).unwrap()
+    );
+}
+
+#[test]
+fn test_steps() {
+    let dir = tempfile::tempdir().unwrap();
+    let foo = dir.path().join("foo");
+    let bar = dir.path().join("bar");
+    init(&foo);
+    init(&bar);
+
+    let steps = |second: &str| {
+        vec![
+            vec!["rev-parse".to_string(), "--git-dir".to_string()],
+            vec![second.to_string()],
+            vec!["status".to_string(), "--short".to_string()],
+        ]
+    };
+    let results = Runner::new(&["unused"])
+        .steps([(foo.clone(), steps("status")), (bar.clone(), steps("nope"))].into())
+        .run(&[foo, bar]);
+
+    // Every step ran in foo, the result is the one of the last
+    assert!(results[0].success);
+    assert_eq!(Some((3, 3)), results[0].step);
+    assert_eq!(vec!["status", "--short"], results[0].args);
+    assert_eq!(2, results[0].steps.len());
+    assert_eq!(".git", results[0].steps[0].stdout.to_string());
+
+    // bar stopped at the unknown command, the last step didn't run
+    assert!(!results[1].success);
+    assert_eq!(Some((2, 3)), results[1].step);
+    assert_eq!(vec!["nope"], results[1].args);
+    assert_eq!(1, results[1].steps.len());
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_maintenance() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+    let upstream = fixture.path("../upstream");
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "--bare"]);
+    for name in ["cloned", "local"] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+        git(&path, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    }
+    git(
+        &work.join("cloned"),
+        &["remote", "add", "origin", upstream.to_str().unwrap()],
+    );
+
+    let run = |args: &[&str]| {
+        let output = fixture.subcommand("maintenance", args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert_eq!(
+        "cloned gc    done\n\
+         cloned prune done\n\
+         local gc     done\n\
+         local prune  skipped, no origin\n\
+         \n\
+         3 done, 1 skipped, 0 failed\n",
+        run(&[])
+    );
+
+    run(&["--task", "commit-graph"]);
+    assert!(work.join("local/.git/objects/info/commit-graph").exists());
+
+    let stdout = run(&["--min-size", "1G"]);
+    assert!(stdout.ends_with("\n2 skipped, 0 failed\n"), "{}", stdout);
+
+    let stdout = run(&["--schedule", "--task", "repack"]);
+    assert!(stdout.starts_with("0 3 * * * "), "{}", stdout);
+    assert!(stdout.ends_with(" --task repack\n"), "{}", stdout);
+}
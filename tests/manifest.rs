@@ -0,0 +1,55 @@
+mod common;
+
+use std::process::Output;
+
+use common::{git, Fixture};
+
+fn manifest(fixture: &Fixture, args: &[&str]) -> Output {
+    let output = fixture
+        .command_in_root(&["manifest"])
+        .args(args)
+        .arg("--root")
+        .arg(&fixture.root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    output
+}
+
+#[test]
+fn test_manifest_round_trip() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(&upstream, &["branch", "dev"]);
+
+    std::fs::create_dir_all(work.join("a")).unwrap();
+    git(work, &["clone", "-q", "../upstream", "foo"]);
+    git(work, &["clone", "-q", "-b", "dev", "../upstream", "a/bar"]);
+
+    let exported = manifest(&fixture, &["export"]).stdout;
+    let exported = String::from_utf8(exported).unwrap();
+    assert!(exported.contains("path = \"a/bar\""), "{}", exported);
+    assert!(exported.contains("branch = \"dev\""), "{}", exported);
+
+    let manifest_path = fixture.path("../manifest.toml");
+    std::fs::write(&manifest_path, &exported).unwrap();
+
+    // Wipe one repository and change the other, which must be left untouched
+    std::fs::remove_dir_all(work.join("a")).unwrap();
+    std::fs::write(work.join("foo/marker"), "").unwrap();
+
+    let output = manifest(&fixture, &["clone", manifest_path.to_str().unwrap()]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("a/bar cloned"), "{}", stdout);
+    assert!(stdout.contains("foo already exists"), "{}", stdout);
+    assert!(work.join("foo/marker").exists());
+
+    let reexported = manifest(&fixture, &["export"]).stdout;
+    assert_eq!(exported, String::from_utf8(reexported).unwrap());
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_off_default() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    for name in ["main", "feature", "detached", "dirty", "trunk"] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q", "-b", "main"]);
+        std::fs::write(path.join("README"), "hello").unwrap();
+        git(&path, &["add", "README"]);
+        git(&path, &["commit", "-q", "-m", "init"]);
+    }
+    git(&work.join("feature"), &["switch", "-q", "-c", "feature"]);
+    git(
+        &work.join("feature"),
+        &["commit", "-q", "--allow-empty", "-m", "wip"],
+    );
+    git(&work.join("detached"), &["checkout", "-q", "--detach"]);
+    git(&work.join("dirty"), &["switch", "-q", "-c", "fix"]);
+    std::fs::write(work.join("dirty/README"), "changed").unwrap();
+    // Neither main nor master, init.defaultBranch tells
+    git(&work.join("trunk"), &["branch", "-q", "-m", "trunk"]);
+    git(
+        &work.join("trunk"),
+        &["config", "init.defaultBranch", "trunk"],
+    );
+
+    let run = |args: &[&str]| {
+        let output = fixture.subcommand("off-default", args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = run(&[]);
+    assert!(
+        stdout.starts_with(
+            "dirty    fix              even with main\n\
+             feature  feature          ahead 1 of main\n\
+             detached detached "
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.ends_with("\n2 on another branch, 1 detached\n"),
+        "{}",
+        stdout
+    );
+
+    let stdout = run(&["--switch-back"]);
+    assert!(
+        stdout.starts_with("dirty   dirty, pass --force to switch anyway\nfeature switched\n"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        "main",
+        git(&work.join("feature"), &["branch", "--show-current"])
+    );
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+use common::{git, Fixture};
+
+fn prune_branches(fixture: &Fixture, args: &[&str]) -> Output {
+    fixture.subcommand("prune-branches", args).output().unwrap()
+}
+
+fn local_branches(path: &Path) -> String {
+    git(
+        path,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )
+}
+
+#[test]
+fn test_prune_branches() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(work, &["clone", "-q", "../upstream", "foo"]);
+
+    let foo = work.join("foo");
+    git(&foo, &["branch", "done"]);
+    git(&foo, &["branch", "release/1"]);
+    git(&foo, &["branch", "busy"]);
+    git(&foo, &["worktree", "add", "-q", "../../busy", "busy"]);
+
+    // Pushed, then its upstream deleted
+    git(&foo, &["checkout", "-q", "-b", "old"]);
+    git(&foo, &["commit", "-q", "--allow-empty", "-m", "old"]);
+    git(&foo, &["push", "-q", "-u", "origin", "old"]);
+    git(&foo, &["push", "-q", "origin", "--delete", "old"]);
+    git(&foo, &["fetch", "-q", "--prune"]);
+
+    git(&foo, &["checkout", "-q", "-b", "wip"]);
+    git(&foo, &["commit", "-q", "--allow-empty", "-m", "wip"]);
+
+    let output = prune_branches(&fixture, &["--protect", "release/*", "--dry-run"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("done"), "{}", stdout);
+    assert!(!stdout.contains("old"), "{}", stdout);
+    assert!(
+        stdout.contains("2 branches would be deleted (foo 2)"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        "busy\ndone\nmain\nold\nrelease/1\nwip",
+        local_branches(&foo)
+    );
+
+    let output = prune_branches(&fixture, &["--protect", "release/*", "--gone"]);
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("busy"), "{}", stdout);
+    assert!(
+        stdout.contains("2 branches deleted (foo 2), 1 failed"),
+        "{}",
+        stdout
+    );
+    assert_eq!("busy\nmain\nrelease/1\nwip", local_branches(&foo));
+}
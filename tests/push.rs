@@ -0,0 +1,109 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_push_all() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream.git");
+    let seed = fixture.path("../seed");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "--bare", "-b", "main"]);
+    git(
+        &fixture.path(".."),
+        &["clone", "-q", "upstream.git", "seed"],
+    );
+    git(&seed, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(&seed, &["push", "-q", "origin", "main"]);
+
+    git(work, &["clone", "-q", "../upstream.git", "behind"]);
+    git(&seed, &["commit", "-q", "--allow-empty", "-m", "remote"]);
+    git(&seed, &["push", "-q", "origin", "main"]);
+    git(&work.join("behind"), &["fetch", "-q"]);
+    for name in ["ahead", "detached", "forced", "local", "synced"] {
+        git(work, &["clone", "-q", "../upstream.git", name]);
+    }
+    for name in ["ahead", "forced"] {
+        git(
+            &work.join(name),
+            &["commit", "-q", "--allow-empty", "-m", "local"],
+        );
+    }
+    git(
+        &work.join("forced"),
+        &["config", "remote.origin.push", "+refs/heads/*:refs/heads/*"],
+    );
+    // The branch is pushed to its upstream whatever push.default says
+    git(&work.join("ahead"), &["config", "push.default", "nothing"]);
+    git(&work.join("detached"), &["checkout", "-q", "--detach"]);
+    git(&work.join("local"), &["switch", "-q", "-c", "dev"]);
+
+    let push_all = |args: &[&str]| fixture.subcommand("push-all", args).output().unwrap();
+
+    let output = push_all(&["--dry-run"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        &[
+            "behind   behind by 1 commit, the push would be rejected",
+            "detached detached",
+            "forced   +refs/heads/*:refs/heads/* forces the push, pass --allow-force to push anyway",
+            "local    no upstream, pass --set-upstream to push and set it",
+        ],
+        &lines[..4],
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[4].starts_with("ahead    would push main "),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        &[
+            "synced   up to date",
+            "",
+            "1 would push, 1 up to date, 4 need attention (1 behind, 1 detached, 1 forced, 1 no upstream), 0 failed",
+        ],
+        &lines[5..],
+        "{}",
+        stdout
+    );
+    // Nothing was pushed
+    assert_eq!("2", git(&upstream, &["rev-list", "--count", "main"]));
+
+    let output = push_all(&["--set-upstream"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with("\n2 pushed, 1 up to date, 3 need attention (1 behind, 1 detached, 1 forced), 0 failed\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\nlocal    pushed dev (new)\n"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        "origin/dev",
+        git(
+            &work.join("local"),
+            &["rev-parse", "--abbrev-ref", "@{upstream}"]
+        )
+    );
+
+    // Forcing is allowed only when asked for
+    let output = push_all(&["--allow-force", "+main:main"]);
+    assert_eq!(Some(0), output.status.code(), "{:?}", output);
+
+    let output = push_all(&["+main:main"]);
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+    assert_eq!(
+        "+main:main forces the push in every repository, pass --allow-force to push anyway\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
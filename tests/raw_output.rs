@@ -0,0 +1,69 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[cfg(unix)]
+#[test]
+fn test_raw_output() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let fixture = Fixture::empty();
+    let repo = fixture.path("latin1");
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+
+    // A file name in latin-1, it's not valid UTF-8
+    let name = OsStr::from_bytes(b"caf\xe9.txt");
+    std::fs::write(repo.join(name), "").unwrap();
+    git(&repo, &["add", "."]);
+
+    let log_file = fixture.path("../run.log");
+    let output = fixture
+        .command(&["--log-file"])
+        .arg(&log_file)
+        .args(["--", "-c", "core.quotepath=false", "ls-files"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // The terminal gets a lossy conversion, the log file the bytes git wrote
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("caf\u{fffd}.txt"), "{}", stdout);
+    let log = std::fs::read(&log_file).unwrap();
+    assert!(
+        log.windows(8).any(|window| window == b"caf\xe9.txt"),
+        "{}",
+        String::from_utf8_lossy(&log)
+    );
+}
+
+#[test]
+fn test_spill_threshold() {
+    let fixture = Fixture::empty();
+    let repo = fixture.path("big");
+    std::fs::create_dir_all(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    for i in 0..500 {
+        std::fs::write(repo.join(format!("file{:03}.txt", i)), "").unwrap();
+    }
+    git(&repo, &["add", "."]);
+
+    let log_file = fixture.path("../run.log");
+    let output = fixture
+        .command(&["--log-file"])
+        .arg(&log_file)
+        .args(["--spill-threshold", "1K", "ls-files"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // Only an excerpt is printed, the log file has everything
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("file000.txt"), "{}", stdout);
+    assert!(stdout.contains(" omitted …"), "{}", stdout);
+    assert!(!stdout.contains("file250.txt"), "{}", stdout);
+    assert!(stdout.contains("file499.txt"), "{}", stdout);
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert!(log.contains("file250.txt"), "{}", log);
+}
@@ -0,0 +1,222 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_remotes_rewrite() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    for (name, url) in [
+        ("foo", "git@github.com:acme/foo.git"),
+        ("bar", "https://github.com/acme/bar.git"),
+        ("baz", "git@gitlab.com:acme/baz.git"),
+    ] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+        git(&path, &["remote", "add", "origin", url]);
+    }
+    git(
+        &work.join("foo"),
+        &[
+            "remote",
+            "set-url",
+            "--push",
+            "origin",
+            "git@github.com:me/foo.git",
+        ],
+    );
+
+    let rewrite = |args: &[&str]| {
+        let output = fixture
+            .command_in_root(&["remotes", "rewrite", "--root"])
+            .arg(work)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let args = [
+        "--regex",
+        "--from",
+        r"^(git@|https://)github\.com[:/]",
+        "--to",
+        "git@github.example.com:",
+    ];
+    assert_eq!(
+        "bar: origin https://github.com/acme/bar.git → git@github.example.com:acme/bar.git\n\
+         foo: origin git@github.com:acme/foo.git → git@github.example.com:acme/foo.git\n\
+         foo: origin (push) git@github.com:me/foo.git → git@github.example.com:me/foo.git\n\
+         \n\
+         3 URLs would be rewritten\n",
+        rewrite(&[&args[..], &["--dry-run"]].concat())
+    );
+    assert_eq!(
+        "https://github.com/acme/bar.git",
+        git(&work.join("bar"), &["remote", "get-url", "origin"])
+    );
+
+    rewrite(&args);
+    assert_eq!(
+        "git@github.example.com:acme/bar.git",
+        git(&work.join("bar"), &["remote", "get-url", "origin"])
+    );
+    assert_eq!(
+        "git@github.example.com:me/foo.git",
+        git(
+            &work.join("foo"),
+            &["remote", "get-url", "--push", "origin"]
+        )
+    );
+    assert_eq!(
+        "git@gitlab.com:acme/baz.git",
+        git(&work.join("baz"), &["remote", "get-url", "origin"])
+    );
+}
+
+#[test]
+fn test_remotes_check() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+    let upstream = fixture.path("../upstream");
+
+    std::fs::create_dir_all(upstream.join("full")).unwrap();
+    std::fs::create_dir_all(upstream.join("empty")).unwrap();
+    git(&upstream.join("full"), &["init", "-q", "--bare"]);
+    git(&upstream.join("empty"), &["init", "-q", "--bare"]);
+
+    let path = work.join("foo");
+    std::fs::create_dir_all(&path).unwrap();
+    git(&path, &["init", "-q"]);
+    git(
+        &path,
+        &[
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ],
+    );
+    for (remote, url) in [("origin", "full"), ("empty", "empty"), ("gone", "gone")] {
+        let url = upstream.join(url);
+        git(&path, &["remote", "add", remote, url.to_str().unwrap()]);
+    }
+    git(&path, &["push", "-q", "origin", "HEAD"]);
+
+    let run = || {
+        fixture
+            .command_in_root(&["remotes", "check", "--root"])
+            .arg(work)
+            .output()
+            .unwrap()
+    };
+
+    let output = run();
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("foo gone   unreachable: "), "{}", stdout);
+    assert!(
+        stdout.ends_with(
+            "foo empty  no HEAD\n\
+             foo origin reachable\n\
+             \n\
+             1 no HEAD, 1 reachable, 1 failed (1 unreachable)\n"
+        ),
+        "{}",
+        stdout
+    );
+
+    git(&path, &["remote", "remove", "gone"]);
+    assert!(run().status.success());
+}
+
+#[test]
+fn test_remote_filters() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    for (name, url) in [
+        ("foo", "git@github.com:acme/foo.git"),
+        ("bar", "https://gitlab.com/acme/bar.git"),
+    ] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+        git(&path, &["remote", "add", "origin", url]);
+    }
+    git(
+        &work.join("bar"),
+        &[
+            "remote",
+            "add",
+            "upstream",
+            "https://gitlab.com/upstream/bar.git",
+        ],
+    );
+    std::fs::create_dir_all(work.join("local")).unwrap();
+    git(&work.join("local"), &["init", "-q"]);
+
+    let run = |args: &[&str]| {
+        let output = fixture
+            .command(&["--output-order", "sorted", "--dry-run"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert_eq!(
+        "foo: git fetch\n",
+        run(&["--remote-matches", "github\\.com", "fetch"])
+    );
+    assert_eq!(
+        "bar: git fetch\n",
+        run(&["--has-remote", "upstream", "fetch"])
+    );
+
+    assert_eq!(
+        "bar: git fetch\nlocal: git fetch\n",
+        run(&["--exclude-host", "GitHub.com", "fetch"])
+    );
+    assert_eq!(
+        "foo: git fetch\nlocal: git fetch\n",
+        run(&["--include-host", "github.com", "fetch"])
+    );
+
+    let stdout = run(&["config", "user.email", "me@{origin_host}"]);
+    assert_eq!(
+        "bar: git config user.email me@gitlab.com\n\
+         foo: git config user.email me@github.com\n\
+         local: git config user.email me@\n",
+        stdout
+    );
+
+    // The remotes are added to the JSON output of status
+    let output = fixture
+        .subcommand("status", &["--with-remotes", "--format", "json"])
+        .output()
+        .unwrap();
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let bar = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["name"] == "bar")
+        .unwrap();
+    assert_eq!(
+        serde_json::json!([
+            {"name": "origin", "url": "https://gitlab.com/acme/bar.git"},
+            {"name": "upstream", "url": "https://gitlab.com/upstream/bar.git"},
+        ]),
+        bar["remotes"]
+    );
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_sizes() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    for name in ["big", "small"] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+    }
+    let big = work.join("big");
+    std::fs::write(big.join(".gitattributes"), "*.bin filter=lfs diff=lfs\n").unwrap();
+    std::fs::write(big.join("data.bin"), vec![b'x'; 200 * 1024]).unwrap();
+    std::fs::write(big.join("README"), "big").unwrap();
+    git(&big, &["add", "."]);
+    git(&big, &["commit", "-q", "-m", "init"]);
+    // Deleted files still count in the history
+    std::fs::remove_file(big.join("data.bin")).unwrap();
+    git(&big, &["commit", "-q", "-a", "-m", "remove"]);
+    git(
+        &work.join("small"),
+        &["commit", "-q", "--allow-empty", "-m", "init"],
+    );
+
+    let output = fixture
+        .subcommand("sizes", &["--largest", "1", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("big", json[0]["name"]);
+    assert_eq!(true, json[0]["lfs"]);
+    assert_eq!("data.bin", json[0]["largest"][0]["path"]);
+    assert_eq!(200 * 1024, json[0]["largest"][0]["size"]);
+    assert_eq!(
+        json[0]["total"].as_u64().unwrap(),
+        json[0]["git_dir"].as_u64().unwrap() + json[0]["work_tree"].as_u64().unwrap()
+    );
+    assert_eq!("small", json[1]["name"]);
+    assert_eq!(false, json[1]["lfs"]);
+    assert_eq!(0, json[1]["work_tree"]);
+
+    let output = fixture.subcommand("sizes", &[]).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].starts_with("big   "), "{}", stdout);
+    assert!(lines[0].ends_with(" LFS"), "{}", stdout);
+    assert!(lines[1].starts_with("small "), "{}", stdout);
+    assert!(lines[1].ends_with("work tree 0 B)"), "{}", stdout);
+    assert!(lines[3].ends_with(" in total"), "{}", stdout);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_stash_all() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    for name in ["clean", "dirty", "untracked", "moved"] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+        std::fs::write(path.join("README"), "hello").unwrap();
+        git(&path, &["add", "README"]);
+        git(&path, &["commit", "-q", "-m", "init"]);
+    }
+    std::fs::write(work.join("dirty/README"), "changed").unwrap();
+    std::fs::write(work.join("untracked/notes.txt"), "todo").unwrap();
+    std::fs::write(work.join("moved/README"), "changed").unwrap();
+
+    let run = |subcommand: &str| fixture.subcommand(subcommand, &[]).output().unwrap();
+
+    let output = run("stash-all");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with("\n3 stashed, 1 clean, 0 failed\n"),
+        "{}",
+        stdout
+    );
+    assert!(!work.join("untracked/notes.txt").exists());
+    assert_eq!("", git(&work.join("dirty"), &["status", "--porcelain"]));
+
+    // Stashing again doesn't stash the recorded repositories twice
+    std::fs::write(work.join("dirty/README"), "changed again").unwrap();
+    let output = run("stash-all");
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("dirty     already stashed as "),
+        "{}",
+        stdout
+    );
+    git(&work.join("dirty"), &["checkout", "README"]);
+
+    // Someone else stashed on top of the recorded stash
+    std::fs::write(work.join("moved/README"), "other").unwrap();
+    git(&work.join("moved"), &["stash", "-q"]);
+
+    let output = run("unstash-all");
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("moved     the stash at the top is "),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.ends_with("\n2 restored, 1 need attention (1 mismatch), 0 failed\n"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        "changed",
+        std::fs::read_to_string(work.join("dirty/README")).unwrap()
+    );
+    assert!(work.join("untracked/notes.txt").exists());
+
+    // The mismatched stash is still recorded and restored once it's back at the top
+    git(&work.join("moved"), &["stash", "drop", "-q"]);
+    let output = run("unstash-all");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        "changed",
+        std::fs::read_to_string(work.join("moved/README")).unwrap()
+    );
+    assert!(!fixture.path("../gitjuggling/stashes.toml").exists());
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::{git, Fixture};
+
+fn init(path: &Path) {
+    std::fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q", "-b", "main"]);
+    std::fs::write(path.join("file"), "base\n").unwrap();
+    git(path, &["add", "file"]);
+    git(path, &["commit", "-q", "-m", "init"]);
+}
+
+/// Creates a repository with a merge stopped on a conflict.
+fn init_merging(path: &Path) {
+    init(path);
+    git(path, &["checkout", "-q", "-b", "topic"]);
+    std::fs::write(path.join("file"), "topic\n").unwrap();
+    git(path, &["commit", "-q", "-am", "topic"]);
+    git(path, &["checkout", "-q", "main"]);
+    std::fs::write(path.join("file"), "main\n").unwrap();
+    git(path, &["commit", "-q", "-am", "main"]);
+    let output = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(["merge", "--quiet", "topic"])
+        .current_dir(path)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_skip_states() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    init(&work.join("clean"));
+
+    let detached = work.join("detached");
+    init(&detached);
+    git(&detached, &["checkout", "-q", "--detach"]);
+
+    let merging = work.join("merging");
+    init_merging(&merging);
+
+    let run = |args: &[&str]| fixture.run(args);
+
+    // The state shows up in the banner
+    let output = run(&["--output-order", "sorted", "status", "--short"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("merging (main, merging) executing"),
+        "{}",
+        stdout
+    );
+
+    let output = run(&[
+        "--show-skipped",
+        "--skip-states",
+        "detached,merging",
+        "status",
+        "--short",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  1\n"), "{}", stdout);
+    assert!(stdout.contains("Skipped:    2\n"), "{}", stdout);
+    assert!(stdout.contains("\ndetached detached\n"), "{}", stdout);
+    assert!(stdout.contains("\nmerging merging\n"), "{}", stdout);
+
+    let output = run(&["--skip-states", "rebase", "status"]);
+    assert_eq!(Some(2), output.status.code(), "{:?}", output);
+}
+
+#[test]
+fn test_skip_in_progress() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    init(&work.join("clean"));
+    init_merging(&work.join("merging"));
+    let bisecting = work.join("bisecting");
+    init(&bisecting);
+    git(&bisecting, &["bisect", "start"]);
+
+    let run = |args: &[&str]| fixture.run(args);
+
+    // fetch writes, the operations in progress are skipped
+    let output = run(&["--show-skipped", "fetch", "--all"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  1\n"), "{}", stdout);
+    assert!(stdout.contains("Skipped:    2\n"), "{}", stdout);
+    assert!(stdout.contains("  in progress:   2\n"), "{}", stdout);
+    assert!(
+        stdout
+            .contains("\nmerging merging in progress, pass --no-skip-in-progress to run anyway\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\nbisecting bisecting in progress"),
+        "{}",
+        stdout
+    );
+
+    // log only reads
+    let output = run(&["log", "--oneline"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  3\n"), "{}", stdout);
+    assert!(!stdout.contains("Skipped:"), "{}", stdout);
+
+    let output = run(&["--no-skip-in-progress", "fetch", "--all"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Succeeded:  3\n"), "{}", stdout);
+    assert!(
+        stdout.contains("merging (main, merging) executing"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_unborn() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    init(&work.join("clean"));
+    let empty = work.join("empty");
+    std::fs::create_dir_all(&empty).unwrap();
+    git(&empty, &["init", "-q", "-b", "main"]);
+
+    let run = |args: &[&str]| fixture.run(args);
+
+    // git log has nothing to show without commits
+    let output = run(&["log", "-1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("empty (main, unborn)"), "{}", stdout);
+    assert!(stdout.contains("Failed:     1\n"), "{}", stdout);
+    assert!(stdout.contains("  no commits:    1\n"), "{}", stdout);
+    // The unborn repository isn't counted as a failure with its exit code
+    assert!(!stdout.contains("exit code"), "{}", stdout);
+
+    let output = run(&["rev-parse", "--verify", "--quiet", "missing"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Failed:     2\n"), "{}", stdout);
+    assert!(stdout.contains("  exit code 1:   1\n"), "{}", stdout);
+    assert!(stdout.contains("  no commits:    1\n"), "{}", stdout);
+
+    let output = run(&["--show-skipped", "--skip-states", "unborn", "log", "-1"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\nempty unborn\n"), "{}", stdout);
+
+    let output = fixture.subcommand("status", &[]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("empty main clean no commits\n"),
+        "{}",
+        stdout
+    );
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::process::Output;
+
+use common::{git, Fixture};
+
+fn status(fixture: &Fixture, args: &[&str]) -> Output {
+    fixture.subcommand("status", args).output().unwrap()
+}
+
+#[test]
+fn test_status() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(work, &["clone", "-q", "../upstream", "clean"]);
+    git(work, &["clone", "-q", "../upstream", "diverged"]);
+    git(work, &["clone", "-q", "../upstream", "dirty"]);
+
+    git(
+        &work.join("diverged"),
+        &["commit", "-q", "--allow-empty", "-m", "local"],
+    );
+    git(
+        &upstream,
+        &["commit", "-q", "--allow-empty", "-m", "remote"],
+    );
+    git(&work.join("diverged"), &["fetch", "-q"]);
+    std::fs::write(work.join("dirty/notes.txt"), "todo").unwrap();
+
+    let output = status(&fixture, &[]);
+    assert_eq!(Some(0), output.status.code(), "{:?}", output);
+    assert_eq!(
+        "diverged main clean       ahead 1, behind 1\n\
+         dirty    main 1 untracked up to date\n\
+         clean    main clean       up to date\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let output = status(&fixture, &["--format", "json", "--check"]);
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("diverged", entries[0]["name"]);
+    assert_eq!(1, entries[0]["behind"]);
+    assert_eq!(true, entries[1]["dirty"]);
+
+    let output = status(&fixture, &["--check", "--exclude", "d*"]);
+    assert_eq!(Some(0), output.status.code(), "{:?}", output);
+
+    // git status still runs in every repository after --
+    let output = fixture.run(&["--porcelain", "--", "status"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(3, String::from_utf8_lossy(&output.stdout).lines().count());
+}
+
+#[test]
+fn test_status_diff() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+    for name in ["foo", "bar"] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q", "-b", "main"]);
+        git(&path, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    }
+
+    let output = status(&fixture, &["--save", "morning"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(fixture
+        .path("../gitjuggling/snapshots/morning.json")
+        .is_file());
+
+    let output = status(&fixture, &["--diff", "morning"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("0 changed since morning at 20"),
+        "{}",
+        stdout
+    );
+
+    std::fs::write(work.join("foo/notes.txt"), "todo").unwrap();
+    git(
+        &work.join("bar"),
+        &["commit", "-q", "--allow-empty", "-m", "more"],
+    );
+    let output = status(&fixture, &["--diff", "morning"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(7, lines.len(), "{}", stdout);
+    assert_eq!("bar", lines[0]);
+    assert!(lines[1].starts_with("  before main at "), "{}", stdout);
+    assert_ne!(lines[1][8..], lines[2][8..]);
+    assert_eq!("foo", lines[3]);
+    assert!(lines[4].ends_with(", clean, no upstream"), "{}", stdout);
+    assert!(
+        lines[5].ends_with(", 1 untracked, no upstream"),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[6].starts_with("2 changed since morning"),
+        "{}",
+        stdout
+    );
+
+    let output = status(&fixture, &["--diff", "morning", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(2, json.as_array().unwrap().len());
+    assert_eq!(0, json[1]["before"]["untracked"]);
+    assert_eq!(1, json[1]["after"]["untracked"]);
+
+    let output = status(&fixture, &["--diff", "evening"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(!status(&fixture, &["--save", "../evening"]).status.success());
+}
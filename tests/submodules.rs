@@ -0,0 +1,91 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_submodules_update() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    for name in ["lib", "gone", "super"] {
+        let path = upstream.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+        git(&path, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    }
+    let super_path = upstream.join("super");
+    for (name, path) in [("lib", "lib"), ("gone", "vendor/gone")] {
+        let url = upstream.join(name);
+        git(
+            &super_path,
+            &["submodule", "add", "-q", url.to_str().unwrap(), path],
+        );
+    }
+    git(&super_path, &["commit", "-q", "-m", "add the submodules"]);
+
+    std::fs::create_dir_all(work.join("plain")).unwrap();
+    git(&work.join("plain"), &["init", "-q"]);
+    git(
+        work,
+        &["clone", "-q", super_path.to_str().unwrap(), "super"],
+    );
+
+    let update = || {
+        fixture
+            .command_in_root(&["submodules", "update", "--root"])
+            .arg(work)
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "protocol.file.allow")
+            .env("GIT_CONFIG_VALUE_0", "always")
+            .output()
+            .unwrap()
+    };
+
+    let output = update();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("super lib         checked out "),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.ends_with("\n2 checked out, 0 failed\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("plain"), "{}", stdout);
+    assert!(work.join("super/lib/.git").exists());
+
+    // git stops at the submodule it can't clone
+    git(
+        work,
+        &["clone", "-q", super_path.to_str().unwrap(), "other"],
+    );
+    std::fs::remove_dir_all(upstream.join("gone")).unwrap();
+
+    let output = update();
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("other lib         failed: not updated, git submodule update stopped: "),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("\nother vendor/gone failed: fatal: clone of "),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.ends_with(
+            "super lib         up to date\n\
+             super vendor/gone up to date\n\
+             \n\
+             2 up to date, 2 failed\n"
+        ),
+        "{}",
+        stdout
+    );
+}
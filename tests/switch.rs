@@ -0,0 +1,76 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_switch_all() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    std::fs::create_dir_all(work.join("other")).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    std::fs::write(upstream.join("README"), "hello").unwrap();
+    git(&upstream, &["add", "README"]);
+    git(&upstream, &["commit", "-q", "-m", "init"]);
+    git(&upstream, &["branch", "dev"]);
+    for name in ["local", "remote", "dirty", "already"] {
+        git(work, &["clone", "-q", "../upstream", name]);
+    }
+    for name in ["local", "dirty", "already"] {
+        git(&work.join(name), &["branch", "dev", "origin/dev"]);
+    }
+    git(&work.join("already"), &["switch", "-q", "dev"]);
+    std::fs::write(work.join("dirty/README"), "changed").unwrap();
+    git(&work.join("other"), &["init", "-q", "-b", "main"]);
+    git(
+        &work.join("other"),
+        &["commit", "-q", "--allow-empty", "-m", "init"],
+    );
+
+    let switch_all = |args: &[&str]| {
+        let output = fixture.subcommand("switch-all", args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    assert_eq!(
+        "dirty   dirty, pass --force to switch anyway\n\
+         other   no such branch\n\
+         remote  only origin/dev exists, pass --create-tracking to create the branch\n\
+         local   switched\n\
+         already already on the branch\n\
+         \n\
+         1 switched, 1 already on the branch, 3 need attention (1 dirty, 1 no such branch, 1 only on a remote), 0 failed\n",
+        switch_all(&["dev"])
+    );
+    assert_eq!(
+        "dev",
+        git(&work.join("local"), &["branch", "--show-current"])
+    );
+    assert_eq!(
+        "main",
+        git(&work.join("dirty"), &["branch", "--show-current"])
+    );
+
+    let stdout = switch_all(&["dev", "--create-tracking", "--force"]);
+    assert!(
+        stdout.contains("remote  created, tracking origin/dev\n"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("dirty   switched\n"), "{}", stdout);
+    assert_eq!(
+        "origin/dev",
+        git(
+            &work.join("remote"),
+            &["rev-parse", "--abbrev-ref", "dev@{upstream}"]
+        )
+    );
+    // The changes are carried over to the branch
+    assert_eq!(
+        "changed",
+        std::fs::read_to_string(work.join("dirty/README")).unwrap()
+    );
+}
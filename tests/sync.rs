@@ -0,0 +1,56 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_sync() {
+    let fixture = Fixture::empty();
+    let upstream = fixture.path("../upstream");
+    let work = &fixture.root;
+
+    std::fs::create_dir_all(&upstream).unwrap();
+    std::fs::create_dir_all(work.join("local")).unwrap();
+    git(&upstream, &["init", "-q", "-b", "main"]);
+    git(&upstream, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    for name in ["behind", "diverged", "dirty", "detached"] {
+        git(work, &["clone", "-q", "../upstream", name]);
+    }
+    git(&work.join("local"), &["init", "-q"]);
+
+    git(
+        &work.join("diverged"),
+        &["commit", "-q", "--allow-empty", "-m", "local"],
+    );
+    git(&work.join("detached"), &["checkout", "-q", "--detach"]);
+    std::fs::write(work.join("dirty/notes.txt"), "todo").unwrap();
+    git(
+        &upstream,
+        &["commit", "-q", "--allow-empty", "-m", "remote"],
+    );
+
+    let output = fixture.subcommand("sync", &[]).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with(
+            "detached detached\n\
+             dirty    dirty\n\
+             diverged diverged, ahead 1 and behind 1\n\
+             local    no commits\n\
+             behind   fast-forwarded by 1 commit\n"
+        ),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.ends_with("1 fast-forwarded, 4 need attention (1 detached, 1 dirty, 1 diverged, 1 no commits), 0 failed\n"),
+        "{}",
+        stdout
+    );
+
+    // Only the repository behind was touched
+    let head = git(&upstream, &["rev-parse", "HEAD"]);
+    assert_eq!(head, git(&work.join("behind"), &["rev-parse", "HEAD"]));
+    assert_ne!(head, git(&work.join("dirty"), &["rev-parse", "HEAD"]));
+}
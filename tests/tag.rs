@@ -0,0 +1,78 @@
+mod common;
+
+use common::{git, Fixture};
+
+#[test]
+fn test_tag_all() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    for name in ["new", "present", "conflict"] {
+        let upstream = fixture.path("../upstream").join(name);
+        std::fs::create_dir_all(&upstream).unwrap();
+        git(&upstream, &["init", "-q", "--bare"]);
+
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+        git(
+            &path,
+            &["remote", "add", "origin", upstream.to_str().unwrap()],
+        );
+        git(&path, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    }
+    git(&work.join("present"), &["tag", "v1.0"]);
+    git(&work.join("conflict"), &["tag", "v1.0"]);
+    git(
+        &work.join("conflict"),
+        &["commit", "-q", "--allow-empty", "-m", "fix"],
+    );
+
+    let tag_all = |code: i32, args: &[&str]| {
+        let output = fixture
+            .subcommand("tag-all", args)
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .unwrap();
+        assert_eq!(Some(code), output.status.code(), "{:?}", output);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    // The conflict is a failure
+    let stdout = tag_all(
+        1,
+        &[
+            "v1.0",
+            "--message",
+            "Release 1.0",
+            "--push",
+            "--per-host",
+            "1",
+        ],
+    );
+    assert!(
+        stdout.starts_with("conflict conflict, the tag points to "),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout
+            .ends_with("\n1 created, 1 already present, 1 need attention (1 conflict), 0 failed\n"),
+        "{}",
+        stdout
+    );
+    assert_eq!("tag", git(&work.join("new"), &["cat-file", "-t", "v1.0"]));
+    let upstream = fixture.path("../upstream");
+    assert_eq!(
+        git(&work.join("new"), &["rev-parse", "v1.0"]),
+        git(&upstream.join("new"), &["rev-parse", "v1.0"])
+    );
+
+    let stdout = tag_all(0, &["v1.0", "--force"]);
+    assert!(stdout.starts_with("conflict moved from "), "{}", stdout);
+    assert_eq!(
+        git(&work.join("conflict"), &["rev-parse", "HEAD"]),
+        git(&work.join("conflict"), &["rev-parse", "v1.0"])
+    );
+}
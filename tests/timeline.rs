@@ -0,0 +1,81 @@
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use common::{git, init, Fixture};
+
+fn commit(path: &Path, email: &str, date: &str, subject: &str) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c"])
+        .arg(format!("user.email={}", email))
+        .args(["commit", "-q", "--allow-empty", "-m", subject])
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date)
+        .current_dir(path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn timeline(fixture: &Fixture, args: &[&str]) -> Output {
+    let output = fixture
+        .subcommand("timeline", args)
+        .env("TZ", "UTC")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    output
+}
+
+#[test]
+fn test_timeline() {
+    let fixture = Fixture::empty();
+    for name in ["foo", "bar", "empty"] {
+        init(&fixture.path(name));
+    }
+    let foo = fixture.path("foo");
+    let bar = fixture.path("bar");
+    commit(
+        &foo,
+        "me@example.com",
+        "2024-05-29T18:30:00Z",
+        "Bump the version",
+    );
+    commit(
+        &bar,
+        "other@example.com",
+        "2024-05-30T09:45:00Z",
+        "Add a test",
+    );
+    commit(
+        &foo,
+        "me@example.com",
+        "2024-05-30T14:02:00Z",
+        "Fix the parser",
+    );
+    git(&foo, &["config", "user.email", "me@example.com"]);
+
+    let output = timeline(&fixture, &["--since", "2024-05-29"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(3, stdout.lines().count(), "{}", stdout);
+    assert!(stdout.starts_with("2024-05-30 14:02 foo "), "{}", stdout);
+    assert!(stdout.ends_with("Bump the version\n"), "{}", stdout);
+    // The repository without commits prints nothing
+    assert!(output.stderr.is_empty(), "{:?}", output);
+
+    // me is the user.email of each repository, bar doesn't have one and is excluded
+    let output = timeline(
+        &fixture,
+        &["--author", "me", "--format", "json", "--exclude", "bar"],
+    );
+    let commits: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(2, commits.as_array().unwrap().len());
+    assert_eq!("foo", commits[0]["repository"]);
+    assert_eq!("Fix the parser", commits[0]["subject"]);
+
+    let output = timeline(&fixture, &["--group-by", "repo"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("foo\n  2024-05-30 14:02 "), "{}", stdout);
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::{git, Fixture};
+
+fn ssh_keygen(path: &Path) {
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", ""])
+        .arg("-f")
+        .arg(path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_verify() {
+    let fixture = Fixture::empty();
+    let work = &fixture.root;
+
+    ssh_keygen(&fixture.path("../trusted"));
+    ssh_keygen(&fixture.path("../unknown"));
+    let public_key = std::fs::read_to_string(fixture.path("../trusted.pub")).unwrap();
+    let allowed_signers = fixture.path("../allowed_signers");
+    std::fs::write(&allowed_signers, format!("test@example.com {}", public_key)).unwrap();
+
+    for name in ["signed", "unknown", "unsigned", "vendor/lib"] {
+        let path = work.join(name);
+        std::fs::create_dir_all(&path).unwrap();
+        git(&path, &["init", "-q"]);
+    }
+    for (name, key) in [("signed", "trusted"), ("unknown", "unknown")] {
+        let key = format!("user.signingkey={}", fixture.path("..").join(key).display());
+        git(
+            &work.join(name),
+            &[
+                "-c",
+                "gpg.format=ssh",
+                "-c",
+                &key,
+                "commit",
+                "-q",
+                "-S",
+                "--allow-empty",
+                "-m",
+                "init",
+            ],
+        );
+    }
+    for name in ["unsigned", "vendor/lib"] {
+        git(
+            &work.join(name),
+            &["commit", "-q", "--allow-empty", "-m", "init"],
+        );
+    }
+
+    std::fs::create_dir_all(fixture.path("../gitjuggling")).unwrap();
+    std::fs::write(
+        fixture.path("../gitjuggling/config.toml"),
+        "[repos.\"vendor/*\"]\nskip = [\"verify\"]\n",
+    )
+    .unwrap();
+
+    let verify = |args: &[&str]| {
+        fixture
+            .subcommand("verify", args)
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "gpg.ssh.allowedSignersFile")
+            .env("GIT_CONFIG_VALUE_0", &allowed_signers)
+            .output()
+            .unwrap()
+    };
+
+    let output = verify(&[]);
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(6, lines.len(), "{}", stdout);
+    assert!(
+        lines[0].starts_with("unknown    ")
+            && lines[0].contains(" unknown key, signed with SHA256:"),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[1].starts_with("unsigned   ") && lines[1].ends_with(" unsigned"),
+        "{}",
+        stdout
+    );
+    assert!(
+        lines[2].starts_with("signed     ")
+            && lines[2].contains(" trusted, signed by test@example.com with SHA256:"),
+        "{}",
+        stdout
+    );
+    assert_eq!("vendor/lib exempted", lines[3]);
+    assert_eq!(
+        "1 exempted, 1 trusted, 2 need attention (1 unknown key, 1 unsigned), 0 failed",
+        lines[5]
+    );
+
+    let output = verify(&["--allow-unknown-key", "--allow-unsigned"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = verify(&["--allow-unsigned", "--format", "json"]);
+    assert_eq!(Some(1), output.status.code(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("unknown_key", json[0]["status"]);
+    assert_eq!("signed", json[2]["name"]);
+    assert_eq!("test@example.com", json[2]["signer"]);
+    assert_eq!("exempted", json[3]["status"]);
+}